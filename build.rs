@@ -7,7 +7,8 @@ extern crate num_bigint;
 use std::env;
 use std::fs::File;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
 use num_bigint::BigUint;
 
@@ -24,6 +25,12 @@ fn main() {
     println!("cargo:rerun-if-changed=build.rs");
 }
 
+const ASM_SRCS: &'static [&'static str] = &[
+    "src/ll/asm/addsub_n.S",
+    "src/ll/asm/mul_1.S",
+    "src/ll/asm/addmul_1.S",
+];
+
 // Compile the asm implementations of operations. This is currently very dumb
 // and should probably be a little smarter in how it does the job. I'll probably
 // need to split out the generic impls and handle that too...
@@ -32,24 +39,69 @@ fn compile_asm() {
         if let Ok(host) = env::var("HOST") {
             if host != target { panic!("Cross compiling not currently supported"); }
 
-            // Currently only supported for 64-bit linux
-            if (target.contains("x86-64") || target.contains("x86_64")) && target.contains("linux")  {
-
-                let asm_srcs = &[
-                    "src/ll/asm/addsub_n.S",
-                    "src/ll/asm/mul_1.S",
-                    "src/ll/asm/addmul_1.S",
-                ];
+            // Currently only supported for 64-bit linux and windows
+            if !(target.contains("x86-64") || target.contains("x86_64")) {
+                println!("cargo:warning=the asm feature is not supported on {}, ignoring it", target);
+                return;
+            }
 
-                gcc::compile_library("libasm.a", asm_srcs);
-                // Use a cfg param so turning the feature on when we don't have
-                // asm impls available doesn't cause compile errors
-                println!("cargo:rustc-cfg=asm");
+            if target.contains("linux") || target.contains("windows-gnu") {
+                gcc::compile_library("libasm.a", ASM_SRCS);
+            } else if target.contains("windows-msvc") {
+                if let Err(e) = compile_asm_msvc(&target) {
+                    println!("cargo:warning=could not build the asm kernels, ignoring the asm feature: {}", e);
+                    return;
+                }
+            } else {
+                println!("cargo:warning=the asm feature is not supported on {}, ignoring it", target);
+                return;
             }
+
+            // Use a cfg param so turning the feature on when we don't have
+            // asm impls available doesn't cause compile errors
+            println!("cargo:rustc-cfg=asm");
         }
     }
 }
 
+// MSVC can't assemble the GNU syntax of the kernels, so clang (or whatever
+// RAMP_ASM_CLANG points to) does it, and lib.exe makes a library of them.
+fn compile_asm_msvc(target: &str) -> Result<(), String> {
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let clang = env::var("RAMP_ASM_CLANG").unwrap_or("clang".to_string());
+    println!("cargo:rerun-if-env-changed=RAMP_ASM_CLANG");
+
+    let mut objs = Vec::new();
+    for &src in ASM_SRCS {
+        let obj = out_dir.join(Path::new(src).file_stem().unwrap()).with_extension("obj");
+        let status = Command::new(&clang)
+            .args(&["--target=x86_64-pc-windows-msvc", "-c", src, "-o"])
+            .arg(&obj)
+            .status();
+        match status {
+            Ok(ref s) if s.success() => objs.push(obj),
+            Ok(s) => return Err(format!("{} failed on {}: {}", clang, src, s)),
+            Err(e) => return Err(format!("could not run {}: {}", clang, e)),
+        }
+    }
+
+    let mut lib = try!(gcc::windows_registry::find(target, "lib.exe")
+                       .ok_or("lib.exe not found".to_string()));
+    let status = lib.arg("/nologo")
+        .arg(format!("/OUT:{}", out_dir.join("asm.lib").display()))
+        .args(&objs)
+        .status();
+    match status {
+        Ok(ref s) if s.success() => {}
+        Ok(s) => return Err(format!("lib.exe failed: {}", s)),
+        Err(e) => return Err(format!("could not run lib.exe: {}", e)),
+    }
+
+    println!("cargo:rustc-link-search=native={}", out_dir.display());
+    println!("cargo:rustc-link-lib=static=asm");
+    Ok(())
+}
+
 fn gen_bases(f: &mut File) {
     let limb_size = get_target_limb_size();
 
@@ -0,0 +1,197 @@
+// Copyright 2015 The Ramp Developers
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Writes the test vectors replayed by `tests/gmp_vectors.rs`, with every
+//! result computed by GMP, so the tests have a second oracle that doesn't
+//! need GMP to run.
+//!
+//! ```text
+//! cargo run --features rust-gmp --bin gen_vectors -- tests/vectors
+//! ```
+//!
+//! Each file holds the vectors of one operation, one per line, as
+//! space-separated integers in hexadecimal. Shift amounts and exponents that
+//! fit in a machine word are in decimal, and so is the result of the decimal
+//! conversion.
+
+extern crate framp as ramp;
+extern crate gmp;
+extern crate rand;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+
+use gmp::mpz::Mpz;
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+use ramp::{Int, RandomInt};
+
+// The sizes of the operands, in bits, on both sides of the thresholds of
+// the subquadratic algorithms
+const SIZES: &'static [usize] = &[64, 192, 640, 2048, 4000];
+// Modular exponentiation is slow to replay, so it stops earlier
+const POWM_SIZES: &'static [usize] = &[64, 192, 640, 2048];
+// Vectors for each size
+const COUNT: usize = 3;
+
+struct Gen {
+    rng: XorShiftRng,
+}
+
+impl Gen {
+    // A non-negative value of exactly `bits` bits
+    fn uint(&mut self, bits: usize) -> Mpz {
+        let top = Int::one() << (bits - 1);
+        let x = if bits > 1 { self.rng.gen_uint(bits - 1) + top } else { top };
+        Mpz::from_str_radix(&x.to_str_radix(16, false), 16).unwrap()
+    }
+
+    // A value of exactly `bits` bits, negative one time in three
+    fn int(&mut self, bits: usize) -> Mpz {
+        let x = self.uint(bits);
+        if self.rng.gen_range(0, 3) == 0 { -x } else { x }
+    }
+
+    fn small(&mut self, lo: usize, hi: usize) -> usize {
+        self.rng.gen_range(lo, hi)
+    }
+}
+
+fn hex(x: &Mpz) -> String {
+    x.to_str_radix(16)
+}
+
+fn write_vectors(dir: &Path, name: &str, fields: &str, vectors: &[Vec<String>]) -> io::Result<()> {
+    let mut f = try!(File::create(dir.join(format!("{}.txt", name))));
+    try!(writeln!(f, "# {}", fields));
+    try!(writeln!(f, "# Computed with GMP, see src/bin/gen_vectors.rs"));
+    for v in vectors {
+        try!(writeln!(f, "{}", v.join(" ")));
+    }
+    Ok(())
+}
+
+// Calls `f` with each size and the index of the vector for that size
+fn each<F: FnMut(usize, usize) -> Vec<String>>(sizes: &[usize], mut f: F) -> Vec<Vec<String>> {
+    let mut vectors = Vec::new();
+    for &bits in sizes {
+        for i in 0..COUNT {
+            vectors.push(f(bits, i));
+        }
+    }
+    vectors
+}
+
+fn generate(dir: &Path) -> io::Result<()> {
+    let mut g = Gen { rng: XorShiftRng::from_seed([0x6d70, 0x76656374, 0x6f7273, 0x72616d70]) };
+
+    let v = each(SIZES, |s, _| {
+        let (a, b) = (g.int(s), g.int(s));
+        vec![hex(&a), hex(&b), hex(&(&a + &b))]
+    });
+    try!(write_vectors(dir, "add", "a b a+b", &v));
+
+    let v = each(SIZES, |s, _| {
+        let (a, b) = (g.int(s), g.int(s));
+        vec![hex(&a), hex(&b), hex(&(&a - &b))]
+    });
+    try!(write_vectors(dir, "sub", "a b a-b", &v));
+
+    // Every other product is unbalanced
+    let v = each(SIZES, |s, i| {
+        let a = g.int(s);
+        let b = g.int(if i % 2 == 1 { s / 3 + 1 } else { s });
+        vec![hex(&a), hex(&b), hex(&(&a * &b))]
+    });
+    try!(write_vectors(dir, "mul", "a b a*b", &v));
+
+    // Quotient and remainder rounded towards zero
+    let v = each(SIZES, |s, _| {
+        let (a, b) = (g.int(2 * s), g.int(s));
+        vec![hex(&a), hex(&b), hex(&(&a / &b)), hex(&(&a % &b))]
+    });
+    try!(write_vectors(dir, "divrem", "a b a/b a%b", &v));
+
+    // Every other pair has a large common factor
+    let v = each(SIZES, |s, i| {
+        let (mut a, mut b) = (g.int(s), g.int(s));
+        if i % 2 == 1 {
+            let c = g.uint(s / 4 + 1);
+            a = &a * &c;
+            b = &b * &c;
+        }
+        vec![hex(&a), hex(&b), hex(&a.gcd(&b)), hex(&a.lcm(&b))]
+    });
+    try!(write_vectors(dir, "gcd", "a b gcd(a,b) lcm(a,b)", &v));
+
+    // Every other modulus is even
+    let v = each(POWM_SIZES, |s, i| {
+        let mut m = g.uint(s);
+        if i % 2 == 0 {
+            m = m | Mpz::one();
+        }
+        let base = g.uint(s) % &m;
+        let e = g.uint(s);
+        vec![hex(&base), hex(&e), hex(&m), hex(&base.powm(&e, &m))]
+    });
+    try!(write_vectors(dir, "powm", "b e m b^e%m", &v));
+
+    let v = each(SIZES, |s, _| {
+        let a = g.int(s / 8 + 8);
+        let k = g.small(2, 16);
+        vec![hex(&a), k.to_string(), hex(&a.pow(k as u32))]
+    });
+    try!(write_vectors(dir, "pow", "a k a^k", &v));
+
+    let v = each(SIZES, |s, _| {
+        let a = g.int(s);
+        let n = g.small(0, 200);
+        vec![hex(&a), n.to_string(), hex(&(&a << n))]
+    });
+    try!(write_vectors(dir, "shl", "a n a<<n", &v));
+
+    // Rounded towards negative infinity
+    let v = each(SIZES, |s, _| {
+        let a = g.int(s);
+        let n = g.small(0, 200);
+        vec![hex(&a), n.to_string(), hex(&(&a >> n))]
+    });
+    try!(write_vectors(dir, "shr", "a n a>>n", &v));
+
+    // On non-negative values, with operands of different lengths every
+    // other time
+    let v = each(SIZES, |s, i| {
+        let a = g.uint(s);
+        let b = g.uint(if i % 2 == 1 { s / 2 + 1 } else { s });
+        vec![hex(&a), hex(&b), hex(&(&a & &b)), hex(&(&a | &b)), hex(&(&a ^ &b))]
+    });
+    try!(write_vectors(dir, "bitops", "a b a&b a|b a^b", &v));
+
+    let v = each(SIZES, |s, _| {
+        let a = g.int(s);
+        vec![hex(&a), a.to_str_radix(10)]
+    });
+    write_vectors(dir, "decimal", "a decimal(a)", &v)
+}
+
+fn main() {
+    let dir = env::args().nth(1).unwrap_or("tests/vectors".to_string());
+    if let Err(e) = generate(Path::new(&dir)) {
+        let _ = writeln!(io::stderr(), "gen_vectors: {}: {}", dir, e);
+        process::exit(1);
+    }
+}
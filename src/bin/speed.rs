@@ -0,0 +1,1070 @@
+// Copyright 2015 The Ramp Developers
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Measures the time taken by the low-level routines, and by some `Int`
+//! operations, over a range of operand sizes, in the manner of GMP's `speed`
+//! program.
+//!
+//! ```text
+//! cargo run --release --bin speed -- -s 1-100 mul sqr
+//! cargo run --release --bin speed -- -s 10-10000*2 divrem gcd
+//! cargo run --release --bin speed -- -l
+//! ```
+//!
+//! With `--format json` or `--format csv`, a run can be saved and given to a
+//! later one with `--baseline`, which then reports the change of every
+//! measurement found in both.
+//!
+//! With the `rust-gmp` feature, `--gmp` also times the matching GMP
+//! operations, and prints how many times slower than GMP each routine is.
+//!
+//! `--crossover A B` finds the smallest size from which `B` is faster than
+//! `A`, by a sweep over the sizes followed by a bisection, and prints the
+//! threshold to use when the pair is one that `ll` picks between:
+//!
+//! ```text
+//! cargo run --release --bin speed -- --crossover mul_basecase mul_toom22
+//! ```
+//!
+//! With the `alloc-stats` feature, `--allocs` counts the allocations made
+//! by one call of each routine instead of timing it: the scratch space
+//! requested from `TmpAllocator`s, in allocations and bytes, and the
+//! allocations that went to the heap. `--thread-scratch` turns on the scratch
+//! arena of the thread, for the timings as well as for the counts.
+//!
+//! Sizes are in limbs. Each routine gets fresh random operands of the given
+//! size, which are prepared before the timing starts. The routine is then
+//! called for a warmup period, and timed over a number of samples, each of
+//! which runs it enough times to last a millisecond. The median of the
+//! samples is reported, along with their median absolute deviation from it.
+//!
+//! Times are read from the time stamp counter on x86_64 and from the virtual
+//! counter on AArch64, and from the system clock elsewhere or with
+//! `--timer ns`. The time stamp counter ticks at a constant rate, which is
+//! the nominal frequency of the processor rather than its current one, and
+//! the virtual counter ticks at a fixed rate of its own, so these only
+//! compare with other runs on the same machine.
+
+#![feature(asm)]
+
+extern crate framp as ramp;
+extern crate rand;
+#[cfg(feature = "rust-gmp")]
+extern crate gmp as gmp_crate;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use ramp::Int;
+use ramp::int::Sign;
+use ramp::int::mtgy::MtgyModulus;
+use ramp::ll;
+use ramp::ll::limb::{BaseInt, Limb};
+use ramp::ll::limb_ptr::{Limbs, LimbsMut};
+
+// Prepares the operands of a routine for a size, and returns a closure that
+// runs the routine once on them.
+type Prepare = fn(usize) -> Box<FnMut()>;
+
+struct Routine {
+    name: &'static str,
+    description: &'static str,
+    prepare: Prepare,
+}
+
+static ROUTINES: &'static [Routine] = &[
+    Routine { name: "add_n", description: "ll::add_n of two n-limb numbers", prepare: add_n },
+    Routine { name: "mul_1", description: "ll::mul_1 of an n-limb number", prepare: mul_1 },
+    Routine { name: "addmul_1", description: "ll::addmul_1 of an n-limb number", prepare: addmul_1 },
+    Routine { name: "mul", description: "ll::mul of two n-limb numbers", prepare: mul },
+    Routine { name: "sqr", description: "ll::sqr of an n-limb number", prepare: sqr },
+    Routine { name: "mullo_n", description: "ll::mullo_n of two n-limb numbers", prepare: mullo_n },
+    Routine { name: "mulhi_n", description: "ll::mulhi_n of two n-limb numbers", prepare: mulhi_n },
+    Routine { name: "mulmid", description: "ll::mulmid of 2n-1 limbs by n limbs", prepare: mulmid },
+    Routine { name: "mulmod_bnm1", description: "ll::mulmod_bnm1 of two n-limb numbers modulo B^n - 1",
+              prepare: mulmod_bnm1 },
+    Routine { name: "mul_basecase", description: "schoolbook multiplication of two n-limb numbers",
+              prepare: mul_basecase },
+    Routine { name: "mul_toom22", description: "one level of Toom-2 on two n-limb numbers, n >= 2",
+              prepare: mul_toom22 },
+    Routine { name: "mul_toom32", description: "one level of Toom-3.2 on n limbs by 2n/3 limbs, n >= 9",
+              prepare: mul_toom32 },
+    Routine { name: "mul_toom42", description: "one level of Toom-4.2 on n limbs by n/2 limbs, n >= 16",
+              prepare: mul_toom42 },
+    Routine { name: "mul_fft", description: "Schönhage-Strassen multiplication of two n-limb numbers",
+              prepare: mul_fft },
+    Routine { name: "mul_ntt", description: "small-prime NTT multiplication of two n-limb numbers",
+              prepare: mul_ntt },
+    Routine { name: "sqr_basecase", description: "schoolbook squaring of an n-limb number",
+              prepare: sqr_basecase },
+    Routine { name: "sqr_toom2", description: "one level of Toom-2 squaring of an n-limb number, n >= 2",
+              prepare: sqr_toom2 },
+    Routine { name: "sqr_toom3", description: "one level of Toom-3 squaring of an n-limb number, n >= 5",
+              prepare: sqr_toom3 },
+    Routine { name: "divrem", description: "ll::divrem of 2n limbs by n limbs", prepare: divrem },
+    Routine { name: "gcd", description: "ll::gcd of two n-limb numbers", prepare: gcd },
+    Routine { name: "modpow", description: "Int::modpow with an n-limb base, exponent and modulus",
+              prepare: modpow },
+    Routine { name: "mtgy_pow", description: "MtgyModulus::pow with an n-limb base, exponent and modulus",
+              prepare: mtgy_pow },
+    Routine { name: "to_string", description: "Int::to_string of an n-limb number", prepare: to_string },
+    Routine { name: "from_str", description: "Int::from_str of an n-limb number", prepare: from_str },
+];
+
+// Each sample lasts at least this long, in nanoseconds
+const MIN_SAMPLE: u64 = 1_000_000;
+const DEFAULT_SAMPLES: usize = 7;
+// In milliseconds
+const DEFAULT_WARMUP: u64 = 10;
+// Slowdowns from the baseline above this percentage are regressions
+const DEFAULT_THRESHOLD: f64 = 5.0;
+// Sizes swept by `--crossover` when none are given
+const CROSSOVER_SIZES: &'static str = "2-1000*1.25";
+// Two routines whose times differ by less than this fraction, or by less
+// than the sum of their deviations, are measured again with more samples, up
+// to `CROSSOVER_RETRIES` times
+const NOISE: f64 = 0.02;
+const CROSSOVER_RETRIES: usize = 3;
+
+// The pairs of routines `ll` picks between, and the threshold that is the
+// last size at which it uses the first one
+static THRESHOLDS: &'static [(&'static str, &'static str, &'static str)] = &[
+    ("mul_basecase", "mul_toom22", "TOOM22_THRESHOLD"),
+    ("sqr_basecase", "sqr_toom2", "SQR_TOOM2_THRESHOLD"),
+    ("sqr_toom2", "sqr_toom3", "SQR_TOOM3_THRESHOLD"),
+    ("mul_toom22", "mul_fft", "FFT_THRESHOLD"),
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Timer {
+    Cycles,
+    Nanos,
+}
+
+impl Timer {
+    fn unit(self) -> &'static str {
+        match self {
+            Timer::Cycles if cfg!(target_arch = "aarch64") => "ticks",
+            Timer::Cycles => "cycles",
+            Timer::Nanos => "ns",
+        }
+    }
+}
+
+// How routines are measured
+#[derive(Clone, Copy)]
+struct Timing {
+    timer: Timer,
+    samples: usize,
+    // In nanoseconds
+    warmup: u64,
+}
+
+// The time of one call of a routine, and the median absolute deviation of
+// the samples, in `unit`
+struct Measurement {
+    routine: String,
+    size: usize,
+    time: f64,
+    mad: f64,
+    unit: String,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut sizes = None;
+    let mut timing = Timing {
+        timer: if HAS_CYCLES { Timer::Cycles } else { Timer::Nanos },
+        samples: DEFAULT_SAMPLES,
+        warmup: DEFAULT_WARMUP * 1_000_000,
+    };
+    let mut format = Format::Table;
+    let mut baseline = None;
+    let mut threshold = DEFAULT_THRESHOLD;
+    let mut with_gmp = false;
+    let mut crossover = None;
+    let mut allocs = false;
+    let mut routines = Vec::new();
+
+    let mut i = 0;
+    while i < args.len() {
+        match &args[i][..] {
+            "-l" => {
+                for r in ROUTINES {
+                    println!("{:<12} {}", r.name, r.description);
+                }
+                return;
+            }
+            "-s" => {
+                i += 1;
+                sizes = Some(or_exit(args.get(i).ok_or("-s needs a value".to_string())
+                                     .and_then(|s| parse_sizes(s))));
+            }
+            "-r" => {
+                i += 1;
+                timing.samples = or_exit(args.get(i).and_then(|s| s.parse().ok())
+                                         .ok_or("-r needs a positive number".to_string()));
+            }
+            "--warmup" => {
+                i += 1;
+                let ms: u64 = or_exit(args.get(i).and_then(|s| s.parse().ok())
+                                      .ok_or("--warmup needs a number of milliseconds".to_string()));
+                timing.warmup = ms * 1_000_000;
+            }
+            "--timer" => {
+                i += 1;
+                timing.timer = match args.get(i).map(|s| &s[..]) {
+                    Some("cycles") if HAS_CYCLES => Timer::Cycles,
+                    Some("cycles") => or_exit(Err("no cycle counter on this target".to_string())),
+                    Some("ns") => Timer::Nanos,
+                    _ => or_exit(Err("--timer needs cycles or ns".to_string())),
+                };
+            }
+            "--format" => {
+                i += 1;
+                format = match args.get(i).map(|s| &s[..]) {
+                    Some("table") => Format::Table,
+                    Some("json") => Format::Json,
+                    Some("csv") => Format::Csv,
+                    _ => or_exit(Err("--format needs table, json or csv".to_string())),
+                };
+            }
+            "--baseline" => {
+                i += 1;
+                let path = or_exit(args.get(i).ok_or("--baseline needs a file".to_string()));
+                baseline = Some(or_exit(read_baseline(path)));
+            }
+            "--threshold" => {
+                i += 1;
+                threshold = or_exit(args.get(i).and_then(|s| s.parse().ok())
+                                    .ok_or("--threshold needs a percentage".to_string()));
+            }
+            "--gmp" => {
+                if !cfg!(feature = "rust-gmp") {
+                    or_exit(Err("--gmp needs the rust-gmp feature".to_string()));
+                }
+                with_gmp = true;
+            }
+            "--allocs" => {
+                if !cfg!(feature = "alloc-stats") {
+                    or_exit(Err("--allocs needs the alloc-stats feature".to_string()));
+                }
+                allocs = true;
+            }
+            "--thread-scratch" => ll::set_thread_scratch(true),
+            "--crossover" => {
+                if i + 2 >= args.len() {
+                    or_exit(Err("--crossover needs two routines".to_string()));
+                }
+                crossover = Some((or_exit(routine(&args[i + 1])), or_exit(routine(&args[i + 2]))));
+                i += 2;
+            }
+            "-h" | "--help" => {
+                usage();
+                return;
+            }
+            name => routines.push(or_exit(routine(name))),
+        }
+        i += 1;
+    }
+    if let Some((a, b)) = crossover {
+        if !routines.is_empty() || timing.samples == 0 {
+            usage();
+            process::exit(1);
+        }
+        let mut sizes = sizes.unwrap_or_else(|| parse_sizes(CROSSOVER_SIZES).unwrap());
+        sizes.sort();
+        sizes.dedup();
+        find_crossover(a, b, &sizes, &timing);
+        return;
+    }
+    if routines.is_empty() || timing.samples == 0 {
+        usage();
+        process::exit(1);
+    }
+    let sizes = sizes.unwrap_or(vec![1, 2, 4, 8, 16, 32, 64, 128]);
+    if allocs {
+        print_allocs(&sizes, &routines);
+        return;
+    }
+
+    let mut results = Vec::new();
+    for &n in &sizes {
+        for r in &routines {
+            let mut f = (r.prepare)(n);
+            let (time, mad) = measure(&mut *f, &timing);
+            results.push(Measurement {
+                routine: r.name.to_string(),
+                size: n,
+                time: time,
+                mad: mad,
+                unit: timing.timer.unit().to_string(),
+            });
+
+            let gmp = if with_gmp { gmp_routine(r.name) } else { None };
+            if let Some(prepare) = gmp {
+                let mut f = prepare(n);
+                let (time, mad) = measure(&mut *f, &timing);
+                results.push(Measurement {
+                    routine: format!("gmp_{}", r.name),
+                    size: n,
+                    time: time,
+                    mad: mad,
+                    unit: timing.timer.unit().to_string(),
+                });
+            }
+        }
+    }
+
+    match format {
+        Format::Table => {
+            print_table(&sizes, &results, &timing);
+            if with_gmp {
+                print_gmp_ratios(&sizes, &results);
+            }
+        }
+        Format::Json => print_json(&results),
+        Format::Csv => print_csv(&results),
+    }
+    if let Some(ref baseline) = baseline {
+        compare(baseline, &results, threshold);
+    }
+}
+
+fn usage() {
+    println!("usage: speed [OPTIONS] ROUTINE...");
+    println!("       speed [OPTIONS] --crossover ROUTINE ROUTINE");
+    println!("       speed -l");
+    println!("");
+    println!("  -s SIZES             sizes to measure, in limbs");
+    println!("  -r SAMPLES           number of samples per measurement (default {})", DEFAULT_SAMPLES);
+    println!("  --warmup MS          time spent calling a routine before measuring it");
+    println!("                       (default {})", DEFAULT_WARMUP);
+    println!("  --timer TIMER        cycles (default where available) or ns");
+    println!("  --format FORMAT      table (default), json or csv");
+    println!("  --baseline FILE      compare with a run saved with --format json or csv");
+    println!("  --threshold PERCENT  slowdown reported as a regression (default {})", DEFAULT_THRESHOLD);
+    println!("  --gmp                also measure the same operations with GMP, with the");
+    println!("                       rust-gmp feature");
+    println!("  --crossover A B      find the size from which B is faster than A");
+    println!("  --allocs             count allocations instead of timing, with the");
+    println!("                       alloc-stats feature");
+    println!("  --thread-scratch     take scratch space from the thread's arena");
+    println!("");
+    println!("SIZES is a comma-separated list of sizes in limbs and of ranges, like");
+    println!("1-100 (every size), 1-100+10 (steps of 10) or 1-10000*2 (doubling).");
+}
+
+fn routine(name: &str) -> Result<&'static Routine, String> {
+    ROUTINES.iter().find(|r| r.name == name).ok_or(format!("unknown routine {}, see -l", name))
+}
+
+// Sweeps the sizes for the last one at which `a` is faster than `b`, from
+// which on `b` stays faster, then bisects between it and the next size. This
+// assumes `b` keeps winning once it has started to, which holds for a pair
+// of algorithms with different complexities.
+fn find_crossover(a: &Routine, b: &Routine, sizes: &[usize], timing: &Timing) {
+    println!("{:<8} {:>14} {:>14} {:>8}    ({} per call)", "size", a.name, b.name, "ratio",
+             timing.timer.unit());
+
+    let mut wins = Vec::new();
+    for &n in sizes {
+        wins.push(b_is_faster(a, b, n, timing));
+    }
+    let lo = match wins.iter().rposition(|&w| !w) {
+        Some(i) if i + 1 == sizes.len() => {
+            println!("{} is faster at all sizes up to {}", a.name, sizes[i]);
+            return;
+        }
+        Some(i) => i,
+        None => {
+            println!("{} is faster at all sizes from {}", b.name, sizes[0]);
+            return;
+        }
+    };
+
+    println!("");
+    let (mut lo, mut hi) = (sizes[lo], sizes[lo + 1]);
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        if b_is_faster(a, b, mid, timing) {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+
+    println!("");
+    println!("{} is faster than {} from {} limbs", b.name, a.name, hi);
+    if let Some(&(_, _, name)) = THRESHOLDS.iter().find(|t| t.0 == a.name && t.1 == b.name) {
+        println!("{} = {}", name, lo);
+    }
+}
+
+// Measures both routines at a size, and tells whether `b` is the faster. A
+// difference within the noise is measured again with twice the samples.
+fn b_is_faster(a: &Routine, b: &Routine, n: usize, timing: &Timing) -> bool {
+    let mut timing = *timing;
+    let mut retries = 0;
+    loop {
+        let (ta, mad_a) = measure(&mut *(a.prepare)(n), &timing);
+        let (tb, mad_b) = measure(&mut *(b.prepare)(n), &timing);
+        let ratio = tb / ta;
+        let clear = (tb - ta).abs() > (mad_a + mad_b).max(NOISE * ta);
+        if clear || retries == CROSSOVER_RETRIES {
+            println!("{:<8} {:>14.1} {:>14.1} {:>8.3}", n, ta, tb, ratio);
+            return ratio < 1.0;
+        }
+        timing.samples *= 2;
+        retries += 1;
+    }
+}
+
+// Prints one column per routine, in the order of the results for each size.
+// The deviations are given relative to the times.
+fn print_table(sizes: &[usize], results: &[Measurement], timing: &Timing) {
+    let columns = results.iter().take_while(|m| m.size == sizes[0]).count();
+    print!("{:<8}", "size");
+    for m in &results[..columns] {
+        print!(" {:>22}", m.routine);
+    }
+    println!("    ({} per call, median and deviation of {} samples)",
+             timing.timer.unit(), timing.samples);
+    for (&n, row) in sizes.iter().zip(results.chunks(columns)) {
+        print!("{:<8}", n);
+        for m in row {
+            print!(" {:>22}", format!("{:.1} \u{b1}{:.1}%", m.time, m.mad / m.time * 100.0));
+        }
+        println!("");
+    }
+}
+
+// Prints how many times slower than GMP each routine is.
+fn print_gmp_ratios(sizes: &[usize], results: &[Measurement]) {
+    let ratio = |m: &Measurement| {
+        let gmp_name = format!("gmp_{}", m.routine);
+        results.iter().find(|g| g.size == m.size && g.routine == gmp_name).map(|g| m.time / g.time)
+    };
+    let first: Vec<&Measurement> = results.iter()
+        .filter(|m| m.size == sizes[0] && ratio(m).is_some())
+        .collect();
+
+    println!("");
+    print!("{:<8}", "size");
+    for m in &first {
+        print!(" {:>14}", format!("{}/gmp", m.routine));
+    }
+    println!("    (time relative to GMP)");
+    for &n in sizes {
+        print!("{:<8}", n);
+        for m in results.iter().filter(|m| m.size == n) {
+            if let Some(r) = ratio(m) {
+                print!(" {:>14.2}", r);
+            }
+        }
+        println!("");
+    }
+}
+
+// Prints the allocations made by one call of each routine. The first call
+// isn't counted, as it can fill the thread's scratch arena.
+#[cfg(feature = "alloc-stats")]
+fn print_allocs(sizes: &[usize], routines: &[&Routine]) {
+    println!("{:<12} {:<8} {:>14} {:>14} {:>14}",
+             "routine", "size", "scratch allocs", "scratch bytes", "heap allocs");
+    for &n in sizes {
+        for r in routines {
+            let mut f = (r.prepare)(n);
+            f();
+            ll::reset_alloc_stats();
+            f();
+            let stats = ll::alloc_stats();
+            println!("{:<12} {:<8} {:>14} {:>14} {:>14}", r.name, n,
+                     stats.scratch_allocations, stats.scratch_bytes, stats.heap_allocations);
+        }
+    }
+}
+
+// `--allocs` is refused without the feature
+#[cfg(not(feature = "alloc-stats"))]
+fn print_allocs(_: &[usize], _: &[&Routine]) {
+    unreachable!()
+}
+
+fn print_json(results: &[Measurement]) {
+    println!("[");
+    for (i, m) in results.iter().enumerate() {
+        let sep = if i + 1 < results.len() { "," } else { "" };
+        println!("  {{\"routine\": \"{}\", \"size\": {}, \"time\": {:.1}, \"mad\": {:.1}, \
+                  \"unit\": \"{}\"}}{}",
+                 m.routine, m.size, m.time, m.mad, m.unit, sep);
+    }
+    println!("]");
+}
+
+fn print_csv(results: &[Measurement]) {
+    println!("routine,size,time,mad,unit");
+    for m in results {
+        println!("{},{},{:.1},{:.1},{}", m.routine, m.size, m.time, m.mad, m.unit);
+    }
+}
+
+// Reads the output of a previous run in the json or csv format. Only the
+// exact layout written by `print_json` and `print_csv` is understood, or the
+// one of the earlier versions, with times in nanoseconds and no deviations.
+fn read_baseline(path: &str) -> Result<Vec<Measurement>, String> {
+    let mut text = String::new();
+    try!(File::open(path).and_then(|mut f| f.read_to_string(&mut text))
+         .map_err(|e| format!("cannot read {}: {}", path, e)));
+    let bad = |line: &str| format!("{}: cannot parse {:?}", path, line);
+
+    let mut ms = Vec::new();
+    for line in text.lines().map(|l| l.trim()) {
+        if line.is_empty() || line == "[" || line == "]" || line.starts_with("routine,") {
+            continue;
+        }
+        let fields: Vec<&str> = if line.starts_with('{') {
+            // {"routine": "mul", "size": 10, "time": 123.4, "mad": 0.5, "unit": "cycles"},
+            line.trim_right_matches(',').trim_matches(|c: char| c == '{' || c == '}')
+                .split(',')
+                .map(|f| f.splitn(2, ':').nth(1).unwrap_or("").trim().trim_matches('"'))
+                .collect()
+        } else {
+            line.split(',').collect()
+        };
+        let (mad, unit) = match fields.len() {
+            3 => (0.0, "ns"),
+            5 => (try!(fields[3].parse().map_err(|_| bad(line))), fields[4]),
+            _ => return Err(bad(line)),
+        };
+        ms.push(Measurement {
+            routine: fields[0].to_string(),
+            size: try!(fields[1].parse().map_err(|_| bad(line))),
+            time: try!(fields[2].parse().map_err(|_| bad(line))),
+            mad: mad,
+            unit: unit.to_string(),
+        });
+    }
+    Ok(ms)
+}
+
+// Prints the change from the baseline of every measurement found in it, to
+// stderr so the results can still be redirected to a new baseline. A change
+// only counts when it is also larger than the deviations of both times put
+// together, and measurements in another unit are skipped.
+fn compare(baseline: &[Measurement], results: &[Measurement], threshold: f64) {
+    let stderr = io::stderr();
+    let mut out = stderr.lock();
+    let mut regressions = 0;
+    let mut skipped = 0;
+
+    writeln!(out, "").unwrap();
+    writeln!(out, "{:<12} {:<8} {:>14} {:>14} {:>9}", "routine", "size", "baseline", "now", "change")
+        .unwrap();
+    for m in results {
+        let old = match baseline.iter().find(|b| b.routine == m.routine && b.size == m.size) {
+            Some(b) => b,
+            None => continue,
+        };
+        if old.unit != m.unit {
+            skipped += 1;
+            continue;
+        }
+        let change = (m.time / old.time - 1.0) * 100.0;
+        let noisy = (m.time - old.time).abs() <= m.mad + old.mad;
+        let note = if noisy {
+            ""
+        } else if change > threshold {
+            regressions += 1;
+            "  regression"
+        } else if change < -threshold {
+            "  improvement"
+        } else {
+            ""
+        };
+        writeln!(out, "{:<12} {:<8} {:>14.1} {:>14.1} {:>+8.1}%{}",
+                 m.routine, m.size, old.time, m.time, change, note).unwrap();
+    }
+    if skipped > 0 {
+        writeln!(out, "{} measurement(s) skipped, in another unit than {}", skipped,
+                 results[0].unit).unwrap();
+    }
+    writeln!(out, "{} regression(s) above {}%", regressions, threshold).unwrap();
+}
+
+fn or_exit<T>(r: Result<T, String>) -> T {
+    match r {
+        Ok(x) => x,
+        Err(e) => {
+            println!("speed: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+// Parses sizes like `4,8,100`, `1-100`, `1-100+10` or `10-10000*1.5`.
+fn parse_sizes(s: &str) -> Result<Vec<usize>, String> {
+    let bad = || format!("bad sizes: {}", s);
+    let mut sizes = Vec::new();
+    for item in s.split(',') {
+        let (range, step) = match item.find(|c: char| c == '+' || c == '*') {
+            Some(i) => (&item[..i], Some((&item[i..i + 1], &item[i + 1..]))),
+            None => (item, None),
+        };
+        let mut bounds = range.splitn(2, '-');
+        let lo: usize = try!(bounds.next().unwrap().parse().map_err(|_| bad()));
+        let hi: usize = match bounds.next() {
+            Some(h) => try!(h.parse().map_err(|_| bad())),
+            None => lo,
+        };
+        if lo == 0 || hi < lo {
+            return Err(bad());
+        }
+
+        let mut n = lo;
+        while n <= hi {
+            sizes.push(n);
+            n = match step {
+                None => n + 1,
+                Some(("+", v)) => n + try!(v.parse::<usize>().map_err(|_| bad())),
+                Some((_, v)) => {
+                    let f: f64 = try!(v.parse().map_err(|_| bad()));
+                    std::cmp::max(n + 1, (n as f64 * f) as usize)
+                }
+            };
+        }
+    }
+    Ok(sizes)
+}
+
+// Returns the median time of one call of `f` over the samples, and their
+// median absolute deviation from it, in the unit of the timer.
+fn measure(f: &mut FnMut(), timing: &Timing) -> (f64, f64) {
+    // Gets the caches, the branch predictors and the clock frequency ready
+    let start = Instant::now();
+    f();
+    while nanos(start.elapsed()) < timing.warmup {
+        f();
+    }
+
+    // Enough calls for a sample to last `MIN_SAMPLE` nanoseconds
+    let mut calls = 1;
+    while nanos(time(f, calls)) < MIN_SAMPLE {
+        calls *= 2;
+    }
+
+    let mut times: Vec<f64> = (0..timing.samples)
+        .map(|_| ticks(f, calls, timing.timer) as f64 / calls as f64)
+        .collect();
+    let mid = median(&mut times);
+    let mut deviations: Vec<f64> = times.iter().map(|t| (t - mid).abs()).collect();
+    (mid, median(&mut deviations))
+}
+
+fn median(v: &mut [f64]) -> f64 {
+    v.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    v[v.len() / 2]
+}
+
+// The time taken by `calls` calls of `f`, in the unit of `timer`
+fn ticks(f: &mut FnMut(), calls: usize, timer: Timer) -> u64 {
+    match timer {
+        Timer::Nanos => nanos(time(f, calls)),
+        Timer::Cycles => {
+            let start = cycles();
+            for _ in 0..calls {
+                f();
+            }
+            cycles().wrapping_sub(start)
+        }
+    }
+}
+
+fn time(f: &mut FnMut(), calls: usize) -> Duration {
+    let start = Instant::now();
+    for _ in 0..calls {
+        f();
+    }
+    start.elapsed()
+}
+
+fn nanos(d: Duration) -> u64 {
+    d.as_secs() * 1_000_000_000 + d.subsec_nanos() as u64
+}
+
+const HAS_CYCLES: bool = cfg!(any(target_arch = "x86_64", target_arch = "aarch64"));
+
+#[cfg(target_arch = "x86_64")]
+fn cycles() -> u64 {
+    let (lo, hi): (u32, u32);
+    unsafe {
+        asm!("rdtsc" : "={eax}"(lo), "={edx}"(hi) : : : "volatile");
+    }
+    (hi as u64) << 32 | lo as u64
+}
+
+// The isb keeps the read from being moved around the code being timed
+#[cfg(target_arch = "aarch64")]
+fn cycles() -> u64 {
+    let t: u64;
+    unsafe {
+        asm!("isb; mrs $0, cntvct_el0" : "=r"(t) : : "memory" : "volatile");
+    }
+    t
+}
+
+// Never called, `--timer cycles` is refused
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn cycles() -> u64 {
+    unreachable!()
+}
+
+// `n` random limbs, the highest of which is not zero.
+fn random_limbs(n: usize) -> Vec<Limb> {
+    let mut rng = rand::thread_rng();
+    let mut v: Vec<Limb> = (0..n).map(|_| Limb(rng.gen::<BaseInt>())).collect();
+    if let Some(top) = v.last_mut() {
+        *top = *top | Limb(1);
+    }
+    v
+}
+
+fn random_int(n: usize) -> Int {
+    Int::from_limbs(Sign::Positive, &random_limbs(n))
+}
+
+fn limbs(v: &[Limb]) -> Limbs {
+    unsafe { Limbs::new(v.as_ptr(), 0, v.len()) }
+}
+
+fn limbs_mut(v: &mut [Limb]) -> LimbsMut {
+    unsafe { LimbsMut::new(v.as_mut_ptr(), 0, v.len()) }
+}
+
+fn add_n(n: usize) -> Box<FnMut()> {
+    let (x, y) = (random_limbs(n), random_limbs(n));
+    let mut w = vec![Limb(0); n];
+    Box::new(move || unsafe {
+        ll::add_n(limbs_mut(&mut w), limbs(&x), limbs(&y), n);
+    })
+}
+
+fn mul_1(n: usize) -> Box<FnMut()> {
+    let x = random_limbs(n);
+    let v = random_limbs(1)[0];
+    let mut w = vec![Limb(0); n];
+    Box::new(move || unsafe {
+        ll::mul_1(limbs_mut(&mut w), limbs(&x), n, v);
+    })
+}
+
+fn addmul_1(n: usize) -> Box<FnMut()> {
+    let x = random_limbs(n);
+    let v = random_limbs(1)[0];
+    let mut w = random_limbs(n);
+    Box::new(move || unsafe {
+        ll::addmul_1(limbs_mut(&mut w), limbs(&x), n, v);
+    })
+}
+
+fn mul(n: usize) -> Box<FnMut()> {
+    let (x, y) = (random_limbs(n), random_limbs(n));
+    let mut w = vec![Limb(0); 2 * n];
+    Box::new(move || unsafe {
+        ll::mul(limbs_mut(&mut w), limbs(&x), n, limbs(&y), n);
+    })
+}
+
+fn sqr(n: usize) -> Box<FnMut()> {
+    let x = random_limbs(n);
+    let mut w = vec![Limb(0); 2 * n];
+    Box::new(move || unsafe {
+        ll::sqr(limbs_mut(&mut w), limbs(&x), n);
+    })
+}
+
+fn mullo_n(n: usize) -> Box<FnMut()> {
+    let (x, y) = (random_limbs(n), random_limbs(n));
+    let mut w = vec![Limb(0); n];
+    Box::new(move || unsafe {
+        ll::mullo_n(limbs_mut(&mut w), limbs(&x), limbs(&y), n);
+    })
+}
+
+fn mulhi_n(n: usize) -> Box<FnMut()> {
+    let (x, y) = (random_limbs(n), random_limbs(n));
+    let mut w = vec![Limb(0); n];
+    Box::new(move || unsafe {
+        ll::mulhi_n(limbs_mut(&mut w), limbs(&x), limbs(&y), n);
+    })
+}
+
+fn mulmid(n: usize) -> Box<FnMut()> {
+    let (x, y) = (random_limbs(2 * n - 1), random_limbs(n));
+    let mut w = vec![Limb(0); n + 2];
+    Box::new(move || unsafe {
+        ll::mulmid(limbs_mut(&mut w), limbs(&x), 2 * n - 1, limbs(&y), n);
+    })
+}
+
+fn mulmod_bnm1(n: usize) -> Box<FnMut()> {
+    let (x, y) = (random_limbs(n), random_limbs(n));
+    let mut w = vec![Limb(0); n];
+    Box::new(move || unsafe {
+        ll::mulmod_bnm1(limbs_mut(&mut w), n, limbs(&x), n, limbs(&y), n);
+    })
+}
+
+fn mul_basecase(n: usize) -> Box<FnMut()> {
+    let (x, y) = (random_limbs(n), random_limbs(n));
+    let mut w = vec![Limb(0); 2 * n];
+    Box::new(move || unsafe {
+        ll::mul_basecase(limbs_mut(&mut w), limbs(&x), n, limbs(&y), n);
+    })
+}
+
+// Only the top level uses Toom-2, the smaller products go through the same
+// choice of algorithm as `ll::mul`.
+fn mul_toom22(n: usize) -> Box<FnMut()> {
+    assert!(n >= 2, "mul_toom22 needs at least 2 limbs");
+    let (x, y) = (random_limbs(n), random_limbs(n));
+    let mut w = vec![Limb(0); 2 * n];
+    let mut scratch = vec![Limb(0); 2 * n];
+    Box::new(move || unsafe {
+        ll::mul_toom22(limbs_mut(&mut w), limbs(&x), n, limbs(&y), n, limbs_mut(&mut scratch));
+    })
+}
+
+fn mul_toom32(n: usize) -> Box<FnMut()> {
+    assert!(n >= 9, "mul_toom32 needs at least 9 limbs");
+    let m = n * 2 / 3;
+    let (x, y) = (random_limbs(n), random_limbs(m));
+    let mut w = vec![Limb(0); n + m];
+    Box::new(move || unsafe {
+        ll::mul_toom32(limbs_mut(&mut w), limbs(&x), n, limbs(&y), m);
+    })
+}
+
+fn mul_toom42(n: usize) -> Box<FnMut()> {
+    assert!(n >= 16, "mul_toom42 needs at least 16 limbs");
+    let m = n / 2;
+    let (x, y) = (random_limbs(n), random_limbs(m));
+    let mut w = vec![Limb(0); n + m];
+    Box::new(move || unsafe {
+        ll::mul_toom42(limbs_mut(&mut w), limbs(&x), n, limbs(&y), m);
+    })
+}
+
+fn mul_fft(n: usize) -> Box<FnMut()> {
+    let (x, y) = (random_limbs(n), random_limbs(n));
+    let mut w = vec![Limb(0); 2 * n];
+    Box::new(move || unsafe {
+        ll::mul_fft(limbs_mut(&mut w), limbs(&x), n, limbs(&y), n);
+    })
+}
+
+fn mul_ntt(n: usize) -> Box<FnMut()> {
+    let (x, y) = (random_limbs(n), random_limbs(n));
+    let mut w = vec![Limb(0); 2 * n];
+    Box::new(move || unsafe {
+        ll::mul_ntt(limbs_mut(&mut w), limbs(&x), n, limbs(&y), n);
+    })
+}
+
+fn sqr_basecase(n: usize) -> Box<FnMut()> {
+    let x = random_limbs(n);
+    let mut w = vec![Limb(0); 2 * n];
+    Box::new(move || unsafe {
+        ll::sqr_basecase(limbs_mut(&mut w), limbs(&x), n);
+    })
+}
+
+fn sqr_toom2(n: usize) -> Box<FnMut()> {
+    assert!(n >= 2, "sqr_toom2 needs at least 2 limbs");
+    let x = random_limbs(n);
+    let mut w = vec![Limb(0); 2 * n];
+    let mut scratch = vec![Limb(0); 2 * n];
+    Box::new(move || unsafe {
+        ll::sqr_toom2(limbs_mut(&mut w), limbs(&x), n, limbs_mut(&mut scratch));
+    })
+}
+
+fn sqr_toom3(n: usize) -> Box<FnMut()> {
+    assert!(n >= 5, "sqr_toom3 needs at least 5 limbs");
+    let x = random_limbs(n);
+    let mut w = vec![Limb(0); 2 * n];
+    Box::new(move || unsafe {
+        ll::sqr_toom3(limbs_mut(&mut w), limbs(&x), n);
+    })
+}
+
+fn divrem(n: usize) -> Box<FnMut()> {
+    let (num, d) = (random_limbs(2 * n), random_limbs(n));
+    let mut q = vec![Limb(0); n + 1];
+    let mut r = vec![Limb(0); n];
+    Box::new(move || unsafe {
+        ll::divrem(limbs_mut(&mut q), limbs_mut(&mut r), limbs(&num), 2 * n, limbs(&d), n);
+    })
+}
+
+// `ll::gcd` destroys its inputs, so they are copied first, which takes a
+// negligible time next to the gcd itself.
+fn gcd(n: usize) -> Box<FnMut()> {
+    let (a, b) = (random_limbs(n), random_limbs(n));
+    let (mut ta, mut tb) = (a.clone(), b.clone());
+    let mut g = vec![Limb(0); n];
+    Box::new(move || unsafe {
+        ta.copy_from_slice(&a);
+        tb.copy_from_slice(&b);
+        ll::gcd(limbs_mut(&mut g), limbs_mut(&mut ta), n, limbs_mut(&mut tb), n);
+    })
+}
+
+fn modpow(n: usize) -> Box<FnMut()> {
+    let m = random_int(n) | Int::one();
+    let b = random_int(n) % &m;
+    let e = random_int(n);
+    Box::new(move || {
+        b.modpow(&e, &m);
+    })
+}
+
+fn mtgy_pow(n: usize) -> Box<FnMut()> {
+    // The modulus has to outlive the closure holding the `MtgyModulus`
+    // borrowing it, so it is leaked.
+    let m: &'static Int = unsafe { &*Box::into_raw(Box::new(random_int(n) | Int::one())) };
+    let mtgy = MtgyModulus::new(m);
+    let b = mtgy.to_mtgy(&(random_int(n) % m));
+    let e = random_int(n);
+    Box::new(move || {
+        mtgy.pow(&b, &e);
+    })
+}
+
+fn to_string(n: usize) -> Box<FnMut()> {
+    let x = random_int(n);
+    Box::new(move || {
+        x.to_string();
+    })
+}
+
+fn from_str(n: usize) -> Box<FnMut()> {
+    let s = random_int(n).to_string();
+    Box::new(move || {
+        s.parse::<Int>().unwrap();
+    })
+}
+
+#[cfg(not(feature = "rust-gmp"))]
+fn gmp_routine(_: &str) -> Option<Prepare> {
+    None
+}
+
+// The GMP operation matching a routine, if there is one. GMP is only used
+// through `Mpz`, so it has a little more overhead than the low-level routines
+// of ramp, which doesn't matter except for the smallest sizes.
+#[cfg(feature = "rust-gmp")]
+fn gmp_routine(name: &str) -> Option<Prepare> {
+    let prepare: Prepare = match name {
+        "mul" => gmp::mul,
+        "sqr" => gmp::sqr,
+        "divrem" => gmp::divrem,
+        "gcd" => gmp::gcd,
+        "modpow" | "mtgy_pow" => gmp::modpow,
+        "to_string" => gmp::to_string,
+        "from_str" => gmp::from_str,
+        _ => return None,
+    };
+    Some(prepare)
+}
+
+#[cfg(feature = "rust-gmp")]
+mod gmp {
+    use gmp_crate::mpz::Mpz;
+
+    use ramp::Int;
+    use super::random_int;
+
+    fn to_mpz(x: &Int) -> Mpz {
+        Mpz::from_str_radix(&x.to_str_radix(16, false), 16).unwrap()
+    }
+
+    fn random_mpz(n: usize) -> Mpz {
+        to_mpz(&random_int(n))
+    }
+
+    pub fn mul(n: usize) -> Box<FnMut()> {
+        let (x, y) = (random_mpz(n), random_mpz(n));
+        Box::new(move || {
+            &x * &y;
+        })
+    }
+
+    pub fn sqr(n: usize) -> Box<FnMut()> {
+        let x = random_mpz(n);
+        Box::new(move || {
+            &x * &x;
+        })
+    }
+
+    // Only the quotient, which is most of the work
+    pub fn divrem(n: usize) -> Box<FnMut()> {
+        let (num, d) = (random_mpz(2 * n), random_mpz(n));
+        Box::new(move || {
+            &num / &d;
+        })
+    }
+
+    pub fn gcd(n: usize) -> Box<FnMut()> {
+        let (a, b) = (random_mpz(n), random_mpz(n));
+        Box::new(move || {
+            a.gcd(&b);
+        })
+    }
+
+    pub fn modpow(n: usize) -> Box<FnMut()> {
+        let m = random_int(n) | Int::one();
+        let b = to_mpz(&(random_int(n) % &m));
+        let (m, e) = (to_mpz(&m), random_mpz(n));
+        Box::new(move || {
+            b.powm(&e, &m);
+        })
+    }
+
+    pub fn to_string(n: usize) -> Box<FnMut()> {
+        let x = random_mpz(n);
+        Box::new(move || {
+            x.to_str_radix(10);
+        })
+    }
+
+    pub fn from_str(n: usize) -> Box<FnMut()> {
+        let s = random_int(n).to_string();
+        Box::new(move || {
+            Mpz::from_str_radix(&s, 10).unwrap();
+        })
+    }
+}
@@ -0,0 +1,243 @@
+// Copyright 2015 The Ramp Developers
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Allocation contexts for batch computations.
+//!
+//! Multiplication, division and exponentiation of large numbers need scratch
+//! space, which is normally allocated from the heap and freed again by every
+//! operation. A `Context` keeps that scratch space in an arena that is reused
+//! from one operation to the next, and keeps a pool of `Int`s whose buffers
+//! are recycled instead of freed.
+
+use std::cell::{RefCell, UnsafeCell};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use int::Int;
+use mem::{self, Arena};
+use traits::Assign;
+
+/// An arena for temporaries and a pool of reusable `Int`s.
+///
+/// Operations run inside `enter` take their scratch space from the arena,
+/// which is released in bulk and kept for the next operation, so a loop doing
+/// the same kind of work stops allocating scratch space after the first
+/// iteration. `Int`s obtained from `int` return their buffer to the context
+/// when dropped.
+///
+/// # Examples
+///
+/// ```rust
+/// use framp::{Context, Int};
+///
+/// let ctx = Context::new();
+/// let x = Int::from(3).pow(5000);
+/// let sum = ctx.enter(|| {
+///     let mut acc = ctx.int();
+///     for i in 0..10 {
+///         let t = &x * &(&x + i);
+///         *acc += &t;
+///     }
+///     acc.into_int()
+/// });
+/// assert_eq!(sum, (0..10).map(|i| &x * &(&x + i)).sum::<Int>());
+/// ```
+pub struct Context {
+    arena: UnsafeCell<Arena>,
+    pool: RefCell<Vec<Int>>,
+}
+
+impl Context {
+    /// Creates a new context. Nothing is allocated until it is used.
+    pub fn new() -> Context {
+        Context::with_scratch_capacity(0)
+    }
+
+    /// Creates a new context with `bytes` bytes of scratch space allocated
+    /// up front.
+    pub fn with_scratch_capacity(bytes: usize) -> Context {
+        Context {
+            arena: UnsafeCell::new(Arena::new(bytes)),
+            pool: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Runs `f` with this context's arena providing the scratch space for all
+    /// operations done by `f` on the current thread.
+    ///
+    /// Calls may be nested, and the previous arena (or the heap) is restored
+    /// when `f` returns or panics.
+    pub fn enter<F, R>(&self, f: F) -> R
+        where F: FnOnce() -> R
+    {
+        struct Restore(*mut Arena);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                unsafe {
+                    mem::set_current_arena(self.0);
+                }
+            }
+        }
+
+        // The arena is only reached through the thread-local pointer, by
+        // `TmpAllocator`s that are dropped before `f` returns.
+        let _restore = Restore(unsafe { mem::set_current_arena(self.arena.get()) });
+        f()
+    }
+
+    /// Returns a zero-valued `Int` whose buffer comes from the context's pool
+    /// and goes back to it when dropped.
+    pub fn int(&self) -> PooledInt {
+        let i = self.pool.borrow_mut().pop().unwrap_or_else(Int::zero);
+        PooledInt {
+            ctx: self,
+            int: Some(i),
+        }
+    }
+
+    /// Returns the number of bytes of scratch space held by the arena.
+    pub fn scratch_capacity(&self) -> usize {
+        unsafe { (*self.arena.get()).capacity() }
+    }
+
+    /// Returns the number of `Int`s waiting in the pool.
+    pub fn pooled(&self) -> usize {
+        self.pool.borrow().len()
+    }
+
+    /// Frees the scratch space and all the pooled `Int`s.
+    pub fn clear(&mut self) {
+        unsafe {
+            *self.arena.get() = Arena::new(0);
+        }
+        self.pool.borrow_mut().clear();
+    }
+}
+
+impl Default for Context {
+    fn default() -> Context {
+        Context::new()
+    }
+}
+
+/// An `Int` borrowed from a `Context`, see `Context::int`.
+pub struct PooledInt<'a> {
+    ctx: &'a Context,
+    // Only `None` once the value has been taken out by `into_int`
+    int: Option<Int>,
+}
+
+impl<'a> PooledInt<'a> {
+    /// Takes the value out, so it is no longer returned to the pool.
+    pub fn into_int(mut self) -> Int {
+        self.int.take().unwrap()
+    }
+}
+
+impl<'a> Deref for PooledInt<'a> {
+    type Target = Int;
+
+    fn deref(&self) -> &Int {
+        self.int.as_ref().unwrap()
+    }
+}
+
+impl<'a> DerefMut for PooledInt<'a> {
+    fn deref_mut(&mut self) -> &mut Int {
+        self.int.as_mut().unwrap()
+    }
+}
+
+impl<'a> Drop for PooledInt<'a> {
+    fn drop(&mut self) {
+        if let Some(mut i) = self.int.take() {
+            i.assign(0);
+            self.ctx.pool.borrow_mut().push(i);
+        }
+    }
+}
+
+impl<'a> fmt::Display for PooledInt<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<'a> fmt::Debug for PooledInt<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[test]
+fn scratch_is_reused() {
+    let ctx = Context::new();
+    let x = Int::from(7).pow(20000);
+    let y = Int::from(11).pow(17000) + 1;
+    let expected = &x * &y;
+
+    let first = ctx.enter(|| &x * &y);
+    assert_eq!(first, expected);
+    let cap = ctx.scratch_capacity();
+
+    for _ in 0..5 {
+        let (q, r) = ctx.enter(|| expected.divmod(&y));
+        assert_eq!(q, x);
+        assert_eq!(r, 0);
+        assert_eq!(ctx.enter(|| &x * &y), expected);
+    }
+    // Repeating the same operations must not grow the arena without bound
+    assert!(ctx.scratch_capacity() <= 4 * ::std::cmp::max(cap, 1 << 20));
+
+    // Outside of `enter`, the heap is used again
+    assert_eq!(&x * &y, expected);
+}
+
+#[test]
+fn pooled_ints() {
+    let ctx = Context::new();
+    {
+        let mut a = ctx.int();
+        a.assign(Int::from(1) << 1000);
+        assert_eq!(*a, Int::from(1) << 1000);
+    }
+    assert_eq!(ctx.pooled(), 1);
+
+    let b = ctx.int();
+    assert_eq!(*b, 0);
+    assert!(b.capacity() >= 16);
+    assert_eq!(ctx.pooled(), 0);
+
+    let c = b.into_int();
+    assert_eq!(c, 0);
+    assert_eq!(ctx.pooled(), 0);
+}
+
+#[test]
+fn nested_enter() {
+    let outer = Context::new();
+    let inner = Context::new();
+    let x = Int::from(5).pow(30000);
+    let r = outer.enter(|| {
+        let a = &x * &x;
+        let b = inner.enter(|| &a * &x);
+        let c = &a * &a;
+        (b, c)
+    });
+    assert_eq!(r.0, x.pow(3));
+    assert_eq!(r.1, x.pow(4));
+    assert!(outer.scratch_capacity() > 0);
+    assert!(inner.scratch_capacity() > 0);
+}
@@ -0,0 +1,404 @@
+// Copyright 2015 The Ramp Developers
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Integers stored in decimal.
+//!
+//! Converting an `Int` to or from a decimal string costs time quadratic in the
+//! number of digits. `DecInt` stores its value in base 10<sup>19</sup>
+//! instead, which makes those conversions linear. It is meant for values that
+//! are mostly parsed and printed, with only occasional arithmetic: addition
+//! and subtraction are done directly, everything else goes through `Int`.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Sub, Mul, Neg};
+use std::str::FromStr;
+
+use int::{Int, ParseIntError, ErrorKind};
+
+// The base of the representation and the number of decimal digits in it
+const BASE: u64 = 10_000_000_000_000_000_000;
+const BASE_DIGITS: usize = 19;
+
+/// An arbitrary-precision signed integer stored in base 10<sup>19</sup>.
+///
+/// # Examples
+///
+/// ```rust
+/// use framp::Int;
+/// use framp::dec::DecInt;
+///
+/// let a: DecInt = "123456789012345678901234567890".parse().unwrap();
+/// let b = DecInt::from(&Int::from(-10));
+/// assert_eq!((&a + &b).to_string(), "123456789012345678901234567880");
+/// assert_eq!(Int::from(&(&a * &b)), Int::from(&a) * -10);
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct DecInt {
+    neg: bool,
+    // Least-significant chunk first, without high zero chunks. Zero is empty and not negative.
+    chunks: Vec<u64>,
+}
+
+impl DecInt {
+    /// Returns a zero-valued `DecInt`.
+    pub fn zero() -> DecInt {
+        DecInt { neg: false, chunks: Vec::new() }
+    }
+
+    /// Returns the sign of the number as either -1, 0 or 1.
+    pub fn sign(&self) -> i32 {
+        if self.chunks.is_empty() {
+            0
+        } else if self.neg {
+            -1
+        } else {
+            1
+        }
+    }
+
+    /// Returns true if the number is zero.
+    pub fn is_zero(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Returns the number of decimal digits in the absolute value, one for zero.
+    pub fn num_digits(&self) -> usize {
+        match self.chunks.last() {
+            None => 1,
+            Some(&top) => {
+                let mut n = 1;
+                let mut t = top;
+                while t >= 10 {
+                    t /= 10;
+                    n += 1;
+                }
+                n + (self.chunks.len() - 1) * BASE_DIGITS
+            }
+        }
+    }
+
+    fn normalize(&mut self) {
+        while self.chunks.last() == Some(&0) {
+            self.chunks.pop();
+        }
+        if self.chunks.is_empty() {
+            self.neg = false;
+        }
+    }
+
+    fn cmp_abs(&self, other: &DecInt) -> Ordering {
+        if self.chunks.len() != other.chunks.len() {
+            return self.chunks.len().cmp(&other.chunks.len());
+        }
+        for (a, b) in self.chunks.iter().rev().zip(other.chunks.iter().rev()) {
+            if a != b {
+                return a.cmp(b);
+            }
+        }
+        Ordering::Equal
+    }
+
+    // Adds or subtracts the magnitude of `other` to or from the magnitude of self
+    fn add_signed(&mut self, other: &DecInt, other_neg: bool) {
+        if self.neg == other_neg || self.is_zero() {
+            if self.is_zero() {
+                self.neg = other_neg;
+            }
+            add_abs(&mut self.chunks, &other.chunks);
+        } else if self.cmp_abs(other) != Ordering::Less {
+            sub_abs(&mut self.chunks, &other.chunks);
+        } else {
+            let mut r = other.chunks.clone();
+            sub_abs(&mut r, &self.chunks);
+            self.chunks = r;
+            self.neg = other_neg;
+        }
+        self.normalize();
+    }
+}
+
+// Adds b to a, in place
+fn add_abs(a: &mut Vec<u64>, b: &[u64]) {
+    if a.len() < b.len() {
+        a.resize(b.len(), 0);
+    }
+
+    let mut carry = 0;
+    for i in 0..a.len() {
+        if i >= b.len() && carry == 0 {
+            break;
+        }
+
+        // `a[i] + carry` is at most BASE, but adding `b[i]` can go past BASE
+        // and even past `u64::MAX`, as `2 * BASE` doesn't fit. Either way
+        // BASE is taken off, wrapping back into range, and the carry goes to
+        // the next chunk, or to a new top chunk after the loop.
+        let mut s = a[i] + carry;
+        let (t, o) = s.overflowing_add(if i < b.len() { b[i] } else { 0 });
+        s = t;
+        if o || s >= BASE {
+            s = s.wrapping_sub(BASE);
+            carry = 1;
+        } else {
+            carry = 0;
+        }
+        a[i] = s;
+    }
+
+    if carry != 0 {
+        a.push(carry);
+    }
+}
+
+// Subtracts b from a, in place. The magnitude of a must be at least that of b.
+fn sub_abs(a: &mut Vec<u64>, b: &[u64]) {
+    let mut borrow = 0;
+    for i in 0..a.len() {
+        if i >= b.len() && borrow == 0 {
+            break;
+        }
+
+        let sub = (if i < b.len() { b[i] } else { 0 }) + borrow;
+        if a[i] >= sub {
+            a[i] -= sub;
+            borrow = 0;
+        } else {
+            a[i] = a[i] + (BASE - sub);
+            borrow = 1;
+        }
+    }
+    debug_assert!(borrow == 0);
+}
+
+impl FromStr for DecInt {
+    type Err = ParseIntError;
+
+    fn from_str(src: &str) -> Result<DecInt, ParseIntError> {
+        let bytes = src.as_bytes();
+        let mut pos = 0;
+
+        let mut neg = false;
+        if bytes.len() > 0 && bytes[0] == b'-' {
+            neg = true;
+            pos = 1;
+        }
+
+        if bytes.len() == pos {
+            return Err(ParseIntError::new(ErrorKind::Empty, pos));
+        }
+
+        if let Some(off) = bytes[pos..].iter().position(|&c| c < b'0' || c > b'9') {
+            return Err(ParseIntError::new(ErrorKind::InvalidDigit, pos + off));
+        }
+
+        let digits = &bytes[pos..];
+        let mut chunks = Vec::with_capacity(digits.len() / BASE_DIGITS + 1);
+        let mut end = digits.len();
+        while end > 0 {
+            let start = if end > BASE_DIGITS { end - BASE_DIGITS } else { 0 };
+            let chunk = digits[start..end].iter().fold(0, |acc, &c| acc * 10 + (c - b'0') as u64);
+            chunks.push(chunk);
+            end = start;
+        }
+
+        let mut d = DecInt { neg: neg, chunks: chunks };
+        d.normalize();
+        Ok(d)
+    }
+}
+
+impl fmt::Display for DecInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut s = String::with_capacity(self.num_digits());
+        match self.chunks.last() {
+            None => s.push('0'),
+            Some(top) => {
+                s.push_str(&top.to_string());
+                for c in self.chunks.iter().rev().skip(1) {
+                    s.push_str(&format!("{:019}", c));
+                }
+            }
+        }
+
+        f.pad_integral(!self.neg, "", &s)
+    }
+}
+
+impl fmt::Debug for DecInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl<'a> From<&'a Int> for DecInt {
+    fn from(i: &'a Int) -> DecInt {
+        i.to_str_radix(10, false).parse().unwrap()
+    }
+}
+
+impl From<Int> for DecInt {
+    fn from(i: Int) -> DecInt {
+        DecInt::from(&i)
+    }
+}
+
+impl<'a> From<&'a DecInt> for Int {
+    fn from(d: &'a DecInt) -> Int {
+        let r = chunks_to_int(&d.chunks);
+        if d.neg { -r } else { r }
+    }
+}
+
+// Below this many chunks, the value is built one chunk at a time
+const SPLIT_CHUNKS: usize = 32;
+
+// The value of `chunks`, least-significant first. Short runs are multiplied
+// and added chunk by chunk, longer ones are split in halves whose values are
+// combined with one product, so that fast multiplication makes the whole
+// conversion subquadratic.
+fn chunks_to_int(chunks: &[u64]) -> Int {
+    if chunks.len() <= SPLIT_CHUNKS {
+        let mut r = Int::zero();
+        for &c in chunks.iter().rev() {
+            r *= BASE;
+            r += c;
+        }
+        return r;
+    }
+    let half = chunks.len() / 2;
+    let (lo, hi) = chunks.split_at(half);
+    chunks_to_int(hi) * Int::from(BASE).pow(half) + chunks_to_int(lo)
+}
+
+impl From<DecInt> for Int {
+    fn from(d: DecInt) -> Int {
+        Int::from(&d)
+    }
+}
+
+impl PartialOrd for DecInt {
+    fn partial_cmp(&self, other: &DecInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DecInt {
+    fn cmp(&self, other: &DecInt) -> Ordering {
+        match (self.neg, other.neg) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => self.cmp_abs(other),
+            (true, true) => other.cmp_abs(self),
+        }
+    }
+}
+
+impl Neg for DecInt {
+    type Output = DecInt;
+
+    fn neg(mut self) -> DecInt {
+        if !self.is_zero() {
+            self.neg = !self.neg;
+        }
+        self
+    }
+}
+
+impl<'a, 'b> Add<&'a DecInt> for &'b DecInt {
+    type Output = DecInt;
+
+    fn add(self, other: &'a DecInt) -> DecInt {
+        let mut r = self.clone();
+        r.add_signed(other, other.neg);
+        r
+    }
+}
+
+impl<'a> Add<&'a DecInt> for DecInt {
+    type Output = DecInt;
+
+    fn add(mut self, other: &'a DecInt) -> DecInt {
+        self.add_signed(other, other.neg);
+        self
+    }
+}
+
+impl<'a, 'b> Sub<&'a DecInt> for &'b DecInt {
+    type Output = DecInt;
+
+    fn sub(self, other: &'a DecInt) -> DecInt {
+        let mut r = self.clone();
+        r.add_signed(other, !other.neg && !other.is_zero());
+        r
+    }
+}
+
+impl<'a> Sub<&'a DecInt> for DecInt {
+    type Output = DecInt;
+
+    fn sub(mut self, other: &'a DecInt) -> DecInt {
+        self.add_signed(other, !other.neg && !other.is_zero());
+        self
+    }
+}
+
+impl<'a, 'b> Mul<&'a DecInt> for &'b DecInt {
+    type Output = DecInt;
+
+    fn mul(self, other: &'a DecInt) -> DecInt {
+        DecInt::from(Int::from(self) * Int::from(other))
+    }
+}
+
+#[test]
+fn parse_print() {
+    let cases = ["0", "-1", "9999999999999999999", "10000000000000000000",
+                 "-123456789012345678901234567890123456789012345678901234567890"];
+    for s in cases.iter() {
+        let d: DecInt = s.parse().unwrap();
+        assert_eq!(&d.to_string(), s);
+        assert_eq!(d.num_digits(), s.trim_left_matches('-').len());
+        assert_eq!(Int::from(&d).to_string(), d.to_string());
+    }
+
+    // Long enough to be split when converted
+    let s = format!("-{}", "9876543210".repeat(200));
+    let d: DecInt = s.parse().unwrap();
+    assert_eq!(Int::from(&d), s.parse::<Int>().unwrap());
+
+    let d: DecInt = "-000".parse().unwrap();
+    assert_eq!(d.sign(), 0);
+    assert_eq!(d.to_string(), "0");
+    assert!("12x".parse::<DecInt>().is_err());
+}
+
+#[test]
+fn add_sub() {
+    let cases = [
+        ("9999999999999999999", "1"),
+        ("-10000000000000000000", "1"),
+        ("123456789012345678901234567890", "-123456789012345678901234567891"),
+        ("0", "-5"),
+        ("-99999999999999999999999999999999999999", "-1"),
+    ];
+    for &(a, b) in cases.iter() {
+        let (da, db): (DecInt, DecInt) = (a.parse().unwrap(), b.parse().unwrap());
+        let (ia, ib): (Int, Int) = (a.parse().unwrap(), b.parse().unwrap());
+        assert_eq!((&da + &db).to_string(), (&ia + &ib).to_string());
+        assert_eq!((&da - &db).to_string(), (&ia - &ib).to_string());
+        assert_eq!((&db - &da).to_string(), (&ib - &ia).to_string());
+        assert_eq!(da.cmp(&db), ia.cmp(&ib));
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright 2015 The Ramp Developers
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Deferred operations.
+//!
+//! The operators on `&Int` always allocate a new `Int` for their result. The
+//! `*_ref` methods on `Int` instead return a lightweight value that only
+//! records the operands. It can then be evaluated into an existing `Int` with
+//! `Assign`, reusing its storage, or turned into a new `Int` with `From`.
+//!
+//! ```rust
+//! use framp::Int;
+//! use framp::traits::Assign;
+//!
+//! let a = Int::from(12345);
+//! let b = Int::from(-678);
+//! let mut r = Int::zero();
+//! for _ in 0..10 {
+//!     r.assign(a.mul_ref(&b));
+//! }
+//! assert_eq!(r, Int::from(12345 * -678));
+//! assert_eq!(Int::from(a.add_ref(&b)), &a + &b);
+//! ```
+
+use int::Int;
+use traits::Assign;
+
+macro_rules! impl_incomplete (
+    ($name:ident, |$r:ident, $s:ident| $eval:expr) => (
+        impl<'a> Assign<$name<'a>> for Int {
+            #[inline]
+            fn assign(&mut self, src: $name<'a>) {
+                let $r = self;
+                let $s = src;
+                $eval
+            }
+        }
+
+        impl<'a> From<$name<'a>> for Int {
+            #[inline]
+            fn from(src: $name<'a>) -> Int {
+                let mut r = Int::zero();
+                r.assign(src);
+                r
+            }
+        }
+    )
+);
+
+/// The sum of two `Int`s, as returned by `Int::add_ref`.
+#[derive(Clone, Copy, Debug)]
+pub struct AddIncomplete<'a> {
+    lhs: &'a Int,
+    rhs: &'a Int,
+}
+
+/// The difference of two `Int`s, as returned by `Int::sub_ref`.
+#[derive(Clone, Copy, Debug)]
+pub struct SubIncomplete<'a> {
+    lhs: &'a Int,
+    rhs: &'a Int,
+}
+
+/// The product of two `Int`s, as returned by `Int::mul_ref`.
+#[derive(Clone, Copy, Debug)]
+pub struct MulIncomplete<'a> {
+    lhs: &'a Int,
+    rhs: &'a Int,
+}
+
+/// The square of an `Int`, as returned by `Int::square_ref`.
+#[derive(Clone, Copy, Debug)]
+pub struct SquareIncomplete<'a> {
+    base: &'a Int,
+}
+
+/// A power of an `Int`, as returned by `Int::pow_ref`.
+#[derive(Clone, Copy, Debug)]
+pub struct PowIncomplete<'a> {
+    base: &'a Int,
+    exp: usize,
+}
+
+impl_incomplete!(AddIncomplete, |r, s| r.assign_add(s.lhs, s.rhs));
+impl_incomplete!(SubIncomplete, |r, s| r.assign_sub(s.lhs, s.rhs));
+impl_incomplete!(MulIncomplete, |r, s| r.assign_mul(s.lhs, s.rhs));
+impl_incomplete!(SquareIncomplete, |r, s| r.assign_square(s.base));
+impl_incomplete!(PowIncomplete, |r, s| r.assign_pow(s.base, s.exp));
+
+impl Int {
+    /// Returns `self + other`, to be evaluated later.
+    #[inline]
+    pub fn add_ref<'a>(&'a self, other: &'a Int) -> AddIncomplete<'a> {
+        AddIncomplete { lhs: self, rhs: other }
+    }
+
+    /// Returns `self - other`, to be evaluated later.
+    #[inline]
+    pub fn sub_ref<'a>(&'a self, other: &'a Int) -> SubIncomplete<'a> {
+        SubIncomplete { lhs: self, rhs: other }
+    }
+
+    /// Returns `self * other`, to be evaluated later.
+    #[inline]
+    pub fn mul_ref<'a>(&'a self, other: &'a Int) -> MulIncomplete<'a> {
+        MulIncomplete { lhs: self, rhs: other }
+    }
+
+    /// Returns `self * self`, to be evaluated later.
+    #[inline]
+    pub fn square_ref(&self) -> SquareIncomplete {
+        SquareIncomplete { base: self }
+    }
+
+    /// Returns `self` to the power of `exp`, to be evaluated later.
+    #[inline]
+    pub fn pow_ref(&self, exp: usize) -> PowIncomplete {
+        PowIncomplete { base: self, exp: exp }
+    }
+}
+
+#[test]
+fn incomplete() {
+    let a = Int::from(-3).pow(50);
+    let b = Int::from(7).pow(30);
+    let mut r = Int::zero();
+
+    r.assign(a.add_ref(&b));
+    assert_eq!(r, &a + &b);
+    r.assign(a.sub_ref(&b));
+    assert_eq!(r, &a - &b);
+    r.assign(a.mul_ref(&b));
+    assert_eq!(r, &a * &b);
+    r.assign(b.square_ref());
+    assert_eq!(r, b.square());
+    r.assign(a.pow_ref(5));
+    assert_eq!(r, a.pow(5));
+
+    assert_eq!(Int::from(b.pow_ref(3)), b.pow(3));
+}
@@ -14,6 +14,14 @@
 
 #[path="mtgy.rs"]
 pub mod mtgy;
+#[path="radix.rs"]
+pub mod radix;
+#[path="incomplete.rs"]
+pub mod incomplete;
+#[path="reduce.rs"]
+pub mod reduce;
+#[path="prepared.rs"]
+pub mod prepared;
 
 use std;
 use std::cmp::{
@@ -21,19 +29,21 @@ use std::cmp::{
     Ord, Eq,
     PartialOrd, PartialEq
 };
+use std::collections::BinaryHeap;
 use std::error::Error;
 use std::{io, mem, fmt, hash};
 use std::ops::{
-    Add, Sub, Mul, Div, Rem, Neg,
+    Add, Sub, Mul, Div, Rem, Neg, Not,
     AddAssign, SubAssign, MulAssign, DivAssign, RemAssign,
     Shl, Shr, BitAnd, BitOr, BitXor,
     ShlAssign, ShrAssign, BitAndAssign, BitOrAssign, BitXorAssign,
 };
+use std::ops::Range;
 use std::ptr::Unique;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT, Ordering as AtomicOrdering};
 use rand::Rng;
 
-use hamming;
 use alloc;
 use num_integer::Integer;
 use num_traits::{Num, Zero, One};
@@ -41,10 +51,11 @@ use num_traits::{Num, Zero, One};
 use ll;
 use ll::limb::{BaseInt, Limb};
 use ll::limb_ptr::{Limbs, LimbsMut};
+use mem::TmpAllocator;
 
 use alloc::raw_vec::RawVec;
 
-use traits::DivRem;
+use traits::{Assign, DivRem};
 
 
 /**
@@ -116,25 +127,154 @@ use traits::DivRem;
  * while the remainder/modulo operator returns `R`. The sign of `R` is the same as the sign of `Q`.
  *
  * The "bit-shift" operations are defined as being multiplication and division by a power-of-two for
- * shift-left and shift-right respectively. The sign of the number is unaffected.
+ * shift-left and shift-right respectively. Like for primitive integers, shift-right rounds towards
+ * negative infinity, so `-1 >> 1` is `-1`.
  *
  * The remaining bitwise operands act as if the numbers are stored in two's complement format and as
  * if the two inputs have the same number of bits.
  *
  */
 pub struct Int {
+    // When `cap` is zero, nothing is allocated. A value of a single limb may then be stored in
+    // the pointer itself, which is how the `const fn` constructors avoid allocating; see `limbs`.
+    // The value is moved to the heap as soon as it needs to be modified.
     ptr: Unique<Limb>,
     size: i32,
     cap: u32
 }
 
+#[cold]
+#[inline(never)]
+fn invalid_int(op: &str, problem: &str, size: i32, cap: u32) -> ! {
+    panic!("invalid Int after {}: {} (size: {}, cap: {})", op, problem, size, cap)
+}
+
+/// How to round to a multiple, for `Int::round_to_multiple`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RoundMode {
+    /// Round towards negative infinity.
+    Floor,
+    /// Round towards positive infinity.
+    Ceiling,
+    /// Round towards zero.
+    TowardZero,
+    /// Round away from zero.
+    AwayFromZero,
+    /// Round to the nearest multiple, with ties going away from zero.
+    Nearest,
+}
+
+/// The sign of an `Int`, as returned by `Int::sign_enum`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Sign {
+    Negative,
+    Zero,
+    Positive,
+}
+
+impl Sign {
+    /// Returns the sign as either -1, 0 or 1, like `Int::sign`.
+    #[inline]
+    pub fn to_i32(self) -> i32 {
+        match self {
+            Sign::Negative => -1,
+            Sign::Zero => 0,
+            Sign::Positive => 1,
+        }
+    }
+}
+
+impl Neg for Sign {
+    type Output = Sign;
+
+    #[inline]
+    fn neg(self) -> Sign {
+        match self {
+            Sign::Negative => Sign::Positive,
+            Sign::Zero => Sign::Zero,
+            Sign::Positive => Sign::Negative,
+        }
+    }
+}
+
+impl Mul for Sign {
+    type Output = Sign;
+
+    #[inline]
+    fn mul(self, other: Sign) -> Sign {
+        match (self, other) {
+            (Sign::Zero, _) | (_, Sign::Zero) => Sign::Zero,
+            (a, b) if a == b => Sign::Positive,
+            _ => Sign::Negative,
+        }
+    }
+}
+
+/// The largest number of limbs an `Int` can hold.
+///
+/// Sizes are stored as `i32`, like the lengths taken by the `ll` functions, so
+/// an operation whose result could need more limbs panics before doing any
+/// work. The `try_` methods return an `AllocError` instead.
+pub const MAX_LIMBS: usize = std::i32::MAX as usize;
+
+// Converts a result size computed in `usize` to the `i32` used everywhere else,
+// panicking if it doesn't fit.
+#[inline]
+fn checked_size(limbs: usize) -> i32 {
+    if limbs > MAX_LIMBS {
+        too_large(limbs);
+    }
+    limbs as i32
+}
+
+#[cold]
+#[inline(never)]
+fn too_large(limbs: usize) -> ! {
+    panic!("Int too large: {} limbs, at most {} are supported", limbs, MAX_LIMBS)
+}
+
 impl Int {
-    pub fn zero() -> Int {
-        <Int as Zero>::zero()
+    pub const fn zero() -> Int {
+        Int {
+            ptr: unsafe { Unique::new(alloc::heap::EMPTY as *mut Limb) },
+            size: 0,
+            cap: 0
+        }
+    }
+
+    /// Returns one. This doesn't allocate, and can be used in constants.
+    pub const fn one() -> Int {
+        Int::from_u32_const(1)
+    }
+
+    /// Creates a new Int from `n`. This doesn't allocate, and can be used
+    /// in constants.
+    ///
+    /// ```
+    /// # use framp::Int;
+    /// const TWO: Int = Int::from_u32_const(2);
+    /// assert_eq!(&TWO + 1, 3);
+    /// ```
+    pub const fn from_u32_const(n: u32) -> Int {
+        Int {
+            // The pointer can't be null, it doesn't matter what it is when `n` is zero
+            ptr: unsafe { Unique::new(((n as usize) | ((n == 0) as usize)) as *mut Limb) },
+            size: (n != 0) as i32,
+            cap: 0
+        }
     }
 
-    pub fn one() -> Int {
-        <Int as One>::one()
+    /// Creates a new Int from `n`. This doesn't allocate, and can be used
+    /// in constants.
+    ///
+    /// This is only available with 64-bit limbs.
+    #[cfg(target_pointer_width = "64")]
+    pub const fn from_u64_const(n: u64) -> Int {
+        Int {
+            ptr: unsafe { Unique::new(((n as usize) | ((n == 0) as usize)) as *mut Limb) },
+            size: (n != 0) as i32,
+            cap: 0
+        }
     }
 
     /// Creates a new Int from the given Limb.
@@ -148,12 +288,54 @@ impl Int {
         i
     }
 
+    /**
+     * Creates a new Int from a magnitude given as limbs, least-significant
+     * first, and a sign. High zero limbs are ignored.
+     *
+     * ```
+     * # use framp::Int;
+     * # use framp::int::Sign;
+     * # use framp::ll::limb::Limb;
+     * let i = Int::from_limbs(Sign::Negative, &[Limb(5), Limb(1), Limb(0)]);
+     * assert_eq!(i, -((Int::from(1) << Limb::BITS) + 5));
+     * assert_eq!(i.as_limb_slice(), &[Limb(5), Limb(1)]);
+     * ```
+     *
+     * # Panics
+     *
+     * Panics if `sign` is `Sign::Zero` but the magnitude isn't zero.
+     */
+    pub fn from_limbs(sign: Sign, limbs: &[Limb]) -> Int {
+        let n = limbs.iter().rposition(|&l| l != 0).map_or(0, |i| i + 1);
+        if n == 0 {
+            return Int::zero();
+        }
+        assert!(sign != Sign::Zero, "Int::from_limbs: zero sign with a non-zero magnitude");
+
+        let size = checked_size(n);
+        let mut i = Int::with_capacity(size as u32);
+        unsafe {
+            std::ptr::copy_nonoverlapping(limbs.as_ptr(), i.limbs_uninit().as_mut_ptr(), n);
+        }
+        i.size = if sign == Sign::Negative { -size } else { size };
+        i
+    }
+
     /**
      * Views the internal memory as a `RawVec`, which can be
      * manipulated to change `self`'s allocation.
      */
     fn with_raw_vec<F: FnOnce(&mut RawVec<Limb>)>(&mut self, f: F) {
         unsafe {
+            // A value stored inline is moved into the new allocation
+            let inline = if self.cap == 0 && self.size != 0 {
+                let l = self.to_single_limb();
+                self.ptr = Unique::new(alloc::heap::EMPTY as *mut Limb);
+                Some(l)
+            } else {
+                None
+            };
+
             let old_cap = self.cap as usize;
             let mut vec = RawVec::from_raw_parts(self.ptr.as_ptr(), old_cap);
             // if `f` panics, let `vec` do the cleaning up, not self.
@@ -171,19 +353,36 @@ impl Int {
             std::mem::forget(vec);
 
             if old_cap < new_cap {
+                ::mem::count_heap_allocation();
                 // the allocation got larger, new Limbs should be
                 // zero.
-                let self_ptr = self.limbs_uninit();
-                std::ptr::write_bytes(&mut *self_ptr.offset(old_cap as isize) as *mut _ as *mut u8,
+                std::ptr::write_bytes(self.ptr.as_ptr().offset(old_cap as isize) as *mut u8,
                                       0,
                                       (new_cap - old_cap) * std::mem::size_of::<Limb>());
             }
+
+            if let Some(l) = inline {
+                debug_assert!(new_cap > 0);
+                *self.ptr.as_ptr() = l;
+            }
         }
     }
 
-    fn with_capacity(cap: u32) -> Int {
+    /**
+     * Creates a zero-valued Int with room for at least `cap` limbs, so it can
+     * grow up to that size without being reallocated.
+     *
+     * ```
+     * # use framp::Int;
+     * let i = Int::with_capacity(8);
+     * assert_eq!(i, 0);
+     * assert!(i.capacity() >= 8);
+     * ```
+     */
+    pub fn with_capacity(cap: u32) -> Int {
         let mut ret = Int::zero();
         if cap != 0 {
+            check_memory_budget(cap as usize * mem::size_of::<Limb>());
             ret.with_raw_vec(|v| v.reserve_exact(0, cap as usize))
         }
         ret
@@ -204,6 +403,27 @@ impl Int {
         }
     }
 
+    /**
+     * Returns the sign of the Int as a `Sign`.
+     *
+     * ```
+     * # use framp::Int;
+     * # use framp::int::Sign;
+     * assert_eq!(Int::from(-3).sign_enum(), Sign::Negative);
+     * assert_eq!((-Int::from(-3)).sign_enum(), -Sign::Negative);
+     * ```
+     */
+    #[inline]
+    pub fn sign_enum(&self) -> Sign {
+        if self.size == 0 {
+            Sign::Zero
+        } else if self.size < 0 {
+            Sign::Negative
+        } else {
+            Sign::Positive
+        }
+    }
+
     /**
      * Consumes self and returns the absolute value
      */
@@ -213,6 +433,47 @@ impl Int {
         self
     }
 
+    /**
+     * Returns the low 64 bits of the absolute value of self. Higher bits are
+     * silently dropped.
+     */
+    #[inline]
+    pub fn low_u64(&self) -> u64 {
+        self.low_u128() as u64
+    }
+
+    /**
+     * Returns the low 128 bits of the absolute value of self. Higher bits are
+     * silently dropped.
+     */
+    pub fn low_u128(&self) -> u128 {
+        let n = std::cmp::min(self.abs_size() as usize, 128 / Limb::BITS);
+        let mut r = 0u128;
+        unsafe {
+            let ptr = self.limbs();
+            for i in (0..n).rev() {
+                r = (r << Limb::BITS) | (*ptr.offset(i as isize)).0 as u128;
+            }
+        }
+        r
+    }
+
+    /**
+     * Returns self modulo 2^64, with negative numbers in two's complement,
+     * like an `as u64` cast from a wider primitive integer.
+     *
+     * ```
+     * # use framp::Int;
+     * assert_eq!(Int::from(-1).to_u64_wrapping(), std::u64::MAX);
+     * assert_eq!((Int::from(1) << 64usize).to_u64_wrapping(), 0);
+     * ```
+     */
+    #[inline]
+    pub fn to_u64_wrapping(&self) -> u64 {
+        let low = self.low_u64();
+        if self.sign() < 0 { low.wrapping_neg() } else { low }
+    }
+
     /**
      * Returns the least-significant limb of self.
      */
@@ -221,7 +482,34 @@ impl Int {
         if self.sign() == 0 {
             return Limb(0);
         } else {
-            return unsafe { *self.ptr.as_ref() };
+            return unsafe { *self.limbs() };
+        }
+    }
+
+    /**
+     * Returns the limbs of the absolute value of self, least-significant first.
+     * The most significant limb is never zero, so the slice is empty for zero.
+     */
+    pub fn as_limb_slice(&self) -> &[Limb] {
+        if self.size == 0 {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(self.limbs_ptr(), self.abs_size() as usize) }
+    }
+
+    /**
+     * Consumes self and returns the limbs of its absolute value, as described
+     * in `as_limb_slice`. The buffer of self is reused when there is one.
+     */
+    pub fn into_limbs(self) -> Vec<Limb> {
+        if self.cap == 0 {
+            return self.as_limb_slice().to_vec();
+        }
+        unsafe {
+            let v = Vec::from_raw_parts(self.ptr.as_ptr(), self.abs_size() as usize,
+                                        self.cap as usize);
+            mem::forget(self);
+            v
         }
     }
 
@@ -230,28 +518,62 @@ impl Int {
         self.size.abs()
     }
 
+    // The number of limbs in use, in the form taken by the `ll` functions
+    #[inline(always)]
+    fn abs_len(&self) -> usize {
+        self.abs_size() as usize
+    }
+
     /**
      * Compare the absolute value of self to the absolute value of other,
      * returning an Ordering with the result.
+     *
+     * This is the same as `abs_cmp`, named to match `Ord::cmp`.
+     *
+     * ```
+     * # use framp::Int;
+     * # use std::cmp::Ordering;
+     * assert_eq!(Int::from(-5).cmp_abs(&Int::from(3)), Ordering::Greater);
+     * ```
      */
-    pub fn abs_cmp(&self, other: &Int) -> Ordering {
+    pub fn cmp_abs(&self, other: &Int) -> Ordering {
         if self.abs_size() > other.abs_size() {
             Ordering::Greater
         } else if self.abs_size() < other.abs_size() {
             Ordering::Less
         } else {
             unsafe {
-                ll::cmp(self.limbs(), other.limbs(), self.abs_size())
+                ll::cmp(self.limbs(), other.limbs(), self.abs_len())
             }
         }
     }
 
+    /**
+     * Returns the equality of the absolute values of self and
+     * other.
+     *
+     * This is the same as `abs_eq`, named to match `PartialEq::eq`.
+     */
+    pub fn eq_abs(&self, other: &Int) -> bool {
+        self.abs_size() == other.abs_size() && self.cmp_abs(other) == Ordering::Equal
+    }
+
+    /**
+     * Compare the absolute value of self to the absolute value of other,
+     * returning an Ordering with the result.
+     */
+    #[inline]
+    pub fn abs_cmp(&self, other: &Int) -> Ordering {
+        self.cmp_abs(other)
+    }
+
     /**
      * Returns the equality of the absolute values of self and
      * other.
      */
+    #[inline]
     pub fn abs_eq(&self, other: &Int) -> bool {
-        self.abs_cmp(other) == Ordering::Equal
+        self.eq_abs(other)
     }
 
     /**
@@ -274,6 +596,135 @@ impl Int {
         }
     }
 
+    /**
+     * Returns the number of limbs this Int can hold without reallocating.
+     *
+     * Values built by the `const` constructors keep their single limb inline
+     * and report a capacity of zero.
+     */
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.cap as usize
+    }
+
+    /**
+     * Reserves room for at least `additional` more limbs than the current
+     * value uses. Does nothing if the capacity is already sufficient.
+     *
+     * Panics if the new capacity overflows a `u32`.
+     *
+     * ```
+     * # use framp::Int;
+     * let mut i = Int::from(5);
+     * i.reserve(10);
+     * assert!(i.capacity() >= 11);
+     * assert_eq!(i, 5);
+     * ```
+     */
+    pub fn reserve(&mut self, additional: usize) {
+        let cap = (self.abs_size() as usize).checked_add(additional)
+            .expect("capacity overflow");
+        assert!(cap <= std::u32::MAX as usize, "capacity overflow");
+        self.ensure_capacity(cap as u32);
+    }
+
+    /**
+     * Like `with_capacity`, but returns an error instead of aborting if the
+     * memory can't be allocated.
+     */
+    pub fn try_with_capacity(cap: usize) -> Result<Int, AllocError> {
+        let mut ret = Int::zero();
+        try!(ret.try_ensure_capacity(cap));
+        Ok(ret)
+    }
+
+    /**
+     * Like `reserve`, but returns an error instead of panicking or aborting if
+     * the memory can't be allocated. The value is left untouched on failure.
+     *
+     * ```
+     * # use framp::Int;
+     * let mut i = Int::from(5);
+     * assert!(i.try_reserve(10).is_ok());
+     * assert!(i.try_reserve(std::usize::MAX).is_err());
+     * assert_eq!(i, 5);
+     * ```
+     */
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), AllocError> {
+        let cap = (self.abs_size() as usize).saturating_add(additional);
+        self.try_ensure_capacity(cap)
+    }
+
+    /**
+     * Multiplies self by `other`, returning an error instead of aborting if
+     * the result can't be allocated.
+     *
+     * Only the result is allocated fallibly. The scratch space used by the
+     * larger multiplication algorithms is proportional to the size of the
+     * operands and is still allocated as usual.
+     */
+    pub fn try_mul(&self, other: &Int) -> Result<Int, AllocError> {
+        let size = self.abs_size() as usize + other.abs_size() as usize;
+        let mut ret = try!(Int::try_with_capacity(size));
+        ret.assign_mul(self, other);
+        Ok(ret)
+    }
+
+    /**
+     * Squares self, returning an error instead of aborting if the result
+     * can't be allocated. See `try_mul`.
+     */
+    pub fn try_square(&self) -> Result<Int, AllocError> {
+        let mut ret = try!(Int::try_with_capacity(self.abs_size() as usize * 2));
+        ret.assign_square(self);
+        Ok(ret)
+    }
+
+    /**
+     * Raises self to the power of `exp`, returning an error instead of
+     * panicking or aborting if the result is too big to be allocated.
+     *
+     * ```
+     * # use framp::Int;
+     * assert_eq!(Int::from(3).try_pow(4), Ok(Int::from(81)));
+     * assert!(Int::from(10).pow(100).try_pow(std::usize::MAX).is_err());
+     * ```
+     */
+    pub fn try_pow(&self, exp: usize) -> Result<Int, AllocError> {
+        if exp <= 2 || self.bit_length() <= 1 {
+            let limbs = if exp <= 2 { self.abs_size() as usize * exp } else { 1 };
+            let mut ret = try!(Int::try_with_capacity(limbs));
+            ret.assign_pow(self, exp);
+            return Ok(ret);
+        }
+
+        // The result has at most `exp * bits` bits
+        let limbs = (self.bit_length() as u64).saturating_mul(exp as u64) / Limb::BITS as u64 + 1;
+        if exp > std::u32::MAX as usize || limbs > std::i32::MAX as u64 {
+            return Err(AllocError::new(limbs as usize));
+        }
+        let mut ret = try!(Int::try_with_capacity(limbs as usize));
+        ret.assign_pow(self, exp);
+        Ok(ret)
+    }
+
+    /**
+     * Shifts self left by `cnt` bits, returning an error instead of aborting
+     * if the result can't be allocated.
+     */
+    pub fn try_shl(&self, cnt: usize) -> Result<Int, AllocError> {
+        if self.sign() == 0 {
+            return Ok(Int::zero());
+        }
+        let limbs = (self.abs_size() as usize)
+            .saturating_add(cnt / Limb::BITS as usize)
+            .saturating_add(1);
+        let mut ret = try!(Int::try_with_capacity(limbs));
+        ret.clone_from(self);
+        ret <<= cnt;
+        Ok(ret)
+    }
+
     /**
      * Try to shrink the allocated data for this Int.
      */
@@ -281,14 +732,56 @@ impl Int {
         let mut size = self.abs_size() as usize;
 
         if (self.cap as usize) == size { return; } // already as small as possible
+        if self.cap == 0 { return; } // nothing allocated, the value is stored inline
 
         if size == 0 { size = 1; } // Keep space for at least one limb around
 
+        if cfg!(feature = "zeroize") {
+            self.rebuffer(size as u32);
+            return;
+        }
+
         self.with_raw_vec(|v| {
             v.shrink_to_fit(size);
         })
     }
 
+    /**
+     * Returns the exact number of digits of self in base `base`, without the
+     * sign. Zero has one digit.
+     *
+     * Panics if `base` is less than two or greater than 36.
+     *
+     * ```
+     * # use framp::Int;
+     * assert_eq!(Int::from(-999).digits_in_base(10), 3);
+     * assert_eq!(Int::from(1000).digits_in_base(10), 4);
+     * assert_eq!(Int::from(255).digits_in_base(16), 2);
+     * ```
+     */
+    pub fn digits_in_base(&self, base: u8) -> usize {
+        if base < 2 || base > 36 {
+            panic!("Invalid base: {}", base);
+        }
+
+        let estimate = unsafe {
+            ll::base::num_base_digits(self.limbs(), self.abs_len(), base as u32)
+        };
+        if self.sign() == 0 || (base as u32).is_power_of_two() {
+            // Exact for powers of two
+            return estimate;
+        }
+
+        // The estimate is either exact or one too big, `base^(estimate - 1)`
+        // tells them apart.
+        let p = Int::from(base).pow(estimate - 1);
+        if self.cmp_abs(&p) == Ordering::Less {
+            estimate - 1
+        } else {
+            estimate
+        }
+    }
+
     /**
      * Returns a string containing the value of self in base `base`. For bases greater than
      * ten, if `upper` is true, upper-case letters are used, otherwise lower-case ones are used.
@@ -304,7 +797,7 @@ impl Int {
             panic!("Invalid base: {}", base);
         }
 
-        let size = self.abs_size();
+        let size = self.abs_len();
         let mut num_digits = unsafe {
             ll::base::num_base_digits(self.limbs(), size - 1, base as u32)
         };
@@ -328,7 +821,7 @@ impl Int {
         }
 
         let letter = if upper { b'A' } else { b'a' };
-        let size = self.abs_size();
+        let size = self.abs_len();
 
         unsafe {
             ll::base::to_base(base as u32, self.limbs(), size, |b| {
@@ -344,99 +837,428 @@ impl Int {
     }
 
     /**
-     * Creates a new Int from the given string in base `base`.
+     * Writes the value of self in base `base` to `out`, without allocating an intermediate
+     * string. The digits are the same as the ones produced by `to_str_radix`.
+     *
+     * Panics if `base` is less than two or greater than 36.
      */
-    pub fn from_str_radix(mut src: &str, base: u8) -> Result<Int, ParseIntError> {
+    pub fn write_str_radix<W: fmt::Write>(&self, base: u8, upper: bool, out: &mut W) -> fmt::Result {
+        debug_assert!(self.well_formed());
+
         if base < 2 || base > 36 {
             panic!("Invalid base: {}", base);
         }
 
-        if src.len() == 0 {
-            return Err(ParseIntError { kind: ErrorKind::Empty });
+        if self.sign() == -1 {
+            try!(out.write_char('-'));
         }
 
-        let mut sign = 1;
-        if src.starts_with('-') {
-            sign = -1;
-            src = &src[1..];
-        }
+        let letter = if upper { b'A' } else { b'a' };
+        let size = self.abs_len();
+        let mut res = Ok(());
 
-        if src.len() == 0 {
-            return Err(ParseIntError { kind: ErrorKind::Empty });
+        unsafe {
+            ll::base::to_base(base as u32, self.limbs(), size, |b| {
+                if res.is_ok() {
+                    let c = if b < 10 { b + b'0' } else { (b - 10) + letter };
+                    res = out.write_char(c as char);
+                }
+            });
         }
 
-        let mut buf = Vec::with_capacity(src.len());
-
-        for c in src.bytes() {
-            let b = match c {
-                b'0'...b'9' => c - b'0',
-                b'A'...b'Z' => (c - b'A') + 10,
-                b'a'...b'z' => (c - b'a') + 10,
-                _ => {
-                    return Err(ParseIntError { kind: ErrorKind::InvalidDigit });
-                }
-            };
+        res
+    }
 
-            if b >= base { return Err(ParseIntError { kind: ErrorKind::InvalidDigit }); }
+    /**
+     * Writes the value of self in base `base` into `buf`, returning the number of bytes written,
+     * or `None` if `buf` is too small to hold all the digits. The contents of `buf` are
+     * unspecified in the latter case.
+     *
+     * Panics if `base` is less than two or greater than 36.
+     */
+    pub fn write_str_radix_to_slice(&self, base: u8, upper: bool, buf: &mut [u8]) -> Option<usize> {
+        debug_assert!(self.well_formed());
 
-            buf.push(b);
+        if base < 2 || base > 36 {
+            panic!("Invalid base: {}", base);
         }
 
-        let num_digits = ll::base::base_digits_to_len(src.len(), base as u32);
+        let mut pos = 0;
+        if self.sign() == -1 {
+            if buf.len() == 0 { return None; }
+            buf[0] = b'-';
+            pos = 1;
+        }
 
-        let mut i = Int::with_capacity(num_digits as u32);
+        let letter = if upper { b'A' } else { b'a' };
+        let size = self.abs_len();
+        let mut overflow = false;
 
         unsafe {
-            let size = ll::base::from_base(i.limbs_uninit(), buf.as_ptr(), buf.len() as i32, base as u32);
-            i.size = (size as i32) * sign;
+            ll::base::to_base(base as u32, self.limbs(), size, |b| {
+                if pos < buf.len() {
+                    buf[pos] = if b < 10 { b + b'0' } else { (b - 10) + letter };
+                    pos += 1;
+                } else {
+                    overflow = true;
+                }
+            });
         }
 
-        Ok(i)
+        if overflow { None } else { Some(pos) }
     }
 
     /**
-     * Divide self by other, returning the quotient, Q, and remainder, R as (Q, R).
-     *
-     * With N = self, D = other, Q and R satisfy: `N = QD + R`.
-     * The sign of `Q` and `R` are the same.
+     * Returns the hexadecimal representation of self, converting the digits without
+     * data-dependent branches or table lookups.
      *
-     * This will panic if `other` is zero.
+     * This is intended for exporting secrets such as private keys. Every limb is written out
+     * in full, so the result may have leading zeros and its length only depends on the number
+     * of limbs. Negative numbers are prefixed with `-`.
      */
-    pub fn divmod(&self, other: &Int) -> (Int, Int) {
-        debug_assert!(self.well_formed());
-        debug_assert!(other.well_formed());
-        if other.sign() == 0 {
-            ll::divide_by_zero();
+    pub fn to_hex_ct(&self) -> String {
+        let size = self.abs_size() as usize;
+        let nibbles_per_limb = Limb::BITS / 4;
+
+        let mut s = String::with_capacity(size * nibbles_per_limb + 1);
+        if self.sign() == -1 {
+            s.push('-');
         }
-        if self.sign() == 0 {
-            return (self.clone(), Int::zero())
+        if size == 0 {
+            s.push('0');
+            return s;
         }
 
-        let out_size = if self.abs_size() < other.abs_size() {
-            1
-        } else {
-            (self.abs_size() - other.abs_size()) + 1
-        };
+        let limbs = self.limbs();
+        for j in (0..size).rev() {
+            let l = unsafe { *limbs.offset(j as isize) };
+            for k in (0..nibbles_per_limb).rev() {
+                let n = ((l.0 >> (4 * k)) & 0xf) as i32;
+                // Adds 'a' - '0' - 10 only if n > 9, using the sign of 9 - n as a mask
+                let c = n + (b'0' as i32) + (((9 - n) >> 8) & 0x27);
+                s.push(c as u8 as char);
+            }
+        }
 
-        let out_sign = self.sign() * other.sign();
-        let mut q = Int::with_capacity(out_size as u32);
-        q.size = out_size * out_sign;
+        s
+    }
 
-        let mut r = Int::with_capacity(other.abs_size() as u32);
+    /**
+     * Creates a new Int from a hexadecimal string, decoding the digits without data-dependent
+     * branches or table lookups.
+     *
+     * This is intended for loading secrets such as private keys: the timing only depends on
+     * the length of the input and on whether it is valid. Upper- and lower-case digits and an
+     * optional leading `-` are accepted.
+     */
+    pub fn from_hex_ct(src: &str) -> Result<Int, ParseIntError> {
+        let bytes = src.as_bytes();
+        let mut pos = 0;
+
+        let mut sign = 1;
+        if bytes.len() > 0 && bytes[0] == b'-' {
+            sign = -1;
+            pos = 1;
+        }
+
+        if bytes.len() == pos {
+            return Err(ParseIntError::new(ErrorKind::Empty, pos));
+        }
+
+        let digits = &bytes[pos..];
+        let nibbles_per_limb = Limb::BITS / 4;
+        let size = (digits.len() + nibbles_per_limb - 1) / nibbles_per_limb;
+
+        let mut i = Int::with_capacity(size as u32);
+        let mut invalid = 0;
+
+        unsafe {
+            let out = i.limbs_uninit();
+            ll::zero(out, size);
+
+            for (k, &c) in digits.iter().rev().enumerate() {
+                let c = c as u32;
+
+                // '0'...'9': c_num0 is non-zero and c_num is the value
+                let c_num = c ^ 0x30;
+                let c_num0 = c_num.wrapping_sub(10) >> 8;
+                // 'a'...'f' or 'A'...'F': c_alpha0 is non-zero and c_alpha is the value
+                let c_alpha = (c & !0x20).wrapping_sub(55);
+                let c_alpha0 = (c_alpha.wrapping_sub(10) ^ c_alpha.wrapping_sub(16)) >> 8;
+
+                invalid |= !(c_num0 | c_alpha0) & 1;
+                let val = (c_num0 & c_num) | (c_alpha0 & c_alpha);
+
+                let mut l = out.offset((k / nibbles_per_limb) as isize);
+                *l = *l | (Limb((val & 0xf) as BaseInt) << (4 * (k % nibbles_per_limb)));
+            }
+
+            i.size = (size as i32) * sign;
+        }
+
+        if invalid != 0 {
+            let off = digits.iter().position(|&c| digit_value(c, 16).is_none()).unwrap();
+            return Err(ParseIntError::new(ErrorKind::InvalidDigit, pos + off));
+        }
+
+        i.normalize();
+        Ok(i)
+    }
+
+    /**
+     * Creates a new Int from a string using Rust integer literal syntax.
+     *
+     * The string may start with a `+` or `-` sign, followed by an optional `0x`, `0o` or `0b`
+     * prefix selecting base 16, 8 or 2 respectively. Without a prefix the number is parsed as
+     * decimal. Underscores may be used to separate digits, but the number (after the prefix)
+     * may not start with one.
+     *
+     * ```
+     * # use framp::Int;
+     * let i = Int::from_str_flexible("-0xdead_beef").unwrap();
+     * assert_eq!(i, -0xdeadbeefi64);
+     * ```
+     */
+    pub fn from_str_flexible(src: &str) -> Result<Int, ParseIntError> {
+        let bytes = src.as_bytes();
+        let mut pos = 0;
+
+        let mut sign = 1;
+        if bytes.len() > 0 && (bytes[0] == b'-' || bytes[0] == b'+') {
+            if bytes[0] == b'-' { sign = -1; }
+            pos = 1;
+        }
+
+        let prefixes = [(b'x', 16), (b'X', 16), (b'o', 8), (b'O', 8), (b'b', 2), (b'B', 2)];
+
+        let mut base = 10;
+        if bytes.len() >= pos + 2 && bytes[pos] == b'0' {
+            for &(c, prefix_base) in prefixes.iter() {
+                if bytes[pos + 1] == c {
+                    base = prefix_base;
+                    pos += 2;
+                    break;
+                }
+            }
+        }
+
+        if base == 10 && pos < bytes.len() && bytes[pos] == b'_' {
+            return Err(ParseIntError::new(ErrorKind::InvalidDigit, pos));
+        }
+
+        let mut buf = Vec::with_capacity(bytes.len() - pos);
+        for (off, &c) in bytes[pos..].iter().enumerate() {
+            if c == b'_' { continue; }
+
+            match digit_value(c, base) {
+                Some(b) => buf.push(b),
+                None => return Err(ParseIntError::new(ErrorKind::InvalidDigit, pos + off))
+            }
+        }
+
+        if buf.len() == 0 {
+            return Err(ParseIntError::new(ErrorKind::Empty, bytes.len()));
+        }
+
+        let limit = default_parse_limit();
+        if limit != 0 && buf.len() > limit {
+            return Err(ParseIntError::new(ErrorKind::TooLong, bytes.len()));
+        }
+
+        Ok(Int::from_raw_digits(&buf, base, sign))
+    }
+
+    /**
+     * Creates a new Int from the given string in base `base`.
+     *
+     * Returns an error with kind `ErrorKind::InvalidRadix` if `base` is less than two or
+     * greater than 36, and one with kind `ErrorKind::TooLong` if the string has more digits
+     * than allowed by `set_default_parse_limit`.
+     */
+    pub fn from_str_radix(src: &str, base: u8) -> Result<Int, ParseIntError> {
+        Int::parse_radix(src, base, default_parse_limit())
+    }
+
+    /**
+     * Creates a new Int from the given decimal string, refusing inputs of more than
+     * `max_digits` digits (not counting the sign).
+     *
+     * Converting a string is quadratic in its length, so this check should be used when parsing
+     * untrusted input. It is done before any conversion work happens.
+     *
+     * A `max_digits` of zero means no limit, as for `set_default_parse_limit`, not that only
+     * empty strings are accepted.
+     *
+     * ```
+     * # use framp::Int;
+     * # use framp::int::ErrorKind;
+     * assert_eq!(Int::from_str_bounded("-12345", 5).unwrap(), -12345);
+     * assert_eq!(Int::from_str_bounded("123456", 5).unwrap_err().kind(), ErrorKind::TooLong);
+     * assert_eq!(Int::from_str_bounded("123456", 0).unwrap(), 123456);
+     * ```
+     */
+    pub fn from_str_bounded(src: &str, max_digits: usize) -> Result<Int, ParseIntError> {
+        Int::parse_radix(src, 10, max_digits)
+    }
+
+    fn parse_radix(src: &str, base: u8, max_digits: usize) -> Result<Int, ParseIntError> {
+        if base < 2 || base > 36 {
+            return Err(ParseIntError::new(ErrorKind::InvalidRadix, 0));
+        }
+
+        let bytes = src.as_bytes();
+        let mut pos = 0;
+
+        let mut sign = 1;
+        if bytes.len() > 0 && bytes[0] == b'-' {
+            sign = -1;
+            pos = 1;
+        }
+
+        if bytes.len() == pos {
+            return Err(ParseIntError::new(ErrorKind::Empty, pos));
+        }
+
+        if max_digits != 0 && bytes.len() - pos > max_digits {
+            return Err(ParseIntError::new(ErrorKind::TooLong, pos + max_digits));
+        }
+
+        let mut buf = Vec::with_capacity(bytes.len() - pos);
+
+        for (off, &c) in bytes[pos..].iter().enumerate() {
+            match digit_value(c, base) {
+                Some(b) => buf.push(b),
+                None => return Err(ParseIntError::new(ErrorKind::InvalidDigit, pos + off))
+            }
+        }
+
+        Ok(Int::from_raw_digits(&buf, base, sign))
+    }
+
+    // Builds an Int from the non-empty slice of raw digit values `buf`, most-significant first.
+    fn from_raw_digits(buf: &[u8], base: u8, sign: i32) -> Int {
+        debug_assert!(buf.len() > 0);
+
+        let num_digits = ll::base::base_digits_to_len(buf.len(), base as u32);
+
+        let mut i = Int::with_capacity(num_digits as u32);
+
+        unsafe {
+            let size = ll::base::from_base(i.limbs_uninit(), buf.as_ptr(), buf.len(), base as u32);
+            i.size = (size as i32) * sign;
+        }
+
+        i
+    }
+
+    /**
+     * Divide self by other, returning the quotient, Q, and remainder, R as (Q, R).
+     *
+     * With N = self, D = other, Q and R satisfy: `N = QD + R`.
+     * The sign of `Q` and `R` are the same.
+     *
+     * This will panic if `other` is zero.
+     */
+    pub fn divmod(&self, other: &Int) -> (Int, Int) {
+        debug_assert!(self.well_formed());
+        debug_assert!(other.well_formed());
+        if other.sign() == 0 {
+            ll::divide_by_zero();
+        }
+        if self.sign() == 0 {
+            return (self.clone(), Int::zero())
+        }
+
+        let out_size = if self.abs_size() < other.abs_size() {
+            1
+        } else {
+            (self.abs_size() - other.abs_size()) + 1
+        };
+
+        let out_sign = self.sign() * other.sign();
+        let mut q = Int::with_capacity(out_size as u32);
+        q.size = out_size * out_sign;
+
+        let mut r = Int::with_capacity(other.abs_size() as u32);
         r.size = other.abs_size() * self.sign();
 
         unsafe {
             ll::divrem(q.limbs_mut(), r.limbs_mut(),
-                       self.limbs(), self.abs_size(),
-                       other.limbs(), other.abs_size());
+                       self.limbs(), self.abs_len(),
+                       other.limbs(), other.abs_len());
         }
 
         q.normalize();
         r.normalize();
+        q.validate("divmod");
+        r.validate("divmod");
 
         (q, r)
     }
 
+    /**
+     * Returns the smallest multiple of `m` that is greater than or equal to
+     * `self`.
+     *
+     * Panics if `m` is zero.
+     *
+     * ```
+     * # use framp::Int;
+     * assert_eq!(Int::from(13).next_multiple_of(&Int::from(4)), 16);
+     * assert_eq!(Int::from(-13).next_multiple_of(&Int::from(4)), -12);
+     * ```
+     */
+    pub fn next_multiple_of(&self, m: &Int) -> Int {
+        self.round_to_multiple(m, RoundMode::Ceiling)
+    }
+
+    /**
+     * Returns the largest multiple of `m` that is less than or equal to
+     * `self`.
+     *
+     * Panics if `m` is zero.
+     */
+    pub fn prev_multiple_of(&self, m: &Int) -> Int {
+        self.round_to_multiple(m, RoundMode::Floor)
+    }
+
+    /**
+     * Rounds `self` to a multiple of `m`, in the direction given by `mode`.
+     * Only the magnitude of `m` matters.
+     *
+     * Panics if `m` is zero.
+     */
+    pub fn round_to_multiple(&self, m: &Int, mode: RoundMode) -> Int {
+        if m.sign() == 0 {
+            ll::divide_by_zero();
+        }
+
+        // `r` has the sign of `self`, so `self - r` is `self` rounded towards zero
+        let r = self % m;
+        if r.sign() == 0 {
+            return self.clone();
+        }
+
+        let away = match mode {
+            RoundMode::Floor => r.sign() < 0,
+            RoundMode::Ceiling => r.sign() > 0,
+            RoundMode::TowardZero => false,
+            RoundMode::AwayFromZero => true,
+            RoundMode::Nearest => (&r << 1usize).cmp_abs(m) != Ordering::Less,
+        };
+
+        let mut res = self - &r;
+        if away {
+            // Move one multiple further from zero, in the direction of `r`
+            if r.sign() == m.sign() {
+                res += m;
+            } else {
+                res -= m;
+            }
+        }
+        res
+    }
+
     /**
      * Raises self to the power of exp
      */
@@ -454,24 +1276,66 @@ impl Int {
                 if exp & 1 == 0 {
                     signum = 1
                 }
+                if self.abs_size() == 1 && self.to_single_limb() == 1 {
+                    return Int::from(signum);
+                }
+                assert!(exp <= std::u32::MAX as usize, "power too large");
 
+                // The size of the result is known up front, so this is the
+                // only allocation that ends up in the result.
                 let ret_sz = unsafe {
-                    ll::pow::num_pow_limbs(self.limbs(), self.abs_size(), exp as u32)
+                    ll::pow::num_pow_limbs(self.limbs(), self.abs_len(), exp as u32)
                 };
                 let mut ret = Int::with_capacity(ret_sz as u32);
-                ret.size = ret_sz * signum;
+                ret.size = checked_size(ret_sz) * signum;
 
                 unsafe {
-                    ll::pow::pow(ret.limbs_mut(), self.limbs(), self.abs_size(), exp as u32);
+                    ll::pow::pow(ret.limbs_mut(), self.limbs(), self.abs_len(), exp as u32);
                 }
 
                 ret.normalize();
+                ret.validate("pow");
 
                 ret
             }
         }
     }
 
+    /**
+     * Raises self to the power of exp, or returns `None` if the result would
+     * have more than `max_bits` bits (as counted by `bit_length`).
+     *
+     * Results that are clearly too big are rejected before anything is
+     * computed, so this can be used on untrusted input.
+     *
+     * ```
+     * # use framp::Int;
+     * assert_eq!(Int::from(10).pow_checked(3, 64), Some(Int::from(1000)));
+     * assert_eq!(Int::from(10).pow_checked(1_000_000_000_000, 1 << 20), None);
+     * ```
+     */
+    pub fn pow_checked(&self, exp: usize, max_bits: usize) -> Option<Int> {
+        let bits = self.bit_length() as u64;
+        if bits <= 1 || exp <= 1 {
+            // 0, 1 and -1 raised to any power, and `self` itself or 1
+            let r = self.pow(exp);
+            return if r.bit_length() as usize <= max_bits { Some(r) } else { None };
+        }
+
+        // The result has between `exp * (bits - 1) + 1` and `exp * bits` bits
+        let min_bits = (exp as u64).saturating_mul(bits - 1).saturating_add(1);
+        if min_bits > max_bits as u64 {
+            return None;
+        }
+        // Too big to be represented at all
+        if exp > std::u32::MAX as usize || min_bits > std::i32::MAX as u64 * Limb::BITS as u64 {
+            return None;
+        }
+
+        let r = self.pow(exp);
+        if r.bit_length() as usize <= max_bits { Some(r) } else { None }
+    }
+
     /**
      * Returns the square of `self`.
      */
@@ -490,13 +1354,14 @@ impl Int {
                 unreachable!()
             }
         } else {
-            let sz = self.abs_size() * 2;
+            let sz = checked_size(self.abs_size() as usize * 2);
             let mut ret = Int::with_capacity(sz as u32);
             ret.size = sz;
             unsafe {
-                ll::sqr(ret.limbs_mut(), self.limbs(), self.abs_size());
+                ll::sqr(ret.limbs_mut(), self.limbs(), self.abs_len());
             }
             ret.normalize();
+            ret.validate("square");
 
             ret
         }
@@ -519,7 +1384,30 @@ impl Int {
                 unreachable!()
             }
         } else {
-            self.square()
+            self.square_assign();
+            self
+        }
+    }
+
+    /**
+     * Squares `self` in place, reusing its storage when it is big enough.
+     */
+    pub fn square_assign(&mut self) {
+        debug_assert!(self.well_formed());
+        match self.abs_size() {
+            0 => {}
+            1 => {
+                let l = self.to_single_limb();
+                self.size = 1;
+                *self *= l;
+            }
+            size => {
+                self.with_moved_value((size * 2) as u32, |wp, xp, xs| unsafe {
+                    ll::sqr(wp, xp, xs);
+                });
+                self.size = size * 2;
+                self.normalize();
+            }
         }
     }
 
@@ -581,6 +1469,108 @@ impl Int {
         }
     }
 
+    /// Sets `self` to `a + b`, reusing the storage of `self`.
+    pub fn assign_add(&mut self, a: &Int, b: &Int) {
+        self.clone_from(a);
+        *self += b;
+    }
+
+    /// Sets `self` to `a - b`, reusing the storage of `self`.
+    pub fn assign_sub(&mut self, a: &Int, b: &Int) {
+        self.clone_from(a);
+        *self -= b;
+    }
+
+    /// Sets `self` to `a * b`, reusing the storage of `self`.
+    ///
+    /// The product is written directly into `self`, which only needs to grow
+    /// if it has less than `a.abs_size() + b.abs_size()` limbs of capacity.
+    pub fn assign_mul(&mut self, a: &Int, b: &Int) {
+        debug_assert!(a.well_formed());
+        debug_assert!(b.well_formed());
+        if a.sign() == 0 || b.sign() == 0 {
+            self.size = 0;
+            return;
+        }
+
+        let (x, y) = if a.abs_size() >= b.abs_size() { (a, b) } else { (b, a) };
+        if y.abs_size() == 1 {
+            self.clone_from(x);
+            *self *= y.to_single_limb();
+            if y.sign() < 0 {
+                self.negate();
+            }
+            return;
+        }
+
+        let out_size = checked_size(x.abs_size() as usize + y.abs_size() as usize);
+        self.ensure_capacity_discard(out_size as u32);
+        unsafe {
+            ll::mul(self.limbs_uninit(), x.limbs(), x.abs_len(), y.limbs(), y.abs_len());
+        }
+        self.size = out_size * a.sign() * b.sign();
+        self.normalize();
+    }
+
+    /// Sets `self` to `a * a`, reusing the storage of `self`.
+    pub fn assign_square(&mut self, a: &Int) {
+        debug_assert!(a.well_formed());
+        if a.abs_size() <= 1 {
+            self.assign_mul(a, a);
+            return;
+        }
+
+        let out_size = checked_size(a.abs_size() as usize * 2);
+        self.ensure_capacity_discard(out_size as u32);
+        unsafe {
+            ll::sqr(self.limbs_uninit(), a.limbs(), a.abs_len());
+        }
+        self.size = out_size;
+        self.normalize();
+    }
+
+    /// Sets `self` to `a` raised to the power `exp`, reusing the storage of
+    /// `self`.
+    pub fn assign_pow(&mut self, a: &Int, exp: usize) {
+        debug_assert!(a.well_formed());
+        match exp {
+            0 => self.assign(1),
+            1 => self.clone_from(a),
+            2 => self.assign_square(a),
+            _ => {
+                if a.sign() == 0 {
+                    self.size = 0;
+                    return;
+                }
+                let signum = if exp & 1 == 0 { 1 } else { a.sign() };
+                if a.abs_size() == 1 && a.to_single_limb() == 1 {
+                    self.assign(signum);
+                    return;
+                }
+                assert!(exp <= std::u32::MAX as usize, "power too large");
+
+                let out_size = unsafe {
+                    ll::pow::num_pow_limbs(a.limbs(), a.abs_len(), exp as u32)
+                };
+                self.ensure_capacity_discard(out_size as u32);
+                unsafe {
+                    ll::pow::pow(self.limbs_uninit(), a.limbs(), a.abs_len(), exp as u32);
+                }
+                self.size = checked_size(out_size) * signum;
+                self.normalize();
+            }
+        }
+    }
+
+    /// Sets `self` to `base` raised to the power `exp` modulo `modulus`.
+    ///
+    /// The intermediate values are still allocated, only the storage for the
+    /// result is reused. The same restrictions as for `modpow` apply.
+    pub fn assign_pow_mod(&mut self, base: &Int, exp: &Int, modulus: &Int) {
+        let r = base.modpow(exp, modulus);
+        self.assign(&r);
+    }
+
     /**
      * Negates `self` in-place
      */
@@ -611,11 +1601,44 @@ impl Int {
             0
         } else {
             unsafe {
-                ll::scan_1(self.limbs(), self.abs_size())
+                ll::scan_1(self.limbs(), self.abs_len())
             }
         }
     }
 
+    /**
+     * Returns the index of the first 1 bit at or above bit `from`, treating
+     * negative numbers as if they're stored in two's complement.
+     *
+     * Returns `None` if there is no such bit, which can only happen if
+     * `self` is non-negative.
+     */
+    pub fn bit_scan1(&self, from: u32) -> Option<u32> {
+        debug_assert!(self.well_formed());
+        if self.sign() >= 0 {
+            scan_abs(self, from, true)
+        } else {
+            // in two's complement, -x is !(x - 1)
+            scan_abs(&(-self - 1), from, false)
+        }
+    }
+
+    /**
+     * Returns the index of the first 0 bit at or above bit `from`, treating
+     * negative numbers as if they're stored in two's complement.
+     *
+     * Returns `None` if there is no such bit, which can only happen if
+     * `self` is negative.
+     */
+    pub fn bit_scan0(&self, from: u32) -> Option<u32> {
+        debug_assert!(self.well_formed());
+        if self.sign() >= 0 {
+            scan_abs(self, from, false)
+        } else {
+            scan_abs(&(-self - 1), from, true)
+        }
+    }
+
     /**
      * Returns the number of ones (the population count) in this number
      *
@@ -627,11 +1650,37 @@ impl Int {
         if self.sign() < 0 {
             std::usize::MAX
         } else {
-            let bytes = unsafe {
-                std::slice::from_raw_parts(self.ptr.as_ref() as *const _ as *const u8,
-                                           self.abs_size() as usize * std::mem::size_of::<Limb>())
-            };
-            hamming::weight(bytes) as usize
+            unsafe {
+                ll::popcount(self.limbs(), self.abs_len())
+            }
+        }
+    }
+
+    /**
+     * Returns the number of bits that differ between this number and
+     * `other`, treating negative numbers as if they're stored in two's
+     * complement.
+     *
+     * If exactly one of the numbers is negative, infinitely many bits
+     * differ, so this returns usize::MAX.
+     */
+    pub fn hamming_distance(&self, other: &Int) -> usize {
+        debug_assert!(self.well_formed());
+        debug_assert!(other.well_formed());
+        match (self.sign() < 0, other.sign() < 0) {
+            (false, false) => unsafe {
+                ll::hamdist(self.limbs(), self.abs_len(), other.limbs(), other.abs_len())
+            },
+            (true, true) => {
+                // in two's complement, -x is !(x - 1), and the complements
+                // cancel out
+                let a = -self - 1;
+                let b = -other - 1;
+                unsafe {
+                    ll::hamdist(a.limbs(), a.abs_len(), b.limbs(), b.abs_len())
+                }
+            }
+            _ => std::usize::MAX
         }
     }
 
@@ -647,16 +1696,61 @@ impl Int {
             1
         } else {
             unsafe {
-                ll::base::num_base_digits(self.limbs(), self.abs_size(), 2) as u32
+                ll::base::num_base_digits(self.limbs(), self.abs_len(), 2) as u32
             }
         }
     }
 
     /**
-     * Returns the value of the `bit`th bit in this number, as if it
-     * were represented in two's complement.
+     * Returns an iterator over the bits of the absolute value of this
+     * number, from the least- to the most-significant one.
+     *
+     * The iterator yields exactly `bit_length()` bits for non-zero
+     * numbers, and none for zero. Use `.rev()` to start from the
+     * most-significant bit instead.
+     *
+     * ```
+     * # use framp::Int;
+     * let bits: Vec<bool> = Int::from(6).bits().collect();
+     * assert_eq!(bits, [false, true, true]);
+     * ```
      */
-    #[inline]
+    pub fn bits(&self) -> Bits {
+        let len = if self.sign() == 0 { 0 } else { self.bit_length() };
+        Bits { int: self, front: 0, back: len }
+    }
+
+    /**
+     * Returns an iterator over the `k`-bit windows of the absolute value of
+     * this number, starting from the most-significant end.
+     *
+     * The windows are aligned to the least-significant bit, so the first one
+     * may be narrower than `k` bits, and the number is the sum of the windows
+     * scaled by successive powers of `2^k`. Zero has no windows.
+     *
+     * Panics if `k` is zero or greater than 32.
+     *
+     * ```
+     * # use framp::Int;
+     * let w: Vec<u32> = Int::from(0x12345).windows(8).collect();
+     * assert_eq!(w, [0x01, 0x23, 0x45]);
+     * ```
+     */
+    pub fn windows(&self, k: u32) -> Windows {
+        assert!(k > 0 && k <= 32, "invalid window size: {}", k);
+        let len = if self.sign() == 0 { 0 } else { self.bit_length() };
+        Windows {
+            int: self,
+            k: k,
+            remaining: (len + k - 1) / k,
+        }
+    }
+
+    /**
+     * Returns the value of the `bit`th bit in this number, as if it
+     * were represented in two's complement.
+     */
+    #[inline]
     pub fn bit(&self, bit: u32) -> bool {
         let word = (bit / Limb::BITS as u32) as isize;
         let subbit = bit % Limb::BITS as u32;
@@ -703,8 +1797,8 @@ impl Int {
                 // used once the bit is set
                 self.ensure_capacity(word + 1);
 
-                let size = self.abs_size();
-                ll::zero(self.limbs_uninit().offset(size as isize), word as i32 - size + 1);
+                let size = self.abs_len();
+                ll::zero(self.limbs_uninit().offset(size as isize), word as usize - size + 1);
 
                 self.size = word as i32 + 1;
                 if sign < 0 {
@@ -734,25 +1828,337 @@ impl Int {
         self.normalize()
     }
 
+    /**
+     * Clears the `bit`th bit of this number, treating negative numbers
+     * as if they're stored in two's complement.
+     */
+    #[inline]
+    pub fn clear_bit(&mut self, bit: u32) {
+        self.set_bit(bit, false)
+    }
+
+    /**
+     * Flips the `bit`th bit of this number, treating negative numbers
+     * as if they're stored in two's complement.
+     */
+    #[inline]
+    pub fn toggle_bit(&mut self, bit: u32) {
+        let val = self.bit(bit);
+        self.set_bit(bit, !val)
+    }
+
+    /**
+     * Shifts this number left by `cnt` bits, returning `None` if the result
+     * would be too large to be represented.
+     *
+     * Unlike `<<`, which aborts when it fails to allocate, this can be used
+     * with shift counts coming from untrusted input.
+     */
+    pub fn checked_shl(&self, cnt: usize) -> Option<Int> {
+        debug_assert!(self.well_formed());
+        if self.sign() == 0 {
+            return Some(Int::zero());
+        }
+
+        let limbs = (self.abs_size() as usize)
+            .checked_add(cnt / Limb::BITS)
+            .and_then(|l| l.checked_add(1));
+        match limbs {
+            Some(l) if l <= std::i32::MAX as usize => Some(self << cnt),
+            _ => None
+        }
+    }
+
+    /**
+     * Shifts this number left by `limbs` whole limbs, without moving any
+     * data. See `ShiftedInt` for details.
+     */
+    #[inline]
+    pub fn shl_limbs(self, limbs: usize) -> ShiftedInt {
+        ShiftedInt { value: self, limbs: limbs }
+    }
+
+    /**
+     * Returns the `bits` least-significant bits of this number, treating
+     * negative numbers as if they're stored in two's complement.
+     *
+     * The result is always non-negative and equal to `self` modulo
+     * `2^bits`.
+     */
+    pub fn keep_bits(&self, bits: usize) -> Int {
+        let mut ret = self.clone();
+        ret.keep_bits_assign(bits);
+        ret
+    }
+
+    /**
+     * Truncates this number to its `bits` least-significant bits, treating
+     * negative numbers as if they're stored in two's complement.
+     *
+     * Afterwards, `self` is non-negative and equal to its old value modulo
+     * `2^bits`.
+     */
+    pub fn keep_bits_assign(&mut self, bits: usize) {
+        debug_assert!(self.well_formed());
+        let sign = self.sign();
+        if sign == 0 {
+            return;
+        }
+
+        let words = (bits + Limb::BITS - 1) / Limb::BITS;
+        let size = self.abs_size() as usize;
+
+        unsafe {
+            if sign > 0 {
+                // only a partial top limb still needs masking
+                if words > size || (words == size && bits % Limb::BITS == 0) {
+                    return;
+                }
+            } else {
+                // the two's complement of the low limbs only depends on
+                // those limbs, so work on exactly `words` of them, with
+                // zeros above the magnitude turning into ones
+                if words > size {
+                    self.ensure_capacity(words as u32);
+                    ll::zero(self.limbs_uninit().offset(size as isize), words - size);
+                }
+                let ptr = self.limbs_uninit();
+                ll::twos_complement(ptr, ptr.as_const(), words);
+            }
+
+            self.size = words as i32;
+
+            let rem = bits % Limb::BITS;
+            if rem != 0 {
+                let mut top = self.limbs_uninit().offset((words - 1) as isize);
+                *top = *top & ((Limb(1) << rem) - 1);
+            }
+        }
+
+        self.normalize();
+    }
+
+    /**
+     * Returns `self` modulo `2^bits`, with the sign of `self`, like `%`. Only the low limbs
+     * are copied, there is no division. `keep_bits` gives the non-negative residue instead.
+     *
+     * ```
+     * # use framp::Int;
+     * let x = Int::from(-13);
+     * assert_eq!(x.mod_2exp(3), -5);
+     * assert_eq!(x.keep_bits(3), 3);
+     * ```
+     */
+    pub fn mod_2exp(&self, bits: usize) -> Int {
+        debug_assert!(self.well_formed());
+        let words = std::cmp::min(self.abs_len(), (bits + Limb::BITS - 1) / Limb::BITS);
+        if words == 0 {
+            return Int::zero();
+        }
+
+        let mut ret = Int::with_capacity(words as u32);
+        unsafe {
+            ret.size = ll::mod_2exp(ret.limbs_uninit(), self.limbs(), self.abs_len(), bits) as i32;
+        }
+        if self.sign() < 0 {
+            ret.negate();
+        }
+        ret.validate("mod_2exp");
+        ret
+    }
+
+    /**
+     * Returns the bits of this number in `range` (least-significant bit
+     * first), as a non-negative number. Negative numbers are treated as if
+     * they're stored in two's complement.
+     *
+     * ```
+     * # use framp::Int;
+     * let x = Int::from(0b1101_0110);
+     * assert_eq!(x.extract_bits(2..6), 0b0101);
+     * ```
+     */
+    pub fn extract_bits(&self, range: Range<usize>) -> Int {
+        debug_assert!(self.well_formed());
+        assert!(range.start <= range.end, "invalid bit range");
+
+        if self.sign() < 0 {
+            return self.keep_bits(range.end).extract_bits(range);
+        }
+
+        let size = self.abs_size() as usize;
+        let lo = range.start / Limb::BITS;
+        if lo >= size || range.start == range.end {
+            return Int::zero();
+        }
+        let hi = std::cmp::min(size, (range.end + Limb::BITS - 1) / Limb::BITS);
+        let n = hi - lo;
+
+        let mut ret = Int::with_capacity(n as u32);
+        unsafe {
+            let src = self.limbs().offset(lo as isize);
+            let dst = ret.limbs_uninit();
+            let shift = (range.start % Limb::BITS) as u32;
+            if shift == 0 {
+                ll::copy_incr(src, dst, n);
+            } else {
+                ll::shr(dst, src, n, shift);
+            }
+        }
+        ret.size = n as i32;
+        ret.keep_bits_assign(range.end - range.start);
+        ret
+    }
+
+    /**
+     * Replaces the bits of this number in `range` with the low bits of
+     * `value`. Negative numbers, both `self` and `value`, are treated as if
+     * they're stored in two's complement.
+     *
+     * ```
+     * # use framp::Int;
+     * let mut x = Int::from(0b1111_1111);
+     * x.insert_bits(2..6, &Int::from(0b1001));
+     * assert_eq!(x, 0b1110_0111);
+     * ```
+     */
+    pub fn insert_bits(&mut self, range: Range<usize>, value: &Int) {
+        debug_assert!(self.well_formed());
+        assert!(range.start <= range.end, "invalid bit range");
+
+        let len = range.end - range.start;
+        if len == 0 {
+            return;
+        }
+
+        if self.sign() < 0 {
+            // the bits in the range contribute exactly `old << start` to
+            // the value, so replace that with the new bits
+            let old = self.extract_bits(range.clone());
+            let new = value.keep_bits(len);
+            *self += (new - old) << range.start;
+            return;
+        }
+
+        let lo = range.start / Limb::BITS;
+        let hi = (range.end + Limb::BITS - 1) / Limb::BITS;
+        let size = self.abs_size() as usize;
+        let bits = value.keep_bits(len) << (range.start % Limb::BITS);
+
+        unsafe {
+            if hi > size {
+                self.ensure_capacity(hi as u32);
+                ll::zero(self.limbs_uninit().offset(size as isize), hi - size);
+                self.size = hi as i32;
+            }
+
+            let ptr = self.limbs_uninit();
+            for w in lo..hi {
+                let low = if w == lo { range.start % Limb::BITS } else { 0 };
+                let high = if w == hi - 1 && range.end % Limb::BITS != 0 {
+                    range.end % Limb::BITS
+                } else {
+                    Limb::BITS
+                };
+                let below_high = if high == Limb::BITS { !Limb(0) } else { (Limb(1) << high) - 1 };
+                let mask = below_high & !((Limb(1) << low) - 1);
+
+                let mut l = ptr.offset(w as isize);
+                *l = *l & !mask;
+            }
+
+            let dst = ptr.offset(lo as isize);
+            ll::or_n(dst, dst.as_const(), bits.limbs(), bits.abs_len());
+        }
+
+        self.normalize();
+    }
+
+    /**
+     * Splits this number at bit `bit`, returning the high and low parts
+     * `(h, l)` such that `self == (h << bit) + l` and `0 <= l < 2^bit`.
+     *
+     * For negative numbers this means `h` is rounded towards negative
+     * infinity, and `l` holds the low bits of the two's complement
+     * representation.
+     *
+     * ```
+     * # use framp::Int;
+     * let (h, l) = Int::from(0x1234).split_at_bit(8);
+     * assert_eq!(h, 0x12);
+     * assert_eq!(l, 0x34);
+     * ```
+     */
+    pub fn split_at_bit(&self, bit: usize) -> (Int, Int) {
+        debug_assert!(self.well_formed());
+        if self.sign() >= 0 {
+            // both halves only copy the limbs they need
+            (self >> bit, self.extract_bits(0..bit))
+        } else {
+            let low = self.keep_bits(bit);
+            ((self - &low) >> bit, low)
+        }
+    }
+
+    /**
+     * Divides this number by `2^bit`, rounding towards negative infinity,
+     * and returns the quotient and the (non-negative) remainder. This is
+     * the same as `split_at_bit`, but reuses the storage of `self` for the
+     * quotient.
+     */
+    pub fn div_mod_2exp(mut self, bit: usize) -> (Int, Int) {
+        debug_assert!(self.well_formed());
+        let low = self.keep_bits(bit);
+        if self.sign() < 0 {
+            self -= &low;
+        }
+        self >>= bit;
+        (self, low)
+    }
+
+    // get a pointer to the limbs, which are in `ptr` itself if nothing is allocated
+    #[inline(always)]
+    fn limbs_ptr(&self) -> *const Limb {
+        if self.cap == 0 {
+            &self.ptr as *const Unique<Limb> as *const Limb
+        } else {
+            self.ptr.as_ptr()
+        }
+    }
+    // move a value stored inline to the heap, so it can be modified
+    #[inline(always)]
+    fn make_owned(&mut self) {
+        if self.cap == 0 && self.size != 0 {
+            self.ensure_capacity(1);
+        }
+    }
     // get a Limbs to all limbs currently initialised/in use
     fn limbs(&self) -> Limbs {
         unsafe {
-            Limbs::new(self.ptr.as_ref(), 0, self.abs_size())
+            Limbs::new(self.limbs_ptr(), 0, self.abs_size())
         }
     }
     // get a LimbsMut to all limbs currently initialised/in use
     fn limbs_mut(&mut self) -> LimbsMut {
+        self.make_owned();
         unsafe {
             LimbsMut::new(self.ptr.as_ptr(), 0, self.abs_size())
         }
     }
     // get a LimbsMut to all allocated limbs
     unsafe fn limbs_uninit(&mut self) -> LimbsMut {
+        self.make_owned();
         LimbsMut::new(self.ptr.as_ptr(), 0, self.cap as i32)
     }
 
     fn ensure_capacity(&mut self, cap: u32) {
         if cap > self.cap {
+            checked_size(cap as usize);
+            check_memory_budget(cap as usize * mem::size_of::<Limb>());
+            if cfg!(feature = "zeroize") && self.cap > 0 {
+                self.rebuffer(cap);
+                return;
+            }
             let old_cap = self.cap as usize;
             self.with_raw_vec(|v| {
                 v.reserve_exact(old_cap, cap as usize - old_cap)
@@ -760,9 +2166,130 @@ impl Int {
         }
     }
 
+    // Moves the value to a new buffer of exactly `cap` limbs, wiping and freeing
+    // the old one. With the `zeroize` feature, this replaces reallocation,
+    // which could leave a copy of the value behind in freed memory.
+    fn rebuffer(&mut self, cap: u32) {
+        debug_assert!(cap as i32 >= self.abs_size());
+        let mut new = Int::with_capacity(cap);
+        unsafe {
+            ll::copy_incr(self.limbs(), new.limbs_uninit(), self.abs_len());
+        }
+        new.size = self.size;
+        mem::swap(self, &mut new);
+    }
+
+    // Overwrites the whole allocation with zeros when the `zeroize` feature is
+    // enabled, before it is freed.
+    #[cfg(feature = "zeroize")]
+    #[inline]
+    fn wipe_allocation(&mut self) {
+        unsafe {
+            ::mem::wipe_limbs(self.ptr.as_ptr(), self.cap as usize);
+        }
+    }
+
+    #[cfg(not(feature = "zeroize"))]
+    #[inline(always)]
+    fn wipe_allocation(&mut self) {}
+
+    // Like `ensure_capacity`, but reports allocation failures instead of
+    // aborting. The capacity is exact.
+    fn try_ensure_capacity(&mut self, cap: usize) -> Result<(), AllocError> {
+        if cap <= self.cap as usize {
+            return Ok(());
+        }
+        let elem = mem::size_of::<Limb>();
+        if cap > MAX_LIMBS || cap > std::isize::MAX as usize / elem {
+            return Err(AllocError::new(cap));
+        }
+        if !within_memory_budget(cap * elem) {
+            return Err(AllocError { limbs: cap, over_budget: true });
+        }
+
+        unsafe {
+            let align = mem::align_of::<Limb>();
+            let old_cap = self.cap as usize;
+            let inline = if self.cap == 0 && self.size != 0 {
+                Some(self.to_single_limb())
+            } else {
+                None
+            };
+
+            let new_ptr = if old_cap == 0 {
+                alloc::heap::allocate(cap * elem, align)
+            } else if cfg!(feature = "zeroize") {
+                // Copy and wipe by hand rather than leave the value in freed memory
+                let p = alloc::heap::allocate(cap * elem, align);
+                if !p.is_null() {
+                    let old = self.ptr.as_ptr();
+                    std::ptr::copy_nonoverlapping(old as *const u8, p, old_cap * elem);
+                    self.wipe_allocation();
+                    alloc::heap::deallocate(old as *mut u8, old_cap * elem, align);
+                }
+                p
+            } else {
+                alloc::heap::reallocate(self.ptr.as_ptr() as *mut u8, old_cap * elem,
+                                        cap * elem, align)
+            };
+            if new_ptr.is_null() {
+                return Err(AllocError::new(cap));
+            }
+            ::mem::count_heap_allocation();
+            std::ptr::write_bytes(new_ptr.offset((old_cap * elem) as isize), 0,
+                                  (cap - old_cap) * elem);
+
+            self.ptr = Unique::new(new_ptr as *mut Limb);
+            self.cap = cap as u32;
+            if let Some(l) = inline {
+                *self.ptr.as_ptr() = l;
+            }
+        }
+        Ok(())
+    }
+
+    // Like `ensure_capacity`, but grows the allocation geometrically, so that
+    // a value that keeps growing by a limb or two at a time through in-place
+    // operations doesn't get reallocated every time.
+    fn grow(&mut self, cap: u32) {
+        if cap > self.cap {
+            let cap = std::cmp::max(cap, std::cmp::min(self.cap.saturating_mul(2), MAX_LIMBS as u32));
+            self.ensure_capacity(cap);
+        }
+    }
+
+    // Calls `f` with storage for at least `cap` limbs and a copy of the current
+    // value, for operations whose output can't overlap their input. `f` is
+    // responsible for writing the new value; the size is left for the caller
+    // to set.
+    fn with_moved_value<F>(&mut self, cap: u32, f: F)
+        where F: FnOnce(LimbsMut, Limbs, usize)
+    {
+        let size = self.abs_len();
+        self.grow(cap);
+
+        unsafe {
+            // Small values end up in the allocator's inline buffer
+            let mut tmp = TmpAllocator::new();
+            let xp = tmp.allocate(size);
+            ll::copy_incr(self.limbs(), xp, size);
+
+            f(self.limbs_uninit(), xp.as_const(), size);
+        }
+    }
+
+    // Makes room for `cap` limbs without preserving the current value, which
+    // is set to zero.
+    fn ensure_capacity_discard(&mut self, cap: u32) {
+        self.size = 0;
+        if cap > self.cap {
+            *self = Int::with_capacity(cap);
+        }
+    }
+
     fn push(&mut self, limb: Limb) {
         let new_size = (self.abs_size() + 1) as u32;
-        self.ensure_capacity(new_size);
+        self.grow(new_size);
         unsafe {
             let pos = self.abs_size();
             *self.limbs_uninit().offset(pos as isize) = limb;
@@ -784,7 +2311,7 @@ impl Int {
         let sign = self.sign();
         unsafe {
             while self.size != 0 &&
-                *self.ptr.as_ptr().offset((self.abs_size() - 1) as isize) == 0 {
+                *self.limbs_ptr().offset((self.abs_size() - 1) as isize) == 0 {
 
                 self.size -= sign;
             }
@@ -797,17 +2324,36 @@ impl Int {
      * the capacity and that the most significant limb is non-zero
      */
     fn well_formed(&self) -> bool {
-        if self.size == 0 { return true; }
+        self.invariant_violation().is_none()
+    }
 
-        if (self.abs_size() as u32) > self.cap {
-            return false;
+    // Describes the first broken invariant of the representation, if any
+    fn invariant_violation(&self) -> Option<&'static str> {
+        if self.size == 0 { return None; }
+
+        if (self.abs_size() as u32) > self.cap && !(self.cap == 0 && self.abs_size() == 1) {
+            return Some("size exceeds capacity");
         }
 
         let high_limb = unsafe {
-            *self.ptr.as_ptr().offset((self.abs_size() - 1) as isize)
+            *self.limbs_ptr().offset((self.abs_size() - 1) as isize)
         };
 
-        return high_limb != 0;
+        if high_limb == 0 {
+            return Some("high limb is zero, the size isn't normalized");
+        }
+        None
+    }
+
+    // With the `validate` feature in debug builds, panics if the invariants of
+    // the representation don't hold after the operation `op`.
+    #[inline(always)]
+    fn validate(&self, op: &'static str) {
+        if cfg!(all(feature = "validate", debug_assertions)) && !std::thread::panicking() {
+            if let Some(problem) = self.invariant_violation() {
+                invalid_int(op, problem, self.size, self.cap);
+            }
+        }
     }
 
     /**
@@ -817,7 +2363,7 @@ impl Int {
     fn negate_twos_complement(&mut self) {
         unsafe {
             let self_ptr = self.limbs_mut();
-            let carry = ll::twos_complement(self_ptr, self_ptr.as_const(), self.abs_size());
+            let carry = ll::twos_complement(self_ptr, self_ptr.as_const(), self.abs_len());
             if carry != 0 {
                 self.push(carry)
             }
@@ -825,6 +2371,92 @@ impl Int {
         self.size = -self.size;
     }
 
+    /// Divides out all factors of `f` from the number, returning the
+    /// remaining cofactor and the number of times `f` was divided out.
+    ///
+    /// Zero, and any number when `f` is 1 or -1, is returned unchanged with a
+    /// count of zero.
+    ///
+    /// # Panic
+    ///
+    /// * Panics if `f` is zero.
+    ///
+    /// ```
+    /// # use framp::Int;
+    /// let x = Int::from(2 * 3 * 3 * 3 * 5);
+    /// assert_eq!(x.remove_factor(&Int::from(3)), (Int::from(10), 3));
+    /// assert_eq!(x.remove_factor(&Int::from(-3)), (Int::from(-10), 3));
+    /// ```
+    pub fn remove_factor(&self, f: &Int) -> (Int, u32) {
+        if f.sign() == 0 {
+            ll::divide_by_zero();
+        }
+        if self.sign() == 0 || (f.abs_size() == 1 && f.to_single_limb() == 1) {
+            return (self.clone(), 0);
+        }
+
+        // Powers of two are just shifts
+        let f_zeros = f.trailing_zeros();
+        if f_zeros + 1 == f.bit_length() {
+            let count = self.trailing_zeros() / f_zeros;
+            let mut x = self >> (count * f_zeros) as usize;
+            if f.sign() < 0 && count & 1 == 1 {
+                x.negate();
+            }
+            return (x, count);
+        }
+
+        let (q, r) = self.divmod(f);
+        if r.sign() != 0 {
+            return (self.clone(), 0);
+        }
+        let mut x = q;
+        let mut count = 1;
+
+        // Divide by f^2, f^4, f^8, ... for as long as that works, then by the
+        // same powers in decreasing order to remove what's left. This takes a
+        // logarithmic number of divisions rather than one per factor.
+        let mut powers = vec![f.clone()];
+        loop {
+            let sq = powers.last().unwrap().square();
+            if sq.cmp_abs(&x) == Ordering::Greater {
+                break;
+            }
+            let (q, r) = x.divmod(&sq);
+            if r.sign() != 0 {
+                break;
+            }
+            x = q;
+            count += 1 << powers.len();
+            powers.push(sq);
+        }
+
+        for (i, p) in powers.iter().enumerate().rev() {
+            if p.cmp_abs(&x) == Ordering::Greater {
+                continue;
+            }
+            let (q, r) = x.divmod(p);
+            if r.sign() == 0 {
+                x = q;
+                count += 1 << i;
+            }
+        }
+
+        (x, count)
+    }
+
+    /// Returns the number of times `p` divides the number, its `p`-adic
+    /// valuation.
+    ///
+    /// # Panic
+    ///
+    /// * Panics if `p` is zero, or if the number is zero, whose valuation is
+    ///   infinite.
+    pub fn valuation(&self, p: &Int) -> u32 {
+        assert!(self.sign() != 0, "the valuation of zero is infinite");
+        self.remove_factor(p).1
+    }
+
     /// Calculates the Greatest Common Divisor (GCD) of the number and `other`.
     ///
     /// The result is always positive.
@@ -852,7 +2484,7 @@ impl Int {
         r.size = out_size;
 
         unsafe {
-            ll::gcd(r.limbs_mut(), a.limbs_mut(), a.abs_size(), b.limbs_mut(), b.abs_size());
+            ll::gcd(r.limbs_mut(), a.limbs_mut(), a.abs_len(), b.limbs_mut(), b.abs_len());
             r.normalize();
             r
         }
@@ -931,19 +2563,385 @@ impl Int {
         x1 + q*y
     }
 
-}
-
-impl Clone for Int {
-    fn clone(&self) -> Int {
-        debug_assert!(self.well_formed());
+    /// Computes `self` to the power of `exp` modulo the modulus of `reducer`, reducing the
+    /// intermediate values with it. `self` may be negative or larger than the modulus.
+    ///
+    /// ```
+    /// # use framp::Int;
+    /// use framp::int::reduce::Barrett;
+    /// let m = Barrett::new(&Int::from(1000));
+    /// assert_eq!(Int::from(3).modpow_with(&Int::from(7), &m), 187);
+    /// ```
+    ///
+    /// # Panic
+    ///
+    /// * Panics if exp is negative.
+    pub fn modpow_with<R: reduce::Reducer + ?Sized>(&self, exp: &Int, reducer: &R) -> Int {
+        reducer.pow(self, exp)
+    }
 
-        if self.sign() == 0 {
-            return Int::zero();
+    /// Returns whether `self` is probably prime, after trial division by the small primes and
+    /// `reps` rounds of the Miller-Rabin test.
+    ///
+    /// The Miller-Rabin bases are the first `reps` primes rather than random values. A composite
+    /// passes a round with probability at most 1/4, so the test is reliable for random or
+    /// otherwise non-adversarial inputs; numbers crafted to fool the fixed bases exist.
+    /// Values below 2^24 are always classified correctly by the trial division alone.
+    ///
+    /// Negative values, zero and one are not prime.
+    ///
+    /// ```
+    /// # use framp::Int;
+    /// assert!(Int::from(65537).is_probable_prime(20));
+    /// assert!(!(Int::from(2).pow(127) + 1).is_probable_prime(20));
+    /// assert!((Int::from(2).pow(127) - 1).is_probable_prime(20));
+    /// ```
+    pub fn is_probable_prime(&self, reps: u32) -> bool {
+        if *self < 2 {
+            return false;
         }
 
-        let mut new = Int::with_capacity(self.abs_size() as u32);
+        let primes = small_primes();
+        for &q in &primes {
+            if *self == q {
+                return true;
+            }
+            if self % q == 0 {
+                return false;
+            }
+        }
+        // A composite below SIEVE_LIMIT^2 has a factor below SIEVE_LIMIT
+        if self.bit_length() <= 24 {
+            return true;
+        }
+
+        self.miller_rabin(reps, &primes)
+    }
+
+    // Miller-Rabin with the first `reps` of `bases` as bases, for an odd `self` greater than
+    // all of them.
+    fn miller_rabin(&self, reps: u32, bases: &[u32]) -> bool {
+        let n1 = self - 1;
+        let s = n1.trailing_zeros() as usize;
+        let d = &n1 >> s;
+        'bases: for &b in bases.iter().take(reps as usize) {
+            let mut x = Int::from(b).modpow(&d, self);
+            if x == 1 || x == n1 {
+                continue;
+            }
+            for _ in 1..s {
+                x = x.dsquare() % self;
+                if x == n1 {
+                    continue 'bases;
+                }
+            }
+            return false;
+        }
+        true
+    }
+
+    /// Interprets `bytes` as a big-endian unsigned number and returns it reduced modulo
+    /// `modulus`.
+    ///
+    /// The input is consumed in chunks the size of the modulus, so the intermediate values never
+    /// grow much larger than the modulus, however long `bytes` is.
+    ///
+    /// # Panic
+    ///
+    /// * Panics if modulus is zero or negative.
+    ///
+    /// ```
+    /// # use framp::Int;
+    /// let m = Int::from(1000);
+    /// assert_eq!(Int::from_bytes_be_mod(&[0x12, 0x34, 0x56], &m), 0x123456 % 1000);
+    /// ```
+    pub fn from_bytes_be_mod(bytes: &[u8], modulus: &Int) -> Int {
+        if modulus.sign() == 0 {
+            ll::divide_by_zero();
+        }
+        assert!(modulus.sign() > 0);
+
+        let chunk_bytes = (modulus.abs_size() as usize) * (Limb::BITS / 8);
+        let first = bytes.len() % chunk_bytes;
+
+        let mut acc = Int::from_bytes_be(&bytes[..first]) % modulus;
+        for chunk in bytes[first..].chunks(chunk_bytes) {
+            acc <<= chunk_bytes * 8;
+            acc += Int::from_bytes_be(chunk);
+            acc %= modulus;
+        }
+
+        acc
+    }
+
+    /// Creates a non-negative `Int` from its bytes, most significant first.
+    ///
+    /// Bytes are converted to limbs with `Limb::from_be_bytes`, so the result doesn't depend on
+    /// the byte order of the target.
+    ///
+    /// ```
+    /// # use framp::Int;
+    /// assert_eq!(Int::from_bytes_be(&[0x01, 0x00, 0x02]), 0x010002);
+    /// ```
+    pub fn from_bytes_be(bytes: &[u8]) -> Int {
+        let limb_bytes = Limb::BITS / 8;
+        let mut limbs = Vec::with_capacity((bytes.len() + limb_bytes - 1) / limb_bytes);
+        let mut end = bytes.len();
+        while end > 0 {
+            let start = end.saturating_sub(limb_bytes);
+            limbs.push(Limb::from_be_bytes(&bytes[start..end]));
+            end = start;
+        }
+        Int::from_limbs(Sign::Positive, &limbs)
+    }
+
+    /// Creates a non-negative `Int` from its bytes, least significant first.
+    ///
+    /// ```
+    /// # use framp::Int;
+    /// assert_eq!(Int::from_bytes_le(&[0x01, 0x00, 0x02]), 0x020001);
+    /// ```
+    pub fn from_bytes_le(bytes: &[u8]) -> Int {
+        let limbs: Vec<Limb> = bytes.chunks(Limb::BITS / 8).map(Limb::from_le_bytes).collect();
+        Int::from_limbs(Sign::Positive, &limbs)
+    }
+
+    /// Returns the bytes of the absolute value of `self`, most significant first, without
+    /// leading zeros. The result is empty for zero.
+    pub fn to_bytes_be(&self) -> Vec<u8> {
+        let mut bytes = self.to_bytes_le();
+        bytes.reverse();
+        bytes
+    }
+
+    /// Returns the bytes of the absolute value of `self`, least significant first, without
+    /// trailing zeros. The result is empty for zero.
+    pub fn to_bytes_le(&self) -> Vec<u8> {
+        let limb_bytes = Limb::BITS / 8;
+        let mut bytes = vec![0; self.abs_len() * limb_bytes];
+        for (&l, out) in self.as_limb_slice().iter().zip(bytes.chunks_mut(limb_bytes)) {
+            l.write_le_bytes(out);
+        }
+        while bytes.last() == Some(&0) {
+            bytes.pop();
+        }
+        bytes
+    }
+
+}
+
+/// An iterator over the bits of the absolute value of an `Int`.
+///
+/// This is created by `Int::bits`.
+pub struct Bits<'a> {
+    int: &'a Int,
+    front: u32,
+    back: u32,
+}
+
+impl<'a> Bits<'a> {
+    #[inline]
+    fn get(&self, bit: u32) -> bool {
+        let word = (bit / Limb::BITS as u32) as isize;
+        let subbit = bit % Limb::BITS as u32;
+        unsafe {
+            (*self.int.limbs().offset(word)).0 & (1 << subbit) != 0
+        }
+    }
+}
+
+impl<'a> Iterator for Bits<'a> {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<bool> {
+        if self.front == self.back {
+            return None;
+        }
+        let b = self.get(self.front);
+        self.front += 1;
+        Some(b)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.back - self.front) as usize;
+        (len, Some(len))
+    }
+}
+
+impl<'a> DoubleEndedIterator for Bits<'a> {
+    fn next_back(&mut self) -> Option<bool> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(self.get(self.back))
+    }
+}
+
+impl<'a> ExactSizeIterator for Bits<'a> {}
+
+/// An iterator over the `k`-bit windows of the absolute value of an `Int`,
+/// from the most-significant end.
+///
+/// This is created by `Int::windows`.
+pub struct Windows<'a> {
+    int: &'a Int,
+    k: u32,
+    remaining: u32,
+}
+
+impl<'a> Iterator for Windows<'a> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let bits = Limb::BITS as u32;
+        let start = self.remaining * self.k;
+        let word = (start / bits) as isize;
+        let subbit = start % bits;
+        let size = self.int.abs_size() as isize;
+
+        let limbs = self.int.limbs();
+        let mut w = unsafe { (*limbs.offset(word)).0 >> subbit };
+        if subbit + self.k > bits && word + 1 < size {
+            w |= unsafe { (*limbs.offset(word + 1)).0 << (bits - subbit) };
+        }
+
+        Some((w & (!0 >> (bits - self.k))) as u32)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining as usize, Some(self.remaining as usize))
+    }
+}
+
+impl<'a> ExactSizeIterator for Windows<'a> {}
+
+/// An `Int` shifted left by a whole number of limbs, without the shift
+/// having been carried out yet.
+///
+/// Shifting by whole limbs only changes the position of the limbs, so
+/// `ShiftedInt` records the shift and defers the copy until the value is
+/// actually needed. Adding or subtracting a `ShiftedInt` to or from an `Int`
+/// works directly on the offset limbs, which makes it cheap to accumulate
+/// values at different positions, as is common in polynomial-style
+/// algorithms.
+///
+/// ```
+/// # use framp::Int;
+/// let mut acc = Int::from(1);
+/// acc += &Int::from(3).shl_limbs(2);
+/// assert_eq!(acc, (Int::from(3) << (2 * framp::ll::limb::Limb::BITS)) + 1);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ShiftedInt {
+    value: Int,
+    limbs: usize,
+}
+
+impl ShiftedInt {
+    /// Returns the unshifted value.
+    pub fn value(&self) -> &Int {
+        &self.value
+    }
+
+    /// Returns the shift, in limbs.
+    pub fn shift_limbs(&self) -> usize {
+        self.limbs
+    }
+
+    /// Shifts the value left by a further `limbs` limbs.
+    pub fn shl_limbs(mut self, limbs: usize) -> ShiftedInt {
+        self.limbs += limbs;
+        self
+    }
+
+    /// Carries out the shift, returning the resulting `Int`.
+    pub fn into_int(self) -> Int {
+        self.value << (self.limbs * Limb::BITS)
+    }
+}
+
+impl From<ShiftedInt> for Int {
+    fn from(s: ShiftedInt) -> Int {
+        s.into_int()
+    }
+}
+
+impl<'a> AddAssign<&'a ShiftedInt> for Int {
+    fn add_assign(&mut self, other: &'a ShiftedInt) {
+        debug_assert!(self.well_formed());
+        let sign = other.value.sign();
+        if sign == 0 {
+            self.validate("add_assign");
+            return;
+        }
+        if self.sign() != 0 && self.sign() != sign {
+            // the magnitudes need subtracting, which can affect all the
+            // limbs, so just do the shift
+            *self += other.clone().into_int();
+            self.validate("add_assign");
+            return;
+        }
+
+        let off = other.limbs;
+        let os = other.value.abs_len();
+        let ss = self.abs_len();
+        let size = std::cmp::max(ss, off + os) + 1;
+
+        self.ensure_capacity(size as u32);
+        unsafe {
+            let ptr = self.limbs_uninit();
+            ll::zero(ptr.offset(ss as isize), size - ss);
+
+            let dst = ptr.offset(off as isize);
+            let carry = ll::add(dst, dst.as_const(), size - 1 - off, other.value.limbs(), os);
+            *ptr.offset((size - 1) as isize) = carry;
+        }
+        self.size = checked_size(size) * sign;
+        self.normalize();
+        self.validate("add_assign");
+    }
+}
+
+impl AddAssign<ShiftedInt> for Int {
+    #[inline]
+    fn add_assign(&mut self, other: ShiftedInt) {
+        *self += &other;
+    }
+}
+
+impl<'a> SubAssign<&'a ShiftedInt> for Int {
+    fn sub_assign(&mut self, other: &'a ShiftedInt) {
+        self.negate();
+        *self += other;
+        self.negate();
+        self.validate("sub_assign");
+    }
+}
+
+impl SubAssign<ShiftedInt> for Int {
+    #[inline]
+    fn sub_assign(&mut self, other: ShiftedInt) {
+        *self -= &other;
+    }
+}
+
+impl Clone for Int {
+    fn clone(&self) -> Int {
+        debug_assert!(self.well_formed());
+
+        if self.sign() == 0 {
+            return Int::zero();
+        }
+
+        let mut new = Int::with_capacity(self.abs_size() as u32);
         unsafe {
-            ll::copy_incr(self.limbs(), new.limbs_uninit(), self.abs_size());
+            ll::copy_incr(self.limbs(), new.limbs_uninit(), self.abs_len());
         }
         new.size = self.size;
         new
@@ -959,12 +2957,42 @@ impl Clone for Int {
         }
         self.ensure_capacity(other.abs_size() as u32);
         unsafe {
-            ll::copy_incr(other.limbs(), self.limbs_uninit(), other.abs_size());
+            ll::copy_incr(other.limbs(), self.limbs_uninit(), other.abs_len());
             self.size = other.size;
         }
     }
 }
 
+impl<'a> Assign<&'a Int> for Int {
+    #[inline]
+    fn assign(&mut self, src: &'a Int) {
+        self.clone_from(src);
+    }
+}
+
+impl Assign<Int> for Int {
+    #[inline]
+    fn assign(&mut self, src: Int) {
+        // Taking over the allocation of `src` is cheaper than copying it
+        *self = src;
+    }
+}
+
+macro_rules! impl_assign_prim (
+    ($($t:ty),*) => ($(
+        impl Assign<$t> for Int {
+            #[inline]
+            fn assign(&mut self, src: $t) {
+                self.size = 0;
+                *self += src;
+            }
+        }
+    )*)
+);
+
+impl_assign_prim!(i8, i16, i32, i64, isize, i128);
+impl_assign_prim!(u8, u16, u32, u64, usize, u128);
+
 impl std::default::Default for Int {
     #[inline]
     fn default() -> Int {
@@ -972,9 +3000,28 @@ impl std::default::Default for Int {
     }
 }
 
+/// With the `zeroize` feature, all of the memory held by an `Int` is wiped
+/// before it is freed, including the old buffer when an `Int` grows.
+#[cfg(feature = "zeroize")]
+impl Int {
+    /// Sets the value to zero, overwriting all of the memory held by the
+    /// `Int` with zeros. The allocation is kept.
+    pub fn zeroize(&mut self) {
+        if self.cap == 0 {
+            // Also clears a value stored inline
+            *self = Int::zero();
+        } else {
+            self.wipe_allocation();
+            self.size = 0;
+        }
+    }
+}
+
 impl Drop for Int {
     fn drop(&mut self) {
+        self.validate("drop");
         if self.cap > 0 {
+            self.wipe_allocation();
             unsafe {
                 drop(RawVec::from_raw_parts(self.ptr.as_ptr(),
                                             self.cap as usize));
@@ -992,7 +3039,7 @@ impl PartialEq<Int> for Int {
         debug_assert!(other.well_formed());
         if self.size == other.size {
             unsafe {
-                ll::cmp(self.limbs(), other.limbs(), self.abs_size()) == Ordering::Equal
+                ll::cmp(self.limbs(), other.limbs(), self.abs_len()) == Ordering::Equal
             }
         } else {
             false
@@ -1036,9 +3083,9 @@ impl Ord for Int {
                 // If both are positive, do `self cmp other`, if both are
                 // negative, do `other cmp self`
                 if self.sign() == 1 {
-                    ll::cmp(self.limbs(), other.limbs(), self.abs_size())
+                    ll::cmp(self.limbs(), other.limbs(), self.abs_len())
                 } else {
-                    ll::cmp(other.limbs(), self.limbs(), self.abs_size())
+                    ll::cmp(other.limbs(), self.limbs(), self.abs_len())
                 }
             }
         }
@@ -1087,11 +3134,15 @@ impl hash::Hash for Int {
 impl AddAssign<Limb> for Int {
     fn add_assign(&mut self, other: Limb) {
         debug_assert!(self.well_formed());
-        if other == 0 { return; }
+        if other == 0 {
+            self.validate("add_assign");
+            return;
+        }
 
-        // No capacity means `self` is zero. Just push `other` into it
-        if self.cap == 0 {
+        // No capacity and no value means `self` is zero. Just push `other` into it
+        if self.cap == 0 && self.size == 0 {
             self.push(other);
+            self.validate("add_assign");
             return;
         }
         // This is zero, but has allocated space, so just store `other`
@@ -1099,13 +3150,14 @@ impl AddAssign<Limb> for Int {
             unsafe {
                 *self.limbs_uninit() = other;
                 self.size = 1;
+                self.validate("add_assign");
                 return
             }
         }
         // `self` is non-zero, reuse the storage for the result.
         unsafe {
             let sign = self.sign();
-            let size = self.abs_size();
+            let size = self.abs_len();
             let mut ptr = self.limbs_mut();
 
             // Self is positive, just add `other`
@@ -1126,6 +3178,7 @@ impl AddAssign<Limb> for Int {
                 self.normalize();
             }
         }
+        self.validate("add_assign");
     }
 }
 
@@ -1147,12 +3200,16 @@ impl<'a> AddAssign<&'a Int> for Int {
         if self.sign() == 0 {
             // Try to reuse the allocation from `self`
             self.clone_from(other);
+            self.validate("add_assign");
             return;
         }
         if other.sign() == 0 {
+            self.validate("add_assign");
             return;
         }
 
+        // The pointers to our limbs below have to stay valid once we start writing
+        self.make_owned();
 
         if self.sign() == other.sign() {
             // Signs are the same, add the two numbers together and re-apply
@@ -1165,10 +3222,10 @@ impl<'a> AddAssign<&'a Int> for Int {
                 // if `self` is the smaller of the two we make sure it has enough space
                 // for the result
                 let (xp, xs, yp, ys) = if self.abs_size() >= other.abs_size() {
-                    (self.limbs(), self.abs_size(), other.limbs(), other.abs_size())
+                    (self.limbs(), self.abs_len(), other.limbs(), other.abs_len())
                 } else {
-                    self.ensure_capacity(other.abs_size() as u32);
-                    (other.limbs(), other.abs_size(), self.limbs(), self.abs_size())
+                    self.grow(other.abs_size() as u32 + 1);
+                    (other.limbs(), other.abs_len(), self.limbs(), self.abs_len())
                 };
 
                 // Fetch the pointer first to make completely sure the compiler
@@ -1178,7 +3235,7 @@ impl<'a> AddAssign<&'a Int> for Int {
                 let carry = ll::add(ptr,
                                     xp, xs,
                                     yp, ys);
-                self.size = xs * sign;
+                self.size = xs as i32 * sign;
                 if carry != 0 {
                     self.push(carry);
                 }
@@ -1192,7 +3249,7 @@ impl<'a> AddAssign<&'a Int> for Int {
                 let (xp, xs, yp, ys) = if self.abs_size() > other.abs_size() {
                     (self.limbs(), self.size, other.limbs(), other.size)
                 } else if self.abs_size() < other.abs_size() {
-                    self.ensure_capacity(other.abs_size() as u32);
+                    self.grow(other.abs_size() as u32);
                     (other.limbs(), other.size, self.limbs(), self.size)
                 } else {
                     match self.abs_cmp(other) {
@@ -1200,6 +3257,7 @@ impl<'a> AddAssign<&'a Int> for Int {
                             // They're equal, but opposite signs, so the result
                             // will be zero, clear `self` and return
                             self.size = 0;
+                            self.validate("add_assign");
                             return;
                         }
                         Ordering::Greater =>
@@ -1214,8 +3272,8 @@ impl<'a> AddAssign<&'a Int> for Int {
                 let ptr = self.limbs_uninit();
 
                 let _borrow = ll::sub(ptr,
-                                      xp, xs.abs(),
-                                      yp, ys.abs());
+                                      xp, xs.abs() as usize,
+                                      yp, ys.abs() as usize);
                 // There shouldn't be any borrow
                 debug_assert!(_borrow == 0);
 
@@ -1224,6 +3282,7 @@ impl<'a> AddAssign<&'a Int> for Int {
                 debug_assert!(self.abs_size() > 0);
             }
         }
+        self.validate("add_assign");
     }
 }
 
@@ -1312,12 +3371,16 @@ impl<'a, 'b> Add<&'a Int> for &'b Int {
 impl SubAssign<Limb> for Int {
     fn sub_assign(&mut self, other: Limb) {
         debug_assert!(self.well_formed());
-        if other == 0 { return; }
+        if other == 0 {
+            self.validate("sub_assign");
+            return;
+        }
 
-        // No capacity means `self` is zero. Just push the limb.
-        if self.cap == 0 {
+        // No capacity and no value means `self` is zero. Just push the limb.
+        if self.cap == 0 && self.size == 0 {
             self.push(other);
             self.size = -1;
+            self.validate("sub_assign");
             return;
         }
         // This is zero, but has allocated space, so just store `other`
@@ -1326,12 +3389,13 @@ impl SubAssign<Limb> for Int {
                 *self.limbs_uninit() = other;
                 self.size = -1;
             }
+            self.validate("sub_assign");
             return;
         }
         // `self` is non-zero, reuse the storage for the result.
         unsafe {
             let sign = self.sign();
-            let size = self.abs_size();
+            let size = self.abs_len();
             let ptr = self.limbs_mut();
 
             // Self is negative, just "add" `other`
@@ -1354,6 +3418,7 @@ impl SubAssign<Limb> for Int {
         }
 
         debug_assert!(self.well_formed());
+        self.validate("sub_assign");
     }
 }
 
@@ -1376,13 +3441,18 @@ impl<'a> SubAssign<&'a Int> for Int {
         if self.sign() == 0 {
             self.clone_from(other);
             self.size *= -1;
+            self.validate("sub_assign");
             return;
         }
         // RHS is zero, do nothing
         if other.sign() == 0 {
+            self.validate("sub_assign");
             return;
         }
 
+        // The pointers to our limbs below have to stay valid once we start writing
+        self.make_owned();
+
         if self.sign() == other.sign() {
             unsafe {
                 // Signs are the same, subtract the smaller one from
@@ -1391,10 +3461,11 @@ impl<'a> SubAssign<&'a Int> for Int {
                     Ordering::Equal => {
                         // x - x, just return zero
                         self.size = 0;
+                        self.validate("sub_assign");
                         return;
                     }
                     Ordering::Less => {
-                        self.ensure_capacity(other.abs_size() as u32);
+                        self.grow(other.abs_size() as u32);
                         (other.limbs(), other.size, self.limbs(), self.size, true)
                     }
                     Ordering::Greater =>
@@ -1405,7 +3476,7 @@ impl<'a> SubAssign<&'a Int> for Int {
                 // won't make bogus claims about nonaliasing due to the &mut
                 let ptr = self.limbs_uninit();
 
-                let _borrow = ll::sub(ptr, xp, xs.abs(), yp, ys.abs());
+                let _borrow = ll::sub(ptr, xp, xs.abs() as usize, yp, ys.abs() as usize);
                 debug_assert!(_borrow == 0);
                 self.size = if flip {
                     xs * -1
@@ -1425,10 +3496,10 @@ impl<'a> SubAssign<&'a Int> for Int {
                 unsafe {
                     // Other is negative, handle as addition
                     let (xp, xs, yp, ys) = if self.abs_size() >= other.abs_size() {
-                        (self.limbs(), self.abs_size(), other.limbs(), other.abs_size())
+                        (self.limbs(), self.abs_len(), other.limbs(), other.abs_len())
                     } else {
-                        self.ensure_capacity(other.abs_size() as u32);
-                        (other.limbs(), other.abs_size(), self.limbs(), self.abs_size())
+                        self.grow(other.abs_size() as u32 + 1);
+                        (other.limbs(), other.abs_len(), self.limbs(), self.abs_len())
                     };
 
                     // Fetch the pointer first to make completely sure the compiler
@@ -1436,7 +3507,7 @@ impl<'a> SubAssign<&'a Int> for Int {
                     let ptr = self.limbs_uninit();
 
                     let carry = ll::add(ptr, xp, xs, yp, ys);
-                    self.size = xs;
+                    self.size = xs as i32;
                     if carry != 0 {
                         self.push(carry);
                     }
@@ -1444,6 +3515,7 @@ impl<'a> SubAssign<&'a Int> for Int {
                 }
             }
         }
+        self.validate("sub_assign");
     }
 }
 
@@ -1525,10 +3597,12 @@ impl MulAssign<Limb> for Int {
         debug_assert!(self.well_formed());
         if other == 0 || self.sign() == 0 {
             self.size = 0;
+            self.validate("mul_assign");
             return;
         }
 
         if other == 1 {
+            self.validate("mul_assign");
             return;
         }
 
@@ -1536,11 +3610,12 @@ impl MulAssign<Limb> for Int {
             // Fetch the pointer first to make completely sure the compiler
             // won't make bogus claims about nonaliasing due to the &mut
 
-            let carry = ll::mul_1(self.limbs_mut(), self.limbs(), self.abs_size(), other);
+            let carry = ll::mul_1(self.limbs_mut(), self.limbs(), self.abs_len(), other);
             if carry != 0 {
                 self.push(carry);
             }
         }
+        self.validate("mul_assign");
     }
 }
 
@@ -1569,6 +3644,11 @@ impl<'a, 'b> Mul<&'a Int> for &'b Int {
             return Int::zero();
         }
 
+        // x * x, squaring is faster
+        if self as *const Int == other as *const Int {
+            return self.square();
+        }
+
         let out_sign = self.sign() * other.sign();
 
         if self.abs_size() == 1 {
@@ -1584,21 +3664,22 @@ impl<'a, 'b> Mul<&'a Int> for &'b Int {
             return ret;
         }
 
-        let out_size = self.abs_size() + other.abs_size();
+        let out_size = checked_size(self.abs_size() as usize + other.abs_size() as usize);
 
         let mut out = Int::with_capacity(out_size as u32);
         out.size = out_size * out_sign;
 
         unsafe {
             let (xp, xs, yp, ys) = if self.abs_size() >= other.abs_size() {
-                (self.limbs(), self.abs_size(), other.limbs(), other.abs_size())
+                (self.limbs(), self.abs_len(), other.limbs(), other.abs_len())
             } else {
-                (other.limbs(), other.abs_size(), self.limbs(), self.abs_size())
+                (other.limbs(), other.abs_len(), self.limbs(), self.abs_len())
             };
             ll::mul(out.limbs_mut(), xp, xs, yp, ys);
 
             // Top limb may be zero
             out.normalize();
+            out.validate("mul");
             return out;
         }
     }
@@ -1668,32 +3749,61 @@ impl Mul<Int> for Int {
 }
 
 impl<'a> MulAssign<&'a Int> for Int {
-    #[inline]
     fn mul_assign(&mut self, other: &'a Int) {
+        debug_assert!(self.well_formed());
+        debug_assert!(other.well_formed());
         if self.sign() == 0 {
+            self.validate("mul_assign");
             return;
         }
         if other.sign() == 0 {
             self.size = 0;
+            self.validate("mul_assign");
             return;
         }
-        let res = &*self * other;
-        *self = res;
+
+        let out_sign = self.sign() * other.sign();
+
+        // Single-limb multiplications can be done in place
+        if other.abs_size() == 1 {
+            *self *= other.to_single_limb();
+            self.size = self.abs_size() * out_sign;
+            self.validate("mul_assign");
+            return;
+        }
+        if self.abs_size() == 1 {
+            let l = self.to_single_limb();
+            self.clone_from(other);
+            *self *= l;
+            self.size = self.abs_size() * out_sign;
+            self.validate("mul_assign");
+            return;
+        }
+
+        let out_size = checked_size(self.abs_size() as usize + other.abs_size() as usize);
+        self.with_moved_value(out_size as u32, |wp, xp, xs| unsafe {
+            if xs >= other.abs_len() {
+                ll::mul(wp, xp, xs, other.limbs(), other.abs_len());
+            } else {
+                ll::mul(wp, other.limbs(), other.abs_len(), xp, xs);
+            }
+        });
+
+        self.size = out_size * out_sign;
+        self.normalize();
+        self.validate("mul_assign");
     }
 }
 
 impl MulAssign<Int> for Int {
     #[inline]
-    fn mul_assign(&mut self, other: Int) {
-        if self.sign() == 0 {
-            return;
-        }
-        if other.sign() == 0 {
-            self.size = 0;
-            return;
+    fn mul_assign(&mut self, mut other: Int) {
+        // Keep whichever allocation is already big enough for the product
+        let out_size = self.abs_size() as u32 + other.abs_size() as u32;
+        if self.cap < out_size && other.cap >= out_size {
+            mem::swap(self, &mut other);
         }
-        let res = &*self * other;
-        *self = res;
+        *self *= &other;
     }
 }
 
@@ -1704,15 +3814,17 @@ impl DivAssign<Limb> for Int {
             ll::divide_by_zero();
         }
         if other == 1 || self.sign() == 0 {
+            self.validate("div_assign");
             return;
         }
 
         unsafe {
             // Ignore the remainder
-            ll::divrem_1(self.limbs_mut(), 0, self.limbs(), self.abs_size(), other);
+            ll::divrem_1(self.limbs_mut(), 0, self.limbs(), self.abs_len(), other);
             // Adjust the size if necessary
             self.normalize();
         }
+        self.validate("div_assign");
     }
 }
 
@@ -1779,6 +3891,7 @@ impl<'a> DivAssign<&'a Int> for Int {
     fn div_assign(&mut self, other: &'a Int) {
         let res = &*self / other;
         *self = res;
+        self.validate("div_assign");
     }
 }
 
@@ -1809,11 +3922,12 @@ impl RemAssign<Limb> for Int {
         // x % 1 == 0, 0 % n == 0
         if other == 1 || self.sign() == 0 {
             self.size = 0;
+            self.validate("rem_assign");
             return;
         }
 
         unsafe {
-            let rem = ll::divrem_1(self.limbs_mut(), 0, self.limbs(), self.abs_size(), other);
+            let rem = ll::mod_1(self.limbs(), self.abs_len(), other);
             // Reuse the space from `self`, taking the sign from the numerator
             // Since `rem` has to satisfy `N = QD + R` and D is always positive,
             // `R` will always be the same sign as the numerator.
@@ -1829,6 +3943,29 @@ impl RemAssign<Limb> for Int {
             }
 
         }
+        self.validate("rem_assign");
+    }
+}
+
+impl<'a> Rem<Limb> for &'a Int {
+    type Output = Int;
+
+    fn rem(self, other: Limb) -> Int {
+        debug_assert!(self.well_formed());
+        if other == 0 {
+            ll::divide_by_zero();
+        }
+        if self.sign() == 0 {
+            return Int::zero();
+        }
+
+        // Only the remainder is computed, so `self` isn't copied
+        let rem = unsafe { ll::mod_1(self.limbs(), self.abs_len(), other) };
+        if rem == 0 {
+            return Int::zero();
+        }
+        let r = Int::from_single_limb(rem);
+        if self.sign() < 0 { -r } else { r }
     }
 }
 
@@ -1846,7 +3983,7 @@ impl DivRem<Limb> for Int {
             return (self, Limb(0));
         }
 
-        let rem = unsafe { ll::divrem_1(self.limbs_mut(), 0, self.limbs(), self.abs_size(), other) };
+        let rem = unsafe { ll::divrem_1(self.limbs_mut(), 0, self.limbs(), self.abs_len(), other) };
         self.normalize();
         return (self, rem);
     }
@@ -1864,9 +4001,14 @@ impl<'a, 'b> Rem<&'a Int> for &'b Int {
         if other.sign() == 0 {
             ll::divide_by_zero();
         }
+        // A power of two, like the `1 << k` of `x % (1 << k)`, only keeps the low bits
+        let tz = other.trailing_zeros();
+        if tz + 1 == other.bit_length() {
+            return self.mod_2exp(tz as usize);
+        }
         if other.abs_size() == 1 {
             let l = *other.limbs();
-            return self.clone() % l;
+            return self % l;
         }
 
         self.divmod(other).1
@@ -1921,6 +4063,7 @@ impl<'a> RemAssign<&'a Int> for Int {
     fn rem_assign(&mut self, other: &'a Int) {
         let res = &*self % other;
         *self = res;
+        self.validate("rem_assign");
     }
 }
 
@@ -1944,26 +4087,51 @@ impl<'a> Neg for &'a Int {
     }
 }
 
+impl Not for Int {
+    type Output = Int;
+
+    /// Returns the bitwise complement of self as if it was stored in two's
+    /// complement, that is, `-self - 1`.
+    #[inline]
+    fn not(mut self) -> Int {
+        self.negate();
+        self - 1
+    }
+}
+
+impl<'a> Not for &'a Int {
+    type Output = Int;
+
+    #[inline]
+    fn not(self) -> Int {
+        !self.clone()
+    }
+}
+
 impl ShlAssign<usize> for Int {
     #[inline]
     fn shl_assign(&mut self, mut cnt: usize) {
         debug_assert!(self.well_formed());
-        if self.sign() == 0 { return; }
+        if self.sign() == 0 {
+            self.validate("shl_assign");
+            return;
+        }
 
         if cnt >= Limb::BITS as usize {
-            let extra_limbs = (cnt / Limb::BITS as usize) as u32;
+            let extra_limbs = cnt / Limb::BITS as usize;
             debug_assert!(extra_limbs >= 1);
             cnt = cnt % Limb::BITS as usize;
 
-            let size = self.abs_size() as u32;
             // Extend for the extra limbs, then another one for any potential extra limbs
-            self.ensure_capacity(extra_limbs + size + 1);
+            let size = checked_size((self.abs_size() as usize).saturating_add(extra_limbs));
+            self.grow(size as u32 + 1);
+            let extra_limbs = extra_limbs as u32;
 
             unsafe {
                 let ptr = self.limbs_uninit();
                 let shift = ptr.offset(extra_limbs as isize);
-                ll::copy_decr(ptr.as_const(), shift, self.abs_size());
-                ll::zero(ptr, extra_limbs as i32);
+                ll::copy_decr(ptr.as_const(), shift, self.abs_len());
+                ll::zero(ptr, extra_limbs as usize);
             }
 
             self.size += (extra_limbs as i32) * self.sign();
@@ -1971,9 +4139,12 @@ impl ShlAssign<usize> for Int {
 
         debug_assert!(cnt < Limb::BITS as usize);
 
-        if cnt == 0 { return; }
+        if cnt == 0 {
+            self.validate("shl_assign");
+            return;
+        }
 
-        let size = self.abs_size();
+        let size = self.abs_len();
 
         unsafe {
             let ptr = self.limbs_mut();
@@ -1982,6 +4153,7 @@ impl ShlAssign<usize> for Int {
                 self.push(c);
             }
         }
+        self.validate("shl_assign");
     }
 }
 
@@ -2006,9 +4178,9 @@ impl Shl<usize> for Int {
     }
 }
 
-impl ShrAssign<usize> for Int {
-    #[inline]
-    fn shr_assign(&mut self, mut cnt: usize) {
+impl Int {
+    // Shifts the magnitude of self right by `cnt` bits, keeping the sign
+    fn shr_abs(&mut self, mut cnt: usize) {
         debug_assert!(self.well_formed());
         if self.sign() == 0 { return; }
 
@@ -2028,10 +4200,10 @@ impl ShrAssign<usize> for Int {
                 let new_size = size - removed_limbs as i32;
 
                 // Shift down a whole number of limbs
-                ll::copy_incr(shift.as_const(), ptr, new_size);
+                ll::copy_incr(shift.as_const(), ptr, new_size as usize);
                 // Zero out the high limbs
                 ll::zero(ptr.offset(new_size as isize),
-                         removed_limbs as i32);
+                         removed_limbs as usize);
 
                 self.size = new_size * self.sign();
             }
@@ -2040,7 +4212,7 @@ impl ShrAssign<usize> for Int {
         debug_assert!(cnt < Limb::BITS as usize);
         if cnt == 0 { return; }
 
-        let size = self.abs_size();
+        let size = self.abs_len();
 
         unsafe {
             let ptr = self.limbs_mut();
@@ -2050,6 +4222,22 @@ impl ShrAssign<usize> for Int {
     }
 }
 
+impl ShrAssign<usize> for Int {
+    #[inline]
+    fn shr_assign(&mut self, cnt: usize) {
+        debug_assert!(self.well_formed());
+        // Like for primitive integers, shifting a negative number rounds
+        // towards negative infinity, so the magnitude has to be rounded up
+        // if any of the bits shifted out are set
+        let round_up = self.sign() < 0 && (self.trailing_zeros() as usize) < cnt;
+        self.shr_abs(cnt);
+        if round_up {
+            *self -= 1;
+        }
+        self.validate("shr_assign");
+    }
+}
+
 impl<'a> Shr<usize> for &'a Int {
     type Output = Int;
 
@@ -2071,12 +4259,90 @@ impl Shr<usize> for Int {
     }
 }
 
-#[derive(Copy, Clone)]
-enum BitOp { And, Or, Xor }
+// Shift counts of the other primitive types are converted to `usize`,
+// panicking if they are negative or too large.
+macro_rules! impl_shift_prim {
+    (signed $($t:ty),*) => (
+        $(impl_shift_prim!(common $t, |cnt: $t| {
+            if cnt < 0 {
+                panic!("negative shift count: {}", cnt);
+            }
+            to_shift_count(cnt as u64)
+        });)*
+    );
+    (unsigned $($t:ty),*) => (
+        $(impl_shift_prim!(common $t, |cnt: $t| to_shift_count(cnt as u64));)*
+    );
+    (common $t:ty, $cvt:expr) => (
+        impl ShlAssign<$t> for Int {
+            #[inline]
+            fn shl_assign(&mut self, cnt: $t) {
+                *self <<= $cvt(cnt);
+            }
+        }
 
-fn bitop_ref(this: &mut Int, other: &Int, op: BitOp) -> Result<(), ()> {
-    let this_sign = this.sign();
-    let other_sign = other.sign();
+        impl<'a> Shl<$t> for &'a Int {
+            type Output = Int;
+
+            #[inline]
+            fn shl(self, cnt: $t) -> Int {
+                self << $cvt(cnt)
+            }
+        }
+
+        impl Shl<$t> for Int {
+            type Output = Int;
+
+            #[inline]
+            fn shl(self, cnt: $t) -> Int {
+                self << $cvt(cnt)
+            }
+        }
+
+        impl ShrAssign<$t> for Int {
+            #[inline]
+            fn shr_assign(&mut self, cnt: $t) {
+                *self >>= $cvt(cnt);
+            }
+        }
+
+        impl<'a> Shr<$t> for &'a Int {
+            type Output = Int;
+
+            #[inline]
+            fn shr(self, cnt: $t) -> Int {
+                self >> $cvt(cnt)
+            }
+        }
+
+        impl Shr<$t> for Int {
+            type Output = Int;
+
+            #[inline]
+            fn shr(self, cnt: $t) -> Int {
+                self >> $cvt(cnt)
+            }
+        }
+    );
+}
+
+#[inline]
+fn to_shift_count(cnt: u64) -> usize {
+    if cnt > std::usize::MAX as u64 {
+        panic!("shift count too large: {}", cnt);
+    }
+    cnt as usize
+}
+
+impl_shift_prim!(signed i32);
+impl_shift_prim!(unsigned u32, u64);
+
+#[derive(Copy, Clone)]
+enum BitOp { And, Or, Xor }
+
+fn bitop_ref(this: &mut Int, other: &Int, op: BitOp) -> Result<(), ()> {
+    let this_sign = this.sign();
+    let other_sign = other.sign();
 
     // if other is small, we can fall back to something that'll be
     // more efficient (especially if other is negative)
@@ -2107,31 +4373,31 @@ fn bitop_ref(this: &mut Int, other: &Int, op: BitOp) -> Result<(), ()> {
 
     unsafe {
         let other_ptr = other.limbs();
-        let min_size = std::cmp::min(this.abs_size(), other.abs_size());
-        let max_size = std::cmp::max(this.abs_size(), other.abs_size());
+        let min_size = std::cmp::min(this.abs_len(), other.abs_len());
+        let max_size = std::cmp::max(this.abs_len(), other.abs_len());
         match op {
             BitOp::And => {
                 let this_ptr = this.limbs_mut();
                 ll::and_n(this_ptr, this_ptr.as_const(), other_ptr, min_size);
-                this.size = min_size;
+                this.size = min_size as i32;
             }
             BitOp::Or => {
                 this.ensure_capacity(max_size as u32);
                 let this_ptr = this.limbs_uninit();
                 ll::or_n(this_ptr, this_ptr.as_const(), other_ptr, min_size);
-                if this.abs_size() < max_size {
+                if this.abs_len() < max_size {
                     ll::copy_rest(other_ptr, this_ptr, max_size, min_size);
                 }
-                this.size = max_size;
+                this.size = max_size as i32;
             }
             BitOp::Xor => {
                 this.ensure_capacity(max_size as u32);
                 let this_ptr = this.limbs_uninit();
                 ll::xor_n(this_ptr, this_ptr.as_const(), other_ptr, min_size);
-                if this.abs_size() < max_size {
+                if this.abs_len() < max_size {
                     ll::copy_rest(other_ptr, this_ptr, max_size, min_size);
                 }
-                this.size = max_size;
+                this.size = max_size as i32;
             }
         }
     }
@@ -2163,8 +4429,8 @@ fn bitop_neg(mut a: Int, mut b: Int, op: BitOp) -> Int {
     unsafe {
         let a_ptr = a.limbs_mut();
         let b_ptr = b.limbs();
-        let min_size = b.abs_size();
-        let max_size = a.abs_size();
+        let min_size = b.abs_len();
+        let max_size = a.abs_len();
 
         let (neg_result, use_max_size) = match op {
             BitOp::And => {
@@ -2192,9 +4458,9 @@ fn bitop_neg(mut a: Int, mut b: Int, op: BitOp) -> Int {
         };
 
         a.size = if use_max_size {
-            max_size
+            max_size as i32
         } else {
-            min_size
+            min_size as i32
         };
         if neg_result {
             a.negate_twos_complement();
@@ -2237,7 +4503,7 @@ fn bitop_limb(a: &mut Int, b: Limb, signed: bool, op: BitOp) {
         unsafe {
             let mut a_ptr = a.limbs_mut();
             let min_size = if b == 0 { 0 } else { 1 };
-            let max_size = a.abs_size();
+            let max_size = a.abs_len();
             // we've got to have space to write data to this pointer
             debug_assert!(max_size >= 1);
 
@@ -2263,9 +4529,9 @@ fn bitop_limb(a: &mut Int, b: Limb, signed: bool, op: BitOp) {
                 }
             };
             a.size = if use_max_size {
-                max_size
+                max_size as i32
             } else {
-                min_size
+                min_size as i32
             };
             if neg_result {
                 a.negate_twos_complement();
@@ -2286,7 +4552,8 @@ impl<'a> BitAnd<Limb> for Int {
 
 impl BitAndAssign<Limb> for Int {
     fn bitand_assign(&mut self, other: Limb) {
-        bitop_limb(self, other, false, BitOp::And)
+        bitop_limb(self, other, false, BitOp::And);
+        self.validate("bitand_assign");
     }
 }
 
@@ -2348,6 +4615,7 @@ impl<'a> BitAndAssign<&'a Int> for Int {
             let res = &*self & other;
             *self = res;
         }
+        self.validate("bitand_assign");
     }
 }
 
@@ -2362,7 +4630,8 @@ impl BitOr<Limb> for Int {
 
 impl BitOrAssign<Limb> for Int {
     fn bitor_assign(&mut self, other: Limb) {
-        bitop_limb(self, other, false, BitOp::Or)
+        bitop_limb(self, other, false, BitOp::Or);
+        self.validate("bitor_assign");
     }
 }
 
@@ -2425,6 +4694,7 @@ impl<'a> BitOrAssign<&'a Int> for Int {
             let res = &*self | other;
             *self = res;
         }
+        self.validate("bitor_assign");
     }
 }
 
@@ -2439,7 +4709,8 @@ impl<'a> BitXor<Limb> for Int {
 
 impl BitXorAssign<Limb> for Int {
     fn bitxor_assign(&mut self, other: Limb) {
-        bitop_limb(self, other, false, BitOp::Xor)
+        bitop_limb(self, other, false, BitOp::Xor);
+        self.validate("bitxor_assign");
     }
 }
 
@@ -2502,13 +4773,15 @@ impl<'a> BitXorAssign<&'a Int> for Int {
             let res = &*self ^ other;
             *self = res;
         }
+        self.validate("bitxor_assign");
     }
 }
 
 macro_rules! impl_arith_prim (
     (signed $t:ty) => (
         // Limbs are unsigned, so make sure we account for the sign
-        // when $t is signed
+        // when $t is signed. The magnitude is taken as a `BaseInt` so that
+        // `$t::min_value()` doesn't overflow.
         impl Add<$t> for Int {
             type Output = Int;
 
@@ -2518,7 +4791,7 @@ macro_rules! impl_arith_prim (
                     return self;
                 }
                 if other < 0 {
-                    return self - Limb(other.abs() as BaseInt);
+                    return self - Limb((other as BaseInt).wrapping_neg());
                 }
                 return self + Limb(other as BaseInt);
             }
@@ -2528,7 +4801,7 @@ macro_rules! impl_arith_prim (
             #[inline]
             fn add_assign(&mut self, other: $t) {
                 if other < 0 {
-                    *self -= Limb(other.abs() as BaseInt);
+                    *self -= Limb((other as BaseInt).wrapping_neg());
                 } else if other > 0 {
                     *self += Limb(other as BaseInt);
                 }
@@ -2544,7 +4817,7 @@ macro_rules! impl_arith_prim (
                     return self;
                 }
                 if other < 0 {
-                    return self + Limb(other.abs() as BaseInt);
+                    return self + Limb((other as BaseInt).wrapping_neg());
                 }
                 return self - Limb(other as BaseInt);
             }
@@ -2554,7 +4827,7 @@ macro_rules! impl_arith_prim (
             #[inline]
             fn sub_assign(&mut self, other: $t) {
                 if other < 0 {
-                    *self += Limb(other.abs() as BaseInt);
+                    *self += Limb((other as BaseInt).wrapping_neg());
                 } else if other > 0 {
                     *self -= Limb(other as BaseInt);
                 }
@@ -2580,7 +4853,7 @@ macro_rules! impl_arith_prim (
                     self.negate();
                 } else if other < 0 {
                     self.negate();
-                    *self *= Limb(other.abs() as BaseInt);
+                    *self *= Limb((other as BaseInt).wrapping_neg());
                 } else {
                     *self *= Limb(other as BaseInt);
                 }
@@ -2600,7 +4873,7 @@ macro_rules! impl_arith_prim (
                     self.negate();
                 } else if other < 0 {
                     self.negate();
-                    *self /= Limb(other.abs() as BaseInt);
+                    *self /= Limb((other as BaseInt).wrapping_neg());
                 } else {
                     *self /= Limb(other as BaseInt);
                 }
@@ -2639,7 +4912,8 @@ macro_rules! impl_arith_prim (
                     return self;
                 }
 
-                return self % Limb(other.abs() as BaseInt);
+                let abs = if other < 0 { (other as BaseInt).wrapping_neg() } else { other as BaseInt };
+                return self % Limb(abs);
             }
         }
 
@@ -2660,15 +4934,15 @@ macro_rules! impl_arith_prim (
                         return (self, 0);
                     } else if other < 0 {
                         self.negate();
-                        self.divrem(Limb(other.abs() as BaseInt))
+                        self.divrem(Limb((other as BaseInt).wrapping_neg()))
                     } else {
                         self.divrem(Limb(other as BaseInt))
                     }
                 };
-                let r = (r.0 as $t).checked_mul(sign).unwrap();
+                let r = (r.0 as $t).checked_mul(sign as $t).unwrap();
                 debug_assert!(sign > 0 || r <= 0);
                 debug_assert!(sign < 0 || r >= 0);
-                debug_assert!(r.abs() < other.abs());
+                debug_assert!(other == <$t>::min_value() || r.abs() < other.abs());
                 (q, r)
             }
         }
@@ -2851,6 +5125,150 @@ macro_rules! impl_arith_prim (
 
         impl_arith_prim!(common $t);
     );
+    (wide $t:ty) => (
+        // Types that may not fit in a single limb. Values that do fit take the
+        // limb paths, anything bigger is converted to an `Int` first.
+        impl AddAssign<$t> for Int {
+            #[inline]
+            fn add_assign(&mut self, other: $t) {
+                match other.to_small() {
+                    Some((false, l)) => *self += l,
+                    Some((true, l)) => *self -= l,
+                    None => *self += Int::from(other),
+                }
+            }
+        }
+
+        impl Add<$t> for Int {
+            type Output = Int;
+
+            #[inline]
+            fn add(mut self, other: $t) -> Int {
+                self += other;
+                self
+            }
+        }
+
+        impl SubAssign<$t> for Int {
+            #[inline]
+            fn sub_assign(&mut self, other: $t) {
+                match other.to_small() {
+                    Some((false, l)) => *self -= l,
+                    Some((true, l)) => *self += l,
+                    None => *self -= Int::from(other),
+                }
+            }
+        }
+
+        impl Sub<$t> for Int {
+            type Output = Int;
+
+            #[inline]
+            fn sub(mut self, other: $t) -> Int {
+                self -= other;
+                self
+            }
+        }
+
+        impl MulAssign<$t> for Int {
+            #[inline]
+            fn mul_assign(&mut self, other: $t) {
+                match other.to_small() {
+                    Some((neg, l)) => {
+                        if l == 0 {
+                            self.size = 0;
+                            return;
+                        }
+                        *self *= l;
+                        if neg {
+                            self.negate();
+                        }
+                    }
+                    None => *self *= Int::from(other),
+                }
+            }
+        }
+
+        impl Mul<$t> for Int {
+            type Output = Int;
+
+            #[inline]
+            fn mul(mut self, other: $t) -> Int {
+                self *= other;
+                self
+            }
+        }
+
+        impl DivAssign<$t> for Int {
+            #[inline]
+            fn div_assign(&mut self, other: $t) {
+                match other.to_small() {
+                    Some((neg, l)) => {
+                        *self /= l;
+                        if neg {
+                            self.negate();
+                        }
+                    }
+                    None => *self /= Int::from(other),
+                }
+            }
+        }
+
+        impl Div<$t> for Int {
+            type Output = Int;
+
+            #[inline]
+            fn div(mut self, other: $t) -> Int {
+                self /= other;
+                self
+            }
+        }
+
+        impl RemAssign<$t> for Int {
+            #[inline]
+            fn rem_assign(&mut self, other: $t) {
+                match other.to_small() {
+                    // The remainder takes the sign of `self`, so the sign of
+                    // `other` doesn't matter
+                    Some((_, l)) => *self %= l,
+                    None => *self %= Int::from(other),
+                }
+            }
+        }
+
+        impl Rem<$t> for Int {
+            type Output = Int;
+
+            #[inline]
+            fn rem(mut self, other: $t) -> Int {
+                self %= other;
+                self
+            }
+        }
+
+        impl BitAndAssign<$t> for Int {
+            #[inline]
+            fn bitand_assign(&mut self, other: $t) {
+                *self &= Int::from(other)
+            }
+        }
+
+        impl BitOrAssign<$t> for Int {
+            #[inline]
+            fn bitor_assign(&mut self, other: $t) {
+                *self |= Int::from(other)
+            }
+        }
+
+        impl BitXorAssign<$t> for Int {
+            #[inline]
+            fn bitxor_assign(&mut self, other: $t) {
+                *self ^= Int::from(other)
+            }
+        }
+
+        impl_arith_prim!(common $t);
+    );
     (common $t:ty) => (
         // Common impls, these should just forward to the above
         // impls
@@ -2986,13 +5404,12 @@ macro_rules! impl_arith_prim (
 
             #[inline]
             fn rem(self, other: $t) -> Int {
-                if other == 0 {
-                    ll::divide_by_zero();
+                match other.to_small() {
+                    // The remainder takes the sign of `self`, so the sign of
+                    // `other` doesn't matter
+                    Some((_, l)) => self % l,
+                    None => self.clone() % other,
                 }
-                if self.sign() == 0 || other == 1 {
-                    return Int::zero()
-                };
-                return self.clone() % other;
             }
         }
 
@@ -3141,13 +5558,81 @@ macro_rules! impl_arith_prim (
     )
 );
 
-// Implement for `i32` which is the fallback type, usize and the base integer type.
-// No more than this because the rest of Rust doesn't much coercion for integer types,
-// but allocating an entire multiple-precision `Int` to do `+ 1` seems silly.
+// Splits a primitive into its sign and magnitude, if the magnitude fits in a
+// single limb, which it always does for the types no wider than a limb.
+trait SmallOperand {
+    fn to_small(self) -> Option<(bool, Limb)>;
+}
+
+macro_rules! impl_small_operand (
+    (signed $t:ty, $u:ty) => (
+        impl SmallOperand for $t {
+            #[inline]
+            fn to_small(self) -> Option<(bool, Limb)> {
+                let abs = if self < 0 { (self as $u).wrapping_neg() } else { self as $u };
+                abs.to_small().map(|(_, l)| (self < 0, l))
+            }
+        }
+    );
+    (unsigned $t:ty) => (
+        impl SmallOperand for $t {
+            #[inline]
+            fn to_small(self) -> Option<(bool, Limb)> {
+                if self <= BaseInt::max_value() as $t {
+                    Some((false, Limb(self as BaseInt)))
+                } else {
+                    None
+                }
+            }
+        }
+    );
+);
+
+// Implement for every primitive integer type, so that things like `x + 1u8` don't
+// need a temporary `Int`. Types that always fit in a limb go through the limb
+// operations directly, wider ones only do so when the value is small enough.
+impl_arith_prim!(signed i8);
+impl_arith_prim!(signed i16);
 impl_arith_prim!(signed i32);
+impl_arith_prim!(signed isize);
+impl_arith_prim!(unsigned u8);
+impl_arith_prim!(unsigned u16);
 impl_arith_prim!(unsigned usize);
 impl_arith_prim!(unsigned BaseInt);
 
+#[cfg(target_pointer_width = "64")]
+impl_arith_prim!(signed i64);
+#[cfg(target_pointer_width = "64")]
+impl_arith_prim!(unsigned u32);
+
+impl_small_operand!(signed i8, u8);
+impl_small_operand!(signed i16, u16);
+impl_small_operand!(signed i32, u32);
+impl_small_operand!(signed isize, usize);
+impl_small_operand!(unsigned u8);
+impl_small_operand!(unsigned u16);
+impl_small_operand!(unsigned usize);
+impl_small_operand!(unsigned BaseInt);
+
+#[cfg(target_pointer_width = "64")]
+impl_small_operand!(signed i64, u64);
+#[cfg(target_pointer_width = "64")]
+impl_small_operand!(unsigned u32);
+
+#[cfg(target_pointer_width = "32")]
+impl_small_operand!(signed i64, u64);
+#[cfg(target_pointer_width = "32")]
+impl_small_operand!(unsigned u64);
+#[cfg(target_pointer_width = "32")]
+impl_arith_prim!(wide i64);
+#[cfg(target_pointer_width = "32")]
+impl_arith_prim!(wide u64);
+
+impl_small_operand!(signed i128, u128);
+impl_small_operand!(unsigned u128);
+impl_arith_prim!(wide i128);
+impl_arith_prim!(wide u128);
+
 impl PartialEq<i32> for Int {
     #[inline]
     fn eq(&self, &other: &i32) -> bool {
@@ -3165,10 +5650,10 @@ impl PartialEq<i32> for Int {
         // since it'll fail because of signs
         if sign < 0 {
             if self.abs_size() > 1 { return false; }
-            return *self.limbs() == (other.abs() as BaseInt);
+            return *self.limbs() == (other as BaseInt).wrapping_neg();
         }
 
-        self.eq(&Limb(other.abs() as BaseInt))
+        self.eq(&Limb(other as BaseInt))
     }
 }
 
@@ -3206,10 +5691,10 @@ impl PartialOrd<i32> for Int {
                 if self.abs_size() > 1 {
                     Ordering::Less
                 } else {
-                    self.to_single_limb().cmp(&Limb(other.abs() as BaseInt)).reverse()
+                    self.to_single_limb().cmp(&Limb((other as BaseInt).wrapping_neg())).reverse()
                 }
             } else {
-                return self.partial_cmp(&Limb(other.abs() as BaseInt));
+                return self.partial_cmp(&Limb(other as BaseInt));
             }
         };
 
@@ -3290,6 +5775,44 @@ impl Int {
 
 const MAX_LIMB: u64 = !0 >> (64 - Limb::BITS);
 
+// Scans the magnitude of `x` for the first bit at or above `from` equal to `val`,
+// treating the bits beyond the top limb as zeros.
+fn scan_abs(x: &Int, from: u32, val: bool) -> Option<u32> {
+    let bits = Limb::BITS as u32;
+    let size = x.abs_size() as u32;
+    let not_found = if val { None } else { Some(std::cmp::max(from, size * bits)) };
+
+    let mut word = from / bits;
+    if word >= size {
+        return not_found;
+    }
+
+    let flip = if val { Limb(0) } else { !Limb(0) };
+    let limbs = x.limbs();
+    unsafe {
+        let mut l = (*limbs.offset(word as isize) ^ flip) & (!Limb(0) << (from % bits));
+        loop {
+            if l != 0 {
+                return Some(word * bits + l.trailing_zeros() as u32);
+            }
+            word += 1;
+            if word == size {
+                return not_found;
+            }
+            l = *limbs.offset(word as isize) ^ flip;
+        }
+    }
+}
+
+// The magnitude of `v`, without overflowing on `i64::MIN`
+fn mag_64(v: i64) -> u64 {
+    if v < 0 { (v as u64).wrapping_neg() } else { v as u64 }
+}
+
+fn mag_128(v: i128) -> u128 {
+    if v < 0 { (v as u128).wrapping_neg() } else { v as u128 }
+}
+
 // do a sign-magnitude comparison
 fn eq_64(x: &Int, mag: u64, neg: bool) -> bool {
     let sign = if mag == 0 { 0 } else if neg { -1 } else { 1 };
@@ -3306,7 +5829,7 @@ fn eq_64(x: &Int, mag: u64, neg: bool) -> bool {
     let ptr = x.limbs();
     let lo_limb = *ptr;
 
-    if mag < MAX_LIMB {
+    if mag <= MAX_LIMB {
         abs_size == 1 && lo_limb.0 == mag as BaseInt
     } else {
         // we can only get here when Limbs are small, and the Int
@@ -3336,7 +5859,7 @@ fn cmp_64(x: &Int, mag: u64, neg: bool) -> Ordering {
     let ptr = x.limbs();
     let lo_limb = *ptr;
 
-    let mag_ord = if mag < MAX_LIMB {
+    let mag_ord = if mag <= MAX_LIMB {
         (size.abs(), lo_limb.0).cmp(&(1, mag as BaseInt))
     } else {
         assert_eq!(Limb::BITS, 32);
@@ -3385,92 +5908,306 @@ impl PartialOrd<Int> for u64 {
 
 impl PartialEq<i64> for Int {
     fn eq(&self, &other: &i64) -> bool {
-        eq_64(self, other.abs() as u64, other < 0)
+        eq_64(self, mag_64(other), other < 0)
     }
 }
 
 impl PartialEq<Int> for i64 {
     fn eq(&self, other: &Int) -> bool {
-        eq_64(other, self.abs() as u64, *self < 0)
+        eq_64(other, mag_64(*self), *self < 0)
     }
 }
 
 impl PartialOrd<i64> for Int {
     fn partial_cmp(&self, &other: &i64) -> Option<Ordering> {
-        Some(cmp_64(self, other.abs() as u64, other < 0))
+        Some(cmp_64(self, mag_64(other), other < 0))
     }
 }
 
 impl PartialOrd<Int> for i64 {
     fn partial_cmp(&self, other: &Int) -> Option<Ordering> {
-        Some(cmp_64(other, self.abs() as u64, *self < 0).reverse())
+        Some(cmp_64(other, mag_64(*self), *self < 0).reverse())
     }
 }
 
-macro_rules! impl_from_prim (
-    (signed $($t:ty),*) => {
-        $(impl ::std::convert::From<$t> for Int {
-            fn from(val: $t) -> Int {
-                if val == 0 {
-                    return Int::zero();
-                } if val == <$t>::min_value() {
-                    let shift = val.trailing_zeros() as usize;
-                    let mut i = Int::one();
-                    i = i << shift;
-                    return -i;
-                }
-                // Handle i64/u64 on 32-bit platforms.
-                if std::mem::size_of::<$t>() > std::mem::size_of::<BaseInt>() {
-                    let vabs = val.abs();
-                    let mask : BaseInt = !0;
-                    let vlow = vabs & (mask as $t);
-                    let vhigh = vabs.overflowing_shr(Limb::BITS as u32).0;
+macro_rules! impl_cmp_prim (
+    (signed $($t:ty),*) => ($(
+        impl PartialEq<$t> for Int {
+            #[inline]
+            fn eq(&self, &other: &$t) -> bool {
+                eq_64(self, mag_64(other as i64), other < 0)
+            }
+        }
 
-                    let low_limb = Limb(vlow as BaseInt);
-                    let high_limb = Limb(vhigh as BaseInt);
-                    let mut i = Int::from_single_limb(low_limb);
-                    if high_limb != 0 {
-                        i.push(high_limb);
-                    }
+        impl PartialEq<Int> for $t {
+            #[inline]
+            fn eq(&self, other: &Int) -> bool {
+                other.eq(self)
+            }
+        }
 
-                    if val < 0 {
-                        i.size *= -1;
-                    }
+        impl PartialOrd<$t> for Int {
+            #[inline]
+            fn partial_cmp(&self, &other: &$t) -> Option<Ordering> {
+                Some(cmp_64(self, mag_64(other as i64), other < 0))
+            }
+        }
 
-                    return i;
-                } else {
-                    let limb = Limb(val.abs() as BaseInt);
-                    let mut i = Int::from_single_limb(limb);
-                    if val < 0 {
-                        i.size *= -1;
-                    }
+        impl PartialOrd<Int> for $t {
+            #[inline]
+            fn partial_cmp(&self, other: &Int) -> Option<Ordering> {
+                other.partial_cmp(self).map(|o| o.reverse())
+            }
+        }
+    )*);
+    (unsigned $($t:ty),*) => ($(
+        impl PartialEq<$t> for Int {
+            #[inline]
+            fn eq(&self, &other: &$t) -> bool {
+                eq_64(self, other as u64, false)
+            }
+        }
 
-                    return i;
-                }
+        impl PartialEq<Int> for $t {
+            #[inline]
+            fn eq(&self, other: &Int) -> bool {
+                other.eq(self)
             }
-        })*
-    };
-    (unsigned $($t:ty),*) => {
-        $(impl ::std::convert::From<$t> for Int {
-            fn from(val: $t) -> Int {
-                if val == 0 {
-                    return Int::zero();
-                }
-                // Handle i64/u64 on 32-bit platforms.
-                if std::mem::size_of::<$t>() > std::mem::size_of::<BaseInt>() {
-                    let mask : BaseInt = !0;
-                    let vlow = val & (mask as $t);
-                    let vhigh = val.overflowing_shr(Limb::BITS as u32).0;
+        }
 
-                    let low_limb = Limb(vlow as BaseInt);
-                    let high_limb = Limb(vhigh as BaseInt);
-                    let mut i = Int::from_single_limb(low_limb);
-                    if high_limb != 0 {
-                        i.push(high_limb);
-                    }
+        impl PartialOrd<$t> for Int {
+            #[inline]
+            fn partial_cmp(&self, &other: &$t) -> Option<Ordering> {
+                Some(cmp_64(self, other as u64, false))
+            }
+        }
 
-                    return i;
-                } else {
+        impl PartialOrd<Int> for $t {
+            #[inline]
+            fn partial_cmp(&self, other: &Int) -> Option<Ordering> {
+                other.partial_cmp(self).map(|o| o.reverse())
+            }
+        }
+    )*);
+);
+
+impl_cmp_prim!(signed i8, i16, isize);
+impl_cmp_prim!(unsigned u8, u16, u32);
+
+fn cmp_128(x: &Int, mag: u128, neg: bool) -> Ordering {
+    if mag <= ::std::u64::MAX as u128 {
+        return cmp_64(x, mag as u64, neg);
+    }
+
+    let sign = if neg { -1 } else { 1 };
+    if x.sign() != sign {
+        return x.sign().cmp(&sign);
+    }
+
+    let size = ((128 - mag.leading_zeros() as usize) + Limb::BITS - 1) / Limb::BITS;
+    let mag_ord = if x.abs_size() as usize != size {
+        (x.abs_size() as usize).cmp(&size)
+    } else {
+        let ptr = x.limbs();
+        let mut ord = Ordering::Equal;
+        for i in (0..size).rev() {
+            let l = unsafe { *ptr.offset(i as isize) };
+            let m = Limb((mag >> (i * Limb::BITS)) as BaseInt);
+            if l != m {
+                ord = l.cmp(&m);
+                break;
+            }
+        }
+        ord
+    };
+
+    if neg { mag_ord.reverse() } else { mag_ord }
+}
+
+impl PartialEq<u128> for Int {
+    fn eq(&self, &other: &u128) -> bool {
+        cmp_128(self, other, false) == Ordering::Equal
+    }
+}
+
+impl PartialEq<Int> for u128 {
+    fn eq(&self, other: &Int) -> bool {
+        other.eq(self)
+    }
+}
+
+impl PartialOrd<u128> for Int {
+    fn partial_cmp(&self, &other: &u128) -> Option<Ordering> {
+        Some(cmp_128(self, other, false))
+    }
+}
+
+impl PartialOrd<Int> for u128 {
+    fn partial_cmp(&self, other: &Int) -> Option<Ordering> {
+        Some(cmp_128(other, *self, false).reverse())
+    }
+}
+
+impl PartialEq<i128> for Int {
+    fn eq(&self, &other: &i128) -> bool {
+        cmp_128(self, mag_128(other), other < 0) == Ordering::Equal
+    }
+}
+
+impl PartialEq<Int> for i128 {
+    fn eq(&self, other: &Int) -> bool {
+        other.eq(self)
+    }
+}
+
+impl PartialOrd<i128> for Int {
+    fn partial_cmp(&self, &other: &i128) -> Option<Ordering> {
+        Some(cmp_128(self, mag_128(other), other < 0))
+    }
+}
+
+impl PartialOrd<Int> for i128 {
+    fn partial_cmp(&self, other: &Int) -> Option<Ordering> {
+        Some(cmp_128(other, mag_128(*self), *self < 0).reverse())
+    }
+}
+
+// Compares `x` with the float `f` exactly. Going through `to_f64` would round
+// `x` to 53 bits first, which makes for example 2^53 + 1 compare equal to 2^53.
+fn cmp_f64(x: &Int, f: f64) -> Option<Ordering> {
+    if f.is_nan() {
+        return None;
+    }
+    if f.is_infinite() {
+        return Some(if f > 0.0 { Ordering::Less } else { Ordering::Greater });
+    }
+
+    let t = f.trunc();
+    if t.abs() < 9223372036854775808.0 {
+        // The integer part fits in an i64, so compare against that and break
+        // ties with the fractional part
+        let ord = cmp_64(x, mag_64(t as i64), t < 0.0);
+        if ord != Ordering::Equal {
+            return Some(ord);
+        }
+        let frac = f - t;
+        return Some(if frac > 0.0 {
+            Ordering::Less
+        } else if frac < 0.0 {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        });
+    }
+
+    // |f| >= 2^63, so it's an integer of the form m * 2^e with m < 2^53
+    let sign = if f < 0.0 { -1 } else { 1 };
+    if x.sign() != sign {
+        return Some(x.sign().cmp(&sign));
+    }
+
+    let bits = f.to_bits();
+    let m = (bits & ((1 << 52) - 1)) | (1 << 52);
+    let e = ((bits >> 52) & 0x7ff) as usize - 1075;
+
+    let len = x.bit_length() as usize;
+    let mag_ord = if len != 53 + e {
+        len.cmp(&(53 + e))
+    } else {
+        x.cmp_abs(&(Int::from(m) << e))
+    };
+
+    Some(if sign < 0 { mag_ord.reverse() } else { mag_ord })
+}
+
+impl PartialEq<f64> for Int {
+    fn eq(&self, &other: &f64) -> bool {
+        cmp_f64(self, other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialEq<Int> for f64 {
+    fn eq(&self, other: &Int) -> bool {
+        other.eq(self)
+    }
+}
+
+impl PartialOrd<f64> for Int {
+    fn partial_cmp(&self, &other: &f64) -> Option<Ordering> {
+        cmp_f64(self, other)
+    }
+}
+
+impl PartialOrd<Int> for f64 {
+    fn partial_cmp(&self, other: &Int) -> Option<Ordering> {
+        cmp_f64(other, *self).map(|o| o.reverse())
+    }
+}
+
+macro_rules! impl_from_prim (
+    (signed $($t:ty),*) => {
+        $(impl ::std::convert::From<$t> for Int {
+            fn from(val: $t) -> Int {
+                if val == 0 {
+                    return Int::zero();
+                } if val == <$t>::min_value() {
+                    let shift = val.trailing_zeros() as usize;
+                    let mut i = Int::one();
+                    i = i << shift;
+                    return -i;
+                }
+                // Handle i64/u64 on 32-bit platforms.
+                if std::mem::size_of::<$t>() > std::mem::size_of::<BaseInt>() {
+                    let vabs = val.abs();
+                    let mask : BaseInt = !0;
+                    let vlow = vabs & (mask as $t);
+                    let vhigh = vabs.overflowing_shr(Limb::BITS as u32).0;
+
+                    let low_limb = Limb(vlow as BaseInt);
+                    let high_limb = Limb(vhigh as BaseInt);
+                    let mut i = Int::from_single_limb(low_limb);
+                    if high_limb != 0 {
+                        i.push(high_limb);
+                    }
+
+                    if val < 0 {
+                        i.size *= -1;
+                    }
+
+                    return i;
+                } else {
+                    let limb = Limb(val.abs() as BaseInt);
+                    let mut i = Int::from_single_limb(limb);
+                    if val < 0 {
+                        i.size *= -1;
+                    }
+
+                    return i;
+                }
+            }
+        })*
+    };
+    (unsigned $($t:ty),*) => {
+        $(impl ::std::convert::From<$t> for Int {
+            fn from(val: $t) -> Int {
+                if val == 0 {
+                    return Int::zero();
+                }
+                // Handle i64/u64 on 32-bit platforms.
+                if std::mem::size_of::<$t>() > std::mem::size_of::<BaseInt>() {
+                    let mask : BaseInt = !0;
+                    let vlow = val & (mask as $t);
+                    let vhigh = val.overflowing_shr(Limb::BITS as u32).0;
+
+                    let low_limb = Limb(vlow as BaseInt);
+                    let high_limb = Limb(vhigh as BaseInt);
+                    let mut i = Int::from_single_limb(low_limb);
+                    if high_limb != 0 {
+                        i.push(high_limb);
+                    }
+
+                    return i;
+                } else {
                     let limb = Limb(val as BaseInt);
                     return Int::from_single_limb(limb);
                 }
@@ -3482,6 +6219,28 @@ macro_rules! impl_from_prim (
 impl_from_prim!(signed   i8, i16, i32, i64, isize);
 impl_from_prim!(unsigned u8, u16, u32, u64, usize);
 
+impl From<u128> for Int {
+    fn from(mut val: u128) -> Int {
+        let mut i = Int::zero();
+        while val != 0 {
+            i.push(Limb(val as BaseInt));
+            val >>= Limb::BITS;
+        }
+        i
+    }
+}
+
+impl From<i128> for Int {
+    fn from(val: i128) -> Int {
+        let abs = if val < 0 { (val as u128).wrapping_neg() } else { val as u128 };
+        let mut i = Int::from(abs);
+        if val < 0 {
+            i.negate();
+        }
+        i
+    }
+}
+
 // Number formatting - There's not much difference between the impls,
 // hence the macro
 
@@ -3510,36 +6269,254 @@ macro_rules! impl_fmt (
 impl_fmt!(fmt::Binary,    2, "0b");
 impl_fmt!(fmt::Octal,     8, "0o");
 impl_fmt!(fmt::Display,  10, "");
-impl_fmt!(fmt::Debug,    10, "");
 impl_fmt!(fmt::LowerHex, 16, false, "0x");
 impl_fmt!(fmt::UpperHex, 16, true, "0x");
 
+/// `{:?}` prints the value in decimal, like `Display`. The alternate form,
+/// `{:#?}`, shows the internal representation instead: the sign, the number of
+/// limbs in use, the capacity and the limbs themselves in hex, least
+/// significant first.
+impl fmt::Debug for Int {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !f.alternate() {
+            return fmt::Display::fmt(self, f);
+        }
+
+        try!(write!(f, "Int {{ sign: {}, size: {}, cap: {}, limbs: [",
+                    self.sign(), self.abs_size(), self.cap));
+        unsafe {
+            let ptr = self.limbs();
+            for i in 0..self.abs_size() {
+                if i > 0 {
+                    try!(f.write_str(", "));
+                }
+                try!(write!(f, "{:#0w$x}", (*ptr.offset(i as isize)).0, w = Limb::BITS / 4 + 2));
+            }
+        }
+        f.write_str("] }")
+    }
+}
+
 // String parsing
 
+/// An error which can be returned when parsing an `Int`.
 #[derive(Debug, Clone, PartialEq)]
-pub struct ParseIntError { kind: ErrorKind }
+pub struct ParseIntError { kind: ErrorKind, offset: usize }
 
-#[derive(Debug, Clone, PartialEq)]
-enum ErrorKind {
+/// The kind of error that caused parsing an `Int` to fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The string contained no digits.
     Empty,
-    InvalidDigit
+    /// The string contained a character that isn't a digit in the given base.
+    InvalidDigit,
+    /// The requested base is not between 2 and 36.
+    InvalidRadix,
+    /// The string has more digits than the parsing limit allows.
+    TooLong
+}
+
+impl ParseIntError {
+    pub(crate) fn new(kind: ErrorKind, offset: usize) -> ParseIntError {
+        ParseIntError { kind: kind, offset: offset }
+    }
+
+    /// Returns the kind of error.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Returns the byte offset in the input at which the error was detected. For
+    /// `ErrorKind::Empty` this is the length of the input.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    // Moves the offset of an error that happened in a substring starting at `start`.
+    pub(crate) fn shifted(self, start: usize) -> ParseIntError {
+        ParseIntError::new(self.kind, self.offset + start)
+    }
 }
 
 impl Error for ParseIntError {
     fn description<'a>(&'a self) -> &'a str {
         match self.kind {
             ErrorKind::Empty => "cannot parse empty string",
-            ErrorKind::InvalidDigit => "invalid digit found in string"
+            ErrorKind::InvalidDigit => "invalid digit found in string",
+            ErrorKind::InvalidRadix => "radix must be between 2 and 36",
+            ErrorKind::TooLong => "number too long to parse"
         }
     }
 }
 
 impl fmt::Display for ParseIntError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.description().fmt(f)
+        match self.kind {
+            ErrorKind::InvalidDigit => write!(f, "{} at offset {}", self.description(), self.offset),
+            _ => self.description().fmt(f)
+        }
+    }
+}
+
+/// An error returned by the `try_` methods of `Int` when memory for the
+/// result could not be allocated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllocError { limbs: usize, over_budget: bool }
+
+impl AllocError {
+    fn new(limbs: usize) -> AllocError {
+        AllocError { limbs: limbs, over_budget: false }
+    }
+
+    /// Returns the number of limbs that the failed allocation asked for.
+    pub fn limbs(&self) -> usize {
+        self.limbs
+    }
+
+    /// Returns true if the allocation was refused because it exceeds the
+    /// budget set by `set_memory_budget`, rather than by the allocator.
+    pub fn is_over_budget(&self) -> bool {
+        self.over_budget
+    }
+}
+
+impl Error for AllocError {
+    fn description(&self) -> &str {
+        if self.over_budget {
+            "memory budget exceeded"
+        } else {
+            "memory allocation failed"
+        }
+    }
+}
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({} limbs)", self.description(), self.limbs)
+    }
+}
+
+/// The panic payload raised when an operation needs an allocation bigger
+/// than the budget set by `set_memory_budget`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BudgetExceeded { requested: usize, budget: usize }
+
+impl BudgetExceeded {
+    /// Returns the size in bytes of the allocation that was refused.
+    pub fn requested(&self) -> usize {
+        self.requested
+    }
+
+    /// Returns the budget in bytes that was in effect.
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+}
+
+impl Error for BudgetExceeded {
+    fn description(&self) -> &str {
+        "memory budget exceeded"
+    }
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} bytes requested, the budget is {} bytes",
+               self.description(), self.requested, self.budget)
+    }
+}
+
+static MEMORY_BUDGET: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/**
+ * Sets the largest allocation, in bytes, that a single `Int` or block of scratch space may need.
+ *
+ * An operation that would exceed it panics before allocating, with a `BudgetExceeded` value as
+ * the panic payload, which can be recovered with `std::panic::catch_unwind`. The `try_` methods
+ * of `Int` return an `AllocError` instead. This keeps the evaluation of untrusted expressions from
+ * exhausting memory.
+ *
+ * The budget applies to the whole program. A budget of zero, the default, means no limit.
+ *
+ * ```
+ * use std::panic;
+ * use framp::Int;
+ * use framp::int::{set_memory_budget, BudgetExceeded};
+ *
+ * set_memory_budget(1 << 20);
+ * let x = Int::from(3);
+ * assert!(x.try_pow(100_000_000).unwrap_err().is_over_budget());
+ *
+ * let err = panic::catch_unwind(|| x.pow(100_000_000)).unwrap_err();
+ * assert_eq!(err.downcast_ref::<BudgetExceeded>().unwrap().budget(), 1 << 20);
+ *
+ * assert_eq!(x.pow(1000).bit_length(), 1585);
+ * set_memory_budget(0);
+ * ```
+ */
+pub fn set_memory_budget(max_bytes: usize) {
+    MEMORY_BUDGET.store(max_bytes, AtomicOrdering::Relaxed);
+}
+
+/**
+ * Returns the current memory budget set by `set_memory_budget`, zero meaning no limit.
+ */
+pub fn memory_budget() -> usize {
+    MEMORY_BUDGET.load(AtomicOrdering::Relaxed)
+}
+
+#[inline]
+fn within_memory_budget(bytes: usize) -> bool {
+    let budget = memory_budget();
+    budget == 0 || bytes <= budget
+}
+
+// Panics with a `BudgetExceeded` if an allocation of `bytes` bytes isn't allowed
+#[inline]
+pub(crate) fn check_memory_budget(bytes: usize) {
+    if !within_memory_budget(bytes) {
+        budget_exceeded(bytes);
     }
 }
 
+#[cold]
+#[inline(never)]
+fn budget_exceeded(bytes: usize) -> ! {
+    panic!(BudgetExceeded { requested: bytes, budget: memory_budget() })
+}
+
+static PARSE_LIMIT: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/**
+ * Sets the maximum number of digits accepted when parsing an `Int` with `from_str`,
+ * `from_str_radix` or `from_str_flexible`. Longer inputs fail with `ErrorKind::TooLong` before
+ * any conversion work is done.
+ *
+ * The limit applies to the whole program. A limit of zero, the default, means no limit.
+ */
+pub fn set_default_parse_limit(max_digits: usize) {
+    PARSE_LIMIT.store(max_digits, AtomicOrdering::Relaxed);
+}
+
+/**
+ * Returns the current parsing limit set by `set_default_parse_limit`, zero meaning no limit.
+ */
+pub fn default_parse_limit() -> usize {
+    PARSE_LIMIT.load(AtomicOrdering::Relaxed)
+}
+
+// Returns the value of the ASCII digit `c` in base `base`, if it is one.
+fn digit_value(c: u8, base: u8) -> Option<u8> {
+    let b = match c {
+        b'0'...b'9' => c - b'0',
+        b'A'...b'Z' => (c - b'A') + 10,
+        b'a'...b'z' => (c - b'a') + 10,
+        _ => return None
+    };
+
+    if b < base { Some(b) } else { None }
+}
+
 impl FromStr for Int {
     type Err = ParseIntError;
 
@@ -3562,7 +6539,7 @@ macro_rules! impl_from_for_prim (
                     // Handle conversion where BaseInt = u32 and $t = i64
                     if i.abs_size() >= 2 { // Fallthrough if there's only one limb
                         let lower = i.to_single_limb().0 as $t;
-                        let higher = unsafe { (*i.ptr.as_ptr().offset(1)).0 } as $t;
+                        let higher = unsafe { (*i.limbs().offset(1)).0 } as $t;
 
                         // Combine the two
                         let n : $t = lower | higher.overflowing_shl(Limb::BITS as u32).0;
@@ -3590,7 +6567,7 @@ macro_rules! impl_from_for_prim (
                     // Handle conversion where BaseInt = u32 and $t = u64
                     if i.abs_size() >= 2 { // Fallthrough if there's only one limb
                         let lower = i.to_single_limb().0 as $t;
-                        let higher = unsafe { (*i.ptr.as_ptr().offset(1)).0 } as $t;
+                        let higher = unsafe { (*i.limbs().offset(1)).0 } as $t;
 
                         // Combine the two
                         let n : $t = lower | higher.overflowing_shl(Limb::BITS as u32).0;
@@ -3610,11 +6587,7 @@ impl_from_for_prim!(unsigned u8, u16, u32, u64, usize);
 
 impl Zero for Int {
     fn zero() -> Int {
-        Int {
-            ptr: unsafe { Unique::new(alloc::heap::EMPTY as *mut Limb) },
-            size: 0,
-            cap: 0
-        }
+        Int::zero()
     }
 
     fn is_zero(&self) -> bool {
@@ -3624,7 +6597,7 @@ impl Zero for Int {
 
 impl One for Int {
     fn one() -> Int {
-        Int::from(1)
+        Int::one()
     }
 }
 
@@ -3633,6 +6606,9 @@ impl Num for Int {
 
     #[inline]
     fn from_str_radix(src: &str, radix: u32) -> Result<Int, ParseIntError> {
+        if radix > 36 {
+            return Err(ParseIntError::new(ErrorKind::InvalidRadix, 0));
+        }
         Int::from_str_radix(src, radix as u8)
     }
 }
@@ -3684,9 +6660,121 @@ impl Integer for Int {
     }
 }
 
-impl std::iter::Step for Int {
-    fn step(&self, by: &Int) -> Option<Int> {
-        Some(self + by)
+impl std::iter::Sum for Int {
+    fn sum<I: Iterator<Item=Int>>(iter: I) -> Int {
+        let mut sum = Int::zero();
+        for x in iter {
+            sum += x;
+        }
+        sum
+    }
+}
+
+impl<'a> std::iter::Sum<&'a Int> for Int {
+    fn sum<I: Iterator<Item=&'a Int>>(iter: I) -> Int {
+        let mut sum = Int::zero();
+        for x in iter {
+            sum += x;
+        }
+        sum
+    }
+}
+
+// The factors of `Int::product_tree`, the shortest first out of a `BinaryHeap`
+struct BySize(Int);
+
+impl PartialEq for BySize {
+    fn eq(&self, other: &BySize) -> bool {
+        self.0.abs_size() == other.0.abs_size()
+    }
+}
+
+impl Eq for BySize {}
+
+impl PartialOrd for BySize {
+    fn partial_cmp(&self, other: &BySize) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BySize {
+    fn cmp(&self, other: &BySize) -> Ordering {
+        other.0.abs_size().cmp(&self.0.abs_size())
+    }
+}
+
+impl Int {
+    /**
+     * Returns the product of `factors`, multiplying the two shortest values left at each
+     * step. The operands of each multiplication then have similar sizes, so the fast
+     * algorithms get to work on big balanced inputs, rather than a growing product being
+     * multiplied by one small factor at a time, which takes time quadratic in the size of the
+     * result. The single-limb factors are first multiplied together with `ll::prod_limbs`.
+     *
+     * This is what `Product` uses, for factorials, primorials or the product of many moduli.
+     *
+     * ```
+     * use framp::Int;
+     *
+     * let f = Int::product_tree((1..101).map(Int::from));
+     * assert_eq!(f, (1..101).fold(Int::one(), |acc, i| acc * i));
+     * ```
+     */
+    pub fn product_tree<I: IntoIterator<Item=Int>>(factors: I) -> Int {
+        let mut negative = false;
+        let mut small = Vec::new();
+        let mut heap = BinaryHeap::new();
+        for f in factors {
+            match f.sign() {
+                0 => return Int::zero(),
+                -1 => negative = !negative,
+                _ => {}
+            }
+            if f.abs_size() == 1 {
+                small.push(f.to_single_limb());
+            } else {
+                heap.push(BySize(f.abs()));
+            }
+        }
+
+        if !small.is_empty() {
+            let mut p = Int::with_capacity(small.len() as u32);
+            p.size = checked_size(small.len());
+            unsafe {
+                let n = ll::prod_limbs(p.limbs_mut(), Limbs::new(small.as_ptr(), 0, small.len()),
+                                       small.len());
+                p.size = n as i32;
+            }
+            p.validate("product_tree");
+            heap.push(BySize(p));
+        }
+
+        while heap.len() > 1 {
+            let a = heap.pop().unwrap().0;
+            let b = heap.pop().unwrap().0;
+            heap.push(BySize(a * b));
+        }
+
+        let p = heap.pop().map_or(Int::one(), |p| p.0);
+        if negative { -p } else { p }
+    }
+}
+
+impl std::iter::Product for Int {
+    fn product<I: Iterator<Item=Int>>(iter: I) -> Int {
+        Int::product_tree(iter)
+    }
+}
+
+impl<'a> std::iter::Product<&'a Int> for Int {
+    fn product<I: Iterator<Item=&'a Int>>(iter: I) -> Int {
+        Int::product_tree(iter.cloned())
+    }
+}
+
+impl std::iter::Step for Int {
+    fn step(&self, by: &Int) -> Option<Int> {
+        Some(self + by)
     }
 
     fn steps_between(start: &Int, end: &Int, by: &Int) -> Option<usize> {
@@ -3746,6 +6834,10 @@ impl std::iter::Step for Int {
 ///     let big_i = rng.gen_int(256);
 /// }
 /// ```
+///
+/// This is implemented for the `Rng` trait of rand 0.3. The generators of
+/// rand 0.8 and 0.9, or any other source of random numbers, can be used
+/// through the `U64Rng` adapter.
 pub trait RandomInt {
     /// Generate a random unsigned `Int` of given bit size.
     fn gen_uint(&mut self, bits: usize) -> Int;
@@ -3760,28 +6852,131 @@ pub trait RandomInt {
     fn gen_int_range(&mut self, lbound: &Int, ubound: &Int) -> Int;
 }
 
-impl<R: Rng> RandomInt for R {
-    fn gen_uint(&mut self, bits: usize) -> Int {
-        assert!(bits > 0);
+fn random_limb<R: Rng + ?Sized>(rng: &mut R) -> Limb {
+    if Limb::BITS == 64 {
+        Limb(rng.next_u64() as BaseInt)
+    } else {
+        Limb(rng.next_u32() as BaseInt)
+    }
+}
+
+fn random_bool<R: Rng + ?Sized>(rng: &mut R) -> bool {
+    rng.next_u32() & 1 == 1
+}
 
-        let limbs = (bits / &Limb::BITS) as u32;
-        let rem = bits % &Limb::BITS;
+// Trial division and sieving use the primes below this
+const SIEVE_LIMIT: usize = 1 << 12;
+// The number of odd candidates in a window of `random_prime`
+const PRIME_WINDOW: usize = 2048;
+// The Miller-Rabin rounds passed by the results of `random_prime`
+const PRIME_REPS: u32 = 20;
+
+fn small_primes() -> Vec<u32> {
+    let mut composite = vec![false; SIEVE_LIMIT];
+    let mut primes = Vec::new();
+    for i in 2..SIEVE_LIMIT {
+        if !composite[i] {
+            primes.push(i as u32);
+            let mut j = i * i;
+            while j < SIEVE_LIMIT {
+                composite[j] = true;
+                j += i;
+            }
+        }
+    }
+    primes
+}
 
-        let mut i = Int::with_capacity(limbs + 1);
+// An odd random value with exactly `bits` bits, to start a window from
+fn prime_window_start<R: Rng + ?Sized>(rng: &mut R, bits: usize) -> Int {
+    let mut start = Int::random_exact_bits(rng, bits);
+    if start.is_even() {
+        start += 1;
+    }
+    start
+}
 
-        for _ in 0..limbs {
-            let limb = Limb(self.gen());
-            i.push(limb);
+// Looks for a probable prime of `bits` bits among the first `PRIME_WINDOW`
+// odd numbers from `start`, which must be larger than all of `primes`.
+fn prime_in_window(start: &Int, bits: usize, primes: &[u32]) -> Option<Int> {
+    let mut composite = vec![false; PRIME_WINDOW];
+    // Candidate `i` is `start + 2i`, skip the prime 2
+    for &q in &primes[1..] {
+        let q = q as usize;
+        let r = usize::from(&(start % q));
+        // Solve `start + 2i = 0 mod q`, with `(q + 1) / 2` the inverse of 2
+        let mut i = (q - r) % q * ((q + 1) / 2) % q;
+        while i < PRIME_WINDOW {
+            composite[i] = true;
+            i += q;
         }
+    }
 
-        if rem > 0 {
-            let final_limb = Limb(self.gen());
-            i.push(final_limb >> (&Limb::BITS - rem));
+    for (i, _) in composite.iter().enumerate().filter(|&(_, &c)| !c) {
+        let candidate = start + 2 * i;
+        if candidate.bit_length() as usize > bits {
+            return None;
         }
+        if candidate.miller_rabin(PRIME_REPS, primes) {
+            return Some(candidate);
+        }
+    }
+    None
+}
 
-        i.normalize();
+#[cfg(not(feature = "rayon"))]
+fn random_prime_round<R: Rng + ?Sized>(rng: &mut R, bits: usize, primes: &[u32]) -> Option<Int> {
+    prime_in_window(&prime_window_start(rng, bits), bits, primes)
+}
 
-        i
+#[cfg(feature = "rayon")]
+fn random_prime_round<R: Rng + ?Sized>(rng: &mut R, bits: usize, primes: &[u32]) -> Option<Int> {
+    use rayon::prelude::*;
+    let starts: Vec<Int> = (0..::rayon::current_num_threads())
+        .map(|_| prime_window_start(rng, bits))
+        .collect();
+    starts.par_iter()
+        .filter_map(|start| prime_in_window(start, bits, primes))
+        .find_any(|_| true)
+}
+
+/// Adapts a closure returning random `u64`s to the `Rng` trait of rand 0.3,
+/// and so to `RandomInt`. This is how the generators of newer versions of
+/// rand, or of any other crate, are used without this crate depending on
+/// them.
+///
+/// ```
+/// extern crate rand;
+/// extern crate framp as ramp;
+///
+/// use rand::Rng;
+/// use ramp::{RandomInt, U64Rng};
+///
+/// fn main() {
+///     // With rand 0.8 or 0.9, this would be `rand::thread_rng()` or
+///     // `rand::rng()`, with `RngCore` in scope
+///     let mut rng = rand::thread_rng();
+///     let x = U64Rng(|| rng.next_u64()).gen_uint(256);
+///     assert!(x.bit_length() <= 256);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct U64Rng<F>(pub F);
+
+impl<F: FnMut() -> u64> Rng for U64Rng<F> {
+    fn next_u32(&mut self) -> u32 {
+        (self.0)() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        (self.0)()
+    }
+}
+
+impl<R: Rng + ?Sized> RandomInt for R {
+    fn gen_uint(&mut self, bits: usize) -> Int {
+        assert!(bits > 0);
+        Int::random_bits(self, bits)
     }
 
     fn gen_int(&mut self, bits: usize) -> Int {
@@ -3792,12 +6987,12 @@ impl<R: Rng> RandomInt for R {
             // again with probability 0.5. This is because otherwise,
             // the probability of generating a zero BigInt would be
             // double that of any other number.
-            if self.gen() {
+            if random_bool(self) {
                 return self.gen_uint(bits);
             } else {
                 i
             }
-        } else if self.gen() {
+        } else if random_bool(self) {
             -i
         } else {
             i
@@ -3808,32 +7003,292 @@ impl<R: Rng> RandomInt for R {
 
     fn gen_uint_below(&mut self, bound: &Int) -> Int {
         assert!(*bound > Int::zero());
-        // If we haven't got a valid number after 10,000 tries, then something
-        // has probably gone wrong, as there is a 1 in 10^3000 chance of this
-        // happening, in the worst case.
-        const ITER_LIMIT : usize = 10000;
+        Int::random_below(self, bound)
+    }
+
+    fn gen_int_range(&mut self, lbound: &Int, ubound: &Int) -> Int {
+        assert!(*lbound < *ubound);
+        lbound + self.gen_uint_below(&(ubound - lbound))
+    }
+}
+
+impl Int {
+    /**
+     * Returns a uniformly distributed random value in `range`, which
+     * includes its start but not its end.
+     *
+     * Values are drawn by rejection, so all of the range is equally likely,
+     * unlike with the common approach of reducing a random number modulo the
+     * length of the range.
+     *
+     * ```
+     * # extern crate rand;
+     * # extern crate framp;
+     * # use framp::Int;
+     * # fn main() {
+     * let mut rng = rand::thread_rng();
+     * let lo = Int::from(-5);
+     * let hi = Int::from(10).pow(30);
+     * let x = Int::random_range(&mut rng, &lo..&hi);
+     * assert!(lo <= x && x < hi);
+     * # }
+     * ```
+     *
+     * # Panics
+     *
+     * Panics if the range is empty.
+     */
+    pub fn random_range<R: Rng + ?Sized>(rng: &mut R, range: Range<&Int>) -> Int {
+        assert!(range.start < range.end, "Int::random_range: empty range");
+        Int::random_below(rng, &(range.end - range.start)) + range.start
+    }
+
+    /**
+     * Returns a uniformly distributed random value in `[0, 2^bits)`.
+     *
+     * The limbs are filled straight from `rng`.
+     */
+    pub fn random_bits<R: Rng + ?Sized>(rng: &mut R, bits: usize) -> Int {
+        Int::random_bits_impl(rng, bits, false)
+    }
+
+    /**
+     * Returns a uniformly distributed random value with exactly `bits` bits,
+     * that is, in `[2^(bits - 1), 2^bits)`.
+     *
+     * ```
+     * # extern crate rand;
+     * # extern crate framp;
+     * # use framp::Int;
+     * # fn main() {
+     * let mut rng = rand::thread_rng();
+     * let x = Int::random_exact_bits(&mut rng, 1024);
+     * assert_eq!(x.bit_length(), 1024);
+     * # }
+     * ```
+     *
+     * # Panics
+     *
+     * Panics if `bits` is zero.
+     */
+    pub fn random_exact_bits<R: Rng + ?Sized>(rng: &mut R, bits: usize) -> Int {
+        assert!(bits > 0, "Int::random_exact_bits: no value has zero bits");
+        Int::random_bits_impl(rng, bits, true)
+    }
+
+    /**
+     * Returns a uniformly distributed random value in `[1, m)` that is
+     * coprime to `m`, and so has an inverse modulo `m`.
+     *
+     * Values are drawn with `random_below` until one is found. Zero and, for
+     * an even `m`, even values are dropped without computing a gcd.
+     *
+     * ```
+     * # extern crate rand;
+     * # extern crate framp;
+     * # use framp::Int;
+     * # fn main() {
+     * let mut rng = rand::thread_rng();
+     * let n = Int::from(3 * 5 * 7 * 11);
+     * let r = Int::random_invertible(&mut rng, &n);
+     * assert_eq!(r.gcd(&n), 1);
+     * # }
+     * ```
+     *
+     * # Panics
+     *
+     * Panics if `m` is less than 2.
+     */
+    pub fn random_invertible<R: Rng + ?Sized>(rng: &mut R, m: &Int) -> Int {
+        assert!(*m > 1, "Int::random_invertible: the modulus must be greater than one");
+        let m_even = m.is_even();
+        loop {
+            let x = Int::random_below(rng, m);
+            if x.sign() == 0 || (m_even && x.is_even()) {
+                continue;
+            }
+            if x == 1 || x.gcd(m) == 1 {
+                return x;
+            }
+        }
+    }
+
+    /**
+     * Returns a random probable prime with exactly `bits` bits.
+     *
+     * A random odd starting point is drawn, and the following odd numbers
+     * are sieved by the small primes. The survivors are then tested with
+     * `is_probable_prime`'s Miller-Rabin rounds until one passes. If the
+     * window runs out first, a new starting point is drawn. Like all such
+     * searches, this slightly favours primes that follow long prime gaps.
+     *
+     * With the `rayon` feature, one window per thread is searched at a time
+     * and the first prime found in any of them is returned, which cuts the
+     * latency for large sizes by about the number of cores. The result is
+     * then not a function of the state of `rng` alone.
+     *
+     * ```
+     * # extern crate rand;
+     * # extern crate framp;
+     * # use framp::Int;
+     * # fn main() {
+     * let mut rng = rand::thread_rng();
+     * let p = Int::random_prime(&mut rng, 256);
+     * assert_eq!(p.bit_length(), 256);
+     * assert!(p.is_probable_prime(20));
+     * # }
+     * ```
+     *
+     * # Panics
+     *
+     * Panics if `bits` is less than 2.
+     */
+    pub fn random_prime<R: Rng + ?Sized>(rng: &mut R, bits: usize) -> Int {
+        assert!(bits >= 2, "Int::random_prime: no prime has fewer than two bits");
+        if bits <= 16 {
+            // Too small for the sieve, which would strike out the small
+            // primes themselves
+            loop {
+                let x = Int::random_exact_bits(rng, bits);
+                if x.is_probable_prime(PRIME_REPS) {
+                    return x;
+                }
+            }
+        }
+
+        let primes = small_primes();
+        loop {
+            if let Some(p) = random_prime_round(rng, bits, &primes) {
+                return p;
+            }
+        }
+    }
+
+    fn random_bits_impl<R: Rng + ?Sized>(rng: &mut R, bits: usize, set_top: bool) -> Int {
+        let n = (bits + Limb::BITS - 1) / Limb::BITS;
+        if n == 0 {
+            return Int::zero();
+        }
 
-        let bits = bound.bit_length() as usize;
+        let mut ret = Int::with_capacity(checked_size(n) as u32);
+        unsafe {
+            let out = ret.limbs_uninit();
+            for i in 0..n {
+                *out.offset(i as isize) = random_limb(rng);
+            }
+            Int::fix_random_top(out, bits, set_top);
+        }
+        ret.size = n as i32;
+        ret.normalize();
+        ret
+    }
 
-        // Since it uses a number of bits, gen_uint may return a number too large,
-        // loop until we generate a valid number.
-        // Since the greatest gap between the bound and the largest number produced
-        // is when bound = 2^n (bit string [100000....]), we have, at worst, a 50/50
-        // chance of producing an invalid number each iteration.
-        let mut count = 0;
-        while count < ITER_LIMIT {
-            let n = self.gen_uint(bits);
-            if n < *bound { return n; }
-            count += 1;
+    /**
+     * Returns a uniformly distributed random value in `[0, 2^bits)`, read
+     * from the operating system's random number generator.
+     *
+     * No generator has to be set up by the caller, a new `rand::OsRng` is
+     * opened for each call. This is available with the `getrandom` feature.
+     */
+    #[cfg(feature = "getrandom")]
+    pub fn random_bits_os(bits: usize) -> Result<Int, io::Error> {
+        let n = (bits + Limb::BITS - 1) / Limb::BITS;
+        if n == 0 {
+            return Ok(Int::zero());
         }
 
-        panic!("No valid number generated in {} iterations.\n\
-                Please open an issue at https://github.com/Aatch/ramp", ITER_LIMIT);
+        let mut ret = Int::with_capacity(checked_size(n) as u32);
+        unsafe {
+            let out = ret.limbs_uninit();
+            // Any bytes make valid limbs, and random ones are just as random in
+            // either byte order
+            let bytes = std::slice::from_raw_parts_mut(out.as_mut_ptr() as *mut u8,
+                                                       n * mem::size_of::<Limb>());
+            try!(::rand::OsRng::new()).fill_bytes(bytes);
+            Int::fix_random_top(out, bits, false);
+        }
+        ret.size = n as i32;
+        ret.normalize();
+        Ok(ret)
     }
 
-    fn gen_int_range(&mut self, lbound: &Int, ubound: &Int) -> Int {
-        assert!(*lbound < *ubound);
-        lbound + self.gen_uint_below(&(ubound - lbound))
+    // Clears the bits above `bits` in the top limb of a random value filling
+    // all of `out`, and sets the highest remaining one if `set_top` is true.
+    unsafe fn fix_random_top(out: LimbsMut, bits: usize, set_top: bool) {
+        let n = (bits + Limb::BITS - 1) / Limb::BITS;
+        let mut top = out.offset((n - 1) as isize);
+        let rem = bits % Limb::BITS;
+        if rem != 0 {
+            *top = *top & ((Limb(1) << rem) - 1);
+        }
+        if set_top {
+            let high = if rem == 0 { Limb::BITS - 1 } else { rem - 1 };
+            *top = *top | (Limb(1) << high);
+        }
+    }
+
+    /**
+     * Returns a uniformly distributed random value in `[0, m)`.
+     *
+     * This is rejection sampling of values with as many bits as `m`, but the
+     * limbs are drawn from the top down and a sample is dropped as soon as
+     * its top limbs show that it is too large, so most rejections only cost
+     * a single limb from `rng`.
+     *
+     * ```
+     * # extern crate rand;
+     * # extern crate framp;
+     * # use framp::Int;
+     * # fn main() {
+     * let mut rng = rand::thread_rng();
+     * let m = Int::from(10).pow(40) + 7;
+     * let nonce = Int::random_below(&mut rng, &m);
+     * assert!(nonce >= 0 && nonce < m);
+     * # }
+     * ```
+     *
+     * # Panics
+     *
+     * Panics if `m` is not positive.
+     */
+    pub fn random_below<R: Rng + ?Sized>(rng: &mut R, m: &Int) -> Int {
+        assert!(m.sign() > 0, "Int::random_below: the bound must be positive");
+        let n = m.abs_len();
+        let ml = m.as_limb_slice();
+        let top_bits = Limb::BITS - ml[n - 1].leading_zeros() as usize;
+        let top_mask = if top_bits == Limb::BITS {
+            !Limb(0)
+        } else {
+            (Limb(1) << top_bits) - 1
+        };
+
+        let mut ret = Int::with_capacity(n as u32);
+        unsafe {
+            let out = ret.limbs_uninit();
+            'sample: loop {
+                // Whether the limbs drawn so far are equal to those of `m`
+                let mut equal = true;
+                for i in (0..n).rev() {
+                    let mut l = random_limb(rng);
+                    if i == n - 1 {
+                        l = l & top_mask;
+                    }
+                    *out.offset(i as isize) = l;
+                    if equal {
+                        if l > ml[i] {
+                            continue 'sample;
+                        }
+                        equal = l == ml[i];
+                    }
+                }
+                if !equal {
+                    break;
+                }
+            }
+        }
+        ret.size = n as i32;
+        ret.normalize();
+        ret
     }
 }
 
@@ -3845,7 +7300,7 @@ mod test {
     use test::{self, Bencher};
     use super::*;
     use ll::limb::Limb;
-    use traits::DivRem;
+    use traits::{Assign, DivRem};
     use std::str::FromStr;
 
     macro_rules! assert_mp_eq (
@@ -3881,128 +7336,1031 @@ mod test {
     }
 
     #[test]
-    fn from_string_16() {
-        let cases = [
-            ("0",        0i32),
-            ("abcde",    0xabcde),
-            ("0ABC",     0xabc),
-            ("12AB34cd", 0x12ab34cd),
-            ("-ABC",    -0xabc),
-            ("-0def",   -0xdef),
-        ];
-
-        for &(s, n) in cases.iter() {
-            let i : Int = Int::from_str_radix(s, 16).unwrap();
-            assert!(i == n, "Assertion failed: {:#x} != {:#x}", i, n);
+    fn from_string_16() {
+        let cases = [
+            ("0",        0i32),
+            ("abcde",    0xabcde),
+            ("0ABC",     0xabc),
+            ("12AB34cd", 0x12ab34cd),
+            ("-ABC",    -0xabc),
+            ("-0def",   -0xdef),
+        ];
+
+        for &(s, n) in cases.iter() {
+            let i : Int = Int::from_str_radix(s, 16).unwrap();
+            assert!(i == n, "Assertion failed: {:#x} != {:#x}", i, n);
+        }
+    }
+
+    #[test]
+    fn from_string_flexible() {
+        let cases = [
+            ("0",          0i64),
+            ("+123",       123),
+            ("-1_000_000", -1000000),
+            ("0xff",       0xff),
+            ("-0XDEAD_beef_", -0xdeadbeef),
+            ("0o_777",     0o777),
+            ("+0b1010",    0b1010),
+        ];
+
+        for &(s, n) in cases.iter() {
+            let i = Int::from_str_flexible(s).unwrap();
+            assert_eq!(i, n);
+        }
+
+        let errors = ["", "-", "_1", "0x", "0x_", "0b102", "--1", "+-1", "0x-1", "1 2"];
+        for s in errors.iter() {
+            assert!(Int::from_str_flexible(s).is_err(), "parsed {:?}", s);
+        }
+    }
+
+    #[test]
+    fn hex_ct() {
+        let cases = [
+            "0",
+            "1",
+            "-abc",
+            "0123456789abcdefABCDEF",
+            "-ffffffffffffffffffffffffffffffffffffffff",
+        ];
+
+        for &s in cases.iter() {
+            let n = Int::from_hex_ct(s).unwrap();
+            assert_eq!(n, Int::from_str_radix(s, 16).unwrap());
+            assert_eq!(Int::from_hex_ct(&n.to_hex_ct()).unwrap(), n);
+        }
+
+        for &(s, offset) in [("", 0), ("-", 1), ("12g4", 2), ("-0x1", 2), ("1 ", 1)].iter() {
+            assert_eq!(Int::from_hex_ct(s).unwrap_err().offset(), offset);
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        let cases = [
+            ("",        10, ErrorKind::Empty,        0),
+            ("-",       10, ErrorKind::Empty,        1),
+            ("12a4",    10, ErrorKind::InvalidDigit, 2),
+            ("-1 2",    10, ErrorKind::InvalidDigit, 2),
+            ("fg",      16, ErrorKind::InvalidDigit, 1),
+            ("+1",      10, ErrorKind::InvalidDigit, 0),
+            ("1",        1, ErrorKind::InvalidRadix, 0),
+            ("1",       37, ErrorKind::InvalidRadix, 0),
+        ];
+
+        for &(s, base, kind, offset) in cases.iter() {
+            let err = Int::from_str_radix(s, base).unwrap_err();
+            assert_eq!(err.kind(), kind);
+            assert_eq!(err.offset(), offset);
+        }
+
+        let err = Int::from_str_bounded("-1234", 3).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TooLong);
+        assert_eq!(err.offset(), 4);
+        assert_eq!(Int::from_str_bounded("-123", 3).unwrap(), -123);
+
+        let err = Int::from_str_flexible("-0x12_g").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidDigit);
+        assert_eq!(err.offset(), 6);
+    }
+
+    #[test]
+    fn to_string_10() {
+        let cases = [
+            ("0",        Int::zero()),
+            ("1",        Int::from(1)),
+            ("123",      Int::from(123)),
+            ("-456",     Int::from(-456)),
+            ("987654321012345678910111213",
+             Int::from_str("987654321012345678910111213").unwrap()),
+        ];
+
+        for &(s, ref n) in cases.iter() {
+            assert_eq!(s, &n.to_string());
+        }
+    }
+
+    #[test]
+    fn to_string_16() {
+        let cases = [
+            ("0",        Int::zero()),
+            ("1",        Int::from(1)),
+            ("-1",       Int::from(-1)),
+            ("abc",      Int::from(0xabc)),
+            ("-456",     Int::from(-0x456)),
+            ("987654321012345678910111213",
+             Int::from_str_radix("987654321012345678910111213", 16).unwrap()),
+        ];
+
+        for &(s, ref n) in cases.iter() {
+            assert_eq!(s, &n.to_str_radix(16, false));
+        }
+    }
+
+    #[test]
+    fn write_str_radix() {
+        let cases = [
+            "0",
+            "-1",
+            "123456789",
+            "-987654321012345678910111213",
+        ];
+
+        for &s in cases.iter() {
+            let n = Int::from_str(s).unwrap();
+
+            let mut out = String::new();
+            n.write_str_radix(10, false, &mut out).unwrap();
+            assert_eq!(s, &out);
+
+            let mut buf = [0u8; 64];
+            let len = n.write_str_radix_to_slice(10, false, &mut buf).unwrap();
+            assert_eq!(s.as_bytes(), &buf[..len]);
+
+            assert_eq!(None, n.write_str_radix_to_slice(10, false, &mut buf[..s.len() - 1]));
+        }
+    }
+
+    #[test]
+    fn num_base_digits_pow2() {
+        use ::ll::base::num_base_digits;
+        let cases = [
+            ("10", 2, 4), // 0b 1010
+            ("15", 2, 4), // 0b 1111
+            ("16", 2, 5), // 0b10000
+            ("1023", 2, 10), // 0b 1111111111
+            ("1024", 2, 11), // 0b10000000000
+            ("341", 4, 5), // 4»11111
+            ("5461", 4, 7), // 4»1111111
+            ("16383", 4, 7), // 4» 3333333
+            ("16384", 4, 8), // 4»10000000
+            ("65535", 4, 8), // 4»33333333
+            ("299593", 8, 7), // 8»1111111 // 8**0 + 8**1 + 8**2 + 8**3 + 8**4 + 8**5 + 8**6
+            ("299594", 8, 7), // 8»1111112
+            ("2097151", 8, 7), // 8» 7777777
+            ("2097152", 8, 8), // 8»10000000
+            ("268435455", 16, 7), // 0x fffffff
+            ("268435456", 16, 8), // 0x10000000
+            ("13407807929942597099574024998205846127479365820592393377723561443721764030073546976801874298166903427690031858186486050853753882811946569946433649006084095", 2, 512), // 512 bits with value 1 -> 1 Limb
+            ("13407807929942597099574024998205846127479365820592393377723561443721764030073546976801874298166903427690031858186486050853753882811946569946433649006084096", 2, 513), // 513 bits -> 2 Limbs
+        ];
+        for &(s, base, digits) in cases.iter() {
+            let i : Int = s.parse().unwrap();
+            unsafe {
+                assert_eq!(digits,
+                           num_base_digits(i.limbs(), i.abs_len(), base));
+            }
+        }
+    }
+
+    #[test]
+    fn num_base_digits() {
+        use ::ll::base::num_base_digits;
+        let cases = [
+            ("0", 15, 1),
+            ("0", 58, 1),
+            ("49172", 10, 5),
+            ("49172", 57, 3), // [15, 7, 38], 38 + 7*57 + 15*57**2 = 49172
+            ("185192", 57, 3), // [56, 56, 56], 56 + 56*57 + 56*57**2 = 185192
+            ("185193", 57, 4), // [1, 0, 0, 0], 1*57**3 = 185193
+            ("250046", 63, 3), // [62, 62, 62], 62 + 62*63 + 62*63**2 = 250046
+            ("250047", 63, 4), // [1, 0, 0, 0], 1*63**3 = 250047
+            ("274624", 65, 3), // [64, 64, 64], 64 + 64*65 + 64*65**2 = 274624
+            ("274625", 65, 4), // [1, 0, 0, 0], 1*65**3 = 274625
+        ];
+        for &(s, base, digits) in cases.iter() {
+            let i : Int = s.parse().unwrap();
+            unsafe {
+                let estimate = num_base_digits(i.limbs(), i.abs_len(), base);
+                assert!(digits == estimate || digits == estimate - 1);
+            }
+        }
+    }
+
+    #[test]
+    fn digits_in_base() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..RAND_ITER {
+            let bits = rng.gen_range(1, 500);
+            let x = rng.gen_int(bits);
+            let base = rng.gen_range(2, 37);
+            let s = x.to_str_radix(base, false);
+            assert_eq!(x.digits_in_base(base), s.trim_left_matches('-').len());
+        }
+
+        for &base in [3u8, 7, 10, 36].iter() {
+            let p = Int::from(base).pow(50);
+            assert_eq!(p.digits_in_base(base), 51);
+            assert_eq!((&p - 1).digits_in_base(base), 50);
+        }
+        assert_eq!(Int::zero().digits_in_base(10), 1);
+    }
+
+    #[test]
+    fn debug_fmt() {
+        let x = Int::from(-12345);
+        assert_eq!(format!("{:?}", x), "-12345");
+
+        let mut x = (Int::one() << Limb::BITS) + 0xab;
+        x.negate();
+        let expected = if Limb::BITS == 64 {
+            format!("Int {{ sign: -1, size: 2, cap: {}, limbs: [0x00000000000000ab, \
+                     0x0000000000000001] }}", x.cap)
+        } else {
+            format!("Int {{ sign: -1, size: 2, cap: {}, limbs: [0x000000ab, 0x00000001] }}", x.cap)
+        };
+        assert_eq!(format!("{:#?}", x), expected);
+        assert_eq!(format!("{:#?}", Int::zero()), "Int { sign: 0, size: 0, cap: 0, limbs: [] }");
+    }
+
+    #[test]
+    fn remove_factor() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..RAND_ITER {
+            let fbits = rng.gen_range(1, 70);
+            let cbits = rng.gen_range(1, 200);
+            let f = rng.gen_int(fbits);
+            let c = rng.gen_int(cbits);
+            if f.sign() == 0 || c.sign() == 0 || f.clone().abs() == 1 {
+                continue;
+            }
+            let k = rng.gen_range(0, 40);
+            let x = &c * &f.pow(k);
+
+            let (y, n) = x.remove_factor(&f);
+            assert!(n as usize >= k);
+            assert_mp_eq!(&y * &f.pow(n as usize), x);
+            assert!(&y % &f != 0);
+            assert_eq!(x.valuation(&f), n);
+        }
+
+        assert_eq!(Int::from(48).remove_factor(&Int::from(4)), (Int::from(3), 2));
+        assert_eq!(Int::from(-48).remove_factor(&Int::from(-2)), (Int::from(-3), 4));
+        assert_eq!(Int::from(7).remove_factor(&Int::from(-1)), (Int::from(7), 0));
+        assert_eq!(Int::zero().remove_factor(&Int::from(3)), (Int::zero(), 0));
+    }
+
+    #[test]
+    fn capacity() {
+        let mut i = Int::with_capacity(20);
+        assert_eq!(i, 0);
+        assert!(i.capacity() >= 20);
+        assert!(i.well_formed());
+
+        i.reserve(5);
+        assert!(i.capacity() >= 20);
+
+        i = Int::from(7);
+        i.reserve(30);
+        assert!(i.capacity() >= 31);
+        assert_eq!(i, 7);
+
+        let mut c = Int::from_u32_const(3);
+        assert_eq!(c.capacity(), 0);
+        c.reserve(2);
+        assert!(c.capacity() >= 3);
+        assert_eq!(c, 3);
+        assert!(c.well_formed());
+
+        let mut big = Int::from(1) << 1000;
+        big >>= 990;
+        big.shrink_to_fit();
+        assert_eq!(big.capacity(), 1);
+        assert_eq!(big, 1024);
+    }
+
+    #[test]
+    fn try_alloc() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..RAND_ITER {
+            let a_bits = rng.gen_range(0, 1000);
+            let b_bits = rng.gen_range(0, 1000);
+            let a = rng.gen_int(a_bits);
+            let b = rng.gen_int(b_bits);
+
+            assert_mp_eq!(a.try_mul(&b).unwrap(), &a * &b);
+            assert_mp_eq!(a.try_square().unwrap(), a.square());
+            let cnt = rng.gen_range(0, 300);
+            assert_mp_eq!(a.try_shl(cnt).unwrap(), &a << cnt);
+            let exp = rng.gen_range(0, 10);
+            assert_mp_eq!(a.try_pow(exp).unwrap(), a.pow(exp));
+        }
+
+        let mut i = Int::from_u32_const(9);
+        i.try_reserve(4).unwrap();
+        assert!(i.capacity() >= 5);
+        assert_eq!(i, 9);
+        assert!(i.well_formed());
+
+        let err = i.try_reserve(std::usize::MAX).unwrap_err();
+        assert_eq!(err.limbs(), std::usize::MAX);
+        assert_eq!(i, 9);
+
+        assert_eq!(Int::from(-1).try_pow(std::usize::MAX).unwrap(), -1);
+        assert_eq!(Int::from(-1).try_pow(std::usize::MAX - 1).unwrap(), 1);
+        assert!(Int::from(10).pow(100).try_pow(std::usize::MAX).is_err());
+        assert!(Int::try_with_capacity(std::usize::MAX).is_err());
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    #[should_panic(expected = "Int too large")]
+    fn shl_too_large() {
+        let _ = Int::one() << std::usize::MAX;
+    }
+
+    #[test]
+    fn size_limits() {
+        let mut i = Int::from(3);
+        assert_eq!(i.try_reserve(MAX_LIMBS).unwrap_err().limbs(), MAX_LIMBS + 1);
+        assert_eq!(i, 3);
+    }
+
+    #[test]
+    #[cfg(feature = "zeroize")]
+    fn zeroize() {
+        let mut a = Int::from(3).pow(200);
+        a.zeroize();
+        assert_eq!(a, 0);
+        assert!(a.well_formed());
+        a += 5;
+        assert_eq!(a, 5);
+
+        let mut b = Int::from_u32_const(7);
+        b.zeroize();
+        assert_eq!(b, 0);
+        assert!(b.well_formed());
+
+        // Growing and shrinking moves the value to a fresh buffer
+        let mut c = Int::from(12345);
+        c.reserve(100);
+        c <<= 5000;
+        c >>= 4990;
+        c.shrink_to_fit();
+        assert_eq!(c, Int::from(12345) << 10);
+        c.try_reserve(50).unwrap();
+        assert_eq!(c, Int::from(12345) << 10);
+    }
+
+    #[test]
+    #[cfg(all(feature = "validate", debug_assertions))]
+    #[should_panic(expected = "invalid Int after drop: high limb is zero")]
+    fn validate_on_drop() {
+        let mut a = Int::from(1) << (3 * Limb::BITS);
+        unsafe {
+            *a.limbs_mut().offset(3) = Limb(0);
+        }
+        drop(a);
+    }
+
+    #[test]
+    fn limb_slices() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..RAND_ITER {
+            let a = rng.gen_int(500);
+            let limbs = a.as_limb_slice().to_vec();
+            assert_eq!(limbs.len(), a.abs_size() as usize);
+            assert!(limbs.last().map_or(true, |&l| l != 0));
+
+            assert_mp_eq!(Int::from_limbs(a.sign_enum(), &limbs), a);
+            let mut padded = limbs.clone();
+            padded.extend_from_slice(&[Limb(0), Limb(0)]);
+            assert_mp_eq!(Int::from_limbs(a.sign_enum(), &padded), a);
+
+            let n = -a.clone();
+            assert_eq!(n.into_limbs(), limbs);
+        }
+
+        assert_eq!(Int::zero().as_limb_slice(), &[]);
+        assert!(Int::zero().into_limbs().is_empty());
+        assert_eq!(Int::from_u32_const(3).as_limb_slice(), &[Limb(3)]);
+        assert_eq!(Int::from_u32_const(3).into_limbs(), vec![Limb(3)]);
+        assert_mp_eq!(Int::from_limbs(Sign::Zero, &[Limb(0)]), Int::zero());
+        assert_mp_eq!(Int::from_limbs(Sign::Negative, &[]), Int::zero());
+    }
+
+    #[test]
+    #[should_panic]
+    fn from_limbs_zero_sign() {
+        Int::from_limbs(Sign::Zero, &[Limb(1)]);
+    }
+
+    #[test]
+    fn random_range() {
+        let mut rng = rand::thread_rng();
+
+        let (lo, hi) = (Int::from(-3), Int::from(4));
+        let mut seen = [false; 7];
+        for _ in 0..1000 {
+            let x = Int::random_range(&mut rng, &lo..&hi);
+            assert!(lo <= x && x < hi);
+            seen[usize::from(&(x - &lo))] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+
+        for _ in 0..RAND_ITER {
+            let a = rng.gen_int(300);
+            let b = &a + rng.gen_uint(200) + 1;
+            let x = Int::random_range(&mut rng, &a..&b);
+            assert!(a <= x && x < b);
+        }
+
+        // A range whose length is one more than a power of two, where most
+        // samples are rejected once the low limbs are known
+        let m = (Int::one() << 256) + 1;
+        for _ in 0..100 {
+            let x = Int::random_range(&mut rng, &Int::zero()..&m);
+            assert!(x < m && x.sign() >= 0);
+        }
+    }
+
+    #[test]
+    fn random_below() {
+        let mut rng = rand::thread_rng();
+
+        // Every value turns up about as often as the others
+        let m = Int::from(3);
+        let mut counts = [0; 3];
+        for _ in 0..3000 {
+            counts[usize::from(&Int::random_below(&mut rng, &m))] += 1;
+        }
+        assert!(counts.iter().all(|&c| c > 800 && c < 1200), "{:?}", counts);
+
+        for _ in 0..RAND_ITER {
+            let m = rng.gen_uint(500) + 1;
+            let x = Int::random_below(&mut rng, &m);
+            assert!(x.sign() >= 0 && x < m);
+        }
+
+        // A one in the top limb leaves a single bit to draw for it
+        let m = (Int::one() << (2 * Limb::BITS)) + 12345;
+        for _ in 0..100 {
+            let x = Int::random_below(&mut rng, &m);
+            assert!(x.sign() >= 0 && x < m);
+        }
+        assert_eq!(Int::random_below(&mut rng, &Int::one()), 0);
+    }
+
+    #[test]
+    fn random_bits() {
+        let mut rng = rand::thread_rng();
+        assert_eq!(Int::random_bits(&mut rng, 0), 0);
+        assert_eq!(Int::random_exact_bits(&mut rng, 1), 1);
+
+        for &bits in &[1, 7, Limb::BITS - 1, Limb::BITS, Limb::BITS + 1, 3 * Limb::BITS, 1000] {
+            let mut or = Int::zero();
+            for _ in 0..50 {
+                let x = Int::random_bits(&mut rng, bits);
+                assert!(x.sign() >= 0 && x.bit_length() as usize <= bits);
+                or |= &x;
+
+                let y = Int::random_exact_bits(&mut rng, bits);
+                assert_eq!(y.bit_length() as usize, bits);
+            }
+            // All the bits get set at some point
+            if bits > 1 {
+                assert_eq!(or, (Int::one() << bits) - 1);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "getrandom")]
+    fn random_bits_os() {
+        assert_eq!(Int::random_bits_os(0).unwrap(), 0);
+        let mut or = Int::zero();
+        for _ in 0..50 {
+            let x = Int::random_bits_os(333).unwrap();
+            assert!(x.sign() >= 0 && x.bit_length() <= 333);
+            or |= &x;
+        }
+        assert_eq!(or, (Int::one() << 333) - 1);
+    }
+
+    #[test]
+    fn random_invertible() {
+        let mut rng = rand::thread_rng();
+        assert_eq!(Int::random_invertible(&mut rng, &Int::from(2)), 1);
+
+        let moduli = [Int::from(6), Int::from(3 * 5 * 7 * 11 * 13),
+                      (Int::one() << 521) - 1, Int::from(2).pow(300) * 9];
+        for m in moduli.iter() {
+            for _ in 0..20 {
+                let x = Int::random_invertible(&mut rng, m);
+                assert!(x > 0 && x < *m);
+                assert_eq!(x.gcd(m), 1);
+            }
+        }
+
+        // Only 1 and 5 are coprime to 6
+        let mut seen = [false; 6];
+        for _ in 0..100 {
+            seen[usize::from(&Int::random_invertible(&mut rng, &Int::from(6)))] = true;
+        }
+        assert_eq!(seen, [false, true, false, false, false, true]);
+    }
+
+    #[test]
+    #[should_panic(expected = "the modulus must be greater than one")]
+    fn random_invertible_one() {
+        let mut rng = rand::thread_rng();
+        Int::random_invertible(&mut rng, &Int::one());
+    }
+
+    #[test]
+    #[should_panic(expected = "the bound must be positive")]
+    fn random_below_negative() {
+        let mut rng = rand::thread_rng();
+        Int::random_below(&mut rng, &Int::from(-5));
+    }
+
+    #[test]
+    #[should_panic(expected = "empty range")]
+    fn random_range_empty() {
+        let mut rng = rand::thread_rng();
+        Int::random_range(&mut rng, &Int::one()..&Int::one());
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn parallel_mul() {
+        let mut rng = rand::thread_rng();
+        // Large enough for the top levels of Toom-2 to run in parallel, then for the
+        // pointwise products of Schönhage-Strassen
+        for &limbs in [10000, 12000].iter() {
+            let bits = limbs * Limb::BITS;
+            let x = rng.gen_uint(bits);
+            let y = rng.gen_uint(bits - 1234);
+
+            let p = &x * &y;
+            let (q, r) = p.divmod(&y);
+            assert_eq!(q, x);
+            assert_eq!(r, 0);
+
+            let s = x.square();
+            let (q, r) = s.divmod(&x);
+            assert_eq!(q, x);
+            assert_eq!(r, 0);
+        }
+    }
+
+    #[test]
+    fn toom22_sizes() {
+        let mut rng = rand::thread_rng();
+        // Limb counts with odd and even halves, on both sides of the threshold,
+        // for balanced and unbalanced products
+        let sizes = [20, 21, 27, 39, 40, 41, 45, 53, 79, 80, 81, 160, 163, 333];
+        for &xs in sizes.iter() {
+            for &ys in sizes.iter().filter(|&&ys| ys <= xs) {
+                let (xb, yb) = (xs * Limb::BITS, ys * Limb::BITS);
+                let ones = ((Int::one() << xb) - 1, (Int::one() << yb) - 1);
+                let random = (rng.gen_uint(xb - 1) + (Int::one() << (xb - 1)),
+                              rng.gen_uint(yb - 1) + (Int::one() << (yb - 1)));
+                for &(ref x, ref y) in [ones, random].iter() {
+                    let p = x * y;
+                    let (q, r) = p.divmod(y);
+                    assert_eq!(q, *x, "xs: {}, ys: {}", xs, ys);
+                    assert_eq!(r, 0);
+                    assert_eq!(x.square(), x * &x.clone(), "xs: {}", xs);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sqr_sizes() {
+        let mut rng = rand::thread_rng();
+        // Around the thresholds of Toom-2 and Toom-3 squaring, with every size modulo 3
+        let sizes = (1..36).chain(118..126).chain([200, 361, 362, 363, 1000].iter().cloned());
+        for xs in sizes {
+            let xb = xs * Limb::BITS;
+            let ones = (Int::one() << xb) - 1;
+            let random = rng.gen_uint(xb - 1) + (Int::one() << (xb - 1));
+            for x in [ones, random].iter() {
+                assert_eq!(x.square(), x * &x.clone(), "xs: {}", xs);
+            }
+        }
+    }
+
+    #[test]
+    fn unbalanced_mul() {
+        let mut rng = rand::thread_rng();
+        // Shapes on both sides of the switches between Toom-2, Toom-3.2, Toom-4.2 and cutting
+        // into pieces, checked against products by pieces of y small enough for the basecase
+        for &ys in [59, 60, 61, 100, 157].iter() {
+            for &xs in [ys * 3 / 2 - 1, ys * 3 / 2, ys * 3 / 2 + 1, ys * 2 - 1, ys * 2, ys * 2 + 1,
+                        ys * 5 / 2, ys * 3 - 1, ys * 3].iter() {
+                let (xb, yb) = (xs * Limb::BITS, ys * Limb::BITS);
+                let ones = ((Int::one() << xb) - 1, (Int::one() << yb) - 1);
+                let random = (rng.gen_uint(xb), rng.gen_uint(yb));
+                for &(ref x, ref y) in [ones, random].iter() {
+                    let piece = 16 * Limb::BITS;
+                    let mask = (Int::one() << piece) - 1;
+                    let mut expected = Int::zero();
+                    let mut i = 0;
+                    while i < yb {
+                        expected += (x * ((y >> i) & &mask)) << i;
+                        i += piece;
+                    }
+                    assert_eq!(x * y, expected, "xs: {}, ys: {}", xs, ys);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rem_small() {
+        let mut rng = rand::thread_rng();
+        for &bits in [10, 64, 65, 1000, 5000].iter() {
+            let x = rng.gen_uint(bits);
+            for x in [x.clone(), -x].iter() {
+                for &d in [1u64, 3, 10, 1 << 40, !0].iter() {
+                    let expected = x % Int::from(d);
+                    assert_eq!(x % d, expected);
+                    assert_eq!(x.clone() % d, expected);
+                    assert_eq!(x % Limb(d as BaseInt), x % Int::from(d as BaseInt));
+                }
+                for &d in [-7i32, 7, -1, i32::min_value()].iter() {
+                    assert_eq!(x % d, x % Int::from(d));
+                    let mut y = x.clone();
+                    y %= d;
+                    assert_eq!(y, x % Int::from(d));
+                }
+                assert_eq!(x % (1u128 << 100), x % (Int::one() << 100));
+            }
+        }
+    }
+
+    #[test]
+    fn mod_2exp() {
+        let mut rng = rand::thread_rng();
+        for &bits in [1, 64, 65, 1000].iter() {
+            let x = rng.gen_uint(bits) + 1;
+            for x in [x.clone(), -x].iter() {
+                for &k in [0, 1, 63, 64, 65, 200, 999, 1000, 1001, 5000].iter() {
+                    let a = x.clone().abs();
+                    let low = &a - ((&a >> k) << k);
+                    let expected = if x.sign() < 0 { -low } else { low };
+                    assert_eq!(x.mod_2exp(k), expected, "k: {}", k);
+                    assert_eq!(x % (Int::one() << k), expected, "k: {}", k);
+                    assert_eq!(x % -(Int::one() << k), expected, "k: {}", k);
+                }
+            }
+        }
+        assert_eq!(Int::zero().mod_2exp(10), 0);
+    }
+
+    #[test]
+    fn product_tree() {
+        let mut rng = rand::thread_rng();
+        assert_eq!(Int::product_tree(Vec::new()), 1);
+        assert_eq!(Int::product_tree(vec![Int::from(-7)]), -7);
+        assert_eq!(Int::product_tree(vec![Int::from(3), Int::zero(), Int::from(5)]), 0);
+
+        let f = Int::product_tree((1..1001).map(Int::from));
+        assert_eq!(f, (1..1001).fold(Int::one(), |acc, i| acc * i));
+
+        // Single limbs, including the largest, mixed with longer values of either sign
+        let mut factors = vec![Int::from_single_limb(Limb(!0)); 40];
+        for i in 0..60 {
+            let x = rng.gen_uint(1 + (i * 37) % 700) + 1;
+            factors.push(if i % 3 == 0 { -x } else { x });
+        }
+        let expected = factors.iter().fold(Int::one(), |acc, x| acc * x);
+        assert_eq!(Int::product_tree(factors.clone()), expected);
+        assert_eq!(factors.iter().product::<Int>(), expected);
+    }
+
+    #[test]
+    fn sparse_mul() {
+        let mut rng = rand::thread_rng();
+        let one = Int::one();
+        assert_eq!((&one << 100000) * Int::from(12345), Int::from(12345) << 100000);
+        assert_eq!((&one << 100000) * (&one << 70000), &one << 170000);
+        assert_eq!((&one << 100000).square(), &one << 200000);
+
+        // Zero limbs at both ends and in the middle
+        for &(xs, ys, shift) in [(3, 2, 64), (30, 25, 6400), (100, 90, 12345)].iter() {
+            let x = (rng.gen_uint(xs * Limb::BITS) << shift) + 1;
+            let y = rng.gen_uint(ys * Limb::BITS) << (2 * shift);
+            let (lx, ly) = (&x << (10 * Limb::BITS), &y << Limb::BITS);
+            assert_eq!(&lx * &ly, (&x * &y) << (11 * Limb::BITS));
+            assert_eq!(&ly * &lx, (&x * &y) << (11 * Limb::BITS));
+            assert_eq!(ly.square(), y.square() << (2 * Limb::BITS));
+            let p = &x * &y;
+            assert_eq!(p.divmod(&y), (x.clone(), Int::zero()));
+        }
+    }
+
+    #[test]
+    fn fft_mul() {
+        // The product of a and b, with pieces of b short enough for Toom-2
+        fn by_pieces(a: &Int, b: &Int) -> Int {
+            let bits = 4000 * Limb::BITS;
+            let mask = (Int::one() << bits) - 1;
+            let mut r = Int::zero();
+            let mut shift = 0;
+            while shift < b.bit_length() as usize {
+                r += (a * &((b >> shift) & &mask)) << shift;
+                shift += bits;
+            }
+            r
+        }
+
+        let mut rng = rand::thread_rng();
+        // Past the threshold of Schönhage-Strassen
+        let (xb, yb) = (12000 * Limb::BITS, 10001 * Limb::BITS);
+        let ones = ((Int::one() << xb) - 1, (Int::one() << yb) - 1);
+        let random = (rng.gen_uint(xb), rng.gen_uint(yb - 1) + (Int::one() << (yb - 1)));
+        for &(ref x, ref y) in [ones, random].iter() {
+            assert_eq!(x * y, by_pieces(x, y));
+            assert_eq!(y.square(), by_pieces(y, y));
+        }
+    }
+
+    #[test]
+    fn primes() {
+        let mut composite = vec![false; 5000];
+        for i in 0..5000 {
+            let p = !composite[i] && i >= 2;
+            if p {
+                let mut j = i * i;
+                while j < 5000 {
+                    composite[j] = true;
+                    j += i;
+                }
+            }
+            assert_eq!(Int::from(i).is_probable_prime(10), p, "{}", i);
+        }
+        assert!(!Int::from(-7).is_probable_prime(10));
+
+        // 2^61 - 1 is prime, 2^67 - 1 = 193707721 * 761838257287 is not,
+        // and neither is the Carmichael number 307 * 613 * 919
+        let one = Int::one();
+        assert!(((&one << 61) - 1).is_probable_prime(10));
+        assert!(!((&one << 67) - 1).is_probable_prime(10));
+        assert!(!Int::from(172947529u32).is_probable_prime(10));
+        assert!(!(Int::from(193707721u32) * Int::from(761838257287u64)).is_probable_prime(10));
+    }
+
+    #[test]
+    fn random_prime() {
+        let mut rng = rand::thread_rng();
+        for &bits in [2usize, 3, 10, 17, 64, 65, 200, 512].iter() {
+            let p = Int::random_prime(&mut rng, bits);
+            assert_eq!(p.bit_length() as usize, bits);
+            assert!(p.is_probable_prime(30));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Int::random_prime: no prime has fewer than two bits")]
+    fn random_prime_one_bit() {
+        let mut rng = rand::thread_rng();
+        Int::random_prime(&mut rng, 1);
+    }
+
+    #[test]
+    fn bytes() {
+        // The same value as big-endian bytes, little-endian bytes and hex,
+        // long enough to span several limbs of either size
+        let be: Vec<u8> = (1..18).collect();
+        let mut le = be.clone();
+        le.reverse();
+        let value = Int::from_str_radix("0102030405060708090a0b0c0d0e0f1011", 16).unwrap();
+
+        assert_eq!(Int::from_bytes_be(&be), value);
+        assert_eq!(Int::from_bytes_le(&le), value);
+        assert_eq!(value.to_bytes_be(), be);
+        assert_eq!(value.to_bytes_le(), le);
+        assert_eq!((-&value).to_bytes_be(), be);
+
+        // Zero bytes at the most significant end are dropped
+        let mut padded = vec![0, 0, 0];
+        padded.extend_from_slice(&be);
+        assert_eq!(Int::from_bytes_be(&padded), value);
+        le.extend_from_slice(&[0, 0, 0]);
+        assert_eq!(Int::from_bytes_le(&le), value);
+
+        assert_eq!(Int::from_bytes_be(&[]), 0);
+        assert_eq!(Int::from_bytes_le(&[0, 0]), 0);
+        assert!(Int::zero().to_bytes_le().is_empty());
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..RAND_ITER {
+            let x = rng.gen_uint(300);
+            assert_eq!(Int::from_bytes_be(&x.to_bytes_be()), x);
+            assert_eq!(Int::from_bytes_le(&x.to_bytes_le()), x);
+        }
+    }
+
+    #[test]
+    fn pow() {
+        let bases = ["0", "1", "190000000000000", "192834857324591531",
+                     "340282366920938463463374607431768211456", // 2**128
+                     "100000000", "-1", "-100", "-200", "-192834857324591531",
+                     "-431343873217510631841",
+                     "-340282366920938463463374607431768211456"];
+
+        for b in bases.iter() {
+            let b : Int = b.parse().unwrap();
+            let mut x = Int::one();
+            for e in 0..512 {
+                let a = &b.pow(e);
+                assert_mp_eq!(a.clone(), x.clone());
+                x = &x * &b
+            }
+        }
+    }
+
+    #[test]
+    fn pow_capacity() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let bits = rng.gen_range(1, 200);
+            let b = rng.gen_int(bits);
+            let e = rng.gen_range(3, 100);
+            let x = b.pow(e);
+            if x != 0 {
+                // Allocated once, with less than `e` bits to spare
+                let spare = x.cap as usize - x.abs_len();
+                assert!(spare <= (e - 1) / Limb::BITS + 1, "{}^{}", b, e);
+            }
+        }
+
+        let x = Int::one() << 100usize;
+        assert_eq!(x.pow(10).cap as usize, (1010 + Limb::BITS - 1) / Limb::BITS);
+
+        let big = ::std::usize::MAX - 1;
+        assert_mp_eq!(Int::one().pow(big), Int::one());
+        assert_mp_eq!(Int::from(-1).pow(big), Int::one());
+        assert_mp_eq!(Int::from(-1).pow(big + 1), Int::from(-1));
+    }
+
+    #[test]
+    fn pow_checked() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..RAND_ITER {
+            let bits = rng.gen_range(1, 100);
+            let b = rng.gen_int(bits);
+            let e = rng.gen_range(0, 20);
+            let x = b.pow(e);
+            let len = x.bit_length() as usize;
+            assert_eq!(b.pow_checked(e, len), Some(x));
+            assert_eq!(b.pow_checked(e, len - 1), None);
         }
+
+        assert_eq!(Int::from(10).pow_checked(::std::usize::MAX, ::std::usize::MAX), None);
+        assert_eq!(Int::from(-1).pow_checked(::std::usize::MAX, 1), Some(Int::from(-1)));
+        assert_eq!(Int::zero().pow_checked(::std::usize::MAX, 1), Some(Int::zero()));
     }
 
     #[test]
-    fn to_string_10() {
-        let cases = [
-            ("0",        Int::zero()),
-            ("1",        Int::from(1)),
-            ("123",      Int::from(123)),
-            ("-456",     Int::from(-456)),
-            ("987654321012345678910111213",
-             Int::from_str("987654321012345678910111213").unwrap()),
-        ];
+    fn sum_product() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let n = rng.gen_range(0, 50);
+            let xs: Vec<Int> = (0..n).map(|_| {
+                let bits = rng.gen_range(1, 200);
+                rng.gen_int(bits)
+            }).collect();
+
+            let mut sum = Int::zero();
+            let mut product = Int::one();
+            for x in xs.iter() {
+                sum += x;
+                product *= x;
+            }
 
-        for &(s, ref n) in cases.iter() {
-            assert_eq!(s, &n.to_string());
+            assert_mp_eq!(xs.iter().sum::<Int>(), sum);
+            assert_mp_eq!(xs.iter().product::<Int>(), product);
+            assert_mp_eq!(xs.clone().into_iter().sum::<Int>(), sum);
+            assert_mp_eq!(xs.into_iter().product::<Int>(), product);
         }
     }
 
     #[test]
-    fn to_string_16() {
-        let cases = [
-            ("0",        Int::zero()),
-            ("1",        Int::from(1)),
-            ("-1",       Int::from(-1)),
-            ("abc",      Int::from(0xabc)),
-            ("-456",     Int::from(-0x456)),
-            ("987654321012345678910111213",
-             Int::from_str_radix("987654321012345678910111213", 16).unwrap()),
-        ];
+    fn cmp_abs() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..RAND_ITER {
+            let abits = rng.gen_range(1, 200);
+            let bbits = rng.gen_range(1, 200);
+            let a = rng.gen_int(abits);
+            let b = if rng.gen() { rng.gen_int(bbits) } else { -&a };
 
-        for &(s, ref n) in cases.iter() {
-            assert_eq!(s, &n.to_str_radix(16, false));
+            let ord = a.clone().abs().cmp(&b.clone().abs());
+            assert_eq!(a.cmp_abs(&b), ord);
+            assert_eq!(a.eq_abs(&b), ord == ::std::cmp::Ordering::Equal);
         }
     }
 
     #[test]
-    fn num_base_digits_pow2() {
-        use ::ll::base::num_base_digits;
-        let cases = [
-            ("10", 2, 4), // 0b 1010
-            ("15", 2, 4), // 0b 1111
-            ("16", 2, 5), // 0b10000
-            ("1023", 2, 10), // 0b 1111111111
-            ("1024", 2, 11), // 0b10000000000
-            ("341", 4, 5), // 4»11111
-            ("5461", 4, 7), // 4»1111111
-            ("16383", 4, 7), // 4» 3333333
-            ("16384", 4, 8), // 4»10000000
-            ("65535", 4, 8), // 4»33333333
-            ("299593", 8, 7), // 8»1111111 // 8**0 + 8**1 + 8**2 + 8**3 + 8**4 + 8**5 + 8**6
-            ("299594", 8, 7), // 8»1111112
-            ("2097151", 8, 7), // 8» 7777777
-            ("2097152", 8, 8), // 8»10000000
-            ("268435455", 16, 7), // 0x fffffff
-            ("268435456", 16, 8), // 0x10000000
-            ("13407807929942597099574024998205846127479365820592393377723561443721764030073546976801874298166903427690031858186486050853753882811946569946433649006084095", 2, 512), // 512 bits with value 1 -> 1 Limb
-            ("13407807929942597099574024998205846127479365820592393377723561443721764030073546976801874298166903427690031858186486050853753882811946569946433649006084096", 2, 513), // 513 bits -> 2 Limbs
-        ];
-        for &(s, base, digits) in cases.iter() {
-            let i : Int = s.parse().unwrap();
-            unsafe {
-                assert_eq!(digits,
-                           num_base_digits(i.limbs(), i.abs_size(), base));
+    fn sign_enum() {
+        let vals = [-5, 0, 7];
+        for &a in vals.iter() {
+            let x = Int::from(a);
+            assert_eq!(x.sign_enum().to_i32(), x.sign());
+            assert_eq!((-&x).sign_enum(), -x.sign_enum());
+            for &b in vals.iter() {
+                let y = Int::from(b);
+                assert_eq!((&x * &y).sign_enum(), x.sign_enum() * y.sign_enum());
             }
         }
+        assert!(Sign::Negative < Sign::Zero && Sign::Zero < Sign::Positive);
     }
 
     #[test]
-    fn num_base_digits() {
-        use ::ll::base::num_base_digits;
-        let cases = [
-            ("0", 15, 1),
-            ("0", 58, 1),
-            ("49172", 10, 5),
-            ("49172", 57, 3), // [15, 7, 38], 38 + 7*57 + 15*57**2 = 49172
-            ("185192", 57, 3), // [56, 56, 56], 56 + 56*57 + 56*57**2 = 185192
-            ("185193", 57, 4), // [1, 0, 0, 0], 1*57**3 = 185193
-            ("250046", 63, 3), // [62, 62, 62], 62 + 62*63 + 62*63**2 = 250046
-            ("250047", 63, 4), // [1, 0, 0, 0], 1*63**3 = 250047
-            ("274624", 65, 3), // [64, 64, 64], 64 + 64*65 + 64*65**2 = 274624
-            ("274625", 65, 4), // [1, 0, 0, 0], 1*65**3 = 274625
-        ];
-        for &(s, base, digits) in cases.iter() {
-            let i : Int = s.parse().unwrap();
-            unsafe {
-                let estimate = num_base_digits(i.limbs(), i.abs_size(), base);
-                assert!(digits == estimate || digits == estimate - 1);
+    fn round_to_multiple() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..RAND_ITER {
+            let xbits = rng.gen_range(1, 200);
+            let mbits = rng.gen_range(1, 100);
+            let x = rng.gen_int(xbits);
+            let mut m = rng.gen_int(mbits);
+            if m == 0 {
+                m = Int::one();
             }
+            let a = m.clone().abs();
+
+            let next = x.next_multiple_of(&m);
+            let prev = x.prev_multiple_of(&m);
+            assert!(&next % &m == 0 && &prev % &m == 0);
+            assert!(prev <= x && x <= next);
+            assert!(&next - &prev <= a);
+
+            let zero = x.round_to_multiple(&m, RoundMode::TowardZero);
+            let away = x.round_to_multiple(&m, RoundMode::AwayFromZero);
+            assert!(zero.clone().abs() <= x.clone().abs() && x.clone().abs() <= away.clone().abs());
+
+            let near = x.round_to_multiple(&m, RoundMode::Nearest);
+            assert!(near == prev || near == next);
+            assert!((&near - &x).abs() << 1usize <= a);
         }
+
+        let m = Int::from(4);
+        assert_eq!(Int::from(6).round_to_multiple(&m, RoundMode::Nearest), 8);
+        assert_eq!(Int::from(-6).round_to_multiple(&m, RoundMode::Nearest), -8);
+        assert_eq!(Int::from(5).round_to_multiple(&m, RoundMode::Nearest), 4);
+        assert_eq!(Int::from(8).next_multiple_of(&Int::from(-4)), 8);
     }
 
     #[test]
-    fn pow() {
-        let bases = ["0", "1", "190000000000000", "192834857324591531",
-                     "340282366920938463463374607431768211456", // 2**128
-                     "100000000", "-1", "-100", "-200", "-192834857324591531",
-                     "-431343873217510631841",
-                     "-340282366920938463463374607431768211456"];
+    fn const_constructors() {
+        const ZERO: Int = Int::zero();
+        const ONE: Int = Int::one();
+        const BIG: Int = Int::from_u32_const(0xdead_beef);
+
+        assert_mp_eq!(ZERO, Int::from(0));
+        assert_mp_eq!(ONE, Int::from(1));
+        assert_mp_eq!(BIG, Int::from(0xdead_beefu32));
+        assert_mp_eq!(Int::from_u32_const(0), Int::zero());
+        assert_eq!(ONE.cap, 0);
+
+        // Values stored inline are moved to the heap when modified
+        let mut x = Int::one();
+        x += 41;
+        assert_mp_eq!(x, Int::from(42));
+        let mut x = BIG;
+        x += &BIG;
+        assert_mp_eq!(x, Int::from(0xdead_beefu64 * 2));
+        let mut x = BIG;
+        x -= &Int::from(-1);
+        assert_mp_eq!(x, Int::from(0xdead_beefu64 + 1));
+        let mut x = Int::one();
+        x <<= 200usize;
+        assert_mp_eq!(x, Int::from(2).pow(200));
+        let mut x = BIG;
+        x *= &BIG;
+        assert_mp_eq!(x, Int::from(0xdead_beefu64 * 0xdead_beef));
+        let mut x = ONE;
+        x.set_bit(100, true);
+        assert_mp_eq!(x, Int::from(2).pow(100) + 1);
+        let mut x = -ONE;
+        x.set_bit(0, false);
+        assert_mp_eq!(x, Int::from(-2));
+        let mut x = BIG;
+        x.shrink_to_fit();
+        x.negate();
+        assert_mp_eq!(x.clone(), -BIG);
+    }
 
-        for b in bases.iter() {
-            let b : Int = b.parse().unwrap();
-            let mut x = Int::one();
-            for e in 0..512 {
-                let a = &b.pow(e);
-                assert_mp_eq!(a.clone(), x.clone());
-                x = &x * &b
-            }
+    #[test]
+    fn low_bits() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..RAND_ITER {
+            let bits = rng.gen_range(1, 300);
+            let x = rng.gen_int(bits);
+            let abs = x.clone().abs();
+
+            let low64 = &abs & &((Int::one() << 64usize) - 1);
+            let low128 = &abs & &((Int::one() << 128usize) - 1);
+            assert_eq!(x.low_u64(), u64::from(&low64));
+            assert_mp_eq!(Int::from(x.low_u128()), low128);
+            assert_eq!(x.to_u64_wrapping(), u64::from(&(&x & &((Int::one() << 64usize) - 1))));
         }
+        assert_eq!(Int::zero().low_u128(), 0);
+        assert_eq!(Int::from(-5).low_u64(), 5);
     }
 
     #[test]
@@ -4315,6 +8673,181 @@ mod test {
         }
     }
 
+    #[test]
+    fn bitops_sign_boundaries() {
+        let mut vals = vec![0i64, 1, -1, 2, -2, 3, -3];
+        for &b in [31u32, 32, 33, 62].iter() {
+            let p = 1i64 << b;
+            vals.extend_from_slice(&[p - 1, p, p + 1, -(p - 1), -p, -(p + 1)]);
+        }
+
+        for &a in vals.iter() {
+            let ia = Int::from(a);
+            assert_eq!(!&ia, !a);
+
+            for &b in vals.iter() {
+                let ib = Int::from(b);
+                assert_eq!(&ia & &ib, a & b);
+                assert_eq!(&ia | &ib, a | b);
+                assert_eq!(&ia ^ &ib, a ^ b);
+
+                // the same, with multi-limb values
+                let (sa, sb) = (&ia << 100, &ib << 100);
+                assert_mp_eq!(&sa & &sb, Int::from(a & b) << 100);
+                assert_mp_eq!(&sa | &sb, Int::from(a | b) << 100);
+                assert_mp_eq!(&sa ^ &sb, Int::from(a ^ b) << 100);
+            }
+        }
+    }
+
+    #[test]
+    fn bits() {
+        assert_eq!(Int::zero().bits().count(), 0);
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..RAND_ITER {
+            let bits = rng.gen_range(1, 300);
+            let x = rng.gen_int(bits);
+
+            let mut y = Int::zero();
+            for b in x.bits().rev() {
+                y = (y << 1) + (b as i32);
+            }
+            let abs = x.clone().abs();
+            if abs != 0 {
+                assert_eq!(x.bits().len(), abs.bit_length() as usize);
+            }
+            assert_mp_eq!(y, abs);
+        }
+    }
+
+    #[test]
+    fn keep_bits() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..RAND_ITER {
+            let xbits = rng.gen_range(1, 500);
+            let x = rng.gen_int(xbits);
+            let bits = rng.gen_range(0, 600);
+
+            let m = Int::one() << bits;
+            let mut expected = &x % &m;
+            if expected < 0 {
+                expected += &m;
+            }
+
+            assert_mp_eq!(x.keep_bits(bits), expected);
+        }
+    }
+
+    #[test]
+    fn keep_bits_fixed() {
+        assert_mp_eq!(Int::from(0xFF).keep_bits(4), Int::from(0xF));
+        assert_mp_eq!(Int::from(0xFF).keep_bits(8), Int::from(0xFF));
+        assert_mp_eq!(Int::from(0xFF).keep_bits(0), Int::zero());
+        assert_mp_eq!(Int::from(-1).keep_bits(4), Int::from(0xF));
+        assert_mp_eq!(Int::from(-16).keep_bits(3), Int::zero());
+
+        let ones = (Int::one() << 100) - 1;
+        assert_mp_eq!(ones.keep_bits(70), (Int::one() << 70) - 1);
+        assert_mp_eq!(ones.keep_bits(100), ones.clone());
+        assert_mp_eq!(ones.keep_bits(Limb::BITS), (Int::one() << Limb::BITS) - 1);
+        assert_mp_eq!((-&ones).keep_bits(70), Int::one());
+    }
+
+    #[test]
+    fn extract_insert_bits() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..RAND_ITER {
+            let xbits = rng.gen_range(1, 500);
+            let x = rng.gen_int(xbits);
+            let start = rng.gen_range(0, 600);
+            let end = start + rng.gen_range(0, 300);
+
+            let mut expected = x.keep_bits(end);
+            expected >>= start;
+            assert_mp_eq!(x.extract_bits(start..end), expected);
+
+            let vbits = rng.gen_range(1, 400);
+            let v = rng.gen_int(vbits);
+            let mut y = x.clone();
+            y.insert_bits(start..end, &v);
+            assert_mp_eq!(y.extract_bits(start..end), v.keep_bits(end - start));
+            assert_mp_eq!(y.keep_bits(start), x.keep_bits(start));
+            assert_eq!(y.sign() < 0, x.sign() < 0);
+            let above = end + 700;
+            assert_mp_eq!(y.extract_bits(end..above), x.extract_bits(end..above));
+        }
+    }
+
+    #[test]
+    fn extract_insert_bits_fixed() {
+        let x = Int::from(0b1101_0110);
+        assert_mp_eq!(x.extract_bits(2..6), Int::from(0b0101));
+        assert_mp_eq!(x.extract_bits(0..3), Int::from(0b110));
+        assert_mp_eq!(x.extract_bits(4..100), Int::from(0b1101));
+        assert_mp_eq!(x.extract_bits(8..20), Int::zero());
+        assert_mp_eq!(Int::from(-2).extract_bits(0..4), Int::from(0b1110));
+
+        let big = (Int::from(0xABCD) << 60) + 0x1234;
+        assert_mp_eq!(big.extract_bits(60..68), Int::from(0xCD));
+        assert_mp_eq!(big.extract_bits(4..12), Int::from(0x23));
+
+        let mut y = Int::from(0b1111_1111);
+        y.insert_bits(2..6, &Int::from(0b1001));
+        assert_mp_eq!(y, Int::from(0b1110_0111));
+
+        // only the low bits of the value are used
+        let mut y = Int::zero();
+        y.insert_bits(2..6, &Int::from(0xFF));
+        assert_mp_eq!(y, Int::from(0b0011_1100));
+
+        let mut y = Int::from(-1);
+        y.insert_bits(0..4, &Int::zero());
+        assert_mp_eq!(y, Int::from(-16));
+
+        let mut y = Int::from(1);
+        y.insert_bits(60..68, &Int::from(0x1FF));
+        assert_mp_eq!(y, (Int::from(0xFF) << 60) + 1);
+    }
+
+    #[test]
+    fn split_at_bit() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..RAND_ITER {
+            let xbits = rng.gen_range(1, 500);
+            let x = rng.gen_int(xbits);
+            let bit = rng.gen_range(0, 600);
+
+            let (h, l) = x.split_at_bit(bit);
+            assert!(l >= 0);
+            assert!(l < (Int::one() << bit));
+            assert_mp_eq!((&h << bit) + &l, x);
+
+            let (q, r) = x.clone().div_mod_2exp(bit);
+            assert_mp_eq!(q, h);
+            assert_mp_eq!(r, l);
+        }
+    }
+
+    #[test]
+    fn windows() {
+        assert_eq!(Int::zero().windows(4).count(), 0);
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..RAND_ITER {
+            let bits = rng.gen_range(1, 500);
+            let x = rng.gen_uint(bits);
+            let k = rng.gen_range(1, 33);
+
+            let mut y = Int::zero();
+            for w in x.windows(k) {
+                assert!((w as u64) < (1u64 << k));
+                y = (y << k as usize) + Int::from(w);
+            }
+            assert_mp_eq!(y, x);
+        }
+    }
+
     #[test]
     fn is_even() {
         let cases = [
@@ -4412,6 +8945,220 @@ mod test {
         assert_mp_eq!((-20i32) / &x, "-4".parse().unwrap());
     }
 
+    #[test]
+    fn arith_prim_widths() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..RAND_ITER {
+            let bits = rng.gen_range(1, 300);
+            let x = rng.gen_int(bits);
+
+            macro_rules! check {
+                ($($v:expr),*) => {$({
+                    let v = $v;
+                    let y = Int::from(v);
+                    assert_mp_eq!(&x + v, &x + &y);
+                    assert_mp_eq!(&x - v, &x - &y);
+                    assert_mp_eq!(v - &x, &y - &x);
+                    assert_mp_eq!(&x * v, &x * &y);
+                    assert_mp_eq!(&x & v, &x & &y);
+                    assert_mp_eq!(&x | v, &x | &y);
+                    assert_mp_eq!(&x ^ v, &x ^ &y);
+                    if v != 0 {
+                        assert_mp_eq!(&x / v, &x / &y);
+                        assert_mp_eq!(&x % v, &x % &y);
+                    }
+
+                    let mut z = x.clone();
+                    z += v;
+                    z *= v;
+                    z -= v;
+                    assert_mp_eq!(z, (&x + &y) * &y - &y);
+                })*}
+            }
+
+            check!(rng.gen::<i8>(), rng.gen::<i16>(), rng.gen::<i64>(), rng.gen::<isize>(),
+                   rng.gen::<u8>(), rng.gen::<u16>(), rng.gen::<u32>(), rng.gen::<u64>());
+            check!(rng.gen::<u64>() as u128, rng.gen::<i64>() as i128,
+                   ((rng.gen::<u64>() as u128) << 64) | rng.gen::<u64>() as u128,
+                   ((rng.gen::<i64>() as i128) << 64) | rng.gen::<u64>() as i128);
+        }
+
+        let x = Int::from(1000);
+        assert_mp_eq!(&x + ::std::i8::MIN, Int::from(872));
+        assert_mp_eq!(&x * ::std::i64::MIN, Int::from(::std::i64::MIN) * 1000);
+        assert_mp_eq!(&x % ::std::i32::MIN, x);
+        assert_mp_eq!(&x - ::std::u128::MAX, Int::from(1001) - (Int::one() << 128));
+    }
+
+    #[test]
+    fn cmp_prim() {
+        let vals = [::std::i64::MIN, -(1 << 40), -3, -1, 0, 1, 2, 255, 1 << 40, ::std::i64::MAX];
+        for &a in vals.iter() {
+            let x = Int::from(a);
+            for &b in vals.iter() {
+                assert_eq!(x.partial_cmp(&b), a.partial_cmp(&b), "{} <=> {}", a, b);
+                assert_eq!(b.partial_cmp(&x), b.partial_cmp(&a), "{} <=> {}", b, a);
+                assert_eq!(x.partial_cmp(&(b as i128)), a.partial_cmp(&b));
+                assert_eq!(x.partial_cmp(&(b as f64)), (a as f64).partial_cmp(&(b as f64)).map(|o| {
+                    // `a as f64` may round, the comparison must not
+                    if o == ::std::cmp::Ordering::Equal {
+                        (a as i128).cmp(&(b as f64 as i128))
+                    } else {
+                        o
+                    }
+                }));
+                if b >= 0 {
+                    assert_eq!(x.partial_cmp(&(b as u64)), a.partial_cmp(&b));
+                    assert_eq!(x.partial_cmp(&(b as u128)), a.partial_cmp(&b));
+                }
+            }
+            assert_eq!(x == (a as i8), a == (a as i8) as i64);
+            assert_eq!(x == (a as u16), a == (a as u16) as i64);
+        }
+
+        assert!(Int::from(::std::u64::MAX) == ::std::u64::MAX);
+        let big = Int::from(::std::u128::MAX);
+        assert!(big == ::std::u128::MAX);
+        assert!(big > ::std::u64::MAX);
+        assert!(-&big < ::std::i128::MIN);
+        assert!((&big + 1) > ::std::u128::MAX);
+        assert!((&big >> 1usize) == ::std::i128::MAX);
+    }
+
+    #[test]
+    fn cmp_f64() {
+        // 2^53 + 1 isn't representable, so it must compare greater than 2^53
+        let x = (Int::one() << 53usize) + 1;
+        let f = 9007199254740992.0f64;
+        assert!(x > f);
+        assert!(x != f);
+        assert!(&x - 1 == f);
+        assert!(f < x);
+
+        assert!(Int::from(2) > 1.5);
+        assert!(Int::from(1) < 1.5);
+        assert!(Int::from(-2) < -1.5);
+        assert!(Int::from(-1) > -1.5);
+        assert!(Int::zero() == -0.0);
+        assert!(Int::from(-1) < 0.0);
+
+        let big = Int::one() << 1000usize;
+        assert!(big == 2.0f64.powi(1000));
+        assert!((&big + 1) > 2.0f64.powi(1000));
+        assert!((&big - 1) < 2.0f64.powi(1000));
+        assert!(-&big == -(2.0f64.powi(1000)));
+        assert!(-(&big + 1) < -(2.0f64.powi(1000)));
+        assert!(big < ::std::f64::INFINITY);
+        assert!(-big > ::std::f64::NEG_INFINITY);
+        assert_eq!(Int::one().partial_cmp(&::std::f64::NAN), None);
+        assert!(Int::one() != ::std::f64::NAN);
+    }
+
+    #[test]
+    fn assign_ops() {
+        let mut rng = rand::thread_rng();
+        let mut r = Int::zero();
+        for _ in 0..RAND_ITER {
+            let abits = rng.gen_range(1, 800);
+            let bbits = rng.gen_range(1, 800);
+            let a = rng.gen_int(abits);
+            let b = rng.gen_int(bbits);
+
+            r.assign_add(&a, &b);
+            assert_mp_eq!(r, &a + &b);
+            r.assign_sub(&a, &b);
+            assert_mp_eq!(r, &a - &b);
+            r.assign_mul(&a, &b);
+            assert_mp_eq!(r, &a * &b);
+            r.assign_square(&a);
+            assert_mp_eq!(r, a.square());
+
+            let exp = rng.gen_range(0, 8);
+            r.assign_pow(&a, exp);
+            assert_mp_eq!(r, a.pow(exp));
+
+            r.assign(&b);
+            assert_mp_eq!(r, b);
+            r.assign(-5i64);
+            assert_mp_eq!(r, Int::from(-5));
+        }
+
+        // Storage is reused once it is big enough
+        let a = Int::from(3).pow(200);
+        r.assign_mul(&a, &a);
+        let cap = r.cap;
+        r.assign_mul(&a, &a);
+        assert_eq!(r.cap, cap);
+        r.assign(7u8);
+        assert_eq!(r.cap, cap);
+        assert_mp_eq!(r, Int::from(7));
+
+        let m = Int::from(1000007);
+        r.assign_pow_mod(&Int::from(3), &Int::from(1000), &m);
+        assert_mp_eq!(r, Int::from(3).modpow(&Int::from(1000), &m));
+    }
+
+    #[test]
+    fn in_place_ops() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..RAND_ITER {
+            let abits = rng.gen_range(1, 3000);
+            let bbits = rng.gen_range(1, 3000);
+            let a = rng.gen_int(abits);
+            let b = rng.gen_int(bbits);
+
+            let mut x = a.clone();
+            x *= &b;
+            assert_mp_eq!(x, &a * &b);
+            let mut x = a.clone();
+            x *= b.clone();
+            assert_mp_eq!(x, &a * &b);
+        }
+
+        // Growing a value a little at a time only reallocates occasionally
+        let mut x = Int::one();
+        let mut cap = x.cap;
+        let mut reallocs = 0;
+        for _ in 0..2000 {
+            x <<= 5usize;
+            x += &x.clone();
+            if x.cap != cap {
+                reallocs += 1;
+                cap = x.cap;
+            }
+        }
+        assert!(reallocs < 20, "{} reallocations", reallocs);
+
+        // Multiplying into a big enough value reuses its storage
+        let mut x = Int::from(3).pow(500);
+        let cap = x.cap;
+        x.assign(&Int::from(3).pow(200));
+        x *= &Int::from(7).pow(300);
+        assert_eq!(x.cap, cap);
+    }
+
+    #[test]
+    fn square_assign() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..RAND_ITER {
+            let bits = rng.gen_range(1, 3000);
+            let a = rng.gen_int(bits);
+
+            let mut x = a.clone();
+            x.square_assign();
+            assert_mp_eq!(x, a.square());
+            assert_mp_eq!(x, &a * &a.clone());
+            assert_mp_eq!(a.clone().dsquare(), x);
+        }
+
+        let mut x = Int::zero();
+        x.square_assign();
+        assert_mp_eq!(x, Int::zero());
+        let mut x = Int::from(-7);
+        x.square_assign();
+        assert_mp_eq!(x, Int::from(49));
+    }
+
     #[test]
     fn int_from() {
         let i = Int::from(::std::i64::MIN);
@@ -4502,6 +9249,67 @@ mod test {
         }
     }
 
+    #[test]
+    fn shr_negative() {
+        let vals = [0i64, 1, -1, 2, -2, 5, -5, -(1 << 40), -(1 << 40) - 1, -0x1234_5678_9abc_def0];
+        for &v in vals.iter() {
+            for &s in [0usize, 1, 3, 31, 32, 33, 40, 63].iter() {
+                let x = Int::from(v);
+                assert_eq!(&x >> s, v >> s, "{} >> {}", v, s);
+                assert_eq!((x << 100) >> (s + 100), v >> s, "{} >> {}", v, s);
+            }
+        }
+        assert_eq!(Int::from(-5) >> 1000, -1);
+    }
+
+    #[test]
+    fn shift_prim() {
+        let x = Int::from(-12345);
+        assert_mp_eq!(&x << 70u32, &x << 70usize);
+        assert_mp_eq!(&x << 70u64, &x << 70usize);
+        assert_mp_eq!(&x << 70i32, &x << 70usize);
+        assert_mp_eq!(&x >> 3u32, &x >> 3usize);
+        assert_mp_eq!(&x >> 3u64, &x >> 3usize);
+        assert_mp_eq!(&x >> 3i32, &x >> 3usize);
+
+        let mut y = x.clone();
+        y <<= 5i32;
+        y >>= 5u64;
+        assert_mp_eq!(y, x);
+
+        assert_mp_eq!(x.checked_shl(100).unwrap(), &x << 100usize);
+        assert!(x.checked_shl(std::usize::MAX).is_none());
+        assert_eq!(Int::zero().checked_shl(std::usize::MAX), Some(Int::zero()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn shift_negative_count() {
+        let _ = Int::one() << -1i32;
+    }
+
+    #[test]
+    fn shifted_int() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..RAND_ITER {
+            let xbits = rng.gen_range(1, 500);
+            let ybits = rng.gen_range(1, 500);
+            let x = rng.gen_int(xbits);
+            let y = rng.gen_int(ybits);
+            let k = rng.gen_range(0, 10);
+
+            let shifted = &y << (k * Limb::BITS);
+
+            let mut sum = x.clone();
+            sum += y.clone().shl_limbs(k);
+            assert_mp_eq!(sum, &x + &shifted);
+
+            let mut diff = x.clone();
+            diff -= y.clone().shl_limbs(k);
+            assert_mp_eq!(diff, &x - &shifted);
+        }
+    }
+
     #[test]
     fn bitand_rand() {
         let mut rng = rand::thread_rng();
@@ -4601,6 +9409,26 @@ mod test {
         }
     }
 
+    #[test]
+    fn u64_rng() {
+        let mut ones = U64Rng(|| !0u64);
+        assert_mp_eq!(ones.gen_uint(100), (Int::one() << 100) - 1);
+        let mut zeros = U64Rng(|| 0u64);
+        assert_mp_eq!(zeros.gen_uint(100), Int::zero());
+
+        // The low half of each number is used with 32-bit limbs
+        let mut i = 0u64;
+        let x = U64Rng(|| { i += 1; i }).gen_uint(32);
+        assert_mp_eq!(x, Int::from(1));
+
+        let mut rng = rand::thread_rng();
+        let m = (Int::one() << 300) - 12345;
+        for _ in 0..100 {
+            let x = U64Rng(|| rng.next_u64()).gen_int_range(&-&m, &m);
+            assert!(-&m <= x && x < m);
+        }
+    }
+
     #[test]
     fn gen_uint_below_all_ones() {
         static N : &'static str =
@@ -4710,6 +9538,26 @@ mod test {
         }
     }
 
+    #[test]
+    fn from_bytes_be_mod() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..RAND_ITER {
+            let len = rng.gen_range(0, 100);
+            let bytes : Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let bits = rng.gen_range(1, 300);
+            let m = rng.gen_uint(bits) + 1;
+
+            let mut hex = String::from("0");
+            for b in bytes.iter() {
+                hex.push_str(&format!("{:02x}", b));
+            }
+            let n = Int::from_str_radix(&hex, 16).unwrap();
+
+            assert_mp_eq!(Int::from_bytes_be_mod(&bytes, &m), n % &m);
+        }
+    }
+
     fn bench_add(b: &mut Bencher, xs: usize, ys: usize) {
         let mut rng = rand::thread_rng();
 
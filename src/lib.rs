@@ -16,7 +16,7 @@
 #![crate_name="framp"]
 
 #![feature(core_intrinsics, asm, heap_api, associated_consts)]
-#![feature(step_trait, unique, alloc)]
+#![feature(step_trait, unique, alloc, i128_type, const_fn, abi_sysv64, thread_local_state)]
 
 #![cfg_attr(test, feature(test))]
 
@@ -25,9 +25,10 @@
 extern crate alloc;
 extern crate ieee754;
 extern crate rand;
-extern crate hamming;
 extern crate num_integer;
 extern crate num_traits;
+#[cfg(feature = "proptest")] extern crate proptest as proptest_crate;
+#[cfg(feature = "rayon")] extern crate rayon;
 
 pub mod ll;
 mod mem;
@@ -35,8 +36,16 @@ mod mem;
 pub mod traits;
 pub mod int;
 pub mod rational;
+pub mod dec;
+pub mod rc;
+pub mod context;
+pub mod poly;
+pub mod rns;
+#[cfg(feature = "proptest")] pub mod proptest;
 
 // Re-exports
 
 pub use int::Int;
 pub use int::RandomInt;
+pub use int::U64Rng;
+pub use context::Context;
@@ -12,15 +12,12 @@
 //    See the License for the specific language governing permissions and
 //    limitations under the License.
 
-#![allow(improper_ctypes)]
-
 use ll::limb::Limb;
 use ll::limb_ptr::{Limbs, LimbsMut};
 use super::{copy_rest, same_or_separate};
 
-#[allow(dead_code)]
-unsafe fn add_n_generic(mut wp: LimbsMut, mut xp: Limbs, mut yp: Limbs,
-                        mut n: i32) -> Limb {
+pub unsafe fn add_n_generic(mut wp: LimbsMut, mut xp: Limbs, mut yp: Limbs,
+                        mut n: usize) -> Limb {
 
     let mut carry = Limb(0);
 
@@ -51,28 +48,29 @@ unsafe fn add_n_generic(mut wp: LimbsMut, mut xp: Limbs, mut yp: Limbs,
  * If there was a carry, it is returned.
  */
 #[inline]
-#[cfg(asm)]
-pub unsafe fn add_n(mut wp: LimbsMut, xp: Limbs, yp: Limbs,
-                    n: i32) -> Limb {
-    #[cfg(all(not(feature="fallbacks"),target_arch="x86_64"))]
-    extern "C" { fn ramp_add_n(wp: *mut Limb, xp: *const Limb, yp: *const Limb,
-                               n: i32) -> Limb; }
+#[cfg(all(asm, not(feature="fallbacks")))]
+pub unsafe fn add_n(wp: LimbsMut, xp: Limbs, yp: Limbs,
+                    n: usize) -> Limb {
+    extern "sysv64" {
+        fn ramp_add_n(wp: *mut Limb, xp: *const Limb, yp: *const Limb,
+                      n: i32) -> Limb;
+    }
 
     debug_assert!(n >= 1);
     debug_assert!(same_or_separate(wp, n, xp, n));
     debug_assert!(same_or_separate(wp, n, yp, n));
 
-    return ramp_add_n(&mut *wp, &*xp, &*yp, n);
+    return ramp_add_n(wp.as_mut_ptr(), xp.as_ptr(), yp.as_ptr(), n as i32);
 }
 
 /**
  * Adds the `n` least signficant limbs of `xp` and `yp`, storing the result in {wp, n}.
  * If there was a carry, it is returned.
  */
-#[cfg(any(feature="fallbacks",not(asm)))]
+#[cfg(any(feature="fallbacks", not(asm)))]
 #[inline]
 pub unsafe fn add_n(wp: LimbsMut, xp: Limbs, yp: Limbs,
-                    n: i32) -> Limb {
+                    n: usize) -> Limb {
     debug_assert!(n >= 1);
     debug_assert!(same_or_separate(wp, n, xp, n));
     debug_assert!(same_or_separate(wp, n, yp, n));
@@ -80,9 +78,8 @@ pub unsafe fn add_n(wp: LimbsMut, xp: Limbs, yp: Limbs,
     add_n_generic(wp, xp, yp, n)
 }
 
-#[allow(dead_code)]
-unsafe fn sub_n_generic(mut wp: LimbsMut, mut xp: Limbs, mut yp: Limbs,
-                        mut n: i32) -> Limb {
+pub unsafe fn sub_n_generic(mut wp: LimbsMut, mut xp: Limbs, mut yp: Limbs,
+                        mut n: usize) -> Limb {
     let mut carry = Limb(0);
 
     debug_assert!(n >= 1);
@@ -115,26 +112,26 @@ unsafe fn sub_n_generic(mut wp: LimbsMut, mut xp: Limbs, mut yp: Limbs,
  * Subtracts the `n` least signficant limbs of `yp` from `xp`, storing the result in {wp, n}.
  * If there was a borrow from a higher-limb (i.e., the result would be negative), it is returned.
  */
-#[cfg(asm)]
+#[cfg(all(asm, not(feature="fallbacks")))]
 #[inline]
-pub unsafe fn sub_n(mut wp: LimbsMut, xp: Limbs, yp: Limbs,
-                    n: i32) -> Limb {
-    extern "C" {
+pub unsafe fn sub_n(wp: LimbsMut, xp: Limbs, yp: Limbs,
+                    n: usize) -> Limb {
+    extern "sysv64" {
         fn ramp_sub_n(wp: *mut Limb, xp: *const Limb, yp: *const Limb,
                       n: i32) -> Limb;
     }
 
-    ramp_sub_n(&mut *wp, &*xp, &*yp, n)
+    ramp_sub_n(wp.as_mut_ptr(), xp.as_ptr(), yp.as_ptr(), n as i32)
 }
 
 /**
  * Subtracts the `n` least signficant limbs of `yp` from `xp`, storing the result in {wp, n}.
  * If there was a borrow from a higher-limb (i.e., the result would be negative), it is returned.
  */
-#[cfg(not(asm))]
+#[cfg(any(feature="fallbacks", not(asm)))]
 #[inline]
 pub unsafe fn sub_n(wp: LimbsMut, xp: Limbs, yp: Limbs,
-                    n: i32) -> Limb {
+                    n: usize) -> Limb {
     sub_n_generic(wp, xp, yp, n)
 }
 
@@ -142,11 +139,10 @@ macro_rules! aors {
     ($op:ident, $lop:ident, $f:ident) => {
         #[inline]
         pub unsafe fn $op(wp: LimbsMut,
-                          xp: Limbs, xs: i32,
-                          yp: Limbs, ys: i32) -> Limb {
+                          xp: Limbs, xs: usize,
+                          yp: Limbs, ys: usize) -> Limb {
 
             debug_assert!(xs >= ys);
-            debug_assert!(ys >= 0);
 
             let mut i = ys;
             let carry = $f(wp, xp, yp, ys);
@@ -179,7 +175,7 @@ macro_rules! aors_1 {
     ($op:ident, $f:ident) => {
         #[inline]
         pub unsafe fn $op(mut wp: LimbsMut,
-                          xp: Limbs, xs: i32,
+                          xp: Limbs, xs: usize,
                           y: Limb) -> Limb {
 
             if xs > 0 {
@@ -0,0 +1,36 @@
+/*
+ * Directives that differ between the object formats of the supported targets.
+ *
+ * The kernels use the System V calling convention on every target. On Windows,
+ * they are declared `extern "sysv64"` on the Rust side, so the caller takes care
+ * of the registers, like %rsi and %rdi, that are only callee-saved there.
+ */
+
+#if defined(_WIN32) || defined(__CYGWIN__)
+
+/* COFF: no sizes or types, and no unwind information is needed for leaf
+   functions that leave the stack alone */
+#define FUNCTION(name)                          \
+    .text;                                      \
+    .globl name;                                \
+    .def name; .scl 2; .type 32; .endef;        \
+    .align 16, 0x90;                            \
+name:
+
+#define END_FUNCTION(name)
+
+#else
+
+#define FUNCTION(name)                          \
+    .section .text.name,"ax",@progbits;         \
+    .globl name;                                \
+    .align 16, 0x90;                            \
+    .type name,@function;                       \
+name:                                           \
+    .cfi_startproc
+
+#define END_FUNCTION(name)                      \
+    .size name, . - name;                       \
+    .cfi_endproc
+
+#endif
@@ -50,14 +50,14 @@ fn div_unnorm(n: Limb, d: Limb) -> (Limb, Limb) {
 /// an estimate. It can equal the the actually needed digits or overestimate
 /// by 1.
 /// Returns 1 if the number is 0;
-pub unsafe fn num_base_digits(p: Limbs, n: i32, base: u32) -> usize {
+pub unsafe fn num_base_digits(p: Limbs, n: usize, base: u32) -> usize {
     debug_assert!(base >= 2);
     assume(base >= 2);
 
     if n == 0 { return 1; }
 
     let cnt = (*p.offset((n - 1) as isize)).leading_zeros() as usize;
-    let total_bits = (Limb::BITS * (n as usize)) - cnt;
+    let total_bits = (Limb::BITS * n) - cnt;
 
     if base == 2 {
         // no need to do anything complicated here at all, so let's go
@@ -83,6 +83,17 @@ pub unsafe fn num_base_digits(p: Limbs, n: i32, base: u32) -> usize {
     }
 }
 
+/// Returns the "big base" for `base`, the largest power of `base` that fits in a limb, along
+/// with the number of digits it represents. `base` must not be a power of two.
+#[inline]
+pub fn big_base(base: u32) -> (Limb, u32) {
+    debug_assert!(base >= 2 && !base.is_power_of_two());
+    debug_assert!(base < BASES.len() as u32);
+
+    let b = &BASES[base as usize];
+    (b.big_base, b.digits_per_limb)
+}
+
 #[inline]
 pub fn base_digits_to_len(num: usize, base: u32) -> usize {
     debug_assert!(base >= 2);
@@ -101,14 +112,13 @@ pub fn base_digits_to_len(num: usize, base: u32) -> usize {
  * The values in `out` are the raw values of the base. Conversion for output should be done as a second
  * step.
  */
-pub unsafe fn to_base<F: FnMut(u8)>(base: u32, np: Limbs, nn: i32, mut out_byte: F) {
-    debug_assert!(nn >= 0);
+pub unsafe fn to_base<F: FnMut(u8)>(base: u32, np: Limbs, nn: usize, mut out_byte: F) {
     debug_assert!(base < BASES.len() as u32);
     debug_assert!(base >= 2);
     assume(base < BASES.len() as u32);
     assume(base >= 2);
 
-    if nn <= 0 {
+    if nn == 0 {
         out_byte(0);
         return;
     }
@@ -120,13 +130,13 @@ pub unsafe fn to_base<F: FnMut(u8)>(base: u32, np: Limbs, nn: i32, mut out_byte:
         let mut n1 = *np.offset((nn - 1) as isize);
         let cnt = n1.leading_zeros() as usize;
 
-        let mut bits = Limb::BITS * (nn as usize) - cnt;
+        let mut bits = Limb::BITS * nn - cnt;
         let cnt = bits % bits_per_digit;
         if cnt != 0 {
             bits += bits_per_digit - cnt;
         }
 
-        let mut bit_pos : isize = (bits - (nn as usize - 1) * Limb::BITS) as isize;
+        let mut bit_pos : isize = (bits - (nn - 1) * Limb::BITS) as isize;
 
         let mut i = nn - 1;
         // Convert each limb by shifting and masking to get the value for each output digit
@@ -137,8 +147,8 @@ pub unsafe fn to_base<F: FnMut(u8)>(base: u32, np: Limbs, nn: i32, mut out_byte:
                 out_byte(b);
                 bit_pos -= bits_per_digit as isize;
             }
+            if i == 0 { break; }
             i -= 1;
-            if i < 0 { break; }
 
             let n0 = (n1 << ((-bit_pos) as usize)) & ((Limb(1) << bits_per_digit) - 1);
             n1 = *np.offset(i as isize);
@@ -158,19 +168,19 @@ pub unsafe fn to_base<F: FnMut(u8)>(base: u32, np: Limbs, nn: i32, mut out_byte:
     to_base_impl(0, base, np, nn, out_byte);
 }
 
-unsafe fn to_base_impl<F: FnMut(u8)>(mut len: u32, base: u32, np: Limbs, mut nn: i32, mut out_byte: F) {
+unsafe fn to_base_impl<F: FnMut(u8)>(mut len: u32, base: u32, np: Limbs, mut nn: usize, mut out_byte: F) {
     debug_assert!(base > 2);
 
     let buf_len = num_base_digits(np, nn, base);
     let mut buf : Vec<u8> = vec![0; buf_len];
-    let mut r : Vec<Limb> = vec![Limb(0); (nn + 1) as usize];
-    let rp = LimbsMut::new(&mut r[0], 0, r.len() as i32);
+    let mut r : Vec<Limb> = vec![Limb(0); nn + 1];
+    let rp = LimbsMut::new(r.as_mut_ptr(), 0, r.len());
 
     ll::copy_incr(np, rp.offset(1), nn);
 
     let mut sz = 0;
 
-    let s : *mut u8 = &mut buf[0];
+    let s : *mut u8 = buf.as_mut_ptr();
     let mut s = s.offset(buf_len as isize);
 
     let base = Limb(base as ll::limb::BaseInt);
@@ -258,14 +268,14 @@ unsafe fn to_base_impl<F: FnMut(u8)>(mut len: u32, base: u32, np: Limbs, mut nn:
  * Converts the base `base` bytestring {bp, bs}, storing the limbs in `out`. `out` is assumed to
  * have enough space to store the result.
  */
-pub unsafe fn from_base(mut out: LimbsMut, bp: *const u8, bs: i32, base: u32) -> usize {
+pub unsafe fn from_base(mut out: LimbsMut, bp: *const u8, bs: usize, base: u32) -> usize {
     debug_assert!(bs > 0);
     debug_assert!(base < BASES.len() as u32);
     debug_assert!(base >= 2);
     assume(base < BASES.len() as u32);
     assume(base >= 2);
 
-    if bs <= 0 {
+    if bs == 0 {
         *out = Limb(0);
         return 1;
     }
@@ -306,7 +316,7 @@ pub unsafe fn from_base(mut out: LimbsMut, bp: *const u8, bs: i32, base: u32) ->
     from_base_small(out, bp, bs, base)
 }
 
-unsafe fn from_base_small(mut out: LimbsMut, mut bp: *const u8, bs: i32, base: u32) -> usize {
+unsafe fn from_base_small(mut out: LimbsMut, mut bp: *const u8, bs: usize, base: u32) -> usize {
     debug_assert!(base > 2);
     assume(base > 2);
 
@@ -341,8 +351,8 @@ unsafe fn from_base_small(mut out: LimbsMut, mut bp: *const u8, bs: i32, base: u
                 size = 1;
             }
         } else {
-            let mut carry = ll::mul_1(out, out.as_const(), size as i32, big_base);
-            carry = carry + ll::add_1(out, out.as_const(), size as i32, res_digit);
+            let mut carry = ll::mul_1(out, out.as_const(), size, big_base);
+            carry = carry + ll::add_1(out, out.as_const(), size, res_digit);
             if carry != 0 {
                 *out.offset(size as isize) = carry;
                 size += 1;
@@ -380,8 +390,8 @@ unsafe fn from_base_small(mut out: LimbsMut, mut bp: *const u8, bs: i32, base: u
             size = 1;
         }
     } else {
-        let mut carry = ll::mul_1(out, out.as_const(), size as i32, Limb(big_base));
-        carry = carry + ll::add_1(out, out.as_const(), size as i32, res_digit);
+        let mut carry = ll::mul_1(out, out.as_const(), size, Limb(big_base));
+        carry = carry + ll::add_1(out, out.as_const(), size, res_digit);
         if carry != 0 {
             *out.offset(size as isize) = carry;
             size += 1;
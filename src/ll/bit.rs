@@ -12,6 +12,9 @@
 //    See the License for the specific language governing permissions and
 //    limitations under the License.
 
+use std::cmp;
+
+use ll;
 use ll::limb::{Limb, BaseInt};
 use ll::{same_or_decr, same_or_incr};
 
@@ -23,7 +26,7 @@ use ll::limb_ptr::{Limbs, LimbsMut};
  *
  * If `cnt` is greater than or equal to the number of bits in a limb, the result is undefined.
  */
-pub unsafe fn shl(mut rp: LimbsMut, mut xp: Limbs, mut xs: i32, cnt: u32) -> Limb {
+pub unsafe fn shl(mut rp: LimbsMut, mut xp: Limbs, mut xs: usize, cnt: u32) -> Limb {
     debug_assert!(xs >= 1);
     debug_assert!(cnt >= 1);
     debug_assert!(cnt < Limb::BITS as u32);
@@ -63,7 +66,7 @@ pub unsafe fn shl(mut rp: LimbsMut, mut xp: Limbs, mut xs: i32, cnt: u32) -> Lim
  *
  * If `cnt` is greater than or equal to the number of bits in a limb, the result is undefined.
  */
-pub unsafe fn shr(mut rp: LimbsMut, mut xp: Limbs, mut xs: i32, cnt: u32) -> Limb {
+pub unsafe fn shr(mut rp: LimbsMut, mut xp: Limbs, mut xs: usize, cnt: u32) -> Limb {
     debug_assert!(xs >= 1);
     debug_assert!(cnt >= 1);
     debug_assert!(cnt < Limb::BITS as u32);
@@ -100,7 +103,7 @@ pub unsafe fn shr(mut rp: LimbsMut, mut xp: Limbs, mut xs: i32, cnt: u32) -> Lim
 #[inline(always)]
 unsafe fn bitop<F: Fn(Limb, Limb) -> Limb>(mut wp: LimbsMut,
                                            mut xp: Limbs, mut yp: Limbs,
-                                           n: i32, op: F) {
+                                           n: usize, op: F) {
     debug_assert!(same_or_incr(wp, n, xp, n));
     debug_assert!(same_or_incr(wp, n, yp, n));
 
@@ -120,7 +123,7 @@ unsafe fn bitop<F: Fn(Limb, Limb) -> Limb>(mut wp: LimbsMut,
  */
 pub unsafe fn and_n(wp: LimbsMut,
                     xp: Limbs, yp: Limbs,
-                    n: i32) {
+                    n: usize) {
     bitop(wp, xp, yp, n, |x, y| x & y);
 }
 
@@ -132,7 +135,7 @@ pub unsafe fn and_n(wp: LimbsMut,
  */
 pub unsafe fn and_not_n(wp: LimbsMut,
                      xp: Limbs, yp: Limbs,
-                     n: i32) {
+                     n: usize) {
     bitop(wp, xp, yp, n, |x, y| x & !y);
 }
 
@@ -144,7 +147,7 @@ pub unsafe fn and_not_n(wp: LimbsMut,
  */
 pub unsafe fn nand_n(wp: LimbsMut,
                      xp: Limbs, yp: Limbs,
-                     n: i32) {
+                     n: usize) {
     bitop(wp, xp, yp, n, |x, y| !(x & y));
 }
 
@@ -154,7 +157,7 @@ pub unsafe fn nand_n(wp: LimbsMut,
  */
 pub unsafe fn or_n(wp: LimbsMut,
                     xp: Limbs, yp: Limbs,
-                    n: i32) {
+                    n: usize) {
     bitop(wp, xp, yp, n, |x, y| x | y);
 }
 
@@ -164,7 +167,7 @@ pub unsafe fn or_n(wp: LimbsMut,
  */
 pub unsafe fn or_not_n(wp: LimbsMut,
                     xp: Limbs, yp: Limbs,
-                    n: i32) {
+                    n: usize) {
     bitop(wp, xp, yp, n, |x, y| x | !y);
 }
 
@@ -176,7 +179,7 @@ pub unsafe fn or_not_n(wp: LimbsMut,
  */
 pub unsafe fn nor_n(wp: LimbsMut,
                     xp: Limbs, yp: Limbs,
-                    n: i32) {
+                    n: usize) {
     bitop(wp, xp, yp, n, |x, y| !(x | y));
 }
 
@@ -186,7 +189,7 @@ pub unsafe fn nor_n(wp: LimbsMut,
  */
 pub unsafe fn xor_n(wp: LimbsMut,
                     xp: Limbs, yp: Limbs,
-                    n: i32) {
+                    n: usize) {
     bitop(wp, xp, yp, n, |x, y| x ^ y);
 }
 
@@ -194,7 +197,7 @@ pub unsafe fn xor_n(wp: LimbsMut,
  * Performs a bitwise inversion ("not") of the n least signficant limbs of `xp`, storing the
  * result in `wp`
  */
-pub unsafe fn not(mut wp: LimbsMut, mut xp: Limbs, n: i32) {
+pub unsafe fn not(mut wp: LimbsMut, mut xp: Limbs, n: usize) {
     debug_assert!(same_or_incr(wp, n, xp, n));
 
     let mut i = 0;
@@ -211,7 +214,7 @@ pub unsafe fn not(mut wp: LimbsMut, mut xp: Limbs, n: i32) {
  * of `xp`. The result is stored the result in `wp`, and a carry is
  * returned, if there is one.
  */
-pub unsafe fn twos_complement(mut wp: LimbsMut, mut xp: Limbs, xs: i32) -> Limb {
+pub unsafe fn twos_complement(mut wp: LimbsMut, mut xp: Limbs, xs: usize) -> Limb {
     let mut i = 0;
     let mut carry = Limb(1);
 
@@ -227,11 +230,30 @@ pub unsafe fn twos_complement(mut wp: LimbsMut, mut xp: Limbs, xs: i32) -> Limb
     carry
 }
 
+/**
+ * Stores the `bits` least-significant bits of {xp, xs}, that is {xp, xs} modulo `2^bits`, in
+ * `rp`, and returns their length in limbs, without the high zero limbs. `rp` needs
+ * `min(xs, ceil(bits / BITS))` limbs.
+ */
+pub unsafe fn mod_2exp(rp: LimbsMut, xp: Limbs, xs: usize, bits: usize) -> usize {
+    let words = (bits + Limb::BITS - 1) / Limb::BITS;
+    let n = cmp::min(xs, words);
+    debug_assert!(same_or_incr(rp, n, xp, n));
+
+    ll::copy_incr(xp, rp, n);
+    let rem = bits % Limb::BITS;
+    if n == words && rem != 0 {
+        let top = rp.offset((n - 1) as isize);
+        *top = *top & ((Limb(1) << rem) - 1);
+    }
+    ll::normalize(rp.as_const(), n)
+}
+
 /**
  * Scans for the first 1 bit starting from the least-significant bit the the most, returning
  * the bit index.
  */
-pub unsafe fn scan_1(mut xp: Limbs, mut xs: i32) -> u32 {
+pub unsafe fn scan_1(mut xp: Limbs, mut xs: usize) -> u32 {
     debug_assert!(xs > 0);
     let mut cnt = 0u32;
 
@@ -250,7 +272,7 @@ pub unsafe fn scan_1(mut xp: Limbs, mut xs: i32) -> u32 {
  * Scans for the first 0 bit starting from the least-significant bit the the most, returning
  * the bit index.
  */
-pub unsafe fn scan_0(mut xp: Limbs, mut xs: i32) -> u32 {
+pub unsafe fn scan_0(mut xp: Limbs, mut xs: usize) -> u32 {
     debug_assert!(xs > 0);
     let mut cnt = 0u32;
 
@@ -268,3 +290,40 @@ pub unsafe fn scan_0(mut xp: Limbs, mut xs: i32) -> u32 {
 
     return cnt;
 }
+
+/**
+ * Returns the number of 1 bits in {xp, xs}.
+ */
+pub unsafe fn popcount(mut xp: Limbs, mut xs: usize) -> usize {
+    let mut cnt = 0;
+
+    while xs > 0 {
+        cnt += (*xp).0.count_ones() as usize;
+        xp = xp.offset(1);
+        xs -= 1;
+    }
+
+    cnt
+}
+
+/**
+ * Returns the number of bits that differ between {xp, xs} and {yp, ys}, with the high limbs
+ * missing from the shorter of the two taken as zero.
+ */
+pub unsafe fn hamdist(mut xp: Limbs, mut xs: usize, mut yp: Limbs, mut ys: usize) -> usize {
+    if xs < ys {
+        ::std::mem::swap(&mut xp, &mut yp);
+        ::std::mem::swap(&mut xs, &mut ys);
+    }
+
+    let mut cnt = 0;
+    while ys > 0 {
+        cnt += ((*xp).0 ^ (*yp).0).count_ones() as usize;
+        xp = xp.offset(1);
+        yp = yp.offset(1);
+        xs -= 1;
+        ys -= 1;
+    }
+
+    cnt + popcount(xp, xs)
+}
@@ -27,15 +27,11 @@ use ll::limb_ptr::{Limbs, LimbsMut};
  * Specifically, the integer part is stored in {qp+qxn, xs} and the fractional part (if any) is
  * stored in {qp, qxn}. The remainder is returned.
  */
-pub unsafe fn divrem_1(mut qp: LimbsMut, qxn: i32,
-                       xp: Limbs, mut xs: i32, d: Limb) -> Limb {
-    debug_assert!(qxn >= 0);
-    debug_assert!(xs >= 0);
+pub unsafe fn divrem_1(mut qp: LimbsMut, qxn: usize,
+                       xp: Limbs, mut xs: usize, d: Limb) -> Limb {
     debug_assert!(d != 0);
     debug_assert!(same_or_separate(qp.offset(qxn as isize), xs, xp, xs));
 
-    assume(qxn >= 0);
-    assume(xs >= 0);
     assume(d != 0);
 
     let mut n = xs + qxn;
@@ -60,8 +56,9 @@ pub unsafe fn divrem_1(mut qp: LimbsMut, qxn: i32,
         }
 
         let dinv = d.invert();
-        let mut i = xs - 1;
-        while i >= 0 {
+        let mut i = xs;
+        while i > 0 {
+            i -= 1;
             let n0 = *xp.offset(i as isize);
             let (q, rem) = limb::div_preinv(r, n0, d, dinv);
             r = rem;
@@ -69,17 +66,16 @@ pub unsafe fn divrem_1(mut qp: LimbsMut, qxn: i32,
             if qp > qp_lo {
                 qp = qp.offset(-1);
             }
-            i -= 1;
         }
-        let mut i = qxn - 1;
-        while i >= 0 {
+        let mut i = qxn;
+        while i > 0 {
+            i -= 1;
             let (q, rem) = limb::div_preinv(r, Limb(0), d, dinv);
             r = rem;
             *qp = q;
             if qp > qp_lo {
                 qp = qp.offset(-1);
             }
-            i -= 1;
         }
 
         return r;
@@ -109,8 +105,9 @@ pub unsafe fn divrem_1(mut qp: LimbsMut, qxn: i32,
         if xs != 0 {
             let mut n1 = *xp.offset((xs - 1) as isize);
             r = r | (n1 >> (Limb::BITS - cnt));
-            let mut i = xs - 2;
-            while i >= 0 {
+            let mut i = xs - 1;
+            while i > 0 {
+                i -= 1;
                 let n0 = *xp.offset(i as isize);
                 let nshift = (n1 << cnt) | (n0 >> (Limb::BITS - cnt));
                 let (q, rem) = limb::div_preinv(r, nshift, d, dinv);
@@ -120,7 +117,6 @@ pub unsafe fn divrem_1(mut qp: LimbsMut, qxn: i32,
 
                 qp = qp.offset(-1);
                 n1 = n0;
-                i -= 1;
             }
             let (q, rem) = limb::div_preinv(r, n1 << cnt, d, dinv);
             r = rem;
@@ -130,8 +126,9 @@ pub unsafe fn divrem_1(mut qp: LimbsMut, qxn: i32,
             }
         }
 
-        let mut i = qxn - 1;
-        while i >= 0 {
+        let mut i = qxn;
+        while i > 0 {
+            i -= 1;
             let (q, rem) = limb::div_preinv(r, Limb(0), d, dinv);
             r = rem;
             *qp = q;
@@ -139,18 +136,63 @@ pub unsafe fn divrem_1(mut qp: LimbsMut, qxn: i32,
             if qp > qp_lo {
                 qp = qp.offset(-1);
             }
-            i -= 1;
         }
 
         return r >> cnt;
     }
 }
 
-pub unsafe fn divrem_2(mut qp: LimbsMut, qxn: i32,
-                       mut np: LimbsMut, ns: i32,
+/**
+ * Returns the remainder of the `xs` least-significant limbs at `xp` divided by `d`, without
+ * computing the quotient. Like `divrem_1`, each limb is divided with a precomputed reciprocal
+ * of the normalized `d`, rather than a hardware division.
+ */
+pub unsafe fn mod_1(xp: Limbs, xs: usize, d: Limb) -> Limb {
+    debug_assert!(d != 0);
+    if xs == 0 { return Limb(0); }
+
+    let cnt = d.leading_zeros() as usize;
+    let d = d << cnt;
+    let dinv = d.invert();
+
+    let mut n1 = *xp.offset((xs - 1) as isize);
+    let mut r;
+    if cnt == 0 {
+        r = if n1 >= d { n1 - d } else { n1 };
+        let mut i = xs - 1;
+        while i > 0 {
+            i -= 1;
+            r = limb::div_preinv(r, *xp.offset(i as isize), d, dinv).1;
+        }
+        r
+    } else {
+        // The bits shifted out of the top limb are less than the normalized divisor
+        r = n1 >> (Limb::BITS - cnt);
+        let mut i = xs - 1;
+        while i > 0 {
+            i -= 1;
+            let n0 = *xp.offset(i as isize);
+            let nshift = (n1 << cnt) | (n0 >> (Limb::BITS - cnt));
+            r = limb::div_preinv(r, nshift, d, dinv).1;
+            n1 = n0;
+        }
+        r = limb::div_preinv(r, n1 << cnt, d, dinv).1;
+        r >> cnt
+    }
+}
+
+/**
+ * Divides `{np, ns}` by the two limbs at `dp`, the higher of which must have its top bit set.
+ *
+ * Each limb of the quotient comes from a 3-by-2 division with a reciprocal of the divisor
+ * computed once, rather than from the general schoolbook division. The low `ns - 2` limbs of
+ * the quotient are stored in {qp+qxn, ns - 2}, with `qxn` fractional limbs in {qp, qxn}, and
+ * its high limb, 0 or 1, is returned. The remainder is left in {np, 2}.
+ */
+pub unsafe fn divrem_2(mut qp: LimbsMut, qxn: usize,
+                       mut np: LimbsMut, ns: usize,
                        dp: Limbs) -> Limb {
     debug_assert!(ns >= 2);
-    debug_assert!(qxn >= 0);
     debug_assert!((*dp.offset(1)).high_bit_set());
     debug_assert!(!overlap(qp, ns-2+qxn, np.as_const(), ns) || qp >= np.offset(2));
 
@@ -173,29 +215,27 @@ pub unsafe fn divrem_2(mut qp: LimbsMut, qxn: i32,
 
     qp = qp.offset(qxn as isize);
 
-    let mut i = ns - 2 - 1;
-    while i >= 0 {
+    let mut i = ns - 2;
+    while i > 0 {
+        i -= 1;
         let n0 = *np.offset(-1);
         let (q, r_1, r_0) = divrem_3by2(r1, r0, n0, d1, d0, dinv);
         np = np.offset(-1);
         r1 = r_1;
         r0 = r_0;
         *qp.offset(i as isize) = q;
-
-        i -= 1;
     }
 
     if qxn != 0 {
-        qp = qp.offset(-qxn as isize);
-        let mut i = qxn - 1;
-        while i >= 0 {
+        qp = qp.offset(-(qxn as isize));
+        let mut i = qxn;
+        while i > 0 {
+            i -= 1;
             let (q, r_1, r_0) = divrem_3by2(r1, r0, Limb(0), d1, d0, dinv);
             r1 = r_1;
             r0 = r_0;
 
             *qp.offset(i as isize) = q;
-
-            i -= 1;
         }
     }
 
@@ -258,12 +298,12 @@ fn divrem_3by2(n2: Limb, n1: Limb, n0: Limb, d1: Limb, d0: Limb, dinv: Limb) ->
  * the quotient is stored to {qp, (ns - ds) + 1}. The remainder is always stored to {rp, ds}.
  */
 pub unsafe fn divrem(mut qp: LimbsMut, mut rp: LimbsMut,
-                     np: Limbs, ns: i32,
-                     dp: Limbs, ds: i32) {
+                     np: Limbs, ns: usize,
+                     dp: Limbs, ds: usize) {
     // Space for at least one limb is always needed, even if
     // (logarithmically) the result will be so small that negative
     // would work.
-    let max_result_size = cmp::max((ns - ds) + 1, 1);
+    let max_result_size = cmp::max(ns, ds) - ds + 1;
     debug_assert!(!overlap(qp, max_result_size, np, ns));
 
     if ns < ds {
@@ -289,7 +329,7 @@ pub unsafe fn divrem(mut qp: LimbsMut, mut rp: LimbsMut,
 
             let cnt = dh.leading_zeros() as usize;
             if cnt == 0 {
-                let np_tmp = tmp.allocate((ns + 1) as usize);
+                let np_tmp = tmp.allocate(ns + 1);
                 ll::copy_incr(np, np_tmp, ns);
                 let qhl = divrem_2(qp, 0, np_tmp, ns, dp);
                 *qp.offset((ns - 2) as isize) = qhl;
@@ -297,9 +337,9 @@ pub unsafe fn divrem(mut qp: LimbsMut, mut rp: LimbsMut,
                 *rp.offset(1) = *np_tmp.offset(1);
             } else {
                 let dtmp = [*dp << cnt, (*dp.offset(1) << cnt) | *dp >> (Limb::BITS - cnt)];
-                let dp_tmp = Limbs::new(&dtmp[0], 0, dtmp.len() as i32);
+                let dp_tmp = Limbs::new(dtmp.as_ptr(), 0, dtmp.len());
 
-                let np_tmp = tmp.allocate((ns + 1) as usize);
+                let np_tmp = tmp.allocate(ns + 1);
                 let c = ll::shl(np_tmp, np, ns, cnt as u32);
                 *np_tmp.offset(ns as isize) = c;
 
@@ -327,11 +367,11 @@ pub unsafe fn divrem(mut qp: LimbsMut, mut rp: LimbsMut,
 
             if cnt == 0 {
                 dp_tmp = dp;
-                np_tmp = tmp.allocate(ns_tmp as usize);
+                np_tmp = tmp.allocate(ns_tmp);
                 ll::copy_incr(np, np_tmp, ns);
             } else {
                 ns_tmp += 1;
-                np_tmp = tmp.allocate(ns_tmp as usize);
+                np_tmp = tmp.allocate(ns_tmp);
 
                 let c = ll::shl(np_tmp, np, ns, cnt);
                 if c > 0 {
@@ -340,7 +380,7 @@ pub unsafe fn divrem(mut qp: LimbsMut, mut rp: LimbsMut,
                     ns_tmp -= 1;
                 }
 
-                let dtmp = tmp.allocate(ds as usize);
+                let dtmp = tmp.allocate(ds);
                 ll::shl(dtmp, dp, ds, cnt);
                 dp_tmp = dtmp.as_const();
             }
@@ -362,6 +402,77 @@ pub unsafe fn divrem(mut qp: LimbsMut, mut rp: LimbsMut,
 
 }
 
+/**
+ * Returns the reciprocal of the `ds` limbs at `dp` used by `divrem_preinv`. The most
+ * significant limb of the divisor must have its top bit set.
+ */
+pub unsafe fn divisor_inverse(dp: Limbs, ds: usize) -> Limb {
+    debug_assert!(ds > 0);
+    debug_assert!((*dp.offset((ds - 1) as isize)).high_bit_set());
+    if ds == 1 {
+        (*dp).invert()
+    } else {
+        invert_pi(*dp.offset((ds - 1) as isize), *dp.offset((ds - 2) as isize))
+    }
+}
+
+/**
+ * Divides `{np, ns}` by `{dp, ds}` like `divrem`, except that the divisor has already been
+ * shifted left by `cnt` bits to set the top bit of its most significant limb, and `dinv` is its
+ * reciprocal from `divisor_inverse`. Dividing many numbers by the same divisor this way only
+ * normalizes and inverts it once.
+ *
+ * `ns` must be at least `ds`. The `ns - ds + 1` limbs of the quotient are stored at `qp` and
+ * the `ds` limbs of the (unshifted) remainder at `rp`.
+ */
+pub unsafe fn divrem_preinv(qp: LimbsMut, rp: LimbsMut,
+                            np: Limbs, ns: usize,
+                            dp: Limbs, ds: usize,
+                            cnt: u32, dinv: Limb) {
+    debug_assert!(ds > 0 && ns >= ds);
+    debug_assert!((*dp.offset((ds - 1) as isize)).high_bit_set());
+    debug_assert!(!overlap(qp, ns - ds + 1, np, ns));
+
+    // The numerator is shifted like the divisor, into one more limb. That limb is less than
+    // the top limb of the divisor, so the quotient still fits in `ns - ds + 1` limbs.
+    let mut tmp = mem::TmpAllocator::new();
+    let np_tmp = tmp.allocate(ns + 1);
+    *np_tmp.offset(ns as isize) = if cnt == 0 {
+        ll::copy_incr(np, np_tmp, ns);
+        Limb(0)
+    } else {
+        ll::shl(np_tmp, np, ns, cnt)
+    };
+
+    match ds {
+        1 => {
+            let d = *dp;
+            let mut r = *np_tmp.offset(ns as isize);
+            let mut i = ns;
+            while i > 0 {
+                i -= 1;
+                let (q, rem) = limb::div_preinv(r, *np_tmp.offset(i as isize), d, dinv);
+                *qp.offset(i as isize) = q;
+                r = rem;
+            }
+            *rp = r >> cnt;
+            return;
+        }
+        2 => {
+            divrem_2(qp, 0, np_tmp, ns + 1, dp);
+        }
+        _ => {
+            sb_div(qp, np_tmp, ns + 1, dp, ds, dinv);
+        }
+    }
+
+    if cnt == 0 {
+        ll::copy_incr(np_tmp.as_const(), rp, ds);
+    } else {
+        ll::shr(rp, np_tmp.as_const(), ds, cnt);
+    }
+}
+
 /**
  * "Schoolbook" division of two unsigned integers, N, D, producing Q = floor(N/D).
  * The return value is the highest limb of the quotient, which may be zero.
@@ -379,8 +490,8 @@ pub unsafe fn divrem(mut qp: LimbsMut, mut rp: LimbsMut,
  * It is also assumed that `ns >= ds`.
  */
 unsafe fn sb_div(qp: LimbsMut,
-                 np: LimbsMut, ns: i32,
-                 dp: Limbs, ds: i32,
+                 np: LimbsMut, ns: usize,
+                 dp: Limbs, ds: usize,
                  dinv: Limb) -> Limb {
     debug_assert!(ds > 2);
     debug_assert!(ns >= ds);
@@ -390,10 +501,10 @@ unsafe fn sb_div(qp: LimbsMut,
 
     // If N < D*B^(m-n-1), then the high limb is zero. If not, then the high limb
     // is 1 and we subtract D*B^(m-n-1) from N.
-    let qh = if let Ordering::Less = ll::cmp(np.offset(-ds as isize).as_const(), dp, ds) {
+    let qh = if let Ordering::Less = ll::cmp(np.offset(-(ds as isize)).as_const(), dp, ds) {
         Limb(0)
     } else {
-        let np = np.offset(-ds as isize);
+        let np = np.offset(-(ds as isize));
         ll::sub_n(np, np.as_const(), dp, ds);
         Limb(1)
     };
@@ -409,19 +520,19 @@ unsafe fn sb_div(qp: LimbsMut,
 
     let mut n2 = *np.offset(1);
 
-    let mut i = ns - (ds + 2) as i32;
+    let mut i = ns - (ds + 2) as usize;
     while i > 0 {
         np = np.offset(-1);
         let n1 = *np.offset(1);
         let n0 = *np;
 
         let q = if n2 == d1 && n1 == d0 {
-            ll::submul_1(np.offset(-ds), dp, (ds + 2) as i32, Limb(!0));
+            ll::submul_1(np.offset(-ds), dp, (ds + 2) as usize, Limb(!0));
             n2 = *np.offset(1);
             Limb(!0)
         } else {
             let (q, r1, mut r0) = divrem_3by2(n2, n1, n0, d1, d0, dinv);
-            let cy = ll::submul_1(np.offset(-ds), dp, ds as i32, q);
+            let cy = ll::submul_1(np.offset(-ds), dp, ds as usize, q);
 
             n2 = r1;
 
@@ -434,7 +545,7 @@ unsafe fn sb_div(qp: LimbsMut,
             *np = r0;
 
             if cy {
-                n2 = d1 + n2 + ll::add_n(np.offset(-ds), np.offset(-ds).as_const(), dp, (ds + 1) as i32);
+                n2 = d1 + n2 + ll::add_n(np.offset(-ds), np.offset(-ds).as_const(), dp, (ds + 1) as usize);
                 q - 1
             } else {
                 q
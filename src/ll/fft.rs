@@ -0,0 +1,323 @@
+// Copyright 2015 The Ramp Developers
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Schönhage-Strassen multiplication, for the numbers too large for Toom-2
+//! to be competitive.
+//!
+//! The inputs are cut into pieces of `m` limbs, the coefficients of two
+//! polynomials, and their product is computed with a cyclic convolution of
+//! length `K = 2^k`, through FFTs over the integers modulo `2^n + 1`. As 2 is
+//! a `2n`th root of unity there, the transforms only need shifts, additions
+//! and subtractions, and `n` is large enough for the coefficients of the
+//! product to be exact. The `K` pointwise products go through `ll::mul`, so
+//! they can be FFTs too.
+//!
+//! An element of the ring takes `n/BITS + 1` limbs, and is kept normalized,
+//! at most `2^n`, so its top limb is 1 only for `2^n` itself.
+
+use std::cmp;
+
+use ll;
+use ll::limb::{BaseInt, Limb};
+use ll::limb_ptr::{Limbs, LimbsMut};
+#[cfg(feature = "rayon")]
+use super::Shared;
+use mem;
+
+// The shape of a transform
+struct Params {
+    // log2 of the transform length
+    k: usize,
+    // The transform length
+    len: usize,
+    // The limbs in a piece of the inputs
+    m: usize,
+    // The ring is the integers modulo 2^n + 1
+    n: usize,
+    // The limbs of n bits, an element takes one more
+    nl: usize,
+}
+
+impl Params {
+    // For a product of `rn` limbs
+    fn new(rn: usize) -> Params {
+        // About as many pieces as limbs in a piece, which balances the
+        // transforms against the pointwise products
+        let mut k = 1;
+        while (1 << (2 * k)) < 4 * rn {
+            k += 1;
+        }
+        let len = 1 << k;
+
+        // Enough pieces for the product to not wrap around
+        let m = (rn + len - 2) / (len - 1);
+
+        // The coefficients of the product are sums of at most `len` products
+        // of two pieces. n also has to be a multiple of len/2, for the roots
+        // of unity to be powers of 2.
+        let bits = 2 * m * Limb::BITS + k + 1;
+        let unit = cmp::max(Limb::BITS, len / 2);
+        let n = (bits + unit - 1) / unit * unit;
+
+        Params { k: k, len: len, m: m, n: n, nl: n / Limb::BITS }
+    }
+}
+
+/**
+ * Multiplies `{xp, xs}` by `{yp, ys}`, storing the result to `{wp, xs + ys}`, with
+ * Schönhage-Strassen's algorithm. Squares when both inputs are the same.
+ *
+ * `{wp, xs + ys}` must be disjoint from both inputs.
+ */
+pub unsafe fn mul_fft(wp: LimbsMut, xp: Limbs, xs: usize, yp: Limbs, ys: usize) {
+    debug_assert!(xs >= ys && ys > 0);
+    debug_assert!(!ll::overlap(wp, xs + ys, xp, xs));
+    debug_assert!(!ll::overlap(wp, xs + ys, yp, ys));
+
+    let rn = xs + ys;
+    let p = Params::new(rn);
+    let sqr = xp == yp && xs == ys;
+    let stride = p.nl + 1;
+
+    let mut tmp = mem::TmpAllocator::new();
+    let xt = tmp.allocate(p.len * stride);
+    let yt = if sqr { xt } else { tmp.allocate(p.len * stride) };
+    // An element, the limbs shifted out by `mul_2exp` and a full product
+    let t = tmp.allocate(2 * stride + 2 * p.nl);
+    let h = t.offset(stride as isize);
+    let prod = h.offset(stride as isize);
+
+    split(xt, xp, xs, &p);
+    fft(xt, &p, t, h);
+    if !sqr {
+        split(yt, yp, ys, &p);
+        fft(yt, &p, t, h);
+    }
+
+    pointwise(xt, yt, &p, prod);
+
+    ifft(xt, &p, t, h);
+
+    // Add up the coefficients, divided by the transform length. The limbs of
+    // a coefficient that fall beyond the product are zero.
+    ll::zero(wp, rn);
+    let mut i = 0;
+    while i < p.len && i * p.m < rn {
+        let off = i * p.m;
+        mul_2exp(t, xt.offset((i * stride) as isize).as_const(), 2 * p.n - p.k, &p, h);
+        let n = cmp::min(stride, rn - off);
+        let w = wp.offset(off as isize);
+        let cy = ll::add(w, w.as_const(), rn - off, t.as_const(), n);
+        debug_assert!(cy == 0);
+        i += 1;
+    }
+}
+
+// Multiplies the transforms element by element, into `xt`. `prod` is scratch
+// space for a product.
+#[cfg(not(feature = "rayon"))]
+unsafe fn pointwise(xt: LimbsMut, yt: LimbsMut, p: &Params, prod: LimbsMut) {
+    let stride = p.nl + 1;
+    for i in 0..p.len {
+        let off = (i * stride) as isize;
+        mul(xt.offset(off), xt.offset(off).as_const(), yt.offset(off).as_const(), p.nl, prod);
+    }
+}
+
+// Multiplies the transforms element by element, into `xt`, on rayon's thread
+// pool. Each product gets scratch space of its own.
+#[cfg(feature = "rayon")]
+unsafe fn pointwise(xt: LimbsMut, yt: LimbsMut, p: &Params, _: LimbsMut) {
+    use rayon::prelude::*;
+    let (stride, nl) = (p.nl + 1, p.nl);
+    let ts = Shared((xt, yt));
+    (0..p.len).into_par_iter().for_each(|i| {
+        let (xt, yt) = ts.0;
+        let off = (i * stride) as isize;
+        let mut tmp = mem::TmpAllocator::new();
+        let prod = tmp.allocate(2 * nl);
+        mul(xt.offset(off), xt.offset(off).as_const(), yt.offset(off).as_const(), nl, prod);
+    });
+}
+
+// Cuts `{xp, xs}` into the coefficients of `t`
+unsafe fn split(t: LimbsMut, xp: Limbs, xs: usize, p: &Params) {
+    let stride = p.nl + 1;
+    ll::zero(t, p.len * stride);
+
+    let mut i = 0;
+    let mut off = 0;
+    while off < xs {
+        let n = cmp::min(p.m, xs - off);
+        ll::copy_incr(xp.offset(off as isize), t.offset((i * stride) as isize), n);
+        off += p.m;
+        i += 1;
+    }
+}
+
+// The forward transform, decimation in frequency. The result is in bit
+// reversed order, which `ifft` takes.
+unsafe fn fft(a: LimbsMut, p: &Params, t: LimbsMut, h: LimbsMut) {
+    let stride = p.nl + 1;
+    let mut half = p.len / 2;
+    while half >= 1 {
+        // 2^unit is a (2*half)th root of unity
+        let unit = p.n / half;
+        let mut start = 0;
+        while start < p.len {
+            for j in 0..half {
+                let x = a.offset(((start + j) * stride) as isize);
+                let y = a.offset(((start + j + half) * stride) as isize);
+                sub(t, x.as_const(), y.as_const(), p.nl);
+                add(x, x.as_const(), y.as_const(), p.nl);
+                mul_2exp(y, t.as_const(), j * unit, p, h);
+            }
+            start += 2 * half;
+        }
+        half /= 2;
+    }
+}
+
+// The inverse transform, decimation in time, without the division by the
+// length
+unsafe fn ifft(a: LimbsMut, p: &Params, t: LimbsMut, h: LimbsMut) {
+    let stride = p.nl + 1;
+    let mut half = 1;
+    while half < p.len {
+        let unit = p.n / half;
+        let mut start = 0;
+        while start < p.len {
+            for j in 0..half {
+                let x = a.offset(((start + j) * stride) as isize);
+                let y = a.offset(((start + j + half) * stride) as isize);
+                // 2^-e = 2^(2n - e)
+                let e = if j == 0 { 0 } else { 2 * p.n - j * unit };
+                mul_2exp(t, y.as_const(), e, p, h);
+                sub(y, x.as_const(), t.as_const(), p.nl);
+                add(x, x.as_const(), t.as_const(), p.nl);
+            }
+            start += 2 * half;
+        }
+        half *= 2;
+    }
+}
+
+// Normalizes `{rp, nl + 1}` holding `{rp, nl} + hi*2^n`, which is
+// `{rp, nl} - hi` modulo 2^n + 1
+unsafe fn normalize(rp: LimbsMut, nl: usize, hi: isize) {
+    *rp.offset(nl as isize) = Limb(0);
+    if hi > 0 {
+        if ll::sub_1(rp, rp.as_const(), nl, Limb(hi as BaseInt)) != 0 {
+            // The borrow is -2^n, so 1
+            *rp.offset(nl as isize) = ll::add_1(rp, rp.as_const(), nl, Limb(1));
+        }
+    } else if hi < 0 {
+        if ll::add_1(rp, rp.as_const(), nl, Limb((-hi) as BaseInt)) != 0 {
+            // The carry is 2^n, so -1
+            if ll::is_zero(rp.as_const(), nl) {
+                *rp.offset(nl as isize) = Limb(1);
+            } else {
+                ll::sub_1(rp, rp.as_const(), nl, Limb(1));
+            }
+        }
+    }
+}
+
+pub unsafe fn add(rp: LimbsMut, ap: Limbs, bp: Limbs, nl: usize) {
+    let hi = *ap.offset(nl as isize) + *bp.offset(nl as isize) + ll::add_n(rp, ap, bp, nl);
+    normalize(rp, nl, hi.0 as isize);
+}
+
+pub unsafe fn sub(rp: LimbsMut, ap: Limbs, bp: Limbs, nl: usize) {
+    let hi = (*ap.offset(nl as isize)).0 as isize - (*bp.offset(nl as isize)).0 as isize;
+    let hi = hi - ll::sub_n(rp, ap, bp, nl).0 as isize;
+    normalize(rp, nl, hi);
+}
+
+unsafe fn negate(rp: LimbsMut, nl: usize) {
+    if *rp.offset(nl as isize) != 0 {
+        // -2^n = 1
+        *rp.offset(nl as isize) = Limb(0);
+        *rp = Limb(1);
+    } else if !ll::is_zero(rp.as_const(), nl) {
+        // 2^n + 1 - r
+        ll::twos_complement(rp, rp.as_const(), nl);
+        *rp.offset(nl as isize) = ll::add_1(rp, rp.as_const(), nl, Limb(1));
+    }
+}
+
+// Sets `rp` to `ap * 2^e`, where `e < 2n`. `hp` is scratch space for an
+// element.
+unsafe fn mul_2exp(rp: LimbsMut, ap: Limbs, mut e: usize, p: &Params, hp: LimbsMut) {
+    let nl = p.nl;
+    // 2^n = -1
+    let mut neg = false;
+    if e >= p.n {
+        e -= p.n;
+        neg = true;
+    }
+
+    if *ap.offset(nl as isize) != 0 {
+        ll::zero(rp, nl + 1);
+        *rp.offset((e / Limb::BITS) as isize) = Limb(1) << (e % Limb::BITS);
+        neg = !neg;
+    } else {
+        // a*2^e = l + h*2^n, where the low n bits l go to rp and the q+1
+        // limbs of h to hp, and as 2^n = -1, that's l - h
+        let q = e / Limb::BITS;
+        let b = (e % Limb::BITS) as u32;
+        ll::zero(rp, q);
+        if b > 0 {
+            let c = ll::shl(rp.offset(q as isize), ap, nl - q, b);
+            if q > 0 {
+                *hp.offset(q as isize) = ll::shl(hp, ap.offset((nl - q) as isize), q, b);
+                *hp = *hp | c;
+            } else {
+                *hp = c;
+            }
+        } else {
+            ll::copy_incr(ap, rp.offset(q as isize), nl - q);
+            ll::copy_incr(ap.offset((nl - q) as isize), hp, q);
+            *hp.offset(q as isize) = Limb(0);
+        }
+
+        let bo = ll::sub(rp, rp.as_const(), nl, hp.as_const(), q + 1);
+        normalize(rp, nl, -(bo.0 as isize));
+    }
+
+    if neg {
+        negate(rp, nl);
+    }
+}
+
+// Sets `rp`, which may be `ap`, to `ap * bp`. `prod` is scratch space for
+// `2*nl` limbs. `ll::mulmod_bnm1` uses this too, for products modulo
+// `B^nl + 1`.
+pub unsafe fn mul(rp: LimbsMut, ap: Limbs, bp: Limbs, nl: usize, prod: LimbsMut) {
+    if *ap.offset(nl as isize) != 0 {
+        ll::copy_incr(bp, rp, nl + 1);
+        negate(rp, nl);
+    } else if *bp.offset(nl as isize) != 0 {
+        ll::copy_incr(ap, rp, nl + 1);
+        negate(rp, nl);
+    } else {
+        if ap == bp {
+            ll::sqr(prod, ap, nl);
+        } else {
+            ll::mul(prod, ap, nl, bp, nl);
+        }
+        let bo = ll::sub_n(rp, prod.as_const(), prod.offset(nl as isize).as_const(), nl);
+        normalize(rp, nl, -(bo.0 as isize));
+    }
+}
@@ -17,7 +17,7 @@ use std::cmp::Ordering;
 use ll;
 use ll::limb_ptr::LimbsMut;
 
-pub unsafe fn gcd(mut gp: LimbsMut, mut ap: LimbsMut, mut an: i32, mut bp: LimbsMut, mut bn: i32) -> i32 {
+pub unsafe fn gcd(mut gp: LimbsMut, mut ap: LimbsMut, mut an: usize, mut bp: LimbsMut, mut bn: usize) -> usize {
     assert!(an >= bn);
 
     let mut gc = 0;
@@ -44,6 +44,7 @@ pub type BaseInt = u64;
  * A "Limb" is a single digit in base 2^word size.
  */
 #[derive(Copy, Eq, Ord, Hash)]
+#[repr(C)]
 pub struct Limb(pub BaseInt);
 
 impl Clone for Limb {
@@ -149,6 +150,47 @@ impl Limb {
     pub fn trailing_zeros(self) -> BaseInt {
         self.0.trailing_zeros() as BaseInt
     }
+
+    /**
+     * Reads a limb from `bytes`, least-significant first. There may be fewer bytes than in a limb,
+     * the missing high bytes being zero.
+     *
+     * Bytes and limbs are only ever converted by shifting, like here, so the crate doesn't depend
+     * on the byte order of the target.
+     */
+    pub fn from_le_bytes(bytes: &[u8]) -> Limb {
+        debug_assert!(bytes.len() <= Limb::BITS / 8);
+        bytes.iter().rev().fold(Limb(0), |l, &b| (l << 8) | Limb(b as BaseInt))
+    }
+
+    /**
+     * Reads a limb from `bytes`, most-significant first. There may be fewer bytes than in a limb,
+     * the missing high bytes being zero.
+     */
+    pub fn from_be_bytes(bytes: &[u8]) -> Limb {
+        debug_assert!(bytes.len() <= Limb::BITS / 8);
+        bytes.iter().fold(Limb(0), |l, &b| (l << 8) | Limb(b as BaseInt))
+    }
+
+    /**
+     * Writes the `out.len()` low bytes of the limb to `out`, least-significant first.
+     */
+    pub fn write_le_bytes(self, out: &mut [u8]) {
+        debug_assert!(out.len() <= Limb::BITS / 8);
+        for (i, b) in out.iter_mut().enumerate() {
+            *b = (self >> (8 * i)).0 as u8;
+        }
+    }
+
+    /**
+     * Writes the `out.len()` low bytes of the limb to `out`, most-significant first.
+     */
+    pub fn write_be_bytes(self, out: &mut [u8]) {
+        debug_assert!(out.len() <= Limb::BITS / 8);
+        for (i, b) in out.iter_mut().rev().enumerate() {
+            *b = (self >> (8 * i)).0 as u8;
+        }
+    }
 }
 
 impl Add<Limb> for Limb {
@@ -476,36 +518,7 @@ pub fn mul(u: Limb, v: Limb) -> (Limb, Limb) {
         fallback:
         #[inline(always)]
         fn mul_impl(u: Limb, v: Limb) -> (Limb, Limb) {
-            fn mul_2_usize_to_2_usize(u:Word, v: Word) -> (Word,Word) {
-                // see http://www.hackersdelight.org/hdcodetxt/muldwu.c.txt
-                const BITS:usize = Limb::BITS / 2;
-                const LO_MASK:Word = Wrapping((1usize << BITS) - 1);
-
-                let u0 = u >> BITS;
-                let u1 = u & LO_MASK;
-                let v0 = v >> BITS;
-                let v1 = v & LO_MASK;
-
-                let t = u1 * v1;
-                let w3 = t & LO_MASK;
-                let k = t >> BITS;
-
-                let t = u0*v1 + k;
-                let w2 = t & LO_MASK;
-                let w1 = t >> BITS;
-
-                let t = u1 * v0 + w2;
-                let k = t >> BITS;
-
-                (u0*v0+w1+k, (t<<BITS) + w3)
-            }
-
-            let (h,l) = mul_2_usize_to_2_usize(
-                Wrapping(u.0 as usize),
-                Wrapping(v.0 as usize));
-
-            (Limb(h.0 as BaseInt), Limb(l.0 as BaseInt))
-
+            mul_portable(u, v)
         }
     }
     return mul_impl(u, v);
@@ -562,10 +575,7 @@ pub fn add_2(ah: Limb, al: Limb, bh: Limb, bl: Limb) -> (Limb, Limb) {
         fallback:
         #[inline(always)]
         fn add_2_impl(ah: Limb, al: Limb, bh: Limb, bl: Limb) -> (Limb, Limb) {
-            let (low, carry) = al.add_overflow(bl);
-            let high = ah + bh + carry;
-
-            (high, low)
+            add_2_portable(ah, al, bh, bl)
         }
     }
     return add_2_impl(ah, al, bh, bl);
@@ -622,10 +632,7 @@ pub fn sub_2(ah: Limb, al: Limb, bh: Limb, bl: Limb) -> (Limb, Limb) {
         fallback:
         #[inline(always)]
         fn sub_2_impl(ah: Limb, al: Limb, bh: Limb, bl: Limb) -> (Limb, Limb) {
-            let (low, carry) = al.sub_overflow(bl);
-            let high = ah - bh - carry;
-
-            (high, low)
+            sub_2_portable(ah, al, bh, bl)
         }
     }
     return sub_2_impl(ah, al, bh, bl);
@@ -684,54 +691,7 @@ pub fn div(nh: Limb, nl: Limb, d: Limb) -> (Limb, Limb) {
         fallback:
         #[inline(always)]
         fn div_impl(nh: Limb, nl: Limb, d: Limb) -> (Limb, Limb) {
-            fn div_2_usize_by_usize(u1:Word, u0: Word, v: Word) -> (Word,Word) {
-                // See http://www.hackersdelight.org/hdcodetxt/divlu.c.txt (last one)
-                // s == 0 in our case, d normalization is already done
-                const BITS:usize = Limb::BITS / 2;
-                const ONE:Word = Wrapping(1usize);
-                const B:Word = Wrapping(1usize << BITS);
-                const LO_MASK:Word = Wrapping((1usize << BITS) - 1);
-
-                let vn1 = v >> BITS;
-                let vn0 = v & LO_MASK;
-
-                let un32 = u1;
-                let un10 = u0;
-
-                let un1 = un10 >> BITS;
-                let un0 = un10 & LO_MASK;
-
-                let mut q1 = un32 / vn1;
-                let mut rhat = un32 - q1*vn1;
-
-                while q1 >= B || q1*vn0 > B*rhat + un1 {
-                    q1 -= ONE;
-                    rhat += vn1;
-                    if rhat >= B {
-                        break;
-                    }
-                }
-
-                let un21 = un32*B +un1 - q1*v;
-
-                let mut q0 = un21 / vn1;
-                let mut rhat = un21 - q0*vn1;
-                while q0 >= B || q0*vn0 > B*rhat + un0 {
-                    q0 -= ONE;
-                    rhat += vn1;
-                    if rhat >= B {
-                        break;
-                    }
-                }
-                (q1*B + q0, un21*B + un0 - q0*v)
-            }
-
-            let (q,r) = div_2_usize_by_usize(
-                Wrapping(nh.0 as usize),
-                Wrapping(nl.0 as usize),
-                Wrapping(d.0 as usize));
-
-            (Limb(q.0 as BaseInt), Limb(r.0 as BaseInt))
+            div_portable(nh, nl, d)
         }
     }
 
@@ -767,6 +727,132 @@ pub fn div_preinv(nh: Limb, nl: Limb, d: Limb, dinv: Limb) -> (Limb, Limb) {
     (qh, r)
 }
 
+/**
+ * The portable implementation of `mul`, used when there is no faster one for the target.
+ */
+pub fn mul_portable(u: Limb, v: Limb) -> (Limb, Limb) {
+    fn mul_2_usize_to_2_usize(u:Word, v: Word) -> (Word,Word) {
+        // see http://www.hackersdelight.org/hdcodetxt/muldwu.c.txt
+        const BITS:usize = Limb::BITS / 2;
+        const LO_MASK:Word = Wrapping((1usize << BITS) - 1);
+
+        let u0 = u >> BITS;
+        let u1 = u & LO_MASK;
+        let v0 = v >> BITS;
+        let v1 = v & LO_MASK;
+
+        let t = u1 * v1;
+        let w3 = t & LO_MASK;
+        let k = t >> BITS;
+
+        let t = u0*v1 + k;
+        let w2 = t & LO_MASK;
+        let w1 = t >> BITS;
+
+        let t = u1 * v0 + w2;
+        let k = t >> BITS;
+
+        (u0*v0+w1+k, (t<<BITS) + w3)
+    }
+
+    let (h,l) = mul_2_usize_to_2_usize(
+        Wrapping(u.0 as usize),
+        Wrapping(v.0 as usize));
+
+    (Limb(h.0 as BaseInt), Limb(l.0 as BaseInt))
+}
+
+/**
+ * The portable implementation of `add_2`.
+ */
+pub fn add_2_portable(ah: Limb, al: Limb, bh: Limb, bl: Limb) -> (Limb, Limb) {
+    let (low, carry) = al.add_overflow(bl);
+    let high = ah + bh + carry;
+
+    (high, low)
+}
+
+/**
+ * The portable implementation of `sub_2`.
+ */
+pub fn sub_2_portable(ah: Limb, al: Limb, bh: Limb, bl: Limb) -> (Limb, Limb) {
+    let (low, carry) = al.sub_overflow(bl);
+    let high = ah - bh - carry;
+
+    (high, low)
+}
+
+/**
+ * The portable implementation of `div`, with the same requirements.
+ */
+pub fn div_portable(nh: Limb, nl: Limb, d: Limb) -> (Limb, Limb) {
+    fn div_2_usize_by_usize(u1:Word, u0: Word, v: Word) -> (Word,Word) {
+        // See http://www.hackersdelight.org/hdcodetxt/divlu.c.txt (last one)
+        // s == 0 in our case, d normalization is already done
+        const BITS:usize = Limb::BITS / 2;
+        const ONE:Word = Wrapping(1usize);
+        const B:Word = Wrapping(1usize << BITS);
+        const LO_MASK:Word = Wrapping((1usize << BITS) - 1);
+
+        let vn1 = v >> BITS;
+        let vn0 = v & LO_MASK;
+
+        let un32 = u1;
+        let un10 = u0;
+
+        let un1 = un10 >> BITS;
+        let un0 = un10 & LO_MASK;
+
+        let mut q1 = un32 / vn1;
+        let mut rhat = un32 - q1*vn1;
+
+        while q1 >= B || q1*vn0 > B*rhat + un1 {
+            q1 -= ONE;
+            rhat += vn1;
+            if rhat >= B {
+                break;
+            }
+        }
+
+        let un21 = un32*B +un1 - q1*v;
+
+        let mut q0 = un21 / vn1;
+        let mut rhat = un21 - q0*vn1;
+        while q0 >= B || q0*vn0 > B*rhat + un0 {
+            q0 -= ONE;
+            rhat += vn1;
+            if rhat >= B {
+                break;
+            }
+        }
+        (q1*B + q0, un21*B + un0 - q0*v)
+    }
+
+    let (q,r) = div_2_usize_by_usize(
+        Wrapping(nh.0 as usize),
+        Wrapping(nl.0 as usize),
+        Wrapping(d.0 as usize));
+
+    (Limb(q.0 as BaseInt), Limb(r.0 as BaseInt))
+}
+
+#[test]
+fn test_bytes() {
+    // A byte-swapped fixture reads back as the same limb
+    let be = [0x12, 0x34, 0x56, 0x78];
+    let le = [0x78, 0x56, 0x34, 0x12];
+    assert_eq!(Limb::from_be_bytes(&be), 0x12345678);
+    assert_eq!(Limb::from_le_bytes(&le), 0x12345678);
+    assert_eq!(Limb::from_le_bytes(&[0xff]), 0xff);
+    assert_eq!(Limb::from_be_bytes(&[]), 0);
+
+    let mut out = [0; 4];
+    Limb(0x12345678).write_le_bytes(&mut out);
+    assert_eq!(out, le);
+    Limb(0x12345678).write_be_bytes(&mut out);
+    assert_eq!(out, be);
+}
+
 #[test]
 fn test_bug_div_1() {
     let (q,r) = div(Limb(0), Limb(10), Limb((usize::max_value()/2+1) as BaseInt));
@@ -15,19 +15,21 @@
 use ll::limb::Limb;
 
 use std::{fmt, ops};
-#[cfg(debug_assertions)]
+#[cfg(any(debug_assertions, feature = "checked-limbs"))]
 use std::mem;
 use std::cmp::Ordering;
 
 
-/// A version of `*const Limb` that is bounds-checked when debug assertions are on
+/// A version of `*const Limb` that is bounds-checked when debug assertions or
+/// the `checked-limbs` feature are on
 #[derive(Copy, Clone, Debug)]
 pub struct Limbs {
     ptr: *const Limb,
     bounds: Bounds,
 }
 
-/// A version of `*mut Limb` that is bounds-checked when debug assertions are on
+/// A version of `*mut Limb` that is bounds-checked when debug assertions or
+/// the `checked-limbs` feature are on
 #[derive(Copy, Clone)]
 pub struct LimbsMut {
     ptr: *mut Limb,
@@ -39,7 +41,7 @@ macro_rules! api {
         impl $ty {
             /// Create a new instance, pointing at `base` and valid
             /// from `base.offset(start)` to `base.offset(end)`.
-            pub unsafe fn new(base: $ptr, start: i32, end: i32) -> $ty {
+            pub unsafe fn new(base: $ptr, start: usize, end: usize) -> $ty {
                 $ty {
                     ptr: base,
                     bounds: Bounds::new(base as usize, start, end)
@@ -50,8 +52,8 @@ macro_rules! api {
             /// current location.
             #[inline]
             pub unsafe fn offset(self, x: isize) -> $ty {
-                debug_assert!(self.bounds.offset_valid(self.ptr as usize, x),
-                              "invalid offset of {:?} by {}, which should be in {:?}", self.ptr, x, self.bounds);
+                assert!(self.bounds.offset_valid(self.ptr as usize, x),
+                        "invalid offset of {:?} by {}, which should be in {:?}", self.ptr, x, self.bounds);
                 $ty {
                     ptr: self.ptr.offset(x),
                     bounds: self.bounds,
@@ -79,8 +81,8 @@ macro_rules! api {
         impl ops::Deref for $ty {
             type Target = Limb;
             fn deref(&self) -> &Limb {
-                debug_assert!(self.bounds.can_deref(self.ptr as usize),
-                              "invalid deref of {:?}, which should be in {:?}", self.ptr, self.bounds);
+                assert!(self.bounds.can_deref(self.ptr as usize),
+                        "invalid deref of {:?}, which should be in {:?}", self.ptr, self.bounds);
                 unsafe { &*self.ptr }
             }
         }
@@ -89,7 +91,25 @@ macro_rules! api {
 
 api!(Limbs, *const Limb);
 api!(LimbsMut, *mut Limb);
+impl Limbs {
+    /// Returns the underlying pointer.
+    ///
+    /// Unlike going through `&*`, this doesn't create a reference, so the
+    /// pointer stays valid for the whole buffer and may alias a `LimbsMut`.
+    /// This is what must be passed to functions that access more than one
+    /// limb, such as the assembly routines.
+    #[inline(always)]
+    pub fn as_ptr(self) -> *const Limb {
+        self.ptr
+    }
+}
 impl LimbsMut {
+    /// Returns the underlying pointer, see `Limbs::as_ptr`.
+    #[inline(always)]
+    pub fn as_mut_ptr(self) -> *mut Limb {
+        self.ptr
+    }
+
     /// View the `LimbsMut` as a `Limbs` (an explicit `*const
     /// Limb` -> `*mut Limb` conversion)
     pub fn as_const(self) -> Limbs {
@@ -101,32 +121,34 @@ impl LimbsMut {
 }
 impl ops::DerefMut for LimbsMut {
     fn deref_mut(&mut self) -> &mut Limb {
-        debug_assert!(self.bounds.can_deref(self.ptr as usize),
-                      "invalid mut deref of {:?}, which should be in {:?}", self.ptr, self.bounds);
+        assert!(self.bounds.can_deref(self.ptr as usize),
+                "invalid mut deref of {:?}, which should be in {:?}", self.ptr, self.bounds);
         unsafe { &mut *self.ptr }
     }
 }
 
 // This is where the magic is at: the bounds are only stored/checked
-// in debug mode; release mode just powers ahead without any overhead.
+// in debug mode, or in any mode with the `checked-limbs` feature;
+// otherwise every check is a constant `true` and the asserts above
+// compile away, so release mode just powers ahead without any overhead.
 
 #[derive(Copy, Clone)]
-#[cfg(debug_assertions)]
+#[cfg(any(debug_assertions, feature = "checked-limbs"))]
 struct Bounds {
     lo: usize,
     hi: usize,
 }
 #[derive(Copy, Clone)]
-#[cfg(not(debug_assertions))]
+#[cfg(not(any(debug_assertions, feature = "checked-limbs")))]
 struct Bounds;
 
-#[cfg(debug_assertions)]
+#[cfg(any(debug_assertions, feature = "checked-limbs"))]
 impl Bounds {
-    fn new(ptr: usize, start: i32, end: i32) -> Bounds {
+    fn new(ptr: usize, start: usize, end: usize) -> Bounds {
         assert!(start <= end);
         Bounds {
-            lo: ptr + start as usize * mem::size_of::<Limb>(),
-            hi: ptr + end as usize * mem::size_of::<Limb>(),
+            lo: ptr + start * mem::size_of::<Limb>(),
+            hi: ptr + end * mem::size_of::<Limb>(),
         }
     }
     fn can_deref(self, ptr: usize) -> bool {
@@ -140,21 +162,66 @@ impl Bounds {
         self.lo <= new && new <= self.hi
     }
 }
-#[cfg(not(debug_assertions))]
+#[cfg(not(any(debug_assertions, feature = "checked-limbs")))]
 impl Bounds {
-    fn new(_ptr: usize, _start: i32, _end: i32) -> Bounds { Bounds }
+    #[inline]
+    fn new(_ptr: usize, _start: usize, _end: usize) -> Bounds { Bounds }
     #[inline]
     fn can_deref(self, _ptr: usize) -> bool { true }
     #[inline]
     fn offset_valid(self, _ptr: usize, _offset: isize) -> bool { true }
 }
 impl fmt::Debug for Bounds {
-    #[cfg(debug_assertions)]
+    #[cfg(any(debug_assertions, feature = "checked-limbs"))]
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Bounds {{ lo: 0x{:x}, hi: 0x{:x} }}", self.lo, self.hi)
     }
-    #[cfg(not(debug_assertions))]
+    #[cfg(not(any(debug_assertions, feature = "checked-limbs")))]
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Bounds {{ <optimised out> }}")
     }
 }
+
+#[cfg(any(debug_assertions, feature = "checked-limbs"))]
+#[test]
+fn bounds() {
+    let mut buf = [Limb(0); 4];
+    unsafe {
+        let p = LimbsMut::new(buf.as_mut_ptr(), 0, 4);
+        *p.offset(3) = Limb(7);
+        let end = p.offset(4);
+        assert_eq!(*end.offset(-1), 7);
+        assert_eq!(*p.as_const().offset(3), 7);
+    }
+}
+
+#[cfg(any(debug_assertions, feature = "checked-limbs"))]
+#[test]
+#[should_panic(expected = "invalid offset")]
+fn offset_out_of_bounds() {
+    let buf = [Limb(0); 4];
+    unsafe {
+        Limbs::new(buf.as_ptr(), 0, 4).offset(5);
+    }
+}
+
+#[cfg(any(debug_assertions, feature = "checked-limbs"))]
+#[test]
+#[should_panic(expected = "invalid deref")]
+fn deref_past_end() {
+    let buf = [Limb(0); 4];
+    unsafe {
+        assert_eq!(*Limbs::new(buf.as_ptr(), 0, 4).offset(4), 0);
+    }
+}
+
+#[cfg(any(debug_assertions, feature = "checked-limbs"))]
+#[test]
+#[should_panic(expected = "invalid mut deref")]
+fn deref_mut_before_start() {
+    let mut buf = [Limb(0); 4];
+    unsafe {
+        let p = LimbsMut::new(buf.as_mut_ptr(), 1, 4);
+        *p = Limb(1);
+    }
+}
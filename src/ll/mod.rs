@@ -28,6 +28,8 @@
  *
  * The functions in this module assume that all inputs are valid, though some checking is performed
  * in debug builds.
+ * The `safe` module wraps the most commonly used ones in functions that take slices and check
+ * their arguments instead.
  *
  * ## Limbs
  *
@@ -45,15 +47,26 @@
  * implemented to provide wrapping behaviour by default. The most basic operations are also
  * implemented on `Limb`, notably multiplication with a two-word output and division of a two-word
  * numerator by a one-word denominator. The implementations of these operations are done with
- * inline assembly on x86 platforms with a Rust implementation as fallback.
+ * inline assembly on x86 platforms with a Rust implementation as fallback. The same goes for the
+ * handful of functions that have assembly versions, and `self_check` compares the versions selected
+ * for the target with the portable ones.
  *
  * ## Integer representation
  *
  * Integers are passed around as pointers to a series of `Limb`s. The limbs are stored
- * least-significant first. If required, a size parameter is also provided, but otherwise is
- * omitted when it can be inferred from other sources of information. This is the case with the
- * output pointers used to store the result, they are assumed to have enough memory store the
- * result as the maximum output size is bounded by the size of the inputs.
+ * least-significant first. This is the order of the limbs in memory on every target, and is
+ * unrelated to the order of the bytes within a limb. Code that converts between bytes and limbs
+ * goes through `Limb::from_le_bytes` and its siblings, which shift rather than reinterpret memory,
+ * so the crate behaves the same on big-endian targets. If required, a size parameter is also
+ * provided, but otherwise is omitted when it can be inferred from other sources of information.
+ * This is the case with the output pointers used to store the result, they are assumed to have
+ * enough memory store the result as the maximum output size is bounded by the size of the inputs.
+ *
+ * Outputs are allowed to overlap inputs for many functions, so buffers are only ever accessed
+ * through `Limbs` and `LimbsMut`, one limb at a time. They must never be turned into slices or
+ * references spanning more than one limb, and code that needs a raw pointer, like the assembly
+ * routines, gets it from `as_ptr` or `as_mut_ptr` rather than through a reference, which would
+ * only be valid for a single limb.
  *
  * The integers are not required to be "normalized" in most cases. That is, they may have
  * zero-value limbs in the highest positions. Functions should aim to avoid requiring normalized
@@ -66,6 +79,17 @@
  * and freed before the function returns. Functions that need to make heavy use of scratch space
  * while also being recursive, are split so that scratch space can be re-used.
  *
+ * Scratch space normally comes from the heap. `set_thread_scratch` makes it come from an arena
+ * kept by the current thread instead, which saves an allocation and a free per call.
+ *
+ * With the `alloc-stats` feature, `alloc_stats` counts the scratch and heap allocations made on the
+ * current thread.
+ *
+ * With the `rayon` feature, the sub-products of very large multiplications are computed on rayon's
+ * thread pool: the three products of the top levels of Toom-2, the pointwise products of
+ * Schönhage-Strassen and the transforms modulo each prime of the NTT. Those get their scratch
+ * space on the thread that runs them.
+ *
  * ## Argument Conventions
  *
  * There are no hard-and-fast rules for the argument conventions in this module. There are however
@@ -75,6 +99,8 @@
  * * Pointer and matching size are kept close together in the argument list.
  * * Sizes come after the matching pointers. For example, `add_n` takes two pointers and a length,
  *   the length applies to both pointers and so comes after both of them.
+ * * Sizes are `usize`, counted in limbs, but may not exceed `i32::MAX` as that is what the assembly
+ *   routines take.
  */
 
 use std::intrinsics::abort;
@@ -85,12 +111,15 @@ mod mul;
 mod div;
 mod bit;
 mod gcd;
+mod fft;
+mod ntt;
 
 pub mod pow;
 pub mod base;
 pub mod limb;
 pub mod limb_ptr;
 pub mod mtgy;
+pub mod safe;
 use self::limb::Limb;
 
 use ll::limb_ptr::{Limbs, LimbsMut};
@@ -100,32 +129,60 @@ pub use self::bit::{
     and_n, and_not_n, nand_n,
     or_n, or_not_n, nor_n, xor_n,
     not,
+    mod_2exp,
     scan_1, scan_0,
+    popcount, hamdist,
     twos_complement
 };
 pub use self::addsub::{add_n, sub_n, add, sub, add_1, sub_1, incr, decr};
-pub use self::mul::{addmul_1, submul_1, mul_1, mul, sqr};
-pub use self::div::{divrem_1, divrem_2, divrem};
+pub use self::mul::{addmul_1, submul_1, mul_1, mul, addmul, sqr, mullo_n, mulhi_n, mulmid,
+                    mulmod_bnm1, prod_limbs};
+// The algorithms `mul` and `sqr` pick between, for the speed tool to find
+// the sizes they cross over at
+#[doc(hidden)]
+pub use self::mul::{mul_basecase, mul_toom22, mul_toom32, mul_toom42, sqr_basecase, sqr_toom2,
+                    sqr_toom3};
+#[doc(hidden)]
+pub use self::fft::mul_fft;
+#[doc(hidden)]
+pub use self::ntt::mul_ntt;
+pub use self::div::{divrem_1, mod_1, divrem_2, divrem, divisor_inverse, divrem_preinv};
 pub use self::gcd::gcd;
+pub use mem::{set_thread_scratch, thread_scratch_capacity};
+#[cfg(feature = "alloc-stats")]
+pub use mem::{AllocStats, alloc_stats, reset_alloc_stats};
+
+// Limb pointers handed to other threads. The products computed in parallel
+// write to disjoint parts of the buffers, and `join` returns only once they
+// are all done, so sharing the pointers is safe.
+#[cfg(feature = "rayon")]
+#[derive(Clone, Copy)]
+struct Shared<T>(T);
+
+#[cfg(feature = "rayon")]
+unsafe impl<T> Send for Shared<T> {}
+
+#[cfg(feature = "rayon")]
+unsafe impl<T> Sync for Shared<T> {}
 
 #[inline(always)]
-pub unsafe fn overlap(xp: LimbsMut, xs: i32, yp: Limbs, ys: i32) -> bool {
+pub unsafe fn overlap(xp: LimbsMut, xs: usize, yp: Limbs, ys: usize) -> bool {
     xp.offset(xs as isize).as_const() > yp
         && yp.offset(ys as isize) > xp.as_const()
 }
 
 #[inline(always)]
-pub unsafe fn same_or_separate(xp: LimbsMut, xs: i32, yp: Limbs, ys: i32) -> bool {
+pub unsafe fn same_or_separate(xp: LimbsMut, xs: usize, yp: Limbs, ys: usize) -> bool {
     xp.as_const() == yp || !overlap(xp, xs, yp, ys)
 }
 
 #[inline(always)]
-pub unsafe fn same_or_incr(xp: LimbsMut, xs: i32, yp: Limbs, ys: i32) -> bool {
+pub unsafe fn same_or_incr(xp: LimbsMut, xs: usize, yp: Limbs, ys: usize) -> bool {
     xp.as_const() <= yp || !overlap(xp, xs, yp, ys)
 }
 
 #[inline(always)]
-pub unsafe fn same_or_decr(xp: LimbsMut, xs: i32, yp: Limbs, ys: i32) -> bool {
+pub unsafe fn same_or_decr(xp: LimbsMut, xs: usize, yp: Limbs, ys: usize) -> bool {
     xp.as_const() >= yp || !overlap(xp, xs, yp, ys)
 }
 
@@ -133,7 +190,7 @@ pub unsafe fn same_or_decr(xp: LimbsMut, xs: i32, yp: Limbs, ys: i32) -> bool {
  * Copies the `n` limbs from `src` to `dst` in an incremental fashion.
  */
 #[inline]
-pub unsafe fn copy_incr(src: Limbs, dst: LimbsMut, n: i32) {
+pub unsafe fn copy_incr(src: Limbs, dst: LimbsMut, n: usize) {
     debug_assert!(same_or_incr(dst, n, src, n));
 
     let mut i = 0;
@@ -147,13 +204,12 @@ pub unsafe fn copy_incr(src: Limbs, dst: LimbsMut, n: i32) {
  * Copies the `n` limbs from `src` to `dst` in a decremental fashion.
  */
 #[inline]
-pub unsafe fn copy_decr(src: Limbs, dst: LimbsMut, mut n: i32) {
+pub unsafe fn copy_decr(src: Limbs, dst: LimbsMut, mut n: usize) {
     debug_assert!(same_or_decr(dst, n, src, n));
 
-    n -= 1;
-    while n >= 0 {
-        *dst.offset(n as isize) = *src.offset(n as isize);
+    while n > 0 {
         n -= 1;
+        *dst.offset(n as isize) = *src.offset(n as isize);
     }
 }
 
@@ -161,7 +217,7 @@ pub unsafe fn copy_decr(src: Limbs, dst: LimbsMut, mut n: i32) {
  * Copies the `n - start` limbs from `src + start` to `dst + start`
  */
 #[inline]
-pub unsafe fn copy_rest(src: Limbs, dst: LimbsMut, n: i32, start: i32) {
+pub unsafe fn copy_rest(src: Limbs, dst: LimbsMut, n: usize, start: usize) {
     copy_incr(src.offset(start as isize), dst.offset(start as isize),
                n - start);
 }
@@ -171,8 +227,7 @@ pub unsafe fn copy_rest(src: Limbs, dst: LimbsMut, n: i32, start: i32) {
  * Returns the size of the integer pointed to by `p` such that the most
  * significant limb is non-zero.
  */
-pub unsafe fn normalize(p: Limbs, mut n: i32) -> i32 {
-    debug_assert!(n >= 0);
+pub unsafe fn normalize(p: Limbs, mut n: usize) -> usize {
     while n > 0 && *p.offset((n - 1) as isize) == 0 {
         n -= 1;
     }
@@ -199,7 +254,7 @@ pub fn divide_by_zero() -> ! {
 /**
  * Checks that all `nn` limbs in `np` are zero
  */
-pub unsafe fn is_zero(mut np: Limbs, mut nn: i32) -> bool {
+pub unsafe fn is_zero(mut np: Limbs, mut nn: usize) -> bool {
     while nn > 0 {
         if *np != 0 { return false; }
         np = np.offset(1);
@@ -208,7 +263,7 @@ pub unsafe fn is_zero(mut np: Limbs, mut nn: i32) -> bool {
     return true;
 }
 
-pub unsafe fn zero(mut np: LimbsMut, mut nn: i32) {
+pub unsafe fn zero(mut np: LimbsMut, mut nn: usize) {
     while nn > 0 {
         *np = Limb(0);
         np = np.offset(1);
@@ -220,9 +275,10 @@ pub unsafe fn zero(mut np: LimbsMut, mut nn: i32) {
  * Compares the `n` least-significant limbs of `xp` and `yp`, returning whether
  * {xp, n} is less than, equal to or greater than {yp, n}
  */
-pub unsafe fn cmp(xp: Limbs, yp: Limbs, n: i32) -> Ordering {
-    let mut i = n - 1;
-    while i >= 0 {
+pub unsafe fn cmp(xp: Limbs, yp: Limbs, n: usize) -> Ordering {
+    let mut i = n;
+    while i > 0 {
+        i -= 1;
         let x = *xp.offset(i as isize);
         let y = *yp.offset(i as isize);
         if x != y {
@@ -232,16 +288,119 @@ pub unsafe fn cmp(xp: Limbs, yp: Limbs, n: i32) -> Ordering {
                 Ordering::Less
             };
         }
-        i -= 1;
     }
 
     Ordering::Equal
 }
 
+/**
+ * Checks that the kernels selected for the target, which may be assembly or inline assembly,
+ * agree with their portable implementations on a fixed set of inputs. On failure, the name of
+ * the first kernel found to disagree is returned.
+ *
+ * Every kernel has a portable implementation, which is used on targets without a faster one, such
+ * as wasm32 or riscv, and everywhere with the `fallbacks` feature. This check doesn't rely on the
+ * test suite having been run on the target, so programs can call it at startup when running on
+ * unusual platforms.
+ */
+pub fn self_check() -> Result<(), &'static str> {
+    use self::limb::BaseInt;
+
+    // xorshift64, so the inputs are the same on every run
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        Limb(state as BaseInt)
+    };
+
+    let max = !Limb(0);
+    let high = Limb(1) << (Limb::BITS - 1);
+    let edges = [Limb(0), Limb(1), Limb(2), high - 1, high, high + 1, max - 1, max];
+
+    let mut pairs = Vec::new();
+    for &a in edges.iter() {
+        for &b in edges.iter() {
+            pairs.push((a, b));
+        }
+    }
+    for _ in 0..1000 {
+        let (a, b) = (next(), next());
+        pairs.push((a, b));
+    }
+
+    for &(a, b) in &pairs {
+        if limb::mul(a, b) != limb::mul_portable(a, b) {
+            return Err("mul");
+        }
+        let (c, d) = (b, a);
+        if limb::add_2(a, b, c, d) != limb::add_2_portable(a, b, c, d) {
+            return Err("add_2");
+        }
+        if limb::sub_2(a, b, c, d) != limb::sub_2_portable(a, b, c, d) {
+            return Err("sub_2");
+        }
+        // `div` needs a normalized divisor larger than the high limb
+        let d = b | high;
+        let nh = if a < d { a } else { a - d };
+        if limb::div(nh, b, d) != limb::div_portable(nh, b, d) {
+            return Err("div");
+        }
+    }
+
+    let limbs = |n: usize, next: &mut FnMut() -> Limb| -> Vec<Limb> {
+        (0..n).map(|_| next()).collect()
+    };
+    for n in 1..40 {
+        let x = limbs(n, &mut next);
+        let y = limbs(n, &mut next);
+        let w = limbs(n, &mut next);
+        let v = next();
+        let mut w1 = w.clone();
+        let mut w2 = w.clone();
+
+        macro_rules! check {
+            ($name:expr, $f:expr, $g:expr) => {{
+                w1.copy_from_slice(&w);
+                w2.copy_from_slice(&w);
+                let (r1, r2) = unsafe {
+                    let wp1 = LimbsMut::new(w1.as_mut_ptr(), 0, n);
+                    let wp2 = LimbsMut::new(w2.as_mut_ptr(), 0, n);
+                    let xp = Limbs::new(x.as_ptr(), 0, n);
+                    let yp = Limbs::new(y.as_ptr(), 0, n);
+                    ($f(wp1, xp, yp), $g(wp2, xp, yp))
+                };
+                if r1 != r2 || w1 != w2 {
+                    return Err($name);
+                }
+            }}
+        }
+
+        check!("add_n",
+               |wp, xp, yp| add_n(wp, xp, yp, n),
+               |wp, xp, yp| addsub::add_n_generic(wp, xp, yp, n));
+        check!("sub_n",
+               |wp, xp, yp| sub_n(wp, xp, yp, n),
+               |wp, xp, yp| addsub::sub_n_generic(wp, xp, yp, n));
+        check!("mul_1",
+               |wp, xp, _| mul_1(wp, xp, n, v),
+               |wp, xp, _| mul::mul_1_generic(wp, xp, n, v));
+        check!("addmul_1",
+               |wp, xp, _| addmul_1(wp, xp, n, v),
+               |wp, xp, _| mul::addmul_1_generic(wp, xp, n, v));
+        check!("submul_1",
+               |wp, xp, _| submul_1(wp, xp, n, v),
+               |wp, xp, _| mul::submul_1_generic(wp, xp, n, v));
+    }
+
+    Ok(())
+}
+
 #[doc(hidden)]
 #[allow(unused_must_use)]
 #[cold] #[inline(never)]
-pub unsafe fn dump(lbl: &str, mut p: Limbs, mut n: i32) {
+pub unsafe fn dump(lbl: &str, mut p: Limbs, mut n: usize) {
     use std::io::{self, Write};
     let stdout = io::stdout();
     let mut stdout = stdout.lock();
@@ -277,7 +436,7 @@ mod test {
         (const $nm:ident, $($d:expr),*) => (
             {
                 $nm = [$(Limb($d)),*];
-                let len = $nm.len() as i32;
+                let len = $nm.len();
                 let ptr = unsafe {Limbs::new($nm.as_ptr(), 0, len)};
                 (ptr, len)
             }
@@ -285,11 +444,16 @@ mod test {
         (out $nm:ident, $len:expr) => (
             {
                 $nm = [Limb(0);$len];
-                unsafe {LimbsMut::new($nm.as_mut_ptr(), 0, $len as i32)}
+                unsafe {LimbsMut::new($nm.as_mut_ptr(), 0, $len)}
             }
         );
     }
 
+    #[test]
+    fn test_self_check() {
+        assert_eq!(self_check(), Ok(()));
+    }
+
     #[test]
     fn test_add() {
         let a; let b; let mut c;
@@ -530,9 +694,9 @@ mod test {
         c = [Limb(0); 73];
 
         unsafe {
-            let ap = Limbs::new(&a[0], 0, a.len() as i32);
-            let bp = Limbs::new(&b[0], 0, b.len() as i32);
-            let cp = LimbsMut::new(&mut c[0], 0, c.len() as i32);
+            let ap = Limbs::new(a.as_ptr(), 0, a.len());
+            let bp = Limbs::new(b.as_ptr(), 0, b.len());
+            let cp = LimbsMut::new(c.as_mut_ptr(), 0, c.len());
 
             mul(cp, ap, 43, bp, 30);
         }
@@ -561,9 +725,9 @@ mod test {
         c = [Limb(0); 150];
 
         unsafe {
-            let ap = Limbs::new(&a[0], 0, a.len() as i32);
-            let bp = Limbs::new(&b[0], 0, b.len() as i32);
-            let cp = LimbsMut::new(&mut c[0], 0, c.len() as i32);
+            let ap = Limbs::new(a.as_ptr(), 0, a.len());
+            let bp = Limbs::new(b.as_ptr(), 0, b.len());
+            let cp = LimbsMut::new(c.as_mut_ptr(), 0, c.len());
 
             mul(cp, ap, 124, bp, 26);
         }
@@ -573,6 +737,31 @@ mod test {
         assert_eq!(cp, ep);
     }
 
+    #[test]
+    fn test_sqr_basecase() {
+        use ll::limb::BaseInt;
+
+        // Checked against the general basecase product, for all ones (the largest carries)
+        // and an arbitrary pattern of limbs
+        let k = 0x9E3779B97F4A7C15u64 as BaseInt;
+        for xs in 1..40 {
+            let patterns = [
+                vec![Limb(!0); xs],
+                (0..xs).map(|i| Limb((i as BaseInt + 1).wrapping_mul(k))).collect::<Vec<_>>(),
+            ];
+            for x in patterns.iter() {
+                let mut s = vec![Limb(0); 2 * xs];
+                let mut p = vec![Limb(0); 2 * xs];
+                unsafe {
+                    let xp = Limbs::new(x.as_ptr(), 0, xs);
+                    sqr_basecase(LimbsMut::new(s.as_mut_ptr(), 0, 2 * xs), xp, xs);
+                    mul_basecase(LimbsMut::new(p.as_mut_ptr(), 0, 2 * xs), xp, xs, xp, xs);
+                }
+                assert_eq!(s, p, "xs: {}", xs);
+            }
+        }
+    }
+
     #[test]
     fn test_divrem_1() {
         let a; let mut b;
@@ -631,6 +820,40 @@ mod test {
         assert_eq!(b, [1 << (Limb::BITS - 1), 2 as limb::BaseInt]);
     }
 
+    #[test]
+    fn test_divrem_2() {
+        let d; let mut n; let mut q;
+        let high = !0 ^ (!0 >> 1);
+
+        // (7B^2 + 6B + 5) / (B^2/2)
+        let (dp, _) = make_limbs!(const d, 0, high);
+        let np = make_limbs!(out n, 3);
+        let qp = make_limbs!(out q, 1);
+        n = [Limb(5), Limb(6), Limb(7)];
+
+        unsafe {
+            assert_eq!(divrem_2(qp, 0, np, 3, dp), 0);
+        }
+
+        assert_eq!(q, [14]);
+        assert_eq!(n[..2], [Limb(5), Limb(6)]);
+
+        let d; let mut n; let mut q;
+
+        // (B - 1)B^2 / (B^2/2), with a quotient of two limbs
+        let (dp, _) = make_limbs!(const d, 0, high);
+        let np = make_limbs!(out n, 3);
+        let qp = make_limbs!(out q, 2);
+        n[2] = Limb(!0);
+
+        unsafe {
+            assert_eq!(divrem_2(qp, 1, np, 3, dp), 1);
+        }
+
+        assert_eq!(q, [0, !1]);
+        assert_eq!(n[..2], [Limb(0), Limb(0)]);
+    }
+
     #[test]
     fn test_divrem() {
         let a; let b; let mut q; let mut r;
@@ -19,23 +19,23 @@ use mem;
 use ll::limb_ptr::{Limbs, LimbsMut};
 
 // w <- a^b [m]
-pub unsafe fn modpow(wp: LimbsMut, r_limbs: i32, n: Limbs, nquote0: Limb, a: Limbs, bp: Limbs, bn: i32) {
+pub unsafe fn modpow(wp: LimbsMut, r_limbs: usize, n: Limbs, nquote0: Limb, a: Limbs, bp: Limbs, bn: usize) {
     let k = 6;
 
     let mut tmp = mem::TmpAllocator::new();
-    let t = tmp.allocate((2 * r_limbs + 1) as usize);
-    let scratch_mul = tmp.allocate(2 * r_limbs as usize);
+    let t = tmp.allocate(2 * r_limbs + 1);
+    let scratch_mul = tmp.allocate(2 * r_limbs);
 
     // base ^ 0..2^(k-1)
     let mut table = Vec::with_capacity(1 << k);
-    let mut pow_0 = tmp.allocate(r_limbs as usize);
+    let mut pow_0 = tmp.allocate(r_limbs);
     *pow_0 = Limb(1);
-    let pow_1 = tmp.allocate(r_limbs as usize);
-    ll::copy_incr(a, pow_1, r_limbs as i32);
+    let pow_1 = tmp.allocate(r_limbs);
+    ll::copy_incr(a, pow_1, r_limbs);
     table.push(pow_0);
     table.push(pow_1);
     for _ in 2..(1 << k) {
-        let next = tmp.allocate(r_limbs as usize);
+        let next = tmp.allocate(r_limbs);
         {
             let previous = table.last().unwrap();
             mul(next,
@@ -50,7 +50,7 @@ pub unsafe fn modpow(wp: LimbsMut, r_limbs: i32, n: Limbs, nquote0: Limb, a: Lim
         table.push(next);
     }
 
-    let exp_bit_length = ll::base::num_base_digits(bp, bn, 2) as usize;
+    let exp_bit_length = ll::base::num_base_digits(bp, bn, 2);
     let block_count = (exp_bit_length + k - 1) / k;
     for i in (0..block_count).rev() {
         let mut block_value: usize = 0;
@@ -75,11 +75,28 @@ pub unsafe fn modpow(wp: LimbsMut, r_limbs: i32, n: Limbs, nquote0: Limb, a: Lim
                 scratch_mul);
         }
     }
+
+    // The table and the scratch space hold powers of a possibly secret base
+    wipe(t, 2 * r_limbs + 1);
+    wipe(scratch_mul, 2 * r_limbs);
+    for p in table {
+        wipe(p, r_limbs);
+    }
 }
 
+// Clears scratch space before it is freed, with the `zeroize` feature
+#[cfg(feature = "zeroize")]
+unsafe fn wipe(p: LimbsMut, n: usize) {
+    mem::wipe_limbs(p.as_mut_ptr(), n);
+}
+
+#[cfg(not(feature = "zeroize"))]
+#[inline(always)]
+unsafe fn wipe(_p: LimbsMut, _n: usize) {}
+
 #[inline]
 unsafe fn mul(wp: LimbsMut,
-              r_limbs: i32,
+              r_limbs: usize,
               a: Limbs,
               b: Limbs,
               n: Limbs,
@@ -92,7 +109,7 @@ unsafe fn mul(wp: LimbsMut,
 
 #[inline]
 unsafe fn sqr(wp: LimbsMut,
-              r_limbs: i32,
+              r_limbs: usize,
               a: Limbs,
               n: Limbs,
               nquote0: Limb,
@@ -103,7 +120,7 @@ unsafe fn sqr(wp: LimbsMut,
 }
 
 #[inline]
-pub unsafe fn redc(wp: LimbsMut, r_limbs: i32, n: Limbs, nquote0: Limb, t: LimbsMut) {
+pub unsafe fn redc(wp: LimbsMut, r_limbs: usize, n: Limbs, nquote0: Limb, t: LimbsMut) {
     let mut carry = 0;
     for i in 0..r_limbs {
         carry = 0;
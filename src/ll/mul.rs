@@ -12,21 +12,50 @@
 //    See the License for the specific language governing permissions and
 //    limitations under the License.
 
-#![allow(improper_ctypes)]
-
-use std::cmp::Ordering;
+use std::cmp::{self, Ordering};
 
 use ll;
-use ll::limb::Limb;
+use ll::limb::{BaseInt, Limb};
 use super::{overlap, same_or_separate, same_or_incr};
+#[cfg(feature = "rayon")]
+use super::Shared;
 use mem;
 
 use ll::limb_ptr::{Limbs, LimbsMut};
 
-const TOOM22_THRESHOLD : i32 = 20;
+const TOOM22_THRESHOLD : usize = 20;
+
+// From this many limbs in the smaller input, `mul` multiplies an input 1.5 to 3 times the
+// length of the other with Toom-3.2 or Toom-4.2, instead of cutting it into pieces
+const TOOM32_THRESHOLD : usize = 60;
+
+// `sqr_basecase` only does half the products of `mul_basecase`, so it stays ahead of Toom-2
+// for longer
+const SQR_TOOM2_THRESHOLD : usize = 30;
+
+// Past this many limbs, `sqr` uses Toom-3, with five squares of a third of the size
+const SQR_TOOM3_THRESHOLD : usize = 120;
+
+// Past this many limbs in the smaller input, `mul` and `sqr` use Schönhage-Strassen,
+// or the small-prime NTT with the `ntt` feature
+const FFT_THRESHOLD : usize = 10000;
 
-#[allow(dead_code)]
-unsafe fn mul_1_generic(mut wp: LimbsMut, mut xp: Limbs, mut n: i32, vl: Limb) -> Limb {
+// Below this many limbs, `mullo_n` and `mulhi_n` use the schoolbook method, which
+// only does about half the work of `mul_basecase`
+const SHORT_THRESHOLD : usize = 40;
+
+// Below this many limbs, or for odd sizes, `mulmod_bnm1` folds the full product
+const MULMOD_BNM1_THRESHOLD : usize = 64;
+
+// Up to this many factors, `prod_limbs` multiplies them in one at a time
+const PROD_LIMBS_THRESHOLD : usize = 32;
+
+// The size of the halves from which the sub-products of Toom-2 are computed in
+// parallel, with the `rayon` feature
+#[cfg(feature = "rayon")]
+const PARALLEL_THRESHOLD : usize = 2048;
+
+pub unsafe fn mul_1_generic(mut wp: LimbsMut, mut xp: Limbs, mut n: usize, vl: Limb) -> Limb {
     let mut cl = Limb(0);
     loop {
         let xl = *xp;
@@ -52,9 +81,9 @@ unsafe fn mul_1_generic(mut wp: LimbsMut, mut xp: Limbs, mut n: i32, vl: Limb) -
  *
  * Returns the highest limb of the product
  */
-#[cfg(not(asm))]
+#[cfg(any(feature="fallbacks", not(asm)))]
 #[inline]
-pub unsafe fn mul_1(wp: LimbsMut, xp: Limbs, n: i32, vl: Limb) -> Limb {
+pub unsafe fn mul_1(wp: LimbsMut, xp: Limbs, n: usize, vl: Limb) -> Limb {
     debug_assert!(n > 0);
     debug_assert!(same_or_incr(wp, n, xp, n));
 
@@ -67,20 +96,19 @@ pub unsafe fn mul_1(wp: LimbsMut, xp: Limbs, n: i32, vl: Limb) -> Limb {
  *
  * Returns the highest limb of the product
  */
-#[cfg(asm)]
+#[cfg(all(asm, not(feature="fallbacks")))]
 #[inline]
-pub unsafe fn mul_1(mut wp: LimbsMut, xp: Limbs, n: i32, vl: Limb) -> Limb {
+pub unsafe fn mul_1(wp: LimbsMut, xp: Limbs, n: usize, vl: Limb) -> Limb {
     debug_assert!(n > 0);
     debug_assert!(same_or_incr(wp, n, xp, n));
-    extern "C" {
+    extern "sysv64" {
         fn ramp_mul_1(wp: *mut Limb, xp: *const Limb, n: i32, vl: Limb) -> Limb;
     }
 
-    ramp_mul_1(&mut *wp, &*xp, n, vl)
+    ramp_mul_1(wp.as_mut_ptr(), xp.as_ptr(), n as i32, vl)
 }
 
-#[allow(dead_code)]
-unsafe fn addmul_1_generic(mut wp: LimbsMut, mut xp: Limbs, mut n: i32, vl: Limb) -> Limb {
+pub unsafe fn addmul_1_generic(mut wp: LimbsMut, mut xp: Limbs, mut n: usize, vl: Limb) -> Limb {
     debug_assert!(n > 0);
     debug_assert!(same_or_separate(wp, n, xp, n));
 
@@ -110,9 +138,9 @@ unsafe fn addmul_1_generic(mut wp: LimbsMut, mut xp: Limbs, mut n: i32, vl: Limb
  * Multiplies the `n` least-signficiant digits of `xp` by `vl` and adds them to the `n`
  * least-significant digits of `wp`. Returns the highest limb of the result.
  */
-#[cfg(not(asm))]
+#[cfg(any(feature="fallbacks", not(asm)))]
 #[inline]
-pub unsafe fn addmul_1(wp: LimbsMut, xp: Limbs, n: i32, vl: Limb) -> Limb {
+pub unsafe fn addmul_1(wp: LimbsMut, xp: Limbs, n: usize, vl: Limb) -> Limb {
     addmul_1_generic(wp, xp, n, vl)
 }
 
@@ -120,18 +148,17 @@ pub unsafe fn addmul_1(wp: LimbsMut, xp: Limbs, n: i32, vl: Limb) -> Limb {
  * Multiplies the `n` least-signficiant digits of `xp` by `vl` and adds them to the `n`
  * least-significant digits of `wp`. Returns the highest limb of the result.
  */
-#[cfg(asm)]
+#[cfg(all(asm, not(feature="fallbacks")))]
 #[inline]
-pub unsafe fn addmul_1(mut wp: LimbsMut, xp:  Limbs, n: i32, vl: Limb) -> Limb {
-    extern "C" {
+pub unsafe fn addmul_1(wp: LimbsMut, xp:  Limbs, n: usize, vl: Limb) -> Limb {
+    extern "sysv64" {
         fn ramp_addmul_1(wp: *mut Limb, xp: *const Limb, n: i32, vl: Limb) -> Limb;
     }
 
-    ramp_addmul_1(&mut *wp, &*xp, n, vl)
+    ramp_addmul_1(wp.as_mut_ptr(), xp.as_ptr(), n as i32, vl)
 }
 
-#[allow(dead_code)]
-unsafe fn submul_1_generic(mut wp: LimbsMut, mut xp: Limbs, mut n: i32, vl: Limb) -> Limb {
+pub unsafe fn submul_1_generic(mut wp: LimbsMut, mut xp: Limbs, mut n: usize, vl: Limb) -> Limb {
     debug_assert!(n > 0);
     debug_assert!(same_or_separate(wp, n, xp, n));
 
@@ -161,9 +188,9 @@ unsafe fn submul_1_generic(mut wp: LimbsMut, mut xp: Limbs, mut n: i32, vl: Limb
  * Multiplies the `n` least-signficiant digits of `xp` by `vl` and subtracts them from the `n`
  * least-significant digits of `wp`. Returns the highest limb of the result, adjust for borrow.
  */
-#[cfg(not(asm))]
+#[cfg(any(feature="fallbacks", not(asm)))]
 #[inline]
-pub unsafe fn submul_1(wp: LimbsMut, xp: Limbs, n: i32, vl: Limb) -> Limb {
+pub unsafe fn submul_1(wp: LimbsMut, xp: Limbs, n: usize, vl: Limb) -> Limb {
     submul_1_generic(wp, xp, n, vl)
 }
 
@@ -171,14 +198,27 @@ pub unsafe fn submul_1(wp: LimbsMut, xp: Limbs, n: i32, vl: Limb) -> Limb {
  * Multiplies the `n` least-signficiant digits of `xp` by `vl` and subtracts them from the `n`
  * least-significant digits of `wp`. Returns the highest limb of the result, adjust for borrow.
  */
-#[cfg(asm)]
+#[cfg(all(asm, not(feature="fallbacks")))]
 #[inline]
-pub unsafe fn submul_1(mut wp: LimbsMut, xp: Limbs, n: i32, vl: Limb) -> Limb {
-    extern "C" {
+pub unsafe fn submul_1(wp: LimbsMut, xp: Limbs, n: usize, vl: Limb) -> Limb {
+    extern "sysv64" {
         fn ramp_submul_1(wp: *mut Limb, xp: *const Limb, n: i32, vl: Limb) -> Limb;
     }
 
-    ramp_submul_1(&mut *wp, &*xp, n, vl)
+    ramp_submul_1(wp.as_mut_ptr(), xp.as_ptr(), n as i32, vl)
+}
+
+// The multiplication past `FFT_THRESHOLD`
+#[cfg(not(feature = "ntt"))]
+#[inline]
+unsafe fn mul_big(wp: LimbsMut, xp: Limbs, xs: usize, yp: Limbs, ys: usize) {
+    ll::fft::mul_fft(wp, xp, xs, yp, ys);
+}
+
+#[cfg(feature = "ntt")]
+#[inline]
+unsafe fn mul_big(wp: LimbsMut, xp: Limbs, xs: usize, yp: Limbs, ys: usize) {
+    ll::ntt::mul_ntt(wp, xp, xs, yp, ys);
 }
 
 /**
@@ -186,18 +226,30 @@ pub unsafe fn submul_1(mut wp: LimbsMut, xp: Limbs, n: i32, vl: Limb) -> Limb {
  *
  * `{wp, xs + ys}` must be disjoint from both inputs.
  */
-pub unsafe fn mul(wp: LimbsMut, xp: Limbs, xs: i32, yp: Limbs, ys: i32) {
+pub unsafe fn mul(wp: LimbsMut, xp: Limbs, xs: usize, yp: Limbs, ys: usize) {
     debug_assert!(xs >= ys);
     debug_assert!(ys > 0);
     debug_assert!(!overlap(wp, xs + ys, xp, xs));
     debug_assert!(!overlap(wp, xs + ys, yp, ys));
 
+    // Shifted values and the like have runs of zero limbs at either end, which only move the
+    // product or pad it
+    if *xp == 0 || *yp == 0 || *xp.offset((xs - 1) as isize) == 0 ||
+       *yp.offset((ys - 1) as isize) == 0 {
+        mul_trimmed(wp, xp, xs, yp, ys);
+        return;
+    }
+
     // TODO: Pick between algorithms based on input sizes
     if ys <= TOOM22_THRESHOLD {
         mul_basecase(wp, xp, xs, yp, ys);
+    } else if ys > FFT_THRESHOLD {
+        mul_big(wp, xp, xs, yp, ys);
+    } else if use_toom_x2(xs, ys) {
+        mul_toom_x2(wp, xp, xs, yp, ys);
     } else {
         let mut tmp = mem::TmpAllocator::new();
-        let scratch = tmp.allocate((xs * 2) as usize);
+        let scratch = tmp.allocate(mul_scratch_len(xs, ys));
 
         // Can't use xs >= (ys * 2) because if xs is odd, some other invariants
         // in toom22 don't hold
@@ -209,7 +261,122 @@ pub unsafe fn mul(wp: LimbsMut, xp: Limbs, xs: i32, yp: Limbs, ys: i32) {
     }
 }
 
-unsafe fn mul_basecase(mut wp: LimbsMut, xp: Limbs, xs: i32, mut yp: Limbs, mut ys: i32) {
+// `mul` without the zero limbs at either end of the inputs
+unsafe fn mul_trimmed(wp: LimbsMut, xp: Limbs, xs: usize, yp: Limbs, ys: usize) {
+    let (xl, xn) = trim(xp, xs);
+    let (yl, yn) = trim(yp, ys);
+    if xn == 0 || yn == 0 {
+        ll::zero(wp, xs + ys);
+        return;
+    }
+
+    let low = xl + yl;
+    ll::zero(wp, low);
+    let (w, xp, yp) = (wp.offset(low as isize), xp.offset(xl as isize), yp.offset(yl as isize));
+    if xn >= yn {
+        mul(w, xp, xn, yp, yn);
+    } else {
+        mul(w, yp, yn, xp, xn);
+    }
+    ll::zero(w.offset((xn + yn) as isize), xs + ys - low - xn - yn);
+}
+
+// Returns the number of low zero limbs of `{xp, xs}` and the number of limbs from there to
+// the highest non-zero one, which is 0 if they are all zero
+unsafe fn trim(xp: Limbs, xs: usize) -> (usize, usize) {
+    let mut low = 0;
+    while low < xs && *xp.offset(low as isize) == 0 {
+        low += 1;
+    }
+    if low == xs {
+        (xs, 0)
+    } else {
+        (low, ll::normalize(xp, xs) - low)
+    }
+}
+
+/**
+ * Adds the product of `{xp, xs}` and `{yp, ys}` to `{wp, xs + ys}`, returning the carry out,
+ * which is 0 or 1. Small products are added a row at a time, without a temporary for the
+ * product.
+ *
+ * `{wp, xs + ys}` must be disjoint from both inputs.
+ */
+pub unsafe fn addmul(wp: LimbsMut, xp: Limbs, xs: usize, yp: Limbs, ys: usize) -> Limb {
+    debug_assert!(xs >= ys);
+    debug_assert!(ys > 0);
+    debug_assert!(!overlap(wp, xs + ys, xp, xs));
+    debug_assert!(!overlap(wp, xs + ys, yp, ys));
+
+    if ys < TOOM22_THRESHOLD {
+        // The high limb of each row goes into the limb above it, and the carry from there
+        // into the top of the next row
+        let mut cy = false;
+        for j in 0..ys {
+            let w = wp.offset(j as isize);
+            let hi = addmul_1(w, xp, xs, *yp.offset(j as isize));
+            let top = w.offset(xs as isize);
+            let (sum, c1) = (*top).add_overflow(hi);
+            let (sum, c2) = sum.add_overflow(Limb(cy as BaseInt));
+            *top = sum;
+            cy = c1 || c2;
+        }
+        Limb(cy as BaseInt)
+    } else {
+        let mut tmp = mem::TmpAllocator::new();
+        let t = tmp.allocate(xs + ys);
+        mul(t, xp, xs, yp, ys);
+        ll::add_n(wp, wp.as_const(), t.as_const(), xs + ys)
+    }
+}
+
+/**
+ * Returns the number of limbs of scratch space `mul_rec` needs to multiply an `xs` limb number
+ * by a `ys` limb one, where `xs >= ys`.
+ */
+pub fn mul_scratch_len(xs: usize, ys: usize) -> usize {
+    if ys < TOOM22_THRESHOLD || use_toom_x2(xs, ys) {
+        0
+    } else if (xs * 2) >= (ys * 3) {
+        unbalanced_scratch_len(xs, ys)
+    } else {
+        toom22_scratch_len(xs)
+    }
+}
+
+/**
+ * Returns the number of limbs of scratch space `sqr_rec` needs to square an `xs` limb number.
+ */
+pub fn sqr_scratch_len(xs: usize) -> usize {
+    if xs < SQR_TOOM2_THRESHOLD {
+        0
+    } else {
+        toom22_scratch_len(xs)
+    }
+}
+
+// One product of the halves, the rest of Toom-2 happens in the output
+#[inline]
+fn toom22_scratch_len(xs: usize) -> usize {
+    2 * (xs - (xs >> 1))
+}
+
+// The most scratch space any of the products of `mul_unbalanced` needs
+fn unbalanced_scratch_len(mut xs: usize, ys: usize) -> usize {
+    xs -= ys;
+    while xs >= (ys * 2) {
+        xs -= ys;
+    }
+
+    let last = if xs >= ys {
+        mul_scratch_len(xs, ys)
+    } else {
+        mul_scratch_len(ys, xs)
+    };
+    cmp::max(toom22_scratch_len(ys), last)
+}
+
+pub unsafe fn mul_basecase(mut wp: LimbsMut, xp: Limbs, xs: usize, mut yp: Limbs, mut ys: usize) {
 
     *wp.offset(xs as isize) = ll::mul_1(wp, xp, xs, *yp);
     wp = wp.offset(1);
@@ -217,7 +384,12 @@ unsafe fn mul_basecase(mut wp: LimbsMut, xp: Limbs, xs: i32, mut yp: Limbs, mut
     ys -= 1;
 
     while ys > 0 {
-        *wp.offset(xs as isize) = ll::addmul_1(wp, xp, xs, *yp);
+        // Sparse inputs have zero limbs in the middle too
+        *wp.offset(xs as isize) = if *yp == 0 {
+            Limb(0)
+        } else {
+            ll::addmul_1(wp, xp, xs, *yp)
+        };
 
         wp = wp.offset(1);
         yp = yp.offset(1);
@@ -225,14 +397,16 @@ unsafe fn mul_basecase(mut wp: LimbsMut, xp: Limbs, xs: i32, mut yp: Limbs, mut
     }
 }
 
-// Helper fn
+// Helper fn, `scratch` has to hold `mul_scratch_len(xs, ys)` limbs
 #[inline(always)]
 pub unsafe fn mul_rec(wp: LimbsMut,
-           xp: Limbs, xs: i32,
-           yp: Limbs, ys: i32,
+           xp: Limbs, xs: usize,
+           yp: Limbs, ys: usize,
            scratch: LimbsMut) {
     if ys < TOOM22_THRESHOLD {
         mul_basecase(wp, xp, xs, yp, ys);
+    } else if use_toom_x2(xs, ys) {
+        mul_toom_x2(wp, xp, xs, yp, ys);
     } else if (xs * 2) >= (ys*3) {
         mul_unbalanced(wp, xp, xs, yp, ys, scratch);
     } else {
@@ -240,10 +414,14 @@ pub unsafe fn mul_rec(wp: LimbsMut,
     }
 }
 
-unsafe fn mul_toom22(wp: LimbsMut,
-                     xp: Limbs, xs: i32,
-                     yp: Limbs, ys: i32,
-                     scratch: LimbsMut) {
+/**
+ * Multiplies `{xp, xs}` by `{yp, ys}` with one level of Toom-2, storing the result to
+ * `{wp, xs + ys}`. `scratch` has to hold `2*ceil(xs/2)` limbs, and `xs*2 < ys*3`.
+ */
+pub unsafe fn mul_toom22(wp: LimbsMut,
+                         xp: Limbs, xs: usize,
+                         yp: Limbs, ys: usize,
+                         scratch: LimbsMut) {
     // Split x into x1, x0 where x = x1*(B^n) + x0
     // Split y into y1, y0 where y = y1*(B^n) + y0
     //
@@ -282,8 +460,85 @@ unsafe fn mul_toom22(wp: LimbsMut,
     let x1 = xp.offset(nl as isize); // xh limbs
     let y1 = yp.offset(nl as isize); // yh limbs
 
-    let zx1 = wp; // nl limbs
-    let zy1 = wp.offset(nl as isize); // nl limbs
+    if toom22_parallel(wp, x0, y0, nl, x1, xh, y1, yh, scratch) {
+        return;
+    }
+
+    // Only one of the products is ever kept in `scratch`, the others are computed in the
+    // output, using its bottom limbs as their scratch space. Writing
+    //
+    //    x*y = z0 + (B^n)*z0 + (B^n)*t
+    //    t   = z2 + (B^n)*z2 - z1
+    //
+    // t is built in {wp + nl, nl + xh + yh} out of z1 and z2, before z0 is computed:
+    //
+    // 0      nl+s                            xs+ys
+    // +--------+--------------+-------------+
+    // | scratch|      z1      |             |     scratch = {zx1, zy1}, then z2
+    // +--------+--------------+-------------+
+    //          |                 t          |     scratch = z0
+    //          +----------------------------+
+    //
+    // Where s is one if a product of the halves needs one limb of scratch space more than
+    // nl, zero otherwise.
+
+    let h = xh + yh; // Number of limbs in z2
+    let s = cmp::max(mul_scratch_len(nl, nl), mul_scratch_len(xh, yh)).saturating_sub(nl);
+    debug_assert!(s <= 1 && h >= nl + s,
+                  "assertion failed: s <= 1 && h >= nl + s, xs: {}, ys: {}, s: {}", xs, ys, s);
+
+    let zx1 = scratch; // nl limbs
+    let zy1 = scratch.offset(nl as isize); // nl limbs
+    let z1_neg = toom22_diffs(zx1, x0, x1, xh, zy1, y0, y1, yh, nl);
+
+    // Calculate z1 - 2*nl limbs, then z2 - xh+yh limbs, in the space of the differences
+    let z1 = wp.offset((nl + s) as isize);
+    mul_rec(z1, zx1.as_const(), nl, zy1.as_const(), nl, wp);
+
+    let z2 = scratch;
+    mul_rec(z2, x1, xh, y1, yh, wp);
+
+    let t = wp.offset(nl as isize);
+    let ts = nl + h;
+    if s > 0 {
+        ll::copy_incr(z1.as_const(), t, 2*nl);
+    }
+    ll::zero(t.offset((2*nl) as isize), h - nl);
+
+    // t may be negative until z0 is added, `cy` is the limb above it
+    let mut cy = Limb(0);
+
+    // Subtract or add `z1` depending on the sign of the real result
+    // (we calculate such that it's always positive, easier this way)
+    if !z1_neg {
+        cy = ll::twos_complement(t, t.as_const(), ts) - 1;
+    }
+    cy = cy + ll::add(t, t.as_const(), ts, z2.as_const(), h);
+    cy = cy + ll::add_n(t.offset(nl as isize), t.offset(nl as isize).as_const(), z2.as_const(), h);
+
+    // Calculate z0 - 2*nl limbs. Its scratch space may take up to nl+1 limbs, so the first limb
+    // of t is put aside meanwhile
+    let t0 = *t;
+    let z0 = scratch;
+    mul_rec(z0, x0, nl, y0, nl, wp);
+    *t = t0;
+
+    // {wp, nl} = LOW(z0)
+    // {wp + nl, ts} = t + z0 + HIGH(z0)
+    ll::copy_incr(z0.as_const(), wp, nl);
+    cy = cy + ll::add(t, t.as_const(), ts, z0.as_const(), 2*nl);
+    cy = cy + ll::add(t, t.as_const(), ts, z0.offset(nl as isize).as_const(), nl);
+
+    // Everything fits in the output
+    debug_assert!(cy == 0);
+}
+
+// Computes zx1 = |x0 - x1| and zy1 = |y0 - y1|, `nl` limbs each, returning whether the real
+// z1 is negative.
+#[inline(always)]
+unsafe fn toom22_diffs(zx1: LimbsMut, x0: Limbs, x1: Limbs, xh: usize,
+                       zy1: LimbsMut, y0: Limbs, y1: Limbs, yh: usize,
+                       nl: usize) -> bool {
     let mut z1_neg = false; // Keep track of whether the real z1 is negative
 
     // Calculate zx1
@@ -322,19 +577,61 @@ unsafe fn mul_toom22(wp: LimbsMut,
         }
     }
 
+    z1_neg
+}
+
+// Does the work of `mul_toom22` with the three products computed in parallel, once they are
+// large enough, returning whether it did.
+#[cfg(not(feature = "rayon"))]
+#[inline(always)]
+unsafe fn toom22_parallel(_: LimbsMut, _: Limbs, _: Limbs, _: usize,
+                          _: Limbs, _: usize, _: Limbs, _: usize,
+                          _: LimbsMut) -> bool {
+    false
+}
+
+// Does the work of `mul_toom22` with the three products computed in parallel, once they are
+// large enough, returning whether it did. The products can't share the output as scratch space,
+// so each gets scratch space of its own.
+#[cfg(feature = "rayon")]
+unsafe fn toom22_parallel(wp: LimbsMut, x0: Limbs, y0: Limbs, nl: usize,
+                          x1: Limbs, xh: usize, y1: Limbs, yh: usize,
+                          scratch: LimbsMut) -> bool {
+    if nl < PARALLEL_THRESHOLD {
+        return false;
+    }
+
+    let mut tmp = mem::TmpAllocator::new();
+    let diffs = tmp.allocate(2 * nl);
+    let zx1 = diffs; // nl limbs
+    let zy1 = diffs.offset(nl as isize); // nl limbs
+    let z1_neg = toom22_diffs(zx1, x0, x1, xh, zy1, y0, y1, yh, nl);
+
     let z0 = wp;
     let z1 = scratch;
     let z2 = wp.offset((nl * 2) as isize);
-    let scratch_out = scratch.offset((nl * 2) as isize);
-
-    // Calculate z1 - 2*nl limbs
-    mul_rec(z1, zx1.as_const(), nl, zy1.as_const(), nl, scratch_out);
 
-    // Calculate z0 - 2*nl limbs
-    mul_rec(z0, x0, nl, y0, nl, scratch_out);
-
-    // Calculate z2 - xh+yh limbs
-    mul_rec(z2, x1, xh, y1, yh, scratch_out);
+    let p0 = Shared((z0, x0, y0));
+    let p1 = Shared((z1, zx1.as_const(), zy1.as_const()));
+    let p2 = Shared((z2, x1, y1));
+    ::rayon::join(move || {
+        let (z1, zx1, zy1) = p1.0;
+        let mut tmp = mem::TmpAllocator::new();
+        let scratch = tmp.allocate(mul_scratch_len(nl, nl));
+        mul_rec(z1, zx1, nl, zy1, nl, scratch);
+    }, move || {
+        ::rayon::join(move || {
+            let (z0, x0, y0) = p0.0;
+            let mut tmp = mem::TmpAllocator::new();
+            let scratch = tmp.allocate(mul_scratch_len(nl, nl));
+            mul_rec(z0, x0, nl, y0, nl, scratch);
+        }, move || {
+            let (z2, x1, y1) = p2.0;
+            let mut tmp = mem::TmpAllocator::new();
+            let scratch = tmp.allocate(mul_scratch_len(xh, yh));
+            mul_rec(z2, x1, xh, y1, yh, scratch);
+        });
+    });
 
     // Now {wp, 2*nl} = z0 and {wp + 2*nl, xh+yh} = z2
 
@@ -387,6 +684,8 @@ unsafe fn mul_toom22(wp: LimbsMut,
     // Apply the carries, has to be done last.
     ll::incr(wp.offset((nl * 2) as isize), cy2);
     ll::incr(wp.offset((nl * 3) as isize), cy);
+
+    true
 }
 
 /**
@@ -396,8 +695,8 @@ unsafe fn mul_toom22(wp: LimbsMut,
  * instead of a single limb.
  */
 unsafe fn mul_unbalanced(mut wp: LimbsMut,
-                         mut xp: Limbs, mut xs: i32,
-                         yp: Limbs, ys: i32,
+                         mut xp: Limbs, mut xs: usize,
+                         yp: Limbs, ys: usize,
                          scratch: LimbsMut) {
     debug_assert!(xs  > ys);
 
@@ -407,39 +706,249 @@ unsafe fn mul_unbalanced(mut wp: LimbsMut,
     xp = xp.offset(ys as isize);
     wp = wp.offset(ys as isize);
 
-    // Temporary storage for the output of the multiplication
-    // in the loop, the loop only needs ys*2 limbs, but the last
-    // multiplication needs slightly more than that, but no more
-    // than ys*3
+    // Each product goes straight to the output, over the high half of the
+    // one before, which is put aside and added back
     let mut tmp = mem::TmpAllocator::new();
-    let w_tmp = tmp.allocate((ys * 3) as usize);
+    let w_tmp = tmp.allocate(ys);
 
     while xs >= (ys * 2) {
-        mul_toom22(w_tmp, xp, ys, yp, ys, scratch);
+        ll::copy_incr(wp.as_const(), w_tmp, ys);
+        mul_toom22(wp, xp, ys, yp, ys, scratch);
         xs -= ys;
         xp = xp.offset(ys as isize);
         let cy = ll::add_n(wp, wp.as_const(), w_tmp.as_const(), ys);
-        ll::copy_incr(w_tmp.offset(ys as isize).as_const(),
-                      wp.offset(ys as isize),
-                      ys);
         ll::incr(wp.offset(ys as isize), cy);
 
         wp = wp.offset(ys as isize);
     }
 
+    ll::copy_incr(wp.as_const(), w_tmp, ys);
     if xs >= ys {
-        mul_rec(w_tmp, xp, xs, yp, ys, scratch);
+        mul_rec(wp, xp, xs, yp, ys, scratch);
     } else {
-        mul_rec(w_tmp, yp, ys, xp, xs, scratch);
+        mul_rec(wp, yp, ys, xp, xs, scratch);
     }
 
     let cy = ll::add_n(wp, wp.as_const(), w_tmp.as_const(), ys);
-    ll::copy_incr(w_tmp.offset(ys as isize).as_const(),
-                  wp.offset(ys as isize),
-                  xs);
     ll::incr(wp.offset(ys as isize), cy);
 }
 
+// Whether the shape of the product is one for Toom-3.2 or Toom-4.2, which need no scratch
+// space from the caller
+#[inline]
+fn use_toom_x2(xs: usize, ys: usize) -> bool {
+    ys >= TOOM32_THRESHOLD && (xs * 2) >= (ys * 3) && xs < (ys * 3)
+}
+
+#[inline]
+unsafe fn mul_toom_x2(wp: LimbsMut, xp: Limbs, xs: usize, yp: Limbs, ys: usize) {
+    if xs < (ys * 2) {
+        mul_toom32(wp, xp, xs, yp, ys);
+    } else {
+        mul_toom42(wp, xp, xs, yp, ys);
+    }
+}
+
+/**
+ * Multiplies `{xp, xs}` by `{yp, ys}` with one level of Toom-3.2, storing the result to
+ * `{wp, xs + ys}`. This is for `xs` about 1.5 times `ys`, which would otherwise be cut into
+ * pieces. The temporaries are allocated here, and the four smaller products go through
+ * `mul`. `ys` has to be at least 6, and `ys*3 <= xs*2 < ys*4`.
+ */
+pub unsafe fn mul_toom32(wp: LimbsMut, xp: Limbs, xs: usize, yp: Limbs, ys: usize) {
+    // Splitting x into three pieces and y into two, x = x2*(B^2n) + x1*(B^n) + x0 and
+    // y = y1*(B^n) + y0, the product is
+    //
+    //    x*y = r3*(B^3n) + r2*(B^2n) + r1*(B^n) + r0
+    //
+    // with r0 = x0*y0 and r3 = x2*y1, and the others come from the values at 1 and -1:
+    //
+    //    v1 = (x0 + x1 + x2)*(y0 + y1)
+    //    vm1 = (x0 - x1 + x2)*(y0 - y1)
+    //    r2 = (v1 + vm1)/2 - r0
+    //    r1 = (v1 - vm1)/2 - r3
+    debug_assert!(ys >= 6 && ys * 3 <= xs * 2 && xs < ys * 2);
+    debug_assert!(!overlap(wp, xs + ys, xp, xs));
+    debug_assert!(!overlap(wp, xs + ys, yp, ys));
+
+    let n = cmp::max((xs + 2) / 3, (ys + 1) / 2);
+    let s = xs - 2 * n;
+    let t = ys - n;
+    debug_assert!(s > 0 && s <= n && t > 0 && t <= n);
+    let (x0, x1, x2) = (xp, xp.offset(n as isize), xp.offset((2 * n) as isize));
+    let (y0, y1) = (yp, yp.offset(n as isize));
+
+    // The sums take n + 1 limbs, and their products, like the coefficients, 2n + 2
+    let e = n + 1;
+    let l = 2 * e;
+    let mut tmp = mem::TmpAllocator::new();
+    let a = tmp.allocate(e);
+    let ex = tmp.allocate(e);
+    let ey = tmp.allocate(e);
+    let yh = tmp.allocate(n);
+    let v1 = tmp.allocate(l);
+    let vm1 = tmp.allocate(l);
+
+    ll::copy_incr(y1, yh, t);
+    ll::zero(yh.offset(t as isize), n - t);
+
+    // x0 + x2 +/- x1 and y0 +/- y1
+    *a.offset(n as isize) = ll::add(a, x0, n, x2, s);
+    ll::add(ex, a.as_const(), e, x1, n);
+    *ey.offset(n as isize) = ll::add_n(ey, y0, yh.as_const(), n);
+    mul(v1, ex.as_const(), e, ey.as_const(), e);
+    let neg = abs_sub(ex, a.as_const(), e, x1, n);
+    let neg = abs_sub(ey, y0, n, yh.as_const(), n) != neg;
+    *ey.offset(n as isize) = Limb(0);
+    mul(vm1, ex.as_const(), e, ey.as_const(), e);
+
+    // r0 and r3 go straight to the output
+    mul(wp, x0, n, y0, n);
+    ll::zero(wp.offset((2 * n) as isize), n);
+    if s >= t {
+        mul(wp.offset((3 * n) as isize), x2, s, y1, t);
+    } else {
+        mul(wp.offset((3 * n) as isize), y1, t, x2, s);
+    }
+    let (r0, r3) = (wp.as_const(), wp.offset((3 * n) as isize).as_const());
+
+    // v1 becomes r2 and vm1 r1
+    toom_interpolate_pm1(v1, vm1, l, neg);
+    ll::sub(v1, v1.as_const(), l, r0, 2 * n);
+    ll::sub(vm1, vm1.as_const(), l, r3, s + t);
+
+    add_coeff(wp, xs + ys, n, vm1.as_const(), l);
+    add_coeff(wp, xs + ys, 2 * n, v1.as_const(), l);
+}
+
+/**
+ * Multiplies `{xp, xs}` by `{yp, ys}` with one level of Toom-4.2, storing the result to
+ * `{wp, xs + ys}`. This is for `xs` about twice `ys`. The temporaries are allocated here,
+ * and the five smaller products go through `mul`. `ys` has to be at least 8, and
+ * `ys*2 <= xs < ys*3`.
+ */
+pub unsafe fn mul_toom42(wp: LimbsMut, xp: Limbs, xs: usize, yp: Limbs, ys: usize) {
+    // Splitting x into four pieces and y into two, the product is
+    //
+    //    x*y = r4*(B^4n) + r3*(B^3n) + r2*(B^2n) + r1*(B^n) + r0
+    //
+    // with r0 = x0*y0 and r4 = x3*y1, and the others come from the values at 1, -1 and 2.
+    // Like in `sqr_toom3`, the steps of the interpolation are all non-negative:
+    //
+    //    r2 = (v1 + vm1)/2 - r0 - r4
+    //    r1 + r3 = (v1 - vm1)/2
+    //    r1 + 4*r3 = (v2 - r0 - 4*r2 - 16*r4)/2
+    debug_assert!(ys >= 8 && ys * 2 <= xs && xs < ys * 3);
+    debug_assert!(!overlap(wp, xs + ys, xp, xs));
+    debug_assert!(!overlap(wp, xs + ys, yp, ys));
+
+    let n = cmp::max((xs + 3) / 4, (ys + 1) / 2);
+    let s = xs - 3 * n;
+    let t = ys - n;
+    debug_assert!(s > 0 && s <= n && t > 0 && t <= n);
+    let (x0, x1) = (xp, xp.offset(n as isize));
+    let (x2, x3) = (xp.offset((2 * n) as isize), xp.offset((3 * n) as isize));
+    let (y0, y1) = (yp, yp.offset(n as isize));
+
+    let e = n + 1;
+    let l = 2 * e;
+    let mut tmp = mem::TmpAllocator::new();
+    let a = tmp.allocate(e);
+    let b = tmp.allocate(e);
+    let ex = tmp.allocate(e);
+    let ey = tmp.allocate(e);
+    let yh = tmp.allocate(n);
+    let v1 = tmp.allocate(l);
+    let vm1 = tmp.allocate(l);
+    let v2 = tmp.allocate(l);
+    let w = tmp.allocate(l);
+
+    ll::copy_incr(y1, yh, t);
+    ll::zero(yh.offset(t as isize), n - t);
+
+    // (x0 + x2) +/- (x1 + x3) and y0 +/- y1
+    *a.offset(n as isize) = ll::add_n(a, x0, x2, n);
+    *b.offset(n as isize) = ll::add(b, x1, n, x3, s);
+    ll::add_n(ex, a.as_const(), b.as_const(), e);
+    *ey.offset(n as isize) = ll::add_n(ey, y0, yh.as_const(), n);
+    mul(v1, ex.as_const(), e, ey.as_const(), e);
+    let neg = abs_sub(ex, a.as_const(), e, b.as_const(), e);
+    let neg = abs_sub(ey, y0, n, yh.as_const(), n) != neg;
+    *ey.offset(n as isize) = Limb(0);
+    mul(vm1, ex.as_const(), e, ey.as_const(), e);
+
+    // x0 + 2*x1 + 4*x2 + 8*x3, by Horner's rule, and y0 + 2*y1
+    ll::copy_incr(x3, ex, s);
+    ll::zero(ex.offset(s as isize), e - s);
+    for &xi in [x2, x1, x0].iter() {
+        ll::shl(ex, ex.as_const(), e, 1);
+        ll::add(ex, ex.as_const(), e, xi, n);
+    }
+    *ey.offset(n as isize) = ll::shl(ey, yh.as_const(), n, 1);
+    ll::add(ey, ey.as_const(), e, y0, n);
+    mul(v2, ex.as_const(), e, ey.as_const(), e);
+
+    // r0 and r4 go straight to the output
+    mul(wp, x0, n, y0, n);
+    ll::zero(wp.offset((2 * n) as isize), 2 * n);
+    if s >= t {
+        mul(wp.offset((4 * n) as isize), x3, s, y1, t);
+    } else {
+        mul(wp.offset((4 * n) as isize), y1, t, x3, s);
+    }
+    let (r0, r4) = (wp.as_const(), wp.offset((4 * n) as isize).as_const());
+
+    // v1 becomes r2 and vm1 r1 + r3
+    toom_interpolate_pm1(v1, vm1, l, neg);
+    ll::sub(v1, v1.as_const(), l, r0, 2 * n);
+    ll::sub(v1, v1.as_const(), l, r4, s + t);
+
+    // v2 becomes v2 - r0 - 4*(r2 + 4*r4), then r3
+    ll::copy_incr(r4, w, s + t);
+    ll::zero(w.offset((s + t) as isize), l - s - t);
+    ll::shl(w, w.as_const(), l, 2);
+    ll::add_n(w, w.as_const(), v1.as_const(), l);
+    ll::shl(w, w.as_const(), l, 2);
+    ll::sub(v2, v2.as_const(), l, r0, 2 * n);
+    ll::sub_n(v2, v2.as_const(), w.as_const(), l);
+    ll::shr(v2, v2.as_const(), l, 1);
+    ll::sub_n(v2, v2.as_const(), vm1.as_const(), l);
+    let rem = ll::divrem_1(v2, 0, v2.as_const(), l, Limb(3));
+    debug_assert!(rem == 0);
+
+    // vm1 becomes r1
+    ll::sub_n(vm1, vm1.as_const(), v2.as_const(), l);
+
+    add_coeff(wp, xs + ys, n, vm1.as_const(), l);
+    add_coeff(wp, xs + ys, 2 * n, v1.as_const(), l);
+    add_coeff(wp, xs + ys, 3 * n, v2.as_const(), l);
+}
+
+// Sets `{rp, an}` to the absolute value of `{ap, an} - {bp, bn}`, where `an >= bn`, and
+// returns whether the difference is negative
+unsafe fn abs_sub(rp: LimbsMut, ap: Limbs, an: usize, bp: Limbs, bn: usize) -> bool {
+    if ll::is_zero(ap.offset(bn as isize), an - bn) && ll::cmp(ap, bp, bn) == Ordering::Less {
+        ll::sub_n(rp, bp, ap, bn);
+        ll::zero(rp.offset(bn as isize), an - bn);
+        true
+    } else {
+        ll::sub(rp, ap, an, bp, bn);
+        false
+    }
+}
+
+// Replaces the values of a product at 1 and -1, `{v1, l}` and `{vm1, l}`, the latter negative
+// if `neg`, by the sums of its even and odd coefficients, (v1 + vm1)/2 and (v1 - vm1)/2
+unsafe fn toom_interpolate_pm1(v1: LimbsMut, vm1: LimbsMut, l: usize, neg: bool) {
+    if neg {
+        ll::add_n(vm1, v1.as_const(), vm1.as_const(), l);
+    } else {
+        ll::sub_n(vm1, v1.as_const(), vm1.as_const(), l);
+    }
+    ll::shr(vm1, vm1.as_const(), l, 1);
+    ll::sub_n(v1, v1.as_const(), vm1.as_const(), l);
+}
+
 /**
  * Squares the number in `{xp, xs}` storing the result in `{wp, xs*2}`.
  * This is slightly more efficient than regular multiplication with both
@@ -447,30 +956,99 @@ unsafe fn mul_unbalanced(mut wp: LimbsMut,
  *
  * `{wp, xs*2}` must not overlap with `{xp, xs}`
  */
-pub unsafe fn sqr(wp: LimbsMut, xp: Limbs, xs: i32) {
+pub unsafe fn sqr(wp: LimbsMut, xp: Limbs, xs: usize) {
     debug_assert!(xs > 0);
     debug_assert!(!overlap(wp, 2*xs, xp, xs));
 
-    if xs <= TOOM22_THRESHOLD {
-        mul_basecase(wp, xp, xs, xp, xs);
+    if *xp == 0 || *xp.offset((xs - 1) as isize) == 0 {
+        // As in `mul`, the zero limbs at the ends only move the square
+        let (xl, xn) = trim(xp, xs);
+        ll::zero(wp, 2 * xl);
+        if xn > 0 {
+            sqr(wp.offset((2 * xl) as isize), xp.offset(xl as isize), xn);
+        }
+        ll::zero(wp.offset((2 * (xl + xn)) as isize), 2 * (xs - xl - xn));
+        return;
+    }
+
+    if xs <= SQR_TOOM2_THRESHOLD {
+        sqr_basecase(wp, xp, xs);
+    } else if xs > FFT_THRESHOLD {
+        mul_big(wp, xp, xs, xp, xs);
+    } else if xs > SQR_TOOM3_THRESHOLD {
+        sqr_toom3(wp, xp, xs);
     } else {
         let mut tmp = mem::TmpAllocator::new();
-        let scratch = tmp.allocate((xs * 2) as usize);
+        let scratch = tmp.allocate(sqr_scratch_len(xs));
 
         sqr_toom2(wp, xp, xs, scratch);
     }
 }
 
+/**
+ * Squares `{xp, xs}` with the schoolbook method, storing the result to `{wp, xs*2}`.
+ *
+ * Each product of two different limbs shows up twice in the square, so they are only
+ * computed once, doubled, and the squares of the limbs added.
+ */
+pub unsafe fn sqr_basecase(wp: LimbsMut, xp: Limbs, xs: usize) {
+    debug_assert!(xs > 0);
+    debug_assert!(!overlap(wp, 2*xs, xp, xs));
+
+    if xs == 1 {
+        let (hi, lo) = (*xp).mul_hilo(*xp);
+        *wp = lo;
+        *wp.offset(1) = hi;
+        return;
+    }
+
+    // The sum of x[i]*x[j] for i < j, times B^(i+j)
+    *wp = Limb(0);
+    *wp.offset(xs as isize) = ll::mul_1(wp.offset(1), xp.offset(1), xs - 1, *xp);
+    for i in 1..xs - 1 {
+        let w = wp.offset((2 * i + 1) as isize);
+        *wp.offset((xs + i) as isize) = ll::addmul_1(w, xp.offset((i + 1) as isize), xs - i - 1,
+                                                     *xp.offset(i as isize));
+    }
+
+    // Doubled
+    let w = wp.offset(1);
+    *wp.offset((2 * xs - 1) as isize) = ll::shl(w, w.as_const(), 2 * xs - 2, 1);
+
+    // Plus the squares, x[i]^2 times B^(2i). The carry out of a pair of limbs is at most 2.
+    let mut cy = Limb(0);
+    for i in 0..xs {
+        let x = *xp.offset(i as isize);
+        let (hi, lo) = x.mul_hilo(x);
+        let (w0, w1) = (wp.offset((2 * i) as isize), wp.offset((2 * i + 1) as isize));
+        let (s, c1) = (*w0).add_overflow(lo);
+        let (s, c2) = s.add_overflow(cy);
+        *w0 = s;
+        let (s, c3) = (*w1).add_overflow(hi);
+        let (s, c4) = s.add_overflow(Limb(c1 as BaseInt + c2 as BaseInt));
+        *w1 = s;
+        cy = Limb(c3 as BaseInt + c4 as BaseInt);
+    }
+    debug_assert!(cy == 0);
+}
+
+// Helper fn, `scratch` has to hold `sqr_scratch_len(xs)` limbs
 #[inline(always)]
-pub unsafe fn sqr_rec(wp: LimbsMut, xp: Limbs, xs: i32, scratch: LimbsMut) {
-    if xs < TOOM22_THRESHOLD {
-        mul_basecase(wp, xp, xs, xp, xs);
+pub unsafe fn sqr_rec(wp: LimbsMut, xp: Limbs, xs: usize, scratch: LimbsMut) {
+    if xs < SQR_TOOM2_THRESHOLD {
+        sqr_basecase(wp, xp, xs);
+    } else if xs > SQR_TOOM3_THRESHOLD {
+        sqr_toom3(wp, xp, xs);
     } else {
         sqr_toom2(wp, xp, xs, scratch);
     }
 }
 
-unsafe fn sqr_toom2(wp: LimbsMut, xp: Limbs, xs: i32, scratch: LimbsMut) {
+/**
+ * Squares `{xp, xs}` with one level of Toom-2, storing the result to `{wp, xs*2}`. `scratch`
+ * has to hold `2*ceil(xs/2)` limbs.
+ */
+pub unsafe fn sqr_toom2(wp: LimbsMut, xp: Limbs, xs: usize, scratch: LimbsMut) {
     // This is very similar to regular mul_toom22, however it is slightly more efficient
     // as it can take advantage of the coefficents being the same.
     //
@@ -489,17 +1067,86 @@ unsafe fn sqr_toom2(wp: LimbsMut, xp: Limbs, xs: i32, scratch: LimbsMut) {
     let x0 = xp;
     let x1 = xp.offset(xl as isize);
 
+    if sqr_toom2_parallel(wp, x0, xl, x1, xh, scratch) {
+        return;
+    }
+
+    // As in `mul_toom22`, with t = 2*z1 + (B^n)*z2 built in {wp + xl, xl + 2*xh} and
+    // x*x = z0 + (B^n)*t. Only z2 and z0 go in `scratch`, one after the other.
+    let s = cmp::max(mul_scratch_len(xl, xh), sqr_scratch_len(xh)).saturating_sub(xl);
+    debug_assert!(s <= 1 && s <= xh);
+
+    // Calculate z1 - xs limbs, then z2 - 2*xh limbs
+    let z1 = wp.offset((xl + s) as isize);
+    mul_rec(z1, x0, xl, x1, xh, wp);
+
+    let z2 = scratch;
+    sqr_rec(z2, x1, xh, wp);
+
+    let t = wp.offset(xl as isize);
+    let ts = xl + 2*xh;
+    if s > 0 {
+        ll::copy_incr(z1.as_const(), t, xs);
+    }
+    ll::zero(t.offset(xs as isize), xh);
+
+    // Calculate 2*z1 + (B^n)*z2
+    let cy = ll::add_n(t, t.as_const(), t.as_const(), xs);
+    *t.offset(xs as isize) = cy;
+    ll::add_n(t.offset(xl as isize), t.offset(xl as isize).as_const(), z2.as_const(), 2*xh);
+
+    // Calculate z0 - 2*xl limbs, with the first limb of t put aside, as in `mul_toom22`
+    let t0 = *t;
+    let z0 = scratch;
+    sqr_rec(z0, x0, xl, wp);
+    *t = t0;
+
+    ll::copy_incr(z0.as_const(), wp, xl);
+    ll::add(t, t.as_const(), ts, z0.offset(xl as isize).as_const(), xl);
+}
+
+// Does the work of `sqr_toom2` with the three products computed in parallel, once they are
+// large enough, returning whether it did.
+#[cfg(not(feature = "rayon"))]
+#[inline(always)]
+unsafe fn sqr_toom2_parallel(_: LimbsMut, _: Limbs, _: usize, _: Limbs, _: usize,
+                             _: LimbsMut) -> bool {
+    false
+}
+
+// Does the work of `sqr_toom2` with the three products computed in parallel, once they are
+// large enough, returning whether it did.
+#[cfg(feature = "rayon")]
+unsafe fn sqr_toom2_parallel(wp: LimbsMut, x0: Limbs, xl: usize, x1: Limbs, xh: usize,
+                             scratch: LimbsMut) -> bool {
+    if xl < PARALLEL_THRESHOLD {
+        return false;
+    }
+
+    let xs = xl + xh;
     let z0 = wp;
     let z1 = scratch;
     let z2 = wp.offset((xl * 2) as isize);
-    let scratch_out = scratch.offset((xl * 2) as isize);
 
-    // Calculate z1
-    mul_rec(z1, x0, xl, x1, xh, scratch_out);
-    // Calculate z0
-    sqr_rec(z0, x0, xl, scratch_out);
-    // Calculate z2
-    sqr_rec(z2, x1, xh, scratch_out);
+    let p = Shared((z0, z1, z2, x0, x1));
+    ::rayon::join(move || {
+        let (_, z1, _, x0, x1) = p.0;
+        let mut tmp = mem::TmpAllocator::new();
+        let scratch = tmp.allocate(mul_scratch_len(xl, xh));
+        mul_rec(z1, x0, xl, x1, xh, scratch);
+    }, move || {
+        ::rayon::join(move || {
+            let (z0, _, _, x0, _) = p.0;
+            let mut tmp = mem::TmpAllocator::new();
+            let scratch = tmp.allocate(sqr_scratch_len(xl));
+            sqr_rec(z0, x0, xl, scratch);
+        }, move || {
+            let (_, _, z2, _, x1) = p.0;
+            let mut tmp = mem::TmpAllocator::new();
+            let scratch = tmp.allocate(sqr_scratch_len(xh));
+            sqr_rec(z2, x1, xh, scratch);
+        });
+    });
 
     // Calculate 2*z1
     let mut cy = ll::add_n(z1, z1.as_const(), z1.as_const(), xs);
@@ -509,4 +1156,431 @@ unsafe fn sqr_toom2(wp: LimbsMut, xp: Limbs, xs: i32, scratch: LimbsMut) {
     cy = cy + ll::add_n(wp.offset(xl as isize), wp.offset(xl as isize).as_const(), z1.as_const(), xs);
 
     ll::incr(wp.offset((xl + xs) as isize), cy);
+
+    true
+}
+
+/**
+ * Squares `{xp, xs}` with one level of Toom-3, storing the result to `{wp, xs*2}`. The
+ * temporaries are allocated here, and the five smaller squares go through `sqr`. `xs` has
+ * to be at least 5.
+ */
+pub unsafe fn sqr_toom3(wp: LimbsMut, xp: Limbs, xs: usize) {
+    // Splitting x into three pieces to get x = x2*(B^2n) + x1*(B^n) + x0, its square is
+    //
+    //    x*x = r4*(B^4n) + r3*(B^3n) + r2*(B^2n) + r1*(B^n) + r0
+    //
+    // and the coefficients come from the values of the square at 0, 1, -1, -2 and
+    // infinity:
+    //
+    //    r0 = x0^2, r4 = x2^2
+    //    v1 = (x0 + x1 + x2)^2
+    //    vm1 = (x0 - x1 + x2)^2
+    //    vm2 = (x0 - 2*x1 + 4*x2)^2
+    //
+    // As they are squares, only the absolute values of the sums matter. The coefficients
+    // are all non-negative, and so are the steps of the interpolation:
+    //
+    //    r2 = (v1 + vm1)/2 - r0 - r4
+    //    r1 + r3 = (v1 - vm1)/2
+    //    r1 + 4*r3 = (r0 + 4*r2 + 16*r4 - vm2)/2
+    debug_assert!(xs >= 5);
+    debug_assert!(!overlap(wp, 2*xs, xp, xs));
+
+    let n = (xs + 2) / 3;
+    let s = xs - 2 * n;
+    let (x0, x1, x2) = (xp, xp.offset(n as isize), xp.offset((2 * n) as isize));
+
+    // The sums take n + 1 limbs, and their squares, like the coefficients, 2n + 2
+    let e = n + 1;
+    let l = 2 * e;
+    let mut tmp = mem::TmpAllocator::new();
+    let a = tmp.allocate(e);
+    let b = tmp.allocate(e);
+    let t = tmp.allocate(e);
+    let v1 = tmp.allocate(l);
+    let vm1 = tmp.allocate(l);
+    let vm2 = tmp.allocate(l);
+    let r2 = tmp.allocate(l);
+
+    // x0 + x2 +/- x1
+    *a.offset(n as isize) = ll::add(a, x0, n, x2, s);
+    ll::add(t, a.as_const(), e, x1, n);
+    sqr(v1, t.as_const(), e);
+    if *a.offset(n as isize) != 0 || ll::cmp(a.as_const(), x1, n) != Ordering::Less {
+        ll::sub(t, a.as_const(), e, x1, n);
+    } else {
+        ll::sub_n(t, x1, a.as_const(), n);
+        *t.offset(n as isize) = Limb(0);
+    }
+    sqr(vm1, t.as_const(), e);
+
+    // x0 + 4*x2 - 2*x1
+    ll::copy_incr(x0, a, n);
+    *a.offset(n as isize) = Limb(0);
+    *t.offset(s as isize) = ll::shl(t, x2, s, 2);
+    ll::add(a, a.as_const(), e, t.as_const(), s + 1);
+    *b.offset(n as isize) = ll::shl(b, x1, n, 1);
+    if ll::cmp(a.as_const(), b.as_const(), e) != Ordering::Less {
+        ll::sub_n(t, a.as_const(), b.as_const(), e);
+    } else {
+        ll::sub_n(t, b.as_const(), a.as_const(), e);
+    }
+    sqr(vm2, t.as_const(), e);
+
+    // r0 and r4 go straight to the output
+    sqr(wp, x0, n);
+    ll::zero(wp.offset((2 * n) as isize), 2 * n);
+    sqr(wp.offset((4 * n) as isize), x2, s);
+    let (r0, r4) = (wp.as_const(), wp.offset((4 * n) as isize).as_const());
+
+    ll::add_n(r2, v1.as_const(), vm1.as_const(), l);
+    ll::shr(r2, r2.as_const(), l, 1);
+    ll::sub(r2, r2.as_const(), l, r0, 2 * n);
+    ll::sub(r2, r2.as_const(), l, r4, 2 * s);
+
+    // vm1 becomes r1 + r3
+    ll::sub_n(vm1, v1.as_const(), vm1.as_const(), l);
+    ll::shr(vm1, vm1.as_const(), l, 1);
+
+    // v1 becomes 4*(4*r4 + r2) + r0 - vm2, then r3
+    ll::copy_incr(r4, v1, 2 * s);
+    ll::zero(v1.offset((2 * s) as isize), l - 2 * s);
+    ll::shl(v1, v1.as_const(), l, 2);
+    ll::add_n(v1, v1.as_const(), r2.as_const(), l);
+    ll::shl(v1, v1.as_const(), l, 2);
+    ll::add(v1, v1.as_const(), l, r0, 2 * n);
+    ll::sub_n(v1, v1.as_const(), vm2.as_const(), l);
+    ll::shr(v1, v1.as_const(), l, 1);
+    ll::sub_n(v1, v1.as_const(), vm1.as_const(), l);
+    let rem = ll::divrem_1(v1, 0, v1.as_const(), l, Limb(3));
+    debug_assert!(rem == 0);
+
+    // vm1 becomes r1
+    ll::sub_n(vm1, vm1.as_const(), v1.as_const(), l);
+
+    add_coeff(wp, 2 * xs, n, vm1.as_const(), l);
+    add_coeff(wp, 2 * xs, 2 * n, r2.as_const(), l);
+    add_coeff(wp, 2 * xs, 3 * n, v1.as_const(), l);
+}
+
+// Adds `{cp, cs}` to `{wp, ws}` at limb `off`. The limbs of the coefficient past the end of
+// the output are zero, as the result fits.
+#[inline]
+unsafe fn add_coeff(wp: LimbsMut, ws: usize, off: usize, cp: Limbs, cs: usize) {
+    let n = cmp::min(cs, ws - off);
+    debug_assert!(ll::is_zero(cp.offset(n as isize), cs - n));
+    let w = wp.offset(off as isize);
+    let cy = ll::add(w, w.as_const(), ws - off, cp, n);
+    debug_assert!(cy == 0);
+}
+
+/**
+ * Multiplies `{xp, n}` by `{yp, n}`, storing the `n` low limbs of the product to `{wp, n}`.
+ *
+ * This is the product modulo `B^n`, which is all Montgomery reduction and Newton's
+ * iteration need, for less than the cost of the full product.
+ *
+ * `{wp, n}` must be disjoint from both inputs.
+ */
+pub unsafe fn mullo_n(wp: LimbsMut, xp: Limbs, yp: Limbs, n: usize) {
+    debug_assert!(n > 0);
+    debug_assert!(!overlap(wp, n, xp, n));
+    debug_assert!(!overlap(wp, n, yp, n));
+
+    if n < SHORT_THRESHOLD {
+        mullo_basecase(wp, xp, yp, n);
+        return;
+    }
+
+    // Mulders' short product. Splitting x into x1, x0 to get x = x1*(B^k) + x0, and the
+    // same for y, the low n limbs of x*y are those of
+    //
+    //    x0*y0 + (B^k)*(x1*y0 + x0*y1)
+    //
+    // where only the low l = n - k limbs of the last two products matter, so they are
+    // short products too. With k about 0.7*n, this is cheaper than the full product.
+    let l = n * 3 / 10;
+    let k = n - l;
+
+    let mut tmp = mem::TmpAllocator::new();
+    let p = tmp.allocate(2 * k);
+    let t = tmp.allocate(l);
+
+    mul(p, xp, k, yp, k);
+    ll::copy_incr(p.as_const(), wp, n);
+
+    let w = wp.offset(k as isize);
+    mullo_n(t, xp.offset(k as isize), yp, l);
+    ll::add_n(w, w.as_const(), t.as_const(), l);
+    mullo_n(t, xp, yp.offset(k as isize), l);
+    ll::add_n(w, w.as_const(), t.as_const(), l);
+}
+
+unsafe fn mullo_basecase(wp: LimbsMut, xp: Limbs, yp: Limbs, n: usize) {
+    ll::mul_1(wp, xp, n, *yp);
+    for i in 1..n {
+        ll::addmul_1(wp.offset(i as isize), xp, n - i, *yp.offset(i as isize));
+    }
+}
+
+/**
+ * Multiplies `{xp, n}` by `{yp, n}`, storing an approximation of the `n` high limbs of the
+ * product to `{wp, n}`. The approximation is either the high half of the product, or one
+ * less than it.
+ *
+ * This is what Barrett reduction and the iterations for reciprocals need, as they correct
+ * their estimates anyway.
+ *
+ * `{wp, n}` must be disjoint from both inputs.
+ */
+pub unsafe fn mulhi_n(wp: LimbsMut, xp: Limbs, yp: Limbs, n: usize) {
+    debug_assert!(n > 0);
+    debug_assert!(!overlap(wp, n, xp, n));
+    debug_assert!(!overlap(wp, n, yp, n));
+
+    let mut tmp = mem::TmpAllocator::new();
+    if n >= SHORT_THRESHOLD {
+        // The full product is cheaper than half of the schoolbook one, and exact
+        let p = tmp.allocate(2 * n);
+        mul(p, xp, n, yp, n);
+        ll::copy_incr(p.offset(n as isize).as_const(), wp, n);
+        return;
+    }
+
+    // Only the partial products x[i]*y[j] with i + j >= n - 2 are added up, from the
+    // limb n - 2 of the product. The ones left out sum to less than n*B^(n-1), so at
+    // most one carry into the high half is lost.
+    let t = tmp.allocate(n + 2);
+    ll::zero(t, n + 2);
+    for j in 0..n {
+        let i = n.saturating_sub(j + 2);
+        let off = (i + j + 2 - n) as isize;
+        *t.offset((j + 2) as isize) = ll::addmul_1(t.offset(off), xp.offset(i as isize), n - i,
+                                                     *yp.offset(j as isize));
+    }
+    ll::copy_incr(t.offset(2).as_const(), wp, n);
+}
+
+/**
+ * Computes the middle product of `{xp, xs}` and `{yp, ys}`, the sum of the partial products
+ * `x[i]*y[j]` with `ys - 1 <= i + j <= xs - 1`, shifted down by `ys - 1` limbs, to
+ * `{wp, xs - ys + 3}`. The two extra limbs hold the carries.
+ *
+ * With `xs = 2n - 1` and `ys = n`, these are the `n` limbs in the middle of the product
+ * that a Newton iteration for the reciprocal needs.
+ *
+ * The partial products are summed with the schoolbook method at every size, `xs - ys + 1`
+ * limbs of x for each limb of y. Unlike `mul`, there is no subquadratic algorithm for large
+ * inputs, so for large enough inputs the full product with `mul` is faster.
+ *
+ * `{wp, xs - ys + 3}` must be disjoint from both inputs.
+ */
+pub unsafe fn mulmid(wp: LimbsMut, xp: Limbs, xs: usize, yp: Limbs, ys: usize) {
+    debug_assert!(xs >= ys && ys > 0);
+    debug_assert!(!overlap(wp, xs - ys + 3, xp, xs));
+    debug_assert!(!overlap(wp, xs - ys + 3, yp, ys));
+
+    // Each limb of y meets a window of m limbs of x, lower for the higher limbs of y
+    let m = xs - ys + 1;
+    ll::zero(wp, m + 2);
+    for j in 0..ys {
+        let hi = ll::addmul_1(wp, xp.offset((ys - 1 - j) as isize), m, *yp.offset(j as isize));
+        let (sum, cy) = (*wp.offset(m as isize)).add_overflow(hi);
+        *wp.offset(m as isize) = sum;
+        *wp.offset((m + 1) as isize) = *wp.offset((m + 1) as isize) + cy;
+    }
+}
+
+/**
+ * Multiplies together the `n` limbs of `{vp, n}`, none of which may be zero, storing the
+ * product to `{wp, n}`. Returns the length of the product, without its high zero limbs.
+ *
+ * The limbs are multiplied along a balanced tree, with a single limb at a time only at the
+ * leaves, so the larger products go through the fast algorithms of `mul`.
+ *
+ * `{wp, n}` must be disjoint from `{vp, n}`.
+ */
+pub unsafe fn prod_limbs(wp: LimbsMut, vp: Limbs, n: usize) -> usize {
+    debug_assert!(n > 0);
+    debug_assert!(!overlap(wp, n, vp, n));
+
+    if n <= PROD_LIMBS_THRESHOLD {
+        *wp = *vp;
+        let mut size = 1;
+        for i in 1..n {
+            let v = *vp.offset(i as isize);
+            debug_assert!(v != 0);
+            let hi = mul_1(wp, wp.as_const(), size, v);
+            if hi != 0 {
+                *wp.offset(size as isize) = hi;
+                size += 1;
+            }
+        }
+        return size;
+    }
+
+    let h = n / 2;
+    let mut tmp = mem::TmpAllocator::new();
+    let a = tmp.allocate(h);
+    let b = tmp.allocate(n - h);
+    let an = prod_limbs(a, vp, h);
+    let bn = prod_limbs(b, vp.offset(h as isize), n - h);
+    if an >= bn {
+        mul(wp, a.as_const(), an, b.as_const(), bn);
+    } else {
+        mul(wp, b.as_const(), bn, a.as_const(), an);
+    }
+    ll::zero(wp.offset((an + bn) as isize), n - an - bn);
+    ll::normalize(wp.as_const(), an + bn)
+}
+
+/**
+ * Multiplies `{xp, xs}` by `{yp, ys}` modulo `B^rn - 1`, storing the result to `{rp, rn}`.
+ * The result is fully reduced, less than `B^rn - 1`.
+ *
+ * The inputs may have any length. When `rn` is even, the product is put together from the
+ * products modulo `B^(rn/2) - 1` and `B^(rn/2) + 1`, which cost less than the full one.
+ *
+ * `{rp, rn}` must be disjoint from both inputs.
+ */
+pub unsafe fn mulmod_bnm1(rp: LimbsMut, rn: usize, xp: Limbs, xs: usize, yp: Limbs, ys: usize) {
+    debug_assert!(rn > 0 && xs > 0 && ys > 0);
+    debug_assert!(!overlap(rp, rn, xp, xs));
+    debug_assert!(!overlap(rp, rn, yp, ys));
+
+    let square = xp == yp && xs == ys;
+    let mut tmp = mem::TmpAllocator::new();
+
+    if rn < MULMOD_BNM1_THRESHOLD || rn % 2 == 1 || xs + ys <= rn {
+        // Folding the inputs first keeps the product at most 2*rn limbs
+        let (xp, xs) = if xs > rn {
+            let t = tmp.allocate(rn);
+            fold_bnm1(t, rn, xp, xs);
+            (t.as_const(), rn)
+        } else {
+            (xp, xs)
+        };
+        let (yp, ys) = if square {
+            (xp, xs)
+        } else if ys > rn {
+            let t = tmp.allocate(rn);
+            fold_bnm1(t, rn, yp, ys);
+            (t.as_const(), rn)
+        } else {
+            (yp, ys)
+        };
+
+        let p = tmp.allocate(xs + ys);
+        if square {
+            sqr(p, xp, xs);
+        } else if xs >= ys {
+            mul(p, xp, xs, yp, ys);
+        } else {
+            mul(p, yp, ys, xp, xs);
+        }
+        fold_bnm1(rp, rn, p.as_const(), xs + ys);
+        return;
+    }
+
+    // With h = rn/2, B^rn - 1 = (B^h - 1)*(B^h + 1), and the two factors are coprime. The
+    // product modulo B^h + 1 goes through the ring of `fft`.
+    let h = rn / 2;
+
+    let xm = tmp.allocate(h);
+    fold_bnm1(xm, h, xp, xs);
+    let ym = if square { xm } else { tmp.allocate(h) };
+    if !square {
+        fold_bnm1(ym, h, yp, ys);
+    }
+    let a = tmp.allocate(h + 1);
+    mulmod_bnm1(a, h, xm.as_const(), h, ym.as_const(), h);
+    *a.offset(h as isize) = Limb(0);
+
+    let xq = tmp.allocate(h + 1);
+    fold_bnp1(xq, h, xp, xs);
+    let yq = if square { xq } else { tmp.allocate(h + 1) };
+    if !square {
+        fold_bnp1(yq, h, yp, ys);
+    }
+    let b = tmp.allocate(h + 1);
+    let prod = tmp.allocate(2 * h);
+    ll::fft::mul(b, xq.as_const(), yq.as_const(), h, prod);
+
+    // The product is a + (B^h - 1)*k, where k = (a - b)/2 modulo B^h + 1, as B^h - 1 is -2
+    // there
+    let k = b;
+    ll::fft::sub(k, a.as_const(), b.as_const(), h);
+    if (*k).0 & 1 == 1 {
+        // Adding B^h + 1 makes it even, and fits as k is at most B^h
+        ll::add_1(k, k.as_const(), h + 1, Limb(1));
+        *k.offset(h as isize) = *k.offset(h as isize) + 1;
+    }
+    ll::shr(k, k.as_const(), h + 1, 1);
+
+    // a + B^h*k - k, with B^2h = 1 for the top limb of k, which is 0 or 1
+    ll::copy_incr(a.as_const(), rp, h);
+    ll::copy_incr(k.as_const(), rp.offset(h as isize), h);
+    let top = *k.offset(h as isize);
+    if ll::sub(rp, rp.as_const(), rn, k.as_const(), h + 1) != 0 {
+        // B^2h = 1, and this can't borrow again as k is at most B^h
+        ll::sub_1(rp, rp.as_const(), rn, Limb(1));
+    }
+    if ll::add_1(rp, rp.as_const(), rn, top) != 0 {
+        // rp was B^2h - 1, that is 0
+        *rp = Limb(1);
+    }
+    reduce_bnm1(rp, rn);
+}
+
+// Sets `{rp, n}` to `{xp, xs}` modulo `B^n - 1`, fully reduced
+unsafe fn fold_bnm1(rp: LimbsMut, n: usize, xp: Limbs, xs: usize) {
+    let m = cmp::min(n, xs);
+    ll::copy_incr(xp, rp, m);
+    ll::zero(rp.offset(m as isize), n - m);
+
+    let mut cy = Limb(0);
+    let mut off = n;
+    while off < xs {
+        let len = cmp::min(n, xs - off);
+        cy = cy + ll::add(rp, rp.as_const(), n, xp.offset(off as isize), len);
+        off += n;
+    }
+    // B^n = 1
+    while cy != 0 {
+        cy = ll::add_1(rp, rp.as_const(), n, cy);
+    }
+    reduce_bnm1(rp, n);
+}
+
+// Replaces `B^n - 1` with 0 in `{rp, n}`
+unsafe fn reduce_bnm1(rp: LimbsMut, n: usize) {
+    if (0..n).all(|i| *rp.offset(i as isize) == !0) {
+        ll::zero(rp, n);
+    }
+}
+
+// Sets `{rp, n + 1}` to `{xp, xs}` modulo `B^n + 1`, normalized as in `fft`
+unsafe fn fold_bnp1(rp: LimbsMut, n: usize, xp: Limbs, xs: usize) {
+    let m = cmp::min(n, xs);
+    ll::copy_incr(xp, rp, m);
+    ll::zero(rp.offset(m as isize), n + 1 - m);
+
+    let mut tmp = mem::TmpAllocator::new();
+    let t = tmp.allocate(n + 1);
+    // B^n = -1, so the pieces alternate in sign
+    let mut neg = true;
+    let mut off = n;
+    while off < xs {
+        let len = cmp::min(n, xs - off);
+        ll::copy_incr(xp.offset(off as isize), t, len);
+        ll::zero(t.offset(len as isize), n + 1 - len);
+        if neg {
+            ll::fft::sub(rp, rp.as_const(), t.as_const(), n);
+        } else {
+            ll::fft::add(rp, rp.as_const(), t.as_const(), n);
+        }
+        neg = !neg;
+        off += n;
+    }
 }
@@ -0,0 +1,364 @@
+// Copyright 2015 The Ramp Developers
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Multiplication through number-theoretic transforms modulo three small
+//! primes, an alternative to Schönhage-Strassen for the largest products.
+//!
+//! Each limb of the inputs is a coefficient of a polynomial, and the product
+//! of the polynomials is computed modulo three primes below 2^63, of the form
+//! `c*2^k + 1` with `k >= 55`, so they have roots of unity of any power of 2
+//! order up to `2^55`. A coefficient of the product is less than
+//! `len * 2^(2*BITS)`, far less than the product of the primes, so it is
+//! recovered exactly with the Chinese remainder theorem, then the
+//! coefficients are added up with their carries.
+//!
+//! Unlike the transforms of `fft`, these work on single words, with the same
+//! butterfly throughout, which makes them simpler to vectorize. The
+//! arithmetic modulo the primes is Montgomery's, with `R = 2^64`.
+
+use ll;
+use ll::limb::{BaseInt, Limb};
+use ll::limb_ptr::{Limbs, LimbsMut};
+#[cfg(feature = "rayon")]
+use super::Shared;
+
+// The primes, with a generator of their multiplicative group
+const PRIMES: [(u64, u64); 3] = [
+    (87 << 56 | 1, 5),
+    (197 << 55 | 1, 3),
+    (131 << 55 | 1, 3),
+];
+
+// The longest transform the primes allow
+const MAX_LEN: u64 = 1 << 55;
+
+// The integers modulo an odd p < 2^63. Values are kept below p.
+struct Modulus {
+    p: u64,
+    // -p^-1 modulo 2^64
+    pinv: u64,
+    // R^2 modulo p
+    r2: u64,
+}
+
+impl Modulus {
+    fn new(p: u64) -> Modulus {
+        // Newton's iteration doubles the correct low bits of the inverse,
+        // and p is its own inverse modulo 8
+        let mut inv = p;
+        for _ in 0..5 {
+            inv = inv.wrapping_mul(2u64.wrapping_sub(p.wrapping_mul(inv)));
+        }
+        let r = ((1u128 << 64) % p as u128) as u64;
+        Modulus {
+            p: p,
+            pinv: inv.wrapping_neg(),
+            r2: ((r as u128 * r as u128) % p as u128) as u64,
+        }
+    }
+
+    // a*b/R modulo p, for any a if b < p
+    #[inline]
+    fn mul(&self, a: u64, b: u64) -> u64 {
+        let t = a as u128 * b as u128;
+        let m = (t as u64).wrapping_mul(self.pinv);
+        // Less than 2^65*p, so no overflow, and the result is less than 2p
+        let u = ((t + m as u128 * self.p as u128) >> 64) as u64;
+        if u >= self.p { u - self.p } else { u }
+    }
+
+    #[inline]
+    fn add(&self, a: u64, b: u64) -> u64 {
+        let s = a + b;
+        if s >= self.p { s - self.p } else { s }
+    }
+
+    #[inline]
+    fn sub(&self, a: u64, b: u64) -> u64 {
+        if a >= b { a - b } else { a + (self.p - b) }
+    }
+
+    // a*R modulo p, for any a
+    #[inline]
+    fn to_mont(&self, a: u64) -> u64 {
+        self.mul(a, self.r2)
+    }
+
+    // a^e, both in Montgomery form
+    fn pow(&self, a: u64, mut e: u64) -> u64 {
+        let mut r = self.to_mont(1);
+        let mut b = a;
+        while e > 0 {
+            if e & 1 == 1 {
+                r = self.mul(r, b);
+            }
+            b = self.mul(b, b);
+            e >>= 1;
+        }
+        r
+    }
+
+    // a^-1, both in Montgomery form
+    fn inv(&self, a: u64) -> u64 {
+        self.pow(a, self.p - 2)
+    }
+}
+
+/**
+ * Multiplies `{xp, xs}` by `{yp, ys}`, storing the result to `{wp, xs + ys}`, with
+ * number-theoretic transforms modulo three primes. Squares when both inputs are
+ * the same.
+ *
+ * `{wp, xs + ys}` must be disjoint from both inputs.
+ */
+pub unsafe fn mul_ntt(wp: LimbsMut, xp: Limbs, xs: usize, yp: Limbs, ys: usize) {
+    debug_assert!(xs >= ys && ys > 0);
+    debug_assert!(!ll::overlap(wp, xs + ys, xp, xs));
+    debug_assert!(!ll::overlap(wp, xs + ys, yp, ys));
+
+    let rn = xs + ys;
+    // The product has rn - 1 coefficients, which mustn't wrap around
+    let len = (rn - 1).next_power_of_two();
+    assert!(len as u64 <= MAX_LEN, "product too long for the NTT");
+    let sqr = xp == yp && xs == ys;
+
+    let ms = [Modulus::new(PRIMES[0].0), Modulus::new(PRIMES[1].0), Modulus::new(PRIMES[2].0)];
+    let mut res = [vec![0; len], vec![0; len], vec![0; len]];
+    convolve_all(&ms, &mut res, xp, xs, yp, ys, sqr);
+
+    let crt = Crt::new(&ms);
+    let mut acc = [0u64; 3];
+    for i in 0..rn {
+        if i < rn - 1 {
+            let c = crt.combine(res[0][i], res[1][i], res[2][i]);
+            let mut cy = 0;
+            for j in 0..3 {
+                let s = acc[j] as u128 + c[j] as u128 + cy as u128;
+                acc[j] = s as u64;
+                cy = (s >> 64) as u64;
+            }
+            debug_assert!(cy == 0);
+        }
+        *wp.offset(i as isize) = Limb(acc[0] as BaseInt);
+        shr_limb(&mut acc);
+    }
+    debug_assert!(acc == [0, 0, 0]);
+}
+
+// Sets `res[i]` to the coefficients of the product modulo the `i`th prime
+#[cfg(not(feature = "rayon"))]
+unsafe fn convolve_all(ms: &[Modulus; 3], res: &mut [Vec<u64>; 3],
+                       xp: Limbs, xs: usize, yp: Limbs, ys: usize, sqr: bool) {
+    let mut b = if sqr { Vec::new() } else { vec![0; res[0].len()] };
+    for i in 0..3 {
+        convolve(&ms[i], PRIMES[i].1, &mut res[i], &mut b, xp, xs, yp, ys, sqr);
+    }
+}
+
+// Sets `res[i]` to the coefficients of the product modulo the `i`th prime, with
+// the three primes on rayon's thread pool
+#[cfg(feature = "rayon")]
+unsafe fn convolve_all(ms: &[Modulus; 3], res: &mut [Vec<u64>; 3],
+                       xp: Limbs, xs: usize, yp: Limbs, ys: usize, sqr: bool) {
+    use rayon::prelude::*;
+    let inputs = Shared((xp, yp));
+    res[..].par_iter_mut().enumerate().for_each(|(i, a)| {
+        let (xp, yp) = inputs.0;
+        let mut b = if sqr { Vec::new() } else { vec![0; a.len()] };
+        convolve(&ms[i], PRIMES[i].1, a, &mut b, xp, xs, yp, ys, sqr);
+    });
+}
+
+// Sets `a` to the coefficients of the product modulo `m.p`, where `g`
+// generates the group of the prime. `b` is scratch space of the same length,
+// unused when squaring.
+unsafe fn convolve(m: &Modulus, g: u64, a: &mut [u64], b: &mut [u64],
+                   xp: Limbs, xs: usize, yp: Limbs, ys: usize, sqr: bool) {
+    let len = a.len();
+
+    // w^j and w^-j, for a root w of order len, in Montgomery form
+    let w = m.pow(m.to_mont(g), (m.p - 1) / len as u64);
+    let w_inv = m.inv(w);
+    let one = m.to_mont(1);
+    let mut roots = Vec::with_capacity(len / 2);
+    let mut inv_roots = Vec::with_capacity(len / 2);
+    let (mut r, mut ri) = (one, one);
+    for _ in 0..len / 2 {
+        roots.push(r);
+        inv_roots.push(ri);
+        r = m.mul(r, w);
+        ri = m.mul(ri, w_inv);
+    }
+
+    // The inputs go in Montgomery form, so the pointwise products do too
+    load(m, a, xp, xs);
+    fft(m, a, &roots);
+    if sqr {
+        for i in 0..len {
+            a[i] = m.mul(a[i], a[i]);
+        }
+    } else {
+        load(m, b, yp, ys);
+        fft(m, b, &roots);
+        for i in 0..len {
+            a[i] = m.mul(a[i], b[i]);
+        }
+    }
+    ifft(m, a, &inv_roots);
+
+    // Dividing by the length and leaving Montgomery form at once
+    let scale = m.mul(m.inv(m.to_mont(len as u64)), 1);
+    for i in 0..xs + ys - 1 {
+        a[i] = m.mul(a[i], scale);
+    }
+}
+
+unsafe fn load(m: &Modulus, a: &mut [u64], xp: Limbs, xs: usize) {
+    for i in 0..xs {
+        a[i] = m.to_mont((*xp.offset(i as isize)).0 as u64);
+    }
+    for i in xs..a.len() {
+        a[i] = 0;
+    }
+}
+
+// The forward transform, decimation in frequency. The result is in bit
+// reversed order, which `ifft` takes.
+fn fft(m: &Modulus, a: &mut [u64], roots: &[u64]) {
+    let len = a.len();
+    let mut half = len / 2;
+    while half >= 1 {
+        // roots[j * step] is the jth power of a (2*half)th root of unity
+        let step = len / (2 * half);
+        let mut start = 0;
+        while start < len {
+            for j in 0..half {
+                let (x, y) = (a[start + j], a[start + j + half]);
+                a[start + j] = m.add(x, y);
+                a[start + j + half] = m.mul(m.sub(x, y), roots[j * step]);
+            }
+            start += 2 * half;
+        }
+        half /= 2;
+    }
+}
+
+// The inverse transform, decimation in time, without the division by the
+// length
+fn ifft(m: &Modulus, a: &mut [u64], inv_roots: &[u64]) {
+    let len = a.len();
+    let mut half = 1;
+    while half < len {
+        let step = len / (2 * half);
+        let mut start = 0;
+        while start < len {
+            for j in 0..half {
+                let x = a[start + j];
+                let t = m.mul(a[start + j + half], inv_roots[j * step]);
+                a[start + j] = m.add(x, t);
+                a[start + j + half] = m.sub(x, t);
+            }
+            start += 2 * half;
+        }
+        half *= 2;
+    }
+}
+
+// Garner's recombination of residues modulo the three primes
+struct Crt<'a> {
+    ms: &'a [Modulus; 3],
+    // p0^-1 modulo p1, p0 modulo p2 and (p0*p1)^-1 modulo p2, in Montgomery
+    // form
+    inv01: u64,
+    p0_2: u64,
+    inv012: u64,
+    // p0*p1
+    p01: u128,
+}
+
+impl<'a> Crt<'a> {
+    fn new(ms: &'a [Modulus; 3]) -> Crt<'a> {
+        let (p0, p1) = (ms[0].p, ms[1].p);
+        let p0_2 = ms[2].to_mont(p0 % ms[2].p);
+        Crt {
+            ms: ms,
+            inv01: ms[1].inv(ms[1].to_mont(p0 % ms[1].p)),
+            p0_2: p0_2,
+            inv012: ms[2].inv(ms[2].mul(p0_2, ms[2].to_mont(p1 % ms[2].p))),
+            p01: p0 as u128 * p1 as u128,
+        }
+    }
+
+    // The value less than p0*p1*p2 with the residues r0, r1 and r2, as three
+    // words from the least significant
+    fn combine(&self, r0: u64, r1: u64, r2: u64) -> [u64; 3] {
+        let (m1, m2) = (&self.ms[1], &self.ms[2]);
+        // x = r0 + v1*p0 + v2*p0*p1
+        let v1 = m1.mul(m1.sub(r1, r0 % m1.p), self.inv01);
+        let t = m2.sub(r2, r0 % m2.p);
+        let t = m2.sub(t, m2.mul(v1 % m2.p, self.p0_2));
+        let v2 = m2.mul(t, self.inv012);
+
+        // Less than p0*p1
+        let low = r0 as u128 + v1 as u128 * self.ms[0].p as u128;
+        let lo = v2 as u128 * (self.p01 as u64) as u128;
+        let hi = v2 as u128 * (self.p01 >> 64);
+        let (w0, c) = (lo as u64).overflowing_add(low as u64);
+        let mid = (lo >> 64) + (hi as u64) as u128 + (low >> 64) + c as u128;
+        [w0, mid as u64, ((hi >> 64) + (mid >> 64)) as u64]
+    }
+}
+
+// Shifts `acc` right by a limb
+fn shr_limb(acc: &mut [u64; 3]) {
+    if Limb::BITS == 64 {
+        acc[0] = acc[1];
+        acc[1] = acc[2];
+        acc[2] = 0;
+    } else {
+        let s = Limb::BITS as u32;
+        acc[0] = acc[0].wrapping_shr(s) | acc[1].wrapping_shl(64 - s);
+        acc[1] = acc[1].wrapping_shr(s) | acc[2].wrapping_shl(64 - s);
+        acc[2] = acc[2].wrapping_shr(s);
+    }
+}
+
+#[test]
+fn matches_mul() {
+    use rand::{self, Rng};
+    let mut rng = rand::thread_rng();
+
+    let sizes = [(1, 1), (2, 1), (5, 3), (17, 17), (40, 21), (64, 64), (333, 160), (700, 500)];
+    for &(xs, ys) in sizes.iter() {
+        for &ones in [true, false].iter() {
+            let mut limb = || Limb(if ones { !0 } else { rng.gen() });
+            let x: Vec<Limb> = (0..xs).map(|_| limb()).collect();
+            let y: Vec<Limb> = (0..ys).map(|_| limb()).collect();
+            let mut w = vec![Limb(0); 2 * xs];
+            let mut expected = vec![Limb(0); 2 * xs];
+            unsafe {
+                let (xp, yp) = (Limbs::new(x.as_ptr(), 0, xs), Limbs::new(y.as_ptr(), 0, ys));
+                let wp = LimbsMut::new(w.as_mut_ptr(), 0, 2 * xs);
+                let ep = LimbsMut::new(expected.as_mut_ptr(), 0, 2 * xs);
+                ll::mul(ep, xp, xs, yp, ys);
+                mul_ntt(wp, xp, xs, yp, ys);
+                assert!(w[..xs + ys] == expected[..xs + ys], "xs: {}, ys: {}", xs, ys);
+
+                ll::sqr(ep, xp, xs);
+                mul_ntt(wp, xp, xs, xp, xs);
+                assert!(w == expected, "xs: {}", xs);
+            }
+        }
+    }
+}
@@ -27,7 +27,7 @@ use ll::limb_ptr::{Limbs, LimbsMut};
  * `{ap, an}` must not be zero.
  * `exp` must be greater than 2
  */
-pub unsafe fn pow(mut wp: LimbsMut, mut ap: Limbs, mut an: i32, mut exp: u32) {
+pub unsafe fn pow(mut wp: LimbsMut, mut ap: Limbs, mut an: usize, mut exp: u32) {
     debug_assert!(exp > 2);
     debug_assert!(!ll::is_zero(ap, an));
 
@@ -43,11 +43,11 @@ pub unsafe fn pow(mut wp: LimbsMut, mut ap: Limbs, mut an: i32, mut exp: u32) {
         ap = ap.offset(1);
         an -= 1;
         wp = wp.offset(exp as isize);
-        wn -= exp as i32;
+        wn -= exp as usize;
     }
     let trailing = (*ap).trailing_zeros() as u32;
 
-    let sz = wn as usize;
+    let sz = wn;
 
     // An extra limb of scratch space is needed because the length
     // estimation is precise: if you're doing something like x**7, the
@@ -119,25 +119,19 @@ pub unsafe fn pow(mut wp: LimbsMut, mut ap: Limbs, mut an: i32, mut exp: u32) {
 
 /// Calculates the number of limbs required to store the result of taking
 /// `{xp, xn}` to the power of `exp`
-pub unsafe fn num_pow_limbs(xp: Limbs, xn: i32, exp: u32) -> i32 {
-    // This is a better approximation of log_b(x^e) than simply using the
-    // the number of digits, n.
-    // Instead it uses the most significant digit, a, to calculate
-    // e*log_b(a*b^(n-1)), which is e*(log_b(a) + n - 1)
-    let n = xn - 1;
-    let high_limb = *xp.offset(n as isize);
-
-    // Calculate log_2(a), this is because floor(log_b(a)) will always be
-    // 0
-    let lg2 = Limb::BITS as u32 - high_limb.leading_zeros() as u32;
-
-    // Get e*log_2(a)
-    let lg2e = exp as i32 * lg2 as i32;
-
-    // Now convert it to log_b using the formula
-    // log_a(x) = log_b(x) / log_b(a)
-    let elog_b = lg2e / Limb::BITS as i32;
-
-    // Add a final 1 to account for the error in the estimate
-    elog_b + (exp as i32 * n) + 1
+///
+/// If `x` has `k` bits, `x^exp` has more than `exp * (k - 1)` and at most
+/// `exp * k` bits. This returns the limbs for `exp * k` bits, which is exact for
+/// powers of two and otherwise less than `exp` bits too many.
+///
+/// Panics if the result would need more than `i32::MAX` limbs.
+pub unsafe fn num_pow_limbs(xp: Limbs, xn: usize, exp: u32) -> usize {
+    let high_limb = *xp.offset((xn - 1) as isize);
+    let bits = (xn as u64) * Limb::BITS as u64 - high_limb.leading_zeros() as u64;
+
+    // Neither product can overflow, `bits` is less than 2^37 and `exp` less
+    // than 2^32
+    let limbs = (bits * exp as u64 + Limb::BITS as u64 - 1) / Limb::BITS as u64;
+    assert!(limbs <= ::std::i32::MAX as u64, "power too large");
+    limbs as usize
 }
@@ -0,0 +1,572 @@
+// Copyright 2015 The Ramp Developers
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Safe wrappers around the low-level operations, working on slices of limbs.
+//!
+//! Each function checks that its output is large enough for the result and
+//! panics otherwise, then calls the matching function in `ll`. Outputs are
+//! `&mut` slices, so the borrow checker already rules out an output
+//! overlapping an input. Operations that are commonly done in place have a
+//! separate `_assign` form.
+//!
+//! Numbers are stored least-significant limb first, as everywhere else in
+//! `ll`, and don't need to be normalized: high zero limbs are allowed in both
+//! inputs and outputs.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use framp::ll::limb::Limb;
+//! use framp::ll::safe;
+//!
+//! let x = [Limb(!0), Limb(1)];
+//! let y = [Limb(2)];
+//! let mut w = [Limb(0); 3];
+//! safe::mul(&mut w, &x, &y);
+//! assert_eq!(w, [Limb(!1), Limb(3), Limb(0)]);
+//! ```
+
+use std::cmp::{self, Ordering};
+use std::i32;
+
+use ll;
+use ll::limb::Limb;
+use ll::limb_ptr::{Limbs, LimbsMut};
+
+// The assembly routines take their sizes as `i32`
+fn len(x: &[Limb]) -> usize {
+    assert!(x.len() <= i32::MAX as usize, "too many limbs");
+    x.len()
+}
+
+fn limbs(x: &[Limb]) -> Limbs {
+    unsafe { Limbs::new(x.as_ptr(), 0, len(x)) }
+}
+
+fn limbs_mut(x: &mut [Limb]) -> LimbsMut {
+    let n = len(x);
+    unsafe { LimbsMut::new(x.as_mut_ptr(), 0, n) }
+}
+
+// The length of `x` without its high zero limbs
+fn trimmed(x: &[Limb]) -> usize {
+    x.iter().rposition(|&l| l != 0).map_or(0, |i| i + 1)
+}
+
+fn check_output(w: &[Limb], n: usize) {
+    assert!(w.len() >= n, "output has {} limbs, but {} are needed", w.len(), n);
+}
+
+/// Adds `x` and `y`, storing the result in the first `max(x.len(), y.len())`
+/// limbs of `w`. Returns the carry.
+///
+/// # Panics
+///
+/// Panics if `w` is shorter than the longer input.
+pub fn add(w: &mut [Limb], x: &[Limb], y: &[Limb]) -> Limb {
+    let (x, y) = if x.len() >= y.len() { (x, y) } else { (y, x) };
+    check_output(w, x.len());
+    if y.is_empty() {
+        w[..x.len()].copy_from_slice(x);
+        return Limb(0);
+    }
+    unsafe { ll::add(limbs_mut(w), limbs(x), len(x), limbs(y), len(y)) }
+}
+
+/// Subtracts `y` from `x`, storing the result in the first `x.len()` limbs
+/// of `w`. Returns the borrow, which is one if `y` is greater than `x`.
+///
+/// # Panics
+///
+/// Panics if `y` is longer than `x` or `w` is shorter than `x`.
+pub fn sub(w: &mut [Limb], x: &[Limb], y: &[Limb]) -> Limb {
+    assert!(x.len() >= y.len(), "subtrahend is longer than minuend");
+    check_output(w, x.len());
+    if y.is_empty() {
+        w[..x.len()].copy_from_slice(x);
+        return Limb(0);
+    }
+    unsafe { ll::sub(limbs_mut(w), limbs(x), len(x), limbs(y), len(y)) }
+}
+
+/// Adds `y` to `w` in place. Returns the carry.
+///
+/// # Panics
+///
+/// Panics if `y` is longer than `w`.
+pub fn add_assign(w: &mut [Limb], y: &[Limb]) -> Limb {
+    assert!(w.len() >= y.len(), "addend is longer than output");
+    if y.is_empty() {
+        return Limb(0);
+    }
+    let wp = limbs_mut(w);
+    unsafe { ll::add(wp, wp.as_const(), len(w), limbs(y), len(y)) }
+}
+
+/// Subtracts `y` from `w` in place. Returns the borrow.
+///
+/// # Panics
+///
+/// Panics if `y` is longer than `w`.
+pub fn sub_assign(w: &mut [Limb], y: &[Limb]) -> Limb {
+    assert!(w.len() >= y.len(), "subtrahend is longer than output");
+    if y.is_empty() {
+        return Limb(0);
+    }
+    let wp = limbs_mut(w);
+    unsafe { ll::sub(wp, wp.as_const(), len(w), limbs(y), len(y)) }
+}
+
+/// Multiplies `x` by `y`, storing the product in the first
+/// `x.len() + y.len()` limbs of `w`.
+///
+/// # Panics
+///
+/// Panics if `w` is shorter than `x.len() + y.len()`.
+pub fn mul(w: &mut [Limb], x: &[Limb], y: &[Limb]) {
+    let (x, y) = if x.len() >= y.len() { (x, y) } else { (y, x) };
+    let n = x.len() + y.len();
+    check_output(w, n);
+    if y.is_empty() {
+        for l in &mut w[..n] {
+            *l = Limb(0);
+        }
+        return;
+    }
+    unsafe { ll::mul(limbs_mut(w), limbs(x), len(x), limbs(y), len(y)) }
+}
+
+/// Adds the product of `x` and `y` to `w`, without a full temporary for the
+/// product when it is small. Returns the carry out of `w`.
+///
+/// # Panics
+///
+/// Panics if `w` is shorter than `x.len() + y.len()`.
+pub fn addmul(w: &mut [Limb], x: &[Limb], y: &[Limb]) -> Limb {
+    let (x, y) = if x.len() >= y.len() { (x, y) } else { (y, x) };
+    let n = x.len() + y.len();
+    check_output(w, n);
+    if y.is_empty() {
+        return Limb(0);
+    }
+    let ws = len(w);
+    let wp = limbs_mut(w);
+    unsafe {
+        let cy = ll::addmul(wp, limbs(x), len(x), limbs(y), len(y));
+        if ws > n {
+            let rest = wp.offset(n as isize);
+            ll::add_1(rest, rest.as_const(), ws - n, cy)
+        } else {
+            cy
+        }
+    }
+}
+
+/// Multiplies `x` by `y`, which have the same length, storing the `x.len()`
+/// low limbs of the product in `w`.
+///
+/// # Panics
+///
+/// Panics if `x` and `y` have different lengths or `w` is shorter than them.
+pub fn mullo(w: &mut [Limb], x: &[Limb], y: &[Limb]) {
+    assert!(x.len() == y.len(), "inputs have different lengths");
+    check_output(w, x.len());
+    if x.is_empty() {
+        return;
+    }
+    unsafe { ll::mullo_n(limbs_mut(w), limbs(x), limbs(y), len(x)) }
+}
+
+/// Multiplies `x` by `y`, which have the same length, storing an
+/// approximation of the `x.len()` high limbs of the product in `w`. It is
+/// either exact or one less.
+///
+/// # Panics
+///
+/// Panics if `x` and `y` have different lengths or `w` is shorter than them.
+pub fn mulhi(w: &mut [Limb], x: &[Limb], y: &[Limb]) {
+    assert!(x.len() == y.len(), "inputs have different lengths");
+    check_output(w, x.len());
+    if x.is_empty() {
+        return;
+    }
+    unsafe { ll::mulhi_n(limbs_mut(w), limbs(x), limbs(y), len(x)) }
+}
+
+/// Stores the middle product of `x` and `y`, the sum of the partial products
+/// `x[i]*y[j]` with `y.len() - 1 <= i + j <= x.len() - 1`, shifted down by
+/// `y.len() - 1` limbs, in the first `x.len() - y.len() + 3` limbs of `w`.
+///
+/// # Panics
+///
+/// Panics if `y` is empty or longer than `x`, or if `w` is too short.
+pub fn mulmid(w: &mut [Limb], x: &[Limb], y: &[Limb]) {
+    assert!(!y.is_empty() && x.len() >= y.len(), "mulmid needs 0 < y.len() <= x.len()");
+    check_output(w, x.len() - y.len() + 3);
+    unsafe { ll::mulmid(limbs_mut(w), limbs(x), len(x), limbs(y), len(y)) }
+}
+
+/// Multiplies `x` by `y` modulo `B^w.len() - 1`, where `B` is the limb base,
+/// storing the reduced result in `w`.
+///
+/// # Panics
+///
+/// Panics if `w` is empty.
+pub fn mulmod_bnm1(w: &mut [Limb], x: &[Limb], y: &[Limb]) {
+    assert!(!w.is_empty(), "output is empty");
+    if x.is_empty() || y.is_empty() {
+        for l in w.iter_mut() {
+            *l = Limb(0);
+        }
+        return;
+    }
+    let n = len(w);
+    unsafe { ll::mulmod_bnm1(limbs_mut(w), n, limbs(x), len(x), limbs(y), len(y)) }
+}
+
+/// Squares `x`, storing the result in the first `2 * x.len()` limbs of `w`.
+///
+/// # Panics
+///
+/// Panics if `w` is shorter than `2 * x.len()`.
+pub fn sqr(w: &mut [Limb], x: &[Limb]) {
+    let n = 2 * x.len();
+    check_output(w, n);
+    if x.is_empty() {
+        return;
+    }
+    unsafe { ll::sqr(limbs_mut(w), limbs(x), len(x)) }
+}
+
+/// Multiplies `x` by `v`, storing the low `x.len()` limbs of the product in
+/// `w`. Returns the high limb.
+///
+/// # Panics
+///
+/// Panics if `w` is shorter than `x`.
+pub fn mul_1(w: &mut [Limb], x: &[Limb], v: Limb) -> Limb {
+    check_output(w, x.len());
+    if x.is_empty() {
+        return Limb(0);
+    }
+    unsafe { ll::mul_1(limbs_mut(w), limbs(x), len(x), v) }
+}
+
+/// Adds `x * v` to the first `x.len()` limbs of `w`. Returns the high limb.
+///
+/// # Panics
+///
+/// Panics if `w` is shorter than `x`.
+pub fn addmul_1(w: &mut [Limb], x: &[Limb], v: Limb) -> Limb {
+    check_output(w, x.len());
+    if x.is_empty() {
+        return Limb(0);
+    }
+    unsafe { ll::addmul_1(limbs_mut(w), limbs(x), len(x), v) }
+}
+
+/// Subtracts `x * v` from the first `x.len()` limbs of `w`. Returns the high
+/// limb, adjusted for the borrow.
+///
+/// # Panics
+///
+/// Panics if `w` is shorter than `x`.
+pub fn submul_1(w: &mut [Limb], x: &[Limb], v: Limb) -> Limb {
+    check_output(w, x.len());
+    if x.is_empty() {
+        return Limb(0);
+    }
+    unsafe { ll::submul_1(limbs_mut(w), limbs(x), len(x), v) }
+}
+
+/// Divides `n` by `d`, storing the quotient in `q` and the remainder in `r`.
+/// Both are zero-padded to the full length of their slices.
+///
+/// High zero limbs of `d` are ignored. With `ds` the length of `d` without
+/// them, `q` needs `max(n.len() - ds + 1, 1)` limbs and `r` needs `ds`.
+///
+/// # Panics
+///
+/// Panics if `d` is zero or either output is too short.
+pub fn divrem(q: &mut [Limb], r: &mut [Limb], n: &[Limb], d: &[Limb]) {
+    let ds = trimmed(d);
+    if ds == 0 {
+        ll::divide_by_zero();
+    }
+    let d = &d[..ds];
+    check_output(q, cmp::max(n.len(), ds) - ds + 1);
+    check_output(r, ds);
+
+    for l in q.iter_mut().chain(r.iter_mut()) {
+        *l = Limb(0);
+    }
+    let n = &n[..trimmed(n)];
+    if n.is_empty() {
+        return;
+    }
+    unsafe { ll::divrem(limbs_mut(q), limbs_mut(r), limbs(n), len(n), limbs(d), len(d)) }
+}
+
+/// Divides `x` by `d`, storing the quotient in the first `x.len()` limbs of
+/// `q`. Returns the remainder.
+///
+/// # Panics
+///
+/// Panics if `d` is zero or `q` is shorter than `x`.
+pub fn divrem_1(q: &mut [Limb], x: &[Limb], d: Limb) -> Limb {
+    if d == 0 {
+        ll::divide_by_zero();
+    }
+    check_output(q, x.len());
+    unsafe { ll::divrem_1(limbs_mut(q), 0, limbs(x), len(x), d) }
+}
+
+/// Returns the remainder of `x` divided by `d`, without computing the
+/// quotient.
+///
+/// # Panics
+///
+/// Panics if `d` is zero.
+pub fn mod_1(x: &[Limb], d: Limb) -> Limb {
+    if d == 0 {
+        ll::divide_by_zero();
+    }
+    unsafe { ll::mod_1(limbs(x), len(x), d) }
+}
+
+/// Divides `w` by `d` in place. Returns the remainder.
+///
+/// # Panics
+///
+/// Panics if `d` is zero.
+pub fn divrem_1_assign(w: &mut [Limb], d: Limb) -> Limb {
+    if d == 0 {
+        ll::divide_by_zero();
+    }
+    let wp = limbs_mut(w);
+    unsafe { ll::divrem_1(wp, 0, wp.as_const(), len(w), d) }
+}
+
+/// Shifts `x` left by `cnt` bits, storing the low `x.len()` limbs of the
+/// result in `w`. Returns the bits shifted out of the top limb.
+///
+/// # Panics
+///
+/// Panics if `cnt` is not less than `Limb::BITS` or `w` is shorter than `x`.
+pub fn shl(w: &mut [Limb], x: &[Limb], cnt: u32) -> Limb {
+    assert!((cnt as usize) < Limb::BITS, "shift of {} bits is too large", cnt);
+    check_output(w, x.len());
+    if x.is_empty() {
+        return Limb(0);
+    }
+    if cnt == 0 {
+        w[..x.len()].copy_from_slice(x);
+        return Limb(0);
+    }
+    unsafe { ll::shl(limbs_mut(w), limbs(x), len(x), cnt) }
+}
+
+/// Shifts `x` right by `cnt` bits, storing the result in the first
+/// `x.len()` limbs of `w`. Returns the bits shifted out of the bottom limb,
+/// in the high bits of the returned limb.
+///
+/// # Panics
+///
+/// Panics if `cnt` is not less than `Limb::BITS` or `w` is shorter than `x`.
+pub fn shr(w: &mut [Limb], x: &[Limb], cnt: u32) -> Limb {
+    assert!((cnt as usize) < Limb::BITS, "shift of {} bits is too large", cnt);
+    check_output(w, x.len());
+    if x.is_empty() {
+        return Limb(0);
+    }
+    if cnt == 0 {
+        w[..x.len()].copy_from_slice(x);
+        return Limb(0);
+    }
+    unsafe { ll::shr(limbs_mut(w), limbs(x), len(x), cnt) }
+}
+
+/// Compares the values of `x` and `y`, which may have different lengths.
+pub fn cmp(x: &[Limb], y: &[Limb]) -> Ordering {
+    let (xs, ys) = (trimmed(x), trimmed(y));
+    if xs != ys {
+        return xs.cmp(&ys);
+    }
+    if xs == 0 {
+        return Ordering::Equal;
+    }
+    unsafe { ll::cmp(limbs(x), limbs(y), xs) }
+}
+
+#[cfg(test)]
+fn to_int(x: &[Limb]) -> ::int::Int {
+    let mut i = ::int::Int::zero();
+    for &l in x.iter().rev() {
+        i = (i << Limb::BITS) + l;
+    }
+    i
+}
+
+#[cfg(test)]
+fn random_limbs(n: usize) -> Vec<Limb> {
+    use rand::{self, Rng};
+    let mut rng = rand::thread_rng();
+    (0..n).map(|_| Limb(rng.gen())).collect()
+}
+
+#[test]
+fn add_sub_mul() {
+    use int::Int;
+
+    for &(xs, ys) in [(0, 0), (1, 0), (3, 1), (5, 5), (40, 7), (90, 80), (200, 150)].iter() {
+        let x = random_limbs(xs);
+        let y = random_limbs(ys);
+        let (xi, yi) = (to_int(&x), to_int(&y));
+        let mut y_long = y.clone();
+        y_long.extend(random_limbs(xs - ys));
+
+        let mut w = vec![Limb(0); xs + 1];
+        let c = add(&mut w[..xs], &y, &x);
+        w[xs] = c;
+        assert_eq!(to_int(&w), &xi + &yi);
+
+        let mut d = vec![Limb(0); xs + 1];
+        assert_eq!(sub(&mut d, &w, &y), 0);
+        assert_eq!(to_int(&d), xi);
+
+        let mut v = x.clone();
+        assert_eq!(sub_assign(&mut v, &y), 0);
+        assert_eq!(add_assign(&mut v, &y), 0);
+        assert_eq!(v, x);
+
+        let mut p = vec![Limb(!0); xs + ys + 2];
+        mul(&mut p, &y, &x);
+        assert_eq!(to_int(&p[..xs + ys]), &xi * &yi);
+        sqr(&mut p, &x);
+        assert_eq!(to_int(&p[..2 * xs]), &xi * &xi);
+        mullo(&mut p, &x, &y_long);
+        assert_eq!(to_int(&p[..xs]), &xi * &to_int(&y_long) % (Int::one() << (xs * Limb::BITS)));
+        mulhi(&mut p, &x, &y_long);
+        let high = (&xi * &to_int(&y_long)) >> (xs * Limb::BITS);
+        let approx = to_int(&p[..xs]);
+        assert!(approx == high || approx + 1 == high);
+
+        let mut a = random_limbs(xs + ys);
+        a.push(Limb(0));
+        let ai = to_int(&a);
+        assert_eq!(addmul(&mut a, &y, &x), 0);
+        assert_eq!(to_int(&a), &ai + &xi * &yi);
+        let mut a = vec![Limb(!0); xs + ys];
+        let cy = addmul(&mut a, &x, &y);
+        let top = Int::from(cy.0) << ((xs + ys) * Limb::BITS);
+        assert_eq!(top + to_int(&a), (Int::one() << ((xs + ys) * Limb::BITS)) - 1 + &xi * &yi);
+
+        let mut m = vec![Limb(0); xs];
+        let h = mul_1(&mut m, &x, Limb(12345));
+        assert_eq!((Int::from(h.0) << (xs * Limb::BITS)) + to_int(&m), xi.clone() * Limb(12345));
+        addmul_1(&mut m, &x, Limb(3));
+        assert_eq!(to_int(&m), (xi.clone() * Limb(12348)) % (Int::one() << (xs * Limb::BITS)));
+        submul_1(&mut m, &x, Limb(12348));
+        assert!(m.iter().all(|&l| l == 0));
+    }
+}
+
+#[test]
+fn middle_product() {
+    use int::Int;
+
+    for &(xs, ys) in [(1, 1), (3, 2), (9, 5), (40, 40), (99, 50), (130, 20)].iter() {
+        for &ones in [true, false].iter() {
+            let (x, y) = if ones {
+                (vec![Limb(!0); xs], vec![Limb(!0); ys])
+            } else {
+                (random_limbs(xs), random_limbs(ys))
+            };
+            let m = xs - ys + 1;
+            let mut expected = Int::zero();
+            for j in 0..ys {
+                expected += to_int(&x[ys - 1 - j..ys - 1 - j + m]) * y[j];
+            }
+            let mut w = vec![Limb(0); m + 2];
+            mulmid(&mut w, &x, &y);
+            assert_eq!(to_int(&w), expected, "xs: {}, ys: {}", xs, ys);
+        }
+    }
+}
+
+#[test]
+fn mulmod() {
+    use int::Int;
+
+    let sizes = [(1, 3, 2), (7, 7, 7), (10, 3, 4), (64, 64, 64), (64, 100, 30), (130, 200, 200),
+                 (256, 256, 256), (256, 600, 90)];
+    for &(rn, xs, ys) in sizes.iter() {
+        let m = (Int::one() << (rn * Limb::BITS)) - 1;
+        let mut w = vec![Limb(0); rn];
+        for &ones in [true, false].iter() {
+            let (x, y) = if ones {
+                (vec![Limb(!0); xs], vec![Limb(!0); ys])
+            } else {
+                (random_limbs(xs), random_limbs(ys))
+            };
+            let (xi, yi) = (to_int(&x), to_int(&y));
+
+            mulmod_bnm1(&mut w, &x, &y);
+            assert_eq!(to_int(&w), &xi * &yi % &m, "rn: {}, xs: {}, ys: {}", rn, xs, ys);
+            mulmod_bnm1(&mut w, &x, &x);
+            assert_eq!(to_int(&w), &xi * &xi % &m, "rn: {}, xs: {}", rn, xs);
+        }
+    }
+}
+
+#[test]
+fn div_shift_cmp() {
+    for &(ns, ds) in [(1, 1), (4, 1), (5, 2), (9, 9), (30, 12), (12, 30), (200, 70)].iter() {
+        let n = random_limbs(ns);
+        let mut d = random_limbs(ds);
+        d.push(Limb(0));
+        let (ni, di) = (to_int(&n), to_int(&d));
+
+        let mut q = vec![Limb(0); cmp::max(ns as isize - ds as isize + 1, 1) as usize];
+        let mut r = vec![Limb(0); ds];
+        divrem(&mut q, &mut r, &n, &d);
+        assert_eq!(to_int(&q), &ni / &di);
+        assert_eq!(to_int(&r), &ni % &di);
+
+        let mut q = vec![Limb(0); ns];
+        let rl = divrem_1(&mut q, &n, d[0]);
+        assert_eq!(to_int(&q), ni.clone() / d[0]);
+        assert_eq!(rl, (ni.clone() % d[0]).to_single_limb());
+        assert_eq!(mod_1(&n, d[0]), rl);
+        let small = (d[0] >> 7) | Limb(1);
+        assert_eq!(mod_1(&n, small), (ni.clone() % small).to_single_limb());
+
+        let mut s = vec![Limb(0); ns];
+        let c = shl(&mut s, &n, 5);
+        let back = shr(&mut q, &s, 5);
+        assert_eq!(back, 0);
+        q[ns - 1] = q[ns - 1] | (c << (Limb::BITS - 5));
+        assert_eq!(q, n);
+
+        assert_eq!(cmp(&n, &d), ni.cmp(&di));
+        assert_eq!(cmp(&d, &d[..ds]), Ordering::Equal);
+    }
+}
+
+#[test]
+#[should_panic(expected = "output has 2 limbs, but 3 are needed")]
+fn short_output() {
+    let mut w = [Limb(0); 2];
+    mul(&mut w, &[Limb(1), Limb(2)], &[Limb(3)]);
+}
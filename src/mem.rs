@@ -16,15 +16,18 @@
 //! be used for anything that requires an alignment greater than that.
 
 use alloc::heap;
+use std::cell::Cell;
+use std::cmp;
 use std::mem;
 use std::intrinsics::abort;
 use std::io::{self, Write};
 use std::ptr;
 
-use ll::limb::Limb;
+use ll::limb::{BaseInt, Limb};
 use ll::limb_ptr::LimbsMut;
 
 pub unsafe fn allocate_bytes(size: usize) -> *mut u8 {
+    count_heap_allocation();
     let ret = heap::allocate(size, mem::align_of::<usize>());
     if ret.is_null() {
         let _ = writeln!(io::stderr(), "Failed to allocate memory (size={})", size);
@@ -38,12 +41,64 @@ pub unsafe fn deallocate_bytes(ptr: *mut u8, size: usize) {
     heap::deallocate(ptr, size, mem::align_of::<usize>());
 }
 
+/// Overwrites `n` limbs at `ptr` with zeros, in a way the compiler can't
+/// optimise away even if the memory is about to be freed.
+#[cfg(feature = "zeroize")]
+pub unsafe fn wipe_limbs(ptr: *mut Limb, n: usize) {
+    wipe_bytes(ptr as *mut u8, n * mem::size_of::<Limb>());
+}
+
+// Overwrites `size` bytes at `ptr` with zeros. The writes are volatile, so
+// they aren't removed even though the memory is about to be freed.
+unsafe fn wipe_bytes(ptr: *mut u8, size: usize) {
+    let words = size / mem::size_of::<usize>();
+    let wp = ptr as *mut usize;
+    for i in 0..words {
+        ptr::write_volatile(wp.offset(i as isize), 0);
+    }
+    for i in (words * mem::size_of::<usize>())..size {
+        ptr::write_volatile(ptr.offset(i as isize), 0);
+    }
+}
+
 /// Allocate for temporary storage. Ensures that the allocations are
 /// freed when the structure drops
+///
+/// With the `wipe-scratch` feature, everything handed out is overwritten with
+/// zeros when the allocator drops, so intermediate values derived from
+/// secrets (Montgomery tables, division remainders) don't stay in memory.
+///
+/// Small allocations are carved out of a buffer inside the allocator itself,
+/// so the allocator must not be moved once something has been allocated from
+/// it. Beyond that buffer, if an `Arena` is installed for the current thread,
+/// allocations come from it instead of the heap and are released by rewinding
+/// the arena on drop.
+///
+/// With the `checked-limbs` feature, every allocation made by `allocate` is
+/// followed by a few limbs of poison, which are checked when the allocator
+/// drops. This catches writes past the end of scratch space that don't go
+/// through a bounds-checked `LimbsMut`, such as those of the assembly
+/// routines.
 pub struct TmpAllocator {
-    mark: *mut Marker
+    mark: *mut Marker,
+    arena: *mut Arena,
+    arena_mark: Option<ArenaMark>,
+    inline: [Limb; INLINE_LIMBS],
+    // Bytes of `inline` already handed out
+    inline_used: usize,
+    // The poisoned limbs following each allocation, with `checked-limbs`
+    guards: Vec<*const Limb>,
 }
 
+// The size of the buffer inside a `TmpAllocator`, enough for the scratch
+// space of typical multiplications and reductions of a few hundred bits
+const INLINE_LIMBS: usize = 32;
+
+// The number of poisoned limbs after each allocation with `checked-limbs`,
+// and their value
+const GUARD_LIMBS: usize = 2;
+const POISON: BaseInt = !0 / 0xff * 0xa5;
+
 struct Marker {
     next: *mut Marker,
     size: usize
@@ -52,11 +107,36 @@ struct Marker {
 impl TmpAllocator {
     pub fn new() -> TmpAllocator {
         TmpAllocator {
-            mark: ptr::null_mut()
+            mark: ptr::null_mut(),
+            arena: current_arena(),
+            arena_mark: None,
+            inline: [Limb(0); INLINE_LIMBS],
+            inline_used: 0,
+            guards: Vec::new(),
         }
     }
 
     pub unsafe fn allocate_bytes(&mut self, size: usize) -> *mut u8 {
+        count_scratch_allocation(size);
+        let align = mem::align_of::<usize>();
+        let rounded = (size + align - 1) & !(align - 1);
+        if rounded <= mem::size_of_val(&self.inline) - self.inline_used {
+            // The buffer is zero to begin with and never reused, so it
+            // doesn't need clearing
+            let ptr = (self.inline.as_mut_ptr() as *mut u8).offset(self.inline_used as isize);
+            self.inline_used += rounded;
+            return ptr;
+        }
+
+        ::int::check_memory_budget(size);
+
+        if !self.arena.is_null() {
+            if self.arena_mark.is_none() {
+                self.arena_mark = Some((*self.arena).mark());
+            }
+            return (*self.arena).allocate_bytes(size);
+        }
+
         let size = size + mem::size_of::<Marker>();
         let ptr = allocate_bytes(size);
 
@@ -69,32 +149,490 @@ impl TmpAllocator {
         ptr.offset(mem::size_of::<Marker>() as isize)
     }
 
+    /// Allocates `size` bytes aligned to `align`, which must be a power of two.
+    ///
+    /// Alignments beyond a pointer-width are obtained by over-allocating, so
+    /// this costs up to `align` extra bytes.
+    #[allow(dead_code)]
+    pub unsafe fn allocate_bytes_aligned(&mut self, size: usize, align: usize) -> *mut u8 {
+        assert!(align.is_power_of_two(), "alignment must be a power of two");
+        if align <= mem::align_of::<usize>() {
+            return self.allocate_bytes(size);
+        }
+
+        let ptr = self.allocate_bytes(size + align - 1);
+        let offset = (align - (ptr as usize & (align - 1))) & (align - 1);
+        ptr.offset(offset as isize)
+    }
+
+    /// Allocate space for n limbs, aligned to `align` bytes. See
+    /// `allocate_bytes_aligned`.
+    #[allow(dead_code)]
+    pub unsafe fn allocate_aligned(&mut self, n: usize, align: usize) -> LimbsMut {
+        let ptr = self.allocate_bytes_aligned(n * mem::size_of::<Limb>(), align) as *mut Limb;
+        LimbsMut::new(ptr, 0, n)
+    }
+
     /// Allocate space for n limbs
     pub unsafe fn allocate(&mut self, n: usize) -> LimbsMut {
-        let ptr = self.allocate_bytes(n * mem::size_of::<Limb>()) as *mut Limb;
-        LimbsMut::new(ptr, 0, n as i32)
+        let guard = if cfg!(feature = "checked-limbs") { GUARD_LIMBS } else { 0 };
+        let ptr = self.allocate_bytes((n + guard) * mem::size_of::<Limb>()) as *mut Limb;
+        if guard > 0 {
+            let g = ptr.offset(n as isize);
+            for i in 0..guard {
+                *g.offset(i as isize) = Limb(POISON);
+            }
+            self.guards.push(g);
+        }
+        LimbsMut::new(ptr, 0, n)
+    }
+
+    // Panics if the poison after any allocation has been overwritten
+    unsafe fn check_guards(&self) {
+        for &g in self.guards.iter() {
+            for i in 0..GUARD_LIMBS {
+                if (*g.offset(i as isize)).0 != POISON {
+                    panic!("write past the end of scratch space at {:?}", g.offset(i as isize));
+                }
+            }
+        }
     }
 
     /// Allocates space for n1+n2 limbs and returns a pair of pointers.
     pub unsafe fn allocate_2(&mut self, n1: usize, n2: usize) -> (LimbsMut, LimbsMut) {
-        let mut x = self.allocate(n1 + n2);
-        let mut y = x.offset(n1 as isize);
-        (LimbsMut::new(&mut *x, 0, n1 as i32),
-         LimbsMut::new(&mut *y, 0, n2 as i32))
+        let x = self.allocate(n1 + n2);
+        let y = x.offset(n1 as isize);
+        (LimbsMut::new(x.as_mut_ptr(), 0, n1),
+         LimbsMut::new(y.as_mut_ptr(), 0, n2))
     }
 }
 
 impl Drop for TmpAllocator {
     fn drop(&mut self) {
+        let wipe = cfg!(feature = "wipe-scratch");
         unsafe {
+            if !::std::thread::panicking() {
+                self.check_guards();
+            }
+
+            if wipe {
+                wipe_bytes(self.inline.as_mut_ptr() as *mut u8, self.inline_used);
+            }
+
             let mut next;
             let mut mark = self.mark;
             while !mark.is_null() {
                 next = (*mark).next;
                 let size = (*mark).size;
+                if wipe {
+                    wipe_bytes(mark as *mut u8, size);
+                }
                 deallocate_bytes(mark as *mut u8, size);
                 mark = next;
             }
+
+            if let Some(m) = self.arena_mark {
+                if wipe {
+                    (*self.arena).wipe_since(m);
+                }
+                (*self.arena).reset(m);
+            }
+        }
+    }
+}
+
+thread_local!(static CURRENT_ARENA: Cell<*mut Arena> = Cell::new(ptr::null_mut()));
+
+/// Installs `arena` as the one `TmpAllocator`s on this thread allocate from,
+/// returning the previously installed one. A null pointer restores the heap.
+///
+/// The arena must outlive every `TmpAllocator` created while it is installed.
+pub unsafe fn set_current_arena(arena: *mut Arena) -> *mut Arena {
+    CURRENT_ARENA.with(|a| {
+        let prev = a.get();
+        a.set(arena);
+        prev
+    })
+}
+
+// The arena `TmpAllocator`s allocate from: the one installed by a `Context`,
+// otherwise the thread's scratch arena if enabled, otherwise none. Once the
+// thread-locals are destroyed, as when arithmetic runs in another thread-local
+// destructor, this falls back to the heap.
+fn current_arena() -> *mut Arena {
+    let arena = CURRENT_ARENA.try_with(|a| a.get()).unwrap_or(ptr::null_mut());
+    if !arena.is_null() {
+        return arena;
+    }
+    THREAD_SCRATCH.try_with(|s| s.0.get()).unwrap_or(ptr::null_mut())
+}
+
+struct ThreadScratch(Cell<*mut Arena>);
+
+impl Drop for ThreadScratch {
+    fn drop(&mut self) {
+        let arena = self.0.get();
+        if !arena.is_null() {
+            unsafe {
+                drop(Box::from_raw(arena));
+            }
+        }
+    }
+}
+
+thread_local!(static THREAD_SCRATCH: ThreadScratch = ThreadScratch(Cell::new(ptr::null_mut())));
+
+/// Enables or disables the scratch arena of the current thread.
+///
+/// While enabled, the scratch space needed by the `ll` functions on this
+/// thread (multiplication, division, Montgomery reduction and the like) comes
+/// from an arena that lives as long as the thread, instead of being allocated
+/// and freed by every call. This pays off for workloads doing many mid-sized
+/// operations, such as modular exponentiation. The arena only grows, up to
+/// the largest amount of scratch space used at once; disabling it frees it.
+///
+/// A `Context` that has been entered takes precedence over the thread's arena.
+pub fn set_thread_scratch(enabled: bool) {
+    THREAD_SCRATCH.with(|s| {
+        let arena = s.0.get();
+        if enabled && arena.is_null() {
+            s.0.set(Box::into_raw(Box::new(Arena::new(0))));
+        } else if !enabled && !arena.is_null() {
+            s.0.set(ptr::null_mut());
+            unsafe {
+                drop(Box::from_raw(arena));
+            }
+        }
+    })
+}
+
+/// Returns the number of bytes held by the scratch arena of the current
+/// thread, zero if it is disabled.
+pub fn thread_scratch_capacity() -> usize {
+    THREAD_SCRATCH.with(|s| {
+        let arena = s.0.get();
+        if arena.is_null() { 0 } else { unsafe { (*arena).capacity() } }
+    })
+}
+
+/// Counts of the allocations made on the current thread, kept with the
+/// `alloc-stats` feature.
+#[cfg(feature = "alloc-stats")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AllocStats {
+    /// Allocations of scratch space through a `TmpAllocator`, wherever the
+    /// memory came from
+    pub scratch_allocations: u64,
+    /// The bytes requested by those allocations
+    pub scratch_bytes: u64,
+    /// Allocations and reallocations that went to the heap, for scratch space,
+    /// arena chunks or the limbs of an `Int`
+    pub heap_allocations: u64,
+}
+
+#[cfg(feature = "alloc-stats")]
+thread_local!(static ALLOC_STATS: Cell<AllocStats> = Cell::new(AllocStats::default()));
+
+/// Returns the allocations counted on the current thread since the last
+/// call to `reset_alloc_stats`.
+#[cfg(feature = "alloc-stats")]
+pub fn alloc_stats() -> AllocStats {
+    ALLOC_STATS.with(|s| s.get())
+}
+
+/// Sets the allocation counts of the current thread back to zero.
+#[cfg(feature = "alloc-stats")]
+pub fn reset_alloc_stats() {
+    ALLOC_STATS.with(|s| s.set(AllocStats::default()))
+}
+
+#[cfg(feature = "alloc-stats")]
+pub fn count_heap_allocation() {
+    let _ = ALLOC_STATS.try_with(|s| {
+        let mut stats = s.get();
+        stats.heap_allocations += 1;
+        s.set(stats);
+    });
+}
+
+#[cfg(not(feature = "alloc-stats"))]
+#[inline(always)]
+pub fn count_heap_allocation() {}
+
+#[cfg(feature = "alloc-stats")]
+fn count_scratch_allocation(size: usize) {
+    let _ = ALLOC_STATS.try_with(|s| {
+        let mut stats = s.get();
+        stats.scratch_allocations += 1;
+        stats.scratch_bytes += size as u64;
+        s.set(stats);
+    });
+}
+
+#[cfg(not(feature = "alloc-stats"))]
+#[inline(always)]
+fn count_scratch_allocation(_: usize) {}
+
+/// A stack of memory chunks for temporary storage.
+///
+/// Allocations are bumped off the current chunk and freed in bulk by rewinding
+/// to an earlier mark. Chunks are kept around after a rewind, so a workload
+/// that repeatedly needs the same amount of scratch space stops allocating
+/// after the first round.
+pub struct Arena {
+    chunks: Vec<(*mut u8, usize)>,
+    // The chunk being allocated from and the number of bytes used in it
+    cur: usize,
+    used: usize,
+}
+
+/// A position in an `Arena` to rewind to.
+#[derive(Clone, Copy, Debug)]
+pub struct ArenaMark {
+    chunk: usize,
+    used: usize,
+}
+
+// The smallest chunk allocated
+const MIN_CHUNK: usize = 4096;
+
+impl Arena {
+    /// Creates an arena, with a first chunk of `bytes` bytes if non-zero.
+    pub fn new(bytes: usize) -> Arena {
+        let mut chunks = Vec::new();
+        if bytes > 0 {
+            chunks.push((unsafe { allocate_bytes(bytes) }, bytes));
+        }
+        Arena {
+            chunks: chunks,
+            cur: 0,
+            used: 0,
+        }
+    }
+
+    /// Returns the current position, for a later call to `reset`.
+    pub fn mark(&self) -> ArenaMark {
+        ArenaMark {
+            chunk: self.cur,
+            used: self.used,
+        }
+    }
+
+    /// Frees everything allocated since `mark` was taken.
+    pub unsafe fn reset(&mut self, mark: ArenaMark) {
+        debug_assert!(mark.chunk < self.cur || (mark.chunk == self.cur && mark.used <= self.used));
+        self.cur = mark.chunk;
+        self.used = mark.used;
+    }
+
+    /// Overwrites everything allocated since `mark` was taken with zeros.
+    pub unsafe fn wipe_since(&mut self, mark: ArenaMark) {
+        for i in mark.chunk..cmp::min(self.cur + 1, self.chunks.len()) {
+            let (chunk, size) = self.chunks[i];
+            let start = if i == mark.chunk { mark.used } else { 0 };
+            let end = if i == self.cur { self.used } else { size };
+            wipe_bytes(chunk.offset(start as isize), end - start);
+        }
+    }
+
+    /// Returns the total size of the chunks held by the arena.
+    pub fn capacity(&self) -> usize {
+        self.chunks.iter().map(|&(_, size)| size).sum()
+    }
+
+    /// Allocates `size` zeroed bytes, aligned to a pointer-width.
+    pub unsafe fn allocate_bytes(&mut self, size: usize) -> *mut u8 {
+        let align = mem::align_of::<usize>();
+        let size = (size + align - 1) & !(align - 1);
+
+        loop {
+            if self.cur == self.chunks.len() {
+                let last = self.chunks.last().map(|&(_, s)| s).unwrap_or(0);
+                let chunk_size = cmp::max(cmp::max(size, last * 2), MIN_CHUNK);
+                self.chunks.push((allocate_bytes(chunk_size), chunk_size));
+            }
+
+            let (chunk, chunk_size) = self.chunks[self.cur];
+            if chunk_size - self.used >= size {
+                let ret = chunk.offset(self.used as isize);
+                self.used += size;
+                ptr::write_bytes(ret, 0, size);
+                return ret;
+            }
+
+            self.cur += 1;
+            self.used = 0;
+        }
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        for &(chunk, size) in self.chunks.iter() {
+            unsafe {
+                deallocate_bytes(chunk, size);
+            }
+        }
+    }
+}
+
+#[test]
+fn tmp_allocator() {
+    unsafe {
+        let mut tmp = TmpAllocator::new();
+        let small = tmp.allocate(8);
+        let big = tmp.allocate(1000);
+        let rest = tmp.allocate(INLINE_LIMBS - 8);
+        let more = tmp.allocate(1);
+        for &(p, n) in [(small, 8), (big, 1000), (rest, INLINE_LIMBS - 8), (more, 1)].iter() {
+            for i in 0..n {
+                assert_eq!(*p.offset(i as isize), 0);
+                *p.offset(i as isize) = Limb(i as ::ll::limb::BaseInt + 1);
+            }
         }
+        assert_eq!(*small.offset(7), 8);
+        assert_eq!(*rest.offset(0), 1);
+        assert_eq!(*big.offset(999), 1000);
     }
 }
+
+#[test]
+fn aligned() {
+    unsafe {
+        let mut tmp = TmpAllocator::new();
+        for &align in [1, 8, 32, 64, 4096].iter() {
+            for &n in [1, 3, 40, 500].iter() {
+                let p = tmp.allocate_aligned(n, align);
+                assert_eq!(p.as_mut_ptr() as usize % align, 0);
+                for i in 0..n {
+                    assert_eq!(*p.offset(i as isize), 0);
+                    *p.offset(i as isize) = Limb(!0);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn thread_scratch() {
+    use int::Int;
+
+    let x = Int::from(3).pow(10000);
+    let y = Int::from(5).pow(8000) + 7;
+    let expected = &x * &y;
+
+    assert_eq!(thread_scratch_capacity(), 0);
+    set_thread_scratch(true);
+    assert_eq!(&x * &y, expected);
+    assert_eq!(&expected / &y, x);
+    let cap = thread_scratch_capacity();
+    assert!(cap > 0);
+
+    for _ in 0..10 {
+        assert_eq!(&x * &y, expected);
+        assert_eq!(&expected / &y, x);
+    }
+    assert_eq!(thread_scratch_capacity(), cap);
+
+    set_thread_scratch(false);
+    assert_eq!(thread_scratch_capacity(), 0);
+    assert_eq!(&x * &y, expected);
+}
+
+#[test]
+fn arena_wipe() {
+    unsafe {
+        let mut arena = Arena::new(0);
+        let prev = set_current_arena(&mut arena);
+        let (small, big) = {
+            let mut tmp = TmpAllocator::new();
+            let _inline = tmp.allocate(INLINE_LIMBS);
+            let small = tmp.allocate(10);
+            let big = tmp.allocate(5000);
+            for i in 0..10 {
+                *small.offset(i) = Limb(!0);
+            }
+            for i in 0..5000 {
+                *big.offset(i) = Limb(!0);
+            }
+            (small.as_mut_ptr() as *const Limb, big.as_mut_ptr() as *const Limb)
+        };
+        set_current_arena(prev);
+
+        // The arena still owns the memory, so it can be inspected
+        let expected = if cfg!(feature = "wipe-scratch") { 0 } else { !0 };
+        assert_eq!((*small).0, expected);
+        assert_eq!((*small.offset(9)).0, expected);
+        assert_eq!((*big.offset(4999)).0, expected);
+    }
+}
+
+#[cfg(feature = "alloc-stats")]
+#[test]
+fn alloc_stats_counts() {
+    use int::Int;
+
+    reset_alloc_stats();
+    unsafe {
+        let mut tmp = TmpAllocator::new();
+        tmp.allocate(4);
+        tmp.allocate(1000);
+    }
+    let guard = if cfg!(feature = "checked-limbs") { 2 * GUARD_LIMBS } else { 0 };
+    let stats = alloc_stats();
+    assert_eq!(stats.scratch_allocations, 2);
+    assert_eq!(stats.scratch_bytes, ((1004 + guard) * mem::size_of::<Limb>()) as u64);
+    assert_eq!(stats.heap_allocations, 1);
+
+    reset_alloc_stats();
+    let x = Int::with_capacity(100);
+    assert_eq!(alloc_stats().heap_allocations, 1);
+    drop(x);
+
+    // The arena keeps its chunks, so the second product only allocates its result
+    let y = Int::from(7).pow(3000);
+    let z = Int::from(11).pow(2500);
+    set_thread_scratch(true);
+    let _ = &y * &z;
+    reset_alloc_stats();
+    let _ = &y * &z;
+    assert_eq!(alloc_stats().heap_allocations, 1);
+    set_thread_scratch(false);
+}
+
+#[cfg(feature = "checked-limbs")]
+#[test]
+#[should_panic(expected = "write past the end of scratch space")]
+fn scratch_overrun() {
+    unsafe {
+        let mut tmp = TmpAllocator::new();
+        let a = tmp.allocate(10);
+        let _b = tmp.allocate(100);
+        // Bypass the bounds check, like the assembly routines do
+        *a.as_mut_ptr().offset(10) = Limb(1);
+    }
+}
+
+#[test]
+fn arithmetic_in_tls_destructor() {
+    use std::thread;
+    use int::Int;
+
+    struct MulOnDrop;
+
+    impl Drop for MulOnDrop {
+        fn drop(&mut self) {
+            let x = Int::from(7).pow(500);
+            assert_eq!(&x * &x, Int::from(7).pow(1000));
+        }
+    }
+
+    thread_local!(static MUL_ON_DROP: MulOnDrop = MulOnDrop);
+
+    thread::spawn(|| {
+        // Registered before the scratch arena, so usually destroyed after it
+        MUL_ON_DROP.with(|_| ());
+        set_thread_scratch(true);
+        let _ = Int::from(3).pow(400) * Int::from(5).pow(400);
+    }).join().unwrap();
+}
@@ -22,7 +22,10 @@
 //! on the same data within a constant modular field.
 //!
 
+use num_integer::Integer;
+
 use int::Int;
+use int::reduce::Reducer;
 
 /// A Montgomery modulus.
 ///
@@ -102,6 +105,22 @@ pub struct MtgyModulus<'a> {
 /// MtgyInt from different MtgyModulus).
 pub struct MtgyInt(Int);
 
+impl Drop for MtgyInt {
+    fn drop(&mut self) {
+        // The value is padded to the size of the modulus, which a plain `Int`
+        // isn't allowed to be
+        self.0.normalize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl MtgyInt {
+    /// Sets the value to zero, overwriting its memory, like `Int::zeroize`.
+    pub fn zeroize(&mut self) {
+        self.0.zeroize()
+    }
+}
+
 impl<'a> MtgyModulus<'a> {
     /// Builds a pre-optimized MtgyModulus to perform.
     ///
@@ -128,12 +147,12 @@ impl<'a> MtgyModulus<'a> {
         unsafe {
             assert_eq!(a.abs_size(), 2*self.limbs as i32);
             let mut t = Int::with_capacity(2 * self.limbs as u32);
-            ::ll::copy_incr(a.limbs(), t.limbs_uninit(), a.abs_size());
+            ::ll::copy_incr(a.limbs(), t.limbs_uninit(), a.abs_len());
             for i in a.abs_size()..(t.cap as i32) {
                 *t.limbs_uninit().offset(i as isize) = ::ll::limb::Limb(0);
             }
             ::ll::mtgy::redc(a.limbs_uninit(),
-                             self.limbs as i32,
+                             self.limbs,
                              self.modulus.limbs(),
                              self.modulus_inv0,
                              t.limbs_uninit());
@@ -155,9 +174,9 @@ impl<'a> MtgyModulus<'a> {
             t.size = t.cap as i32;
             ::ll::mul(t.limbs_uninit(),
                       a.0.limbs(),
-                      self.limbs as i32,
+                      self.limbs,
                       b.0.limbs(),
-                      self.limbs as i32);
+                      self.limbs);
             self.redc(&mut t);
             MtgyInt(t)
         }
@@ -174,7 +193,7 @@ impl<'a> MtgyModulus<'a> {
             assert_eq!(a.0.abs_size(), self.limbs as i32);
             let mut t = Int::with_capacity(2 * self.limbs as u32);
             t.size = t.cap as i32;
-            ::ll::sqr(t.limbs_uninit(), a.0.limbs(), self.limbs as i32);
+            ::ll::sqr(t.limbs_uninit(), a.0.limbs(), self.limbs);
             self.redc(&mut t);
             MtgyInt(t)
         }
@@ -196,16 +215,50 @@ impl<'a> MtgyModulus<'a> {
             assert_eq!(basis.0.abs_size(), self.limbs as i32);
             assert!(exponent.sign() >= 0);
             ::ll::mtgy::modpow(result.0.limbs_uninit(),
-                               self.limbs as i32,
+                               self.limbs,
                                self.modulus.limbs(),
                                self.modulus_inv0,
                                basis.0.limbs(),
                                exponent.limbs(),
-                               exponent.abs_size());
+                               exponent.abs_len());
         }
         result
     }
 
+    /// Computes `base^exp` modulo the modulus for each `(base, exp)` pair.
+    ///
+    /// Unlike `pow`, the bases and the results are in natural form. With the
+    /// `rayon` feature, the exponentiations are spread over rayon's thread
+    /// pool, all sharing this modulus.
+    ///
+    /// ```rust
+    /// # use framp::int::Int;
+    /// # use framp::int::mtgy::*;
+    /// let m = Int::from(1009);
+    /// let modulus = MtgyModulus::new(&m);
+    /// let r = modulus.pow_batch(&[(Int::from(2), Int::from(10)), (Int::from(5), Int::from(3))]);
+    /// assert_eq!(r, [Int::from(15), Int::from(125)]);
+    /// ```
+    ///
+    /// # Panic
+    ///
+    /// Panics if an exponent is negative.
+    pub fn pow_batch(&self, items: &[(Int, Int)]) -> Vec<Int> {
+        #[cfg(not(feature = "rayon"))]
+        let results = items.iter().map(|&(ref base, ref exp)| self.pow_natural(base, exp)).collect();
+        #[cfg(feature = "rayon")]
+        let results = {
+            use rayon::prelude::*;
+            items.par_iter().map(|&(ref base, ref exp)| self.pow_natural(base, exp)).collect()
+        };
+        results
+    }
+
+    fn pow_natural(&self, base: &Int, exp: &Int) -> Int {
+        let b = self.to_mtgy(&base.mod_floor(self.modulus));
+        self.to_int(&self.pow(&b, exp))
+    }
+
     fn montgomerize(&self, a: &mut Int) {
         Self::pad_to(a, self.limbs);
     }
@@ -238,7 +291,7 @@ impl<'a> MtgyModulus<'a> {
         assert_eq!(a.0.abs_size(), self.limbs as i32);
         let mut it = unsafe {
             let mut it = Int::with_capacity(2 * self.limbs as u32);
-            ::ll::copy_incr(a.0.limbs(), it.limbs_uninit(), self.limbs as i32);
+            ::ll::copy_incr(a.0.limbs(), it.limbs_uninit(), self.limbs);
             it.size = self.limbs as i32;
             it.normalize();
             it
@@ -251,6 +304,35 @@ impl<'a> MtgyModulus<'a> {
     }
 }
 
+/// Montgomery reduction. The representation of the reducer is the
+/// Montgomery form, normalized rather than padded like `MtgyInt`.
+impl<'a> Reducer for MtgyModulus<'a> {
+    fn modulus(&self) -> &Int {
+        self.modulus
+    }
+
+    // This also divides by R, which keeps the Montgomery form of a product
+    fn reduce(&self, mut a: Int) -> Int {
+        Self::pad_to(&mut a, 2 * self.limbs);
+        self.redc(&mut a);
+        a.normalize();
+        a
+    }
+
+    fn to_repr(&self, a: &Int) -> Int {
+        (a * &self.r) % self.modulus
+    }
+
+    fn from_repr(&self, a: &Int) -> Int {
+        self.reduce(a.clone())
+    }
+
+    fn pow(&self, base: &Int, exp: &Int) -> Int {
+        assert!(exp.sign() >= 0, "negative exponent");
+        self.pow_natural(base, exp)
+    }
+}
+
 #[cfg(target_pointer_width = "64")]
 #[test]
 fn redc() {
@@ -317,3 +399,20 @@ fn mul() {
     }
 }
 
+#[test]
+fn pow_batch() {
+    use rand;
+    use int::RandomInt;
+    let mut rng = rand::thread_rng();
+    let m = rng.gen_uint(700) | Int::one();
+    let mg = MtgyModulus::new(&m);
+
+    let mut items: Vec<(Int, Int)> = (0..20).map(|_| (rng.gen_int(800), rng.gen_uint(300))).collect();
+    items.push((Int::zero(), Int::from(5)));
+    items.push((Int::from(7), Int::zero()));
+    let r = mg.pow_batch(&items);
+    assert_eq!(r.len(), items.len());
+    for (&(ref b, ref e), x) in items.iter().zip(&r) {
+        assert_eq!(*x, b.mod_floor(&m).modpow(e, &m));
+    }
+}
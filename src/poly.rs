@@ -0,0 +1,410 @@
+// Copyright 2015 The Ramp Developers
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Dense polynomials with `Int` coefficients.
+//!
+//! Products are computed with Kronecker substitution: both polynomials are
+//! evaluated at a large enough power of two, packing every coefficient into
+//! its own run of limbs, and the two resulting numbers are multiplied with a
+//! single `Int` multiplication. This lets polynomial multiplication use the
+//! same subquadratic algorithms as `Int` multiplication.
+
+use std::cmp;
+use std::mem;
+use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use int::{Int, Sign};
+use ll::limb::Limb;
+
+/// A polynomial with `Int` coefficients.
+///
+/// The coefficients are stored lowest degree first, and never have a
+/// trailing zero, so the zero polynomial has no coefficients at all.
+///
+/// # Examples
+///
+/// ```rust
+/// use framp::Int;
+/// use framp::poly::Poly;
+///
+/// // (x + 1) * (x - 1) = x^2 - 1
+/// let a = Poly::new(vec![Int::from(1), Int::from(1)]);
+/// let b = Poly::new(vec![Int::from(-1), Int::from(1)]);
+/// let p = &a * &b;
+/// assert_eq!(p.coeffs(), &[Int::from(-1), Int::from(0), Int::from(1)]);
+/// assert_eq!(p.eval(&Int::from(10)), 99);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Poly {
+    coeffs: Vec<Int>,
+}
+
+impl Poly {
+    /// Creates a polynomial from its coefficients, lowest degree first.
+    pub fn new(coeffs: Vec<Int>) -> Poly {
+        let mut p = Poly { coeffs: coeffs };
+        p.normalize();
+        p
+    }
+
+    /// Returns the zero polynomial.
+    pub fn zero() -> Poly {
+        Poly { coeffs: Vec::new() }
+    }
+
+    /// Returns the coefficients, lowest degree first.
+    pub fn coeffs(&self) -> &[Int] {
+        &self.coeffs
+    }
+
+    /// Consumes the polynomial, returning its coefficients.
+    pub fn into_coeffs(self) -> Vec<Int> {
+        self.coeffs
+    }
+
+    /// Returns true if this is the zero polynomial.
+    pub fn is_zero(&self) -> bool {
+        self.coeffs.is_empty()
+    }
+
+    /// Returns the degree, or `None` for the zero polynomial.
+    pub fn degree(&self) -> Option<usize> {
+        self.coeffs.len().checked_sub(1)
+    }
+
+    /// Returns the coefficient of the highest degree term, or `None` for the
+    /// zero polynomial.
+    pub fn leading_coeff(&self) -> Option<&Int> {
+        self.coeffs.last()
+    }
+
+    /// Evaluates the polynomial at `x`.
+    pub fn eval(&self, x: &Int) -> Int {
+        let mut acc = Int::zero();
+        for c in self.coeffs.iter().rev() {
+            acc *= x;
+            acc += c;
+        }
+        acc
+    }
+
+    /// Pseudo-divides `self` by `d`, returning the quotient and remainder.
+    ///
+    /// With `l` the leading coefficient of `d` and `k` one more than the
+    /// difference of the degrees, the result satisfies
+    /// `l^k * self = q * d + r`, with the degree of `r` less than that of
+    /// `d`. Unlike ordinary division, this never leaves the integers. If
+    /// `self` has a lower degree than `d`, the quotient is zero and the
+    /// remainder is `self`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `d` is the zero polynomial.
+    pub fn pseudo_divrem(&self, d: &Poly) -> (Poly, Poly) {
+        assert!(!d.is_zero(), "division by the zero polynomial");
+        let n = d.coeffs.len() - 1;
+        if self.coeffs.len() <= n {
+            return (Poly::zero(), self.clone());
+        }
+        let m = self.coeffs.len() - 1;
+        let l = &d.coeffs[n];
+
+        let mut q = vec![Int::zero(); m - n + 1];
+        let mut r = self.coeffs.clone();
+        // Each step computes r = l*r - c*x^k*d and q = l*q + c*x^k, where c
+        // is the leading coefficient of r, which cancels the top term of r.
+        for k in (0..m - n + 1).rev() {
+            let c = r.pop().unwrap();
+            for qi in &mut q[k + 1..] {
+                *qi *= l;
+            }
+            for ri in &mut r {
+                *ri *= l;
+            }
+            for (ri, di) in r[k..].iter_mut().zip(&d.coeffs[..n]) {
+                *ri -= &c * di;
+            }
+            q[k] = c;
+        }
+
+        (Poly::new(q), Poly::new(r))
+    }
+
+    // Drops the zero coefficients at the top
+    fn normalize(&mut self) {
+        while self.coeffs.last().map_or(false, |c| c.sign() == 0) {
+            self.coeffs.pop();
+        }
+    }
+
+    fn add_coeffs(&mut self, other: &Poly, negate: bool) {
+        if self.coeffs.len() < other.coeffs.len() {
+            let n = other.coeffs.len();
+            self.coeffs.resize(n, Int::zero());
+        }
+        for (a, b) in self.coeffs.iter_mut().zip(&other.coeffs) {
+            if negate {
+                *a -= b;
+            } else {
+                *a += b;
+            }
+        }
+        self.normalize();
+    }
+
+    fn mul_ref(&self, other: &Poly) -> Poly {
+        if self.is_zero() || other.is_zero() {
+            return Poly::zero();
+        }
+        if self.coeffs.len() == 1 || other.coeffs.len() == 1 {
+            let (c, p) = if self.coeffs.len() == 1 {
+                (&self.coeffs[0], other)
+            } else {
+                (&other.coeffs[0], self)
+            };
+            return Poly::new(p.coeffs.iter().map(|x| x * c).collect());
+        }
+
+        // Every coefficient of the product is a sum of at most `terms`
+        // products, so this many bits hold its magnitude, with one more for
+        // the sign.
+        let terms = cmp::min(self.coeffs.len(), other.coeffs.len());
+        let bits = max_bits(&self.coeffs) + max_bits(&other.coeffs)
+            + (mem::size_of::<usize>() * 8 - terms.leading_zeros() as usize) + 1;
+        let w = (bits + Limb::BITS - 1) / Limb::BITS;
+
+        let prod = pack(&self.coeffs, w) * pack(&other.coeffs, w);
+        Poly::new(unpack(&prod, w, self.coeffs.len() + other.coeffs.len() - 1))
+    }
+}
+
+fn max_bits(coeffs: &[Int]) -> usize {
+    coeffs.iter().map(|c| c.bit_length() as usize).max().unwrap_or(0)
+}
+
+// Evaluates the polynomial at 2^(w * Limb::BITS). Every coefficient has to fit
+// in `w` limbs.
+fn pack(coeffs: &[Int], w: usize) -> Int {
+    let mut pos = vec![Limb(0); coeffs.len() * w];
+    let mut neg = vec![Limb(0); coeffs.len() * w];
+    for (i, c) in coeffs.iter().enumerate() {
+        let src = c.as_limb_slice();
+        let dst = if c.sign() < 0 { &mut neg } else { &mut pos };
+        dst[i * w..i * w + src.len()].copy_from_slice(src);
+    }
+    Int::from_limbs(Sign::Positive, &pos) - Int::from_limbs(Sign::Positive, &neg)
+}
+
+// Recovers the `n` coefficients of a polynomial from its value at
+// 2^(w * Limb::BITS), given that all of them are less than half of that in
+// magnitude.
+fn unpack(v: &Int, w: usize, n: usize) -> Vec<Int> {
+    let limbs = v.as_limb_slice();
+    let base = Int::one() << (w * Limb::BITS);
+    let half = Int::one() << (w * Limb::BITS - 1);
+
+    let mut coeffs = Vec::with_capacity(n);
+    let mut carry = false;
+    for i in 0..n {
+        let lo = cmp::min(i * w, limbs.len());
+        let hi = cmp::min(lo + w, limbs.len());
+        let mut c = Int::from_limbs(Sign::Positive, &limbs[lo..hi]);
+        // Digits in the top half stand for negative coefficients, which
+        // borrowed one from the next digit up
+        if carry {
+            c += 1;
+        }
+        carry = c >= half;
+        if carry {
+            c -= &base;
+        }
+        if v.sign() < 0 {
+            c.negate();
+        }
+        coeffs.push(c);
+    }
+    coeffs
+}
+
+impl From<Int> for Poly {
+    fn from(c: Int) -> Poly {
+        Poly::new(vec![c])
+    }
+}
+
+impl From<Vec<Int>> for Poly {
+    fn from(coeffs: Vec<Int>) -> Poly {
+        Poly::new(coeffs)
+    }
+}
+
+impl Neg for Poly {
+    type Output = Poly;
+
+    fn neg(mut self) -> Poly {
+        for c in &mut self.coeffs {
+            c.negate();
+        }
+        self
+    }
+}
+
+impl<'a> Neg for &'a Poly {
+    type Output = Poly;
+
+    fn neg(self) -> Poly {
+        -self.clone()
+    }
+}
+
+impl<'a> AddAssign<&'a Poly> for Poly {
+    fn add_assign(&mut self, other: &'a Poly) {
+        self.add_coeffs(other, false);
+    }
+}
+
+impl<'a> SubAssign<&'a Poly> for Poly {
+    fn sub_assign(&mut self, other: &'a Poly) {
+        self.add_coeffs(other, true);
+    }
+}
+
+impl<'a> MulAssign<&'a Poly> for Poly {
+    fn mul_assign(&mut self, other: &'a Poly) {
+        *self = self.mul_ref(other);
+    }
+}
+
+macro_rules! impl_poly_op {
+    ($tr:ident, $meth:ident, $tr_assign:ident, $meth_assign:ident) => {
+        impl $tr_assign<Poly> for Poly {
+            fn $meth_assign(&mut self, other: Poly) {
+                self.$meth_assign(&other);
+            }
+        }
+
+        impl<'a, 'b> $tr<&'a Poly> for &'b Poly {
+            type Output = Poly;
+
+            fn $meth(self, other: &'a Poly) -> Poly {
+                self.clone().$meth(other)
+            }
+        }
+
+        impl<'a> $tr<Poly> for &'a Poly {
+            type Output = Poly;
+
+            fn $meth(self, other: Poly) -> Poly {
+                self.$meth(&other)
+            }
+        }
+
+        impl<'a> $tr<&'a Poly> for Poly {
+            type Output = Poly;
+
+            fn $meth(mut self, other: &'a Poly) -> Poly {
+                self.$meth_assign(other);
+                self
+            }
+        }
+
+        impl $tr<Poly> for Poly {
+            type Output = Poly;
+
+            fn $meth(mut self, other: Poly) -> Poly {
+                self.$meth_assign(&other);
+                self
+            }
+        }
+    }
+}
+
+impl_poly_op!(Add, add, AddAssign, add_assign);
+impl_poly_op!(Sub, sub, SubAssign, sub_assign);
+impl_poly_op!(Mul, mul, MulAssign, mul_assign);
+
+#[cfg(test)]
+fn schoolbook(a: &Poly, b: &Poly) -> Poly {
+    if a.is_zero() || b.is_zero() {
+        return Poly::zero();
+    }
+    let mut c = vec![Int::zero(); a.coeffs.len() + b.coeffs.len() - 1];
+    for (i, x) in a.coeffs.iter().enumerate() {
+        for (j, y) in b.coeffs.iter().enumerate() {
+            c[i + j] += x * y;
+        }
+    }
+    Poly::new(c)
+}
+
+#[cfg(test)]
+fn random_poly(len: usize, bits: usize) -> Poly {
+    use int::RandomInt;
+    let mut rng = ::rand::thread_rng();
+    Poly::new((0..len).map(|_| rng.gen_int(bits)).collect())
+}
+
+#[test]
+fn add_sub() {
+    let a = Poly::new(vec![Int::from(1), Int::from(2), Int::from(3)]);
+    let b = Poly::new(vec![Int::from(4), Int::from(5), Int::from(-3)]);
+    assert_eq!((&a + &b).coeffs(), &[Int::from(5), Int::from(7)]);
+    assert_eq!((&a - &a), Poly::zero());
+    assert_eq!((&a - &b) + &b, a);
+    assert_eq!(-(&a - &b), &b - &a);
+    assert_eq!(Poly::new(vec![Int::zero(), Int::zero()]).degree(), None);
+}
+
+#[test]
+fn mul() {
+    let x = Poly::new(vec![Int::zero(), Int::one()]);
+    assert_eq!((&x * &x).degree(), Some(2));
+    assert_eq!(&x * &Poly::zero(), Poly::zero());
+
+    for &(la, lb, bits) in &[(1, 5, 10), (3, 3, 1), (7, 20, 64), (40, 33, 200), (100, 2, 3)] {
+        let a = random_poly(la, bits);
+        let b = random_poly(lb, bits + 17);
+        let p = &a * &b;
+        assert_eq!(p, schoolbook(&a, &b));
+        let v = Int::from(-12345);
+        assert_eq!(p.eval(&v), a.eval(&v) * b.eval(&v));
+    }
+
+    // Coefficients that are all at the top of their range
+    let big = Int::from(-1) << 190;
+    let a = Poly::new(vec![big.clone(); 9]);
+    assert_eq!(&a * &a, schoolbook(&a, &a));
+}
+
+#[test]
+fn pseudo_divrem() {
+    for &(la, lb) in &[(10, 3), (5, 5), (3, 6), (20, 1)] {
+        let a = random_poly(la, 70);
+        let d = random_poly(lb, 40);
+        if d.is_zero() {
+            continue;
+        }
+        let (q, r) = a.pseudo_divrem(&d);
+        assert!(r.degree() < d.degree());
+        let k = (a.coeffs.len() + 1).saturating_sub(d.coeffs.len());
+        let scale = Poly::from(d.leading_coeff().unwrap().pow(k));
+        assert_eq!(&scale * &a, &q * &d + &r);
+    }
+}
+
+#[test]
+#[should_panic(expected = "division by the zero polynomial")]
+fn pseudo_divrem_zero() {
+    Poly::new(vec![Int::one()]).pseudo_divrem(&Poly::zero());
+}
@@ -0,0 +1,338 @@
+// Copyright 2015 The Ramp Developers
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Division by a divisor prepared once.
+//!
+//! Dividing by an `Int` first normalizes the divisor, shifting it so the top
+//! bit of its most significant limb is set, and computes a reciprocal of its
+//! high limbs. A `PreparedDivisor` keeps both, so that code dividing many
+//! numbers by the same value, like base conversion or the reductions of a
+//! Chinese remainder computation, only pays for them once.
+//!
+//! `LongDivision` builds on it to divide a dividend that arrives in pieces,
+//! like the digits of a file too large to be read into a single `Int`.
+
+use int::{Int, Sign, ParseIntError, ErrorKind};
+use ll;
+use ll::limb::Limb;
+use ll::limb_ptr::{Limbs, LimbsMut};
+
+/// A divisor, normalized and with its reciprocal computed, for
+/// `Int::div_rem_prepared`.
+///
+/// # Examples
+///
+/// ```rust
+/// use framp::Int;
+/// use framp::int::prepared::PreparedDivisor;
+///
+/// let d = PreparedDivisor::new(&Int::from(1000));
+/// for &x in &[123456, -7890, 42] {
+///     let x = Int::from(x);
+///     assert_eq!(x.div_rem_prepared(&d), x.divmod(d.divisor()));
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct PreparedDivisor {
+    divisor: Int,
+    // The magnitude of the divisor, shifted left by `shift` bits
+    norm: Vec<Limb>,
+    shift: u32,
+    // The reciprocal of the high limbs of `norm`
+    dinv: Limb,
+}
+
+impl PreparedDivisor {
+    /// Prepares `divisor` for repeated divisions.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the divisor is zero.
+    pub fn new(divisor: &Int) -> PreparedDivisor {
+        if divisor.sign() == 0 {
+            ll::divide_by_zero();
+        }
+        let x = divisor.as_limb_slice();
+        let ds = x.len();
+        let shift = x[ds - 1].leading_zeros() as u32;
+
+        let mut norm = vec![Limb(0); ds];
+        let dinv;
+        unsafe {
+            let xp = Limbs::new(x.as_ptr(), 0, ds);
+            let np = LimbsMut::new(norm.as_mut_ptr(), 0, ds);
+            if shift == 0 {
+                ll::copy_incr(xp, np, ds);
+            } else {
+                ll::shl(np, xp, ds, shift);
+            }
+            dinv = ll::divisor_inverse(np.as_const(), ds);
+        }
+
+        PreparedDivisor {
+            divisor: divisor.clone(),
+            norm: norm,
+            shift: shift,
+            dinv: dinv,
+        }
+    }
+
+    /// Returns the divisor.
+    pub fn divisor(&self) -> &Int {
+        &self.divisor
+    }
+}
+
+impl Int {
+    /// Divides `self` by a prepared divisor, returning the quotient and the
+    /// remainder, like `divmod`: the quotient is rounded towards zero and
+    /// the remainder has the sign of `self`.
+    pub fn div_rem_prepared(&self, d: &PreparedDivisor) -> (Int, Int) {
+        debug_assert!(self.well_formed());
+        let ns = self.abs_len();
+        let ds = d.norm.len();
+        if ns < ds {
+            return (Int::zero(), self.clone());
+        }
+
+        let out_size = (ns - ds + 1) as i32;
+        let mut q = Int::with_capacity(out_size as u32);
+        q.size = out_size * self.sign() * d.divisor.sign();
+
+        let mut r = Int::with_capacity(ds as u32);
+        r.size = ds as i32 * self.sign();
+
+        unsafe {
+            ll::divrem_preinv(q.limbs_mut(), r.limbs_mut(),
+                              self.limbs(), ns,
+                              Limbs::new(d.norm.as_ptr(), 0, ds), ds,
+                              d.shift, d.dinv);
+        }
+
+        q.normalize();
+        r.normalize();
+        q.validate("div_rem_prepared");
+        r.validate("div_rem_prepared");
+
+        (q, r)
+    }
+}
+
+/// Long division of a non-negative dividend that is supplied in pieces, most
+/// significant first, by a fixed positive divisor.
+///
+/// Each piece of the dividend yields a piece of the quotient of the same
+/// length, so only the running remainder is kept between pieces and neither
+/// the dividend nor the quotient has to fit in memory. A dividend can be
+/// supplied as limbs, as bytes or as decimal digits, but a division should
+/// stick to one of them.
+///
+/// # Examples
+///
+/// ```rust
+/// use framp::Int;
+/// use framp::int::prepared::{LongDivision, PreparedDivisor};
+///
+/// let d = PreparedDivisor::new(&Int::from(7));
+/// let mut div = LongDivision::new(&d);
+/// let mut q = div.push_ascii_digits(b"1234").unwrap();
+/// q.extend(div.push_ascii_digits(b"56789").unwrap());
+/// assert_eq!(q, b"017636684");
+/// assert_eq!(div.finish(), 123456789 % 7);
+/// ```
+pub struct LongDivision<'a> {
+    divisor: &'a PreparedDivisor,
+    rem: Int,
+    // The number of decimal digits pushed, for the offsets of errors
+    pushed: usize,
+}
+
+impl<'a> LongDivision<'a> {
+    /// Starts a division by `divisor`.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the divisor is negative.
+    pub fn new(divisor: &'a PreparedDivisor) -> LongDivision<'a> {
+        assert!(divisor.divisor.sign() > 0, "LongDivision: negative divisor");
+        LongDivision {
+            divisor: divisor,
+            rem: Int::zero(),
+            pushed: 0,
+        }
+    }
+
+    /// Appends the limbs in `chunk` to the dividend and returns as many limbs
+    /// of the quotient. The limbs of a chunk are least significant first, like
+    /// those of `Int::as_limb_slice`, while the chunks go from the most
+    /// significant to the least.
+    pub fn push_limbs(&mut self, chunk: &[Limb]) -> Vec<Limb> {
+        let n = (&self.rem << (chunk.len() * Limb::BITS)) + Int::from_limbs(Sign::Positive, chunk);
+        let mut q = self.divide(n).as_limb_slice().to_vec();
+        q.resize(chunk.len(), Limb(0));
+        q
+    }
+
+    /// Appends the bytes in `chunk`, most significant first, to the dividend
+    /// and returns as many bytes of the quotient, also most significant first.
+    pub fn push_bytes(&mut self, chunk: &[u8]) -> Vec<u8> {
+        let n = (&self.rem << (8 * chunk.len())) + Int::from_bytes_be(chunk);
+        let q = self.divide(n).to_bytes_be();
+        let mut out = vec![0; chunk.len() - q.len()];
+        out.extend_from_slice(&q);
+        out
+    }
+
+    /// Appends the ASCII decimal digits in `digits` to the dividend and
+    /// returns as many ASCII digits of the quotient.
+    ///
+    /// Returns an error if `digits` contains anything but `'0'` to `'9'`, in
+    /// which case the division is left as it was. The offset of the error
+    /// counts all the digits pushed so far.
+    pub fn push_ascii_digits(&mut self, digits: &[u8]) -> Result<Vec<u8>, ParseIntError> {
+        if let Some(off) = digits.iter().position(|&c| c < b'0' || c > b'9') {
+            return Err(ParseIntError::new(ErrorKind::InvalidDigit, self.pushed + off));
+        }
+        self.pushed += digits.len();
+        if digits.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let raw: Vec<u8> = digits.iter().map(|&c| c - b'0').collect();
+        let n = &self.rem * Int::from(10).pow(digits.len()) + Int::from_raw_digits(&raw, 10, 1);
+        let q = self.divide(n).to_str_radix(10, false);
+        let mut out = vec![b'0'; digits.len() - q.len()];
+        out.extend_from_slice(q.as_bytes());
+        Ok(out)
+    }
+
+    /// Returns the remainder of the dividend supplied so far.
+    pub fn remainder(&self) -> &Int {
+        &self.rem
+    }
+
+    /// Consumes the division, returning the remainder of the whole dividend.
+    pub fn finish(self) -> Int {
+        self.rem
+    }
+
+    // Divides `n`, which is less than the divisor times the base to the
+    // length of the piece, keeping the remainder for the next piece
+    fn divide(&mut self, n: Int) -> Int {
+        let (q, r) = n.div_rem_prepared(self.divisor);
+        self.rem = r;
+        q
+    }
+}
+
+#[test]
+fn prepared_matches_divmod() {
+    use rand::{self, Rng};
+    use int::RandomInt;
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..200 {
+        let dbits = rng.gen_range(1, 600);
+        let mut divisor = rng.gen_int(dbits);
+        if divisor == 0 {
+            divisor = Int::one();
+        }
+        let d = PreparedDivisor::new(&divisor);
+        for _ in 0..4 {
+            let bits = rng.gen_range(0, 1300);
+            let x = rng.gen_int(bits);
+            assert_eq!(x.div_rem_prepared(&d), x.divmod(&divisor),
+                       "{} / {}", x, divisor);
+        }
+    }
+}
+
+#[test]
+fn prepared_edge_cases() {
+    let max = (Int::one() << Limb::BITS) - 1;
+    for divisor in &[Int::one(), Int::from(-3), max.clone(), (&max << 64) + 1, &max << 200] {
+        let d = PreparedDivisor::new(divisor);
+        for x in &[Int::zero(), divisor.clone(), -divisor, divisor - 1, divisor * divisor - 1] {
+            assert_eq!(x.div_rem_prepared(&d), x.divmod(divisor), "{} / {}", x, divisor);
+        }
+    }
+}
+
+#[test]
+#[should_panic]
+fn prepared_zero() {
+    PreparedDivisor::new(&Int::zero());
+}
+
+#[test]
+fn long_division() {
+    use rand::{self, Rng};
+    use int::RandomInt;
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..50 {
+        let dbits = rng.gen_range(1, 400);
+        let divisor = rng.gen_uint(dbits) + 1;
+        let d = PreparedDivisor::new(&divisor);
+        let xbits = rng.gen_range(0, 3000);
+        let x = rng.gen_uint(xbits);
+        let (q, r) = x.divmod(&divisor);
+
+        let limbs = x.as_limb_slice();
+        let mut div = LongDivision::new(&d);
+        let mut ql = Vec::new();
+        let mut end = limbs.len();
+        while end > 0 {
+            let start = end.saturating_sub(rng.gen_range(1, 10));
+            let mut piece = div.push_limbs(&limbs[start..end]);
+            piece.extend_from_slice(&ql);
+            ql = piece;
+            end = start;
+        }
+        assert_eq!(Int::from_limbs(Sign::Positive, &ql), q);
+        assert_eq!(div.finish(), r);
+
+        let bytes = x.to_bytes_be();
+        let mut div = LongDivision::new(&d);
+        let mut qb = Vec::new();
+        for chunk in bytes.chunks(rng.gen_range(1, 70)) {
+            qb.extend(div.push_bytes(chunk));
+        }
+        assert_eq!(qb.len(), bytes.len());
+        assert_eq!(Int::from_bytes_be(&qb), q);
+        assert_eq!(div.remainder(), &r);
+
+        let digits = x.to_string();
+        let mut div = LongDivision::new(&d);
+        let mut qd = Vec::new();
+        for chunk in digits.as_bytes().chunks(rng.gen_range(1, 200)) {
+            qd.extend(div.push_ascii_digits(chunk).unwrap());
+        }
+        assert_eq!(qd.len(), digits.len());
+        assert_eq!(String::from_utf8(qd).unwrap().parse::<Int>().unwrap(), q);
+        assert_eq!(div.finish(), r);
+    }
+}
+
+#[test]
+fn long_division_bad_digit() {
+    let d = PreparedDivisor::new(&Int::from(3));
+    let mut div = LongDivision::new(&d);
+    assert_eq!(div.push_ascii_digits(b"12").unwrap(), b"04");
+    let err = div.push_ascii_digits(b"3x4").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidDigit);
+    assert_eq!(err.offset(), 3);
+    assert_eq!(div.push_ascii_digits(b"5").unwrap(), b"1");
+    assert_eq!(div.finish(), 2);
+}
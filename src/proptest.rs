@@ -0,0 +1,279 @@
+// Copyright 2015 The Ramp Developers
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Strategies for testing code that uses `Int` with proptest.
+//!
+//! This module is available with the `proptest` feature.
+//!
+//! Generated values are biased towards the edge cases of multi-precision
+//! arithmetic: sizes are spread evenly over the allowed number of bits, so
+//! small values are as likely as large ones, and many limbs are all zeros or
+//! all ones, which exercises long carry and borrow chains. A failing value is
+//! shrunk towards zero, or towards the end of the range nearest to zero.
+//!
+//! ```rust
+//! extern crate framp;
+//! extern crate proptest;
+//!
+//! use framp::proptest::any_int;
+//! use proptest::strategy::{Strategy, ValueTree};
+//! use proptest::test_runner::{Config, TestRunner};
+//!
+//! fn main() {
+//!     let mut runner = TestRunner::new(Config::default());
+//!     for _ in 0..100 {
+//!         let a = any_int().new_value(&mut runner).unwrap().current();
+//!         let b = any_int().new_value(&mut runner).unwrap().current();
+//!         assert_eq!(&a + &b - &b, a);
+//!     }
+//! }
+//! ```
+
+use std::ops::Range;
+
+use proptest_crate::strategy::{Strategy, ValueTree};
+use proptest_crate::test_runner::TestRunner;
+use rand::Rng;
+
+use int::{Int, Sign};
+use ll::limb::{BaseInt, Limb};
+
+// The largest values generated by `any_int`
+const ANY_INT_BITS: usize = 1024;
+
+/// A strategy producing `Int`s, see the functions of this module.
+#[derive(Clone, Debug)]
+pub struct IntStrategy {
+    kind: Kind,
+}
+
+#[derive(Clone, Debug)]
+enum Kind {
+    Bits { max_bits: usize, signed: bool },
+    Range { start: Int, end: Int },
+}
+
+/// Returns a strategy for `Int`s of either sign with up to 1024 bits.
+pub fn any_int() -> IntStrategy {
+    IntStrategy {
+        kind: Kind::Bits { max_bits: ANY_INT_BITS, signed: true },
+    }
+}
+
+/// Returns a strategy for non-negative `Int`s of at most `bits` bits.
+pub fn int_with_bits(bits: usize) -> IntStrategy {
+    IntStrategy {
+        kind: Kind::Bits { max_bits: bits, signed: false },
+    }
+}
+
+/// Returns a strategy for `Int`s in `range`, which includes its start but not
+/// its end. Unlike the other strategies, values are uniformly distributed.
+///
+/// # Panics
+///
+/// Panics if the range is empty.
+pub fn int_in_range(range: Range<Int>) -> IntStrategy {
+    assert!(range.start < range.end, "int_in_range: empty range");
+    IntStrategy {
+        kind: Kind::Range { start: range.start, end: range.end },
+    }
+}
+
+impl IntStrategy {
+    fn generate<R: Rng + ?Sized>(&self, rng: &mut R) -> Int {
+        match self.kind {
+            Kind::Bits { max_bits, signed } => {
+                let bits = (rng.next_u64() % (max_bits as u64 + 1)) as usize;
+                let x = edgy_bits(rng, bits);
+                if signed && rng.next_u32() & 1 == 1 { -x } else { x }
+            }
+            Kind::Range { ref start, ref end } => {
+                start + Int::random_below(rng, &(end - start))
+            }
+        }
+    }
+
+    // The value that failing cases shrink towards
+    fn target(&self) -> Int {
+        match self.kind {
+            Kind::Bits { .. } => Int::zero(),
+            Kind::Range { ref start, ref end } => {
+                if start.sign() >= 0 {
+                    start.clone()
+                } else if end.sign() <= 0 {
+                    end - 1
+                } else {
+                    Int::zero()
+                }
+            }
+        }
+    }
+}
+
+impl Strategy for IntStrategy {
+    type Value = IntValueTree;
+
+    fn new_value(&self, runner: &mut TestRunner) -> Result<IntValueTree, String> {
+        let value = self.generate(runner.rng());
+        Ok(IntValueTree::new(self.target(), value))
+    }
+}
+
+/// The shrinkable `Int`s produced by `IntStrategy`.
+///
+/// Shrinking is a binary search on the distance from the target value, as
+/// proptest does for the primitive integers.
+#[derive(Clone, Debug)]
+pub struct IntValueTree {
+    target: Int,
+    negative: bool,
+    // Bounds and current value of the distance from `target`
+    lo: Int,
+    curr: Int,
+    hi: Int,
+}
+
+impl IntValueTree {
+    fn new(target: Int, value: Int) -> IntValueTree {
+        let dist = &value - &target;
+        IntValueTree {
+            target: target,
+            negative: dist.sign() < 0,
+            lo: Int::zero(),
+            curr: dist.clone().abs(),
+            hi: dist.abs(),
+        }
+    }
+
+    fn reposition(&mut self) -> bool {
+        let mid = &self.lo + ((&self.hi - &self.lo) >> 1);
+        if mid == self.curr {
+            false
+        } else {
+            self.curr = mid;
+            true
+        }
+    }
+}
+
+impl ValueTree for IntValueTree {
+    type Value = Int;
+
+    fn current(&self) -> Int {
+        if self.negative {
+            &self.target - &self.curr
+        } else {
+            &self.target + &self.curr
+        }
+    }
+
+    fn simplify(&mut self) -> bool {
+        if self.hi <= self.lo {
+            return false;
+        }
+        self.hi = self.curr.clone();
+        self.reposition()
+    }
+
+    fn complicate(&mut self) -> bool {
+        if self.hi <= self.lo {
+            return false;
+        }
+        self.lo = &self.curr + 1;
+        self.reposition()
+    }
+}
+
+fn random_limb<R: Rng + ?Sized>(rng: &mut R) -> Limb {
+    Limb(rng.next_u64() as BaseInt)
+}
+
+// A non-negative value of at most `bits` bits, where a quarter of the limbs
+// are all zeros or all ones.
+fn edgy_bits<R: Rng + ?Sized>(rng: &mut R, bits: usize) -> Int {
+    let n = (bits + Limb::BITS - 1) / Limb::BITS;
+    let mut limbs = Vec::with_capacity(n);
+    for _ in 0..n {
+        let l = match rng.next_u32() % 8 {
+            0 => Limb(0),
+            1 => !Limb(0),
+            _ => random_limb(rng),
+        };
+        limbs.push(l);
+    }
+    if bits % Limb::BITS != 0 {
+        let top = limbs.last_mut().unwrap();
+        *top = *top & ((Limb(1) << (bits % Limb::BITS)) - 1);
+    }
+    Int::from_limbs(Sign::Positive, &limbs)
+}
+
+#[test]
+fn bits_and_ranges() {
+    use proptest_crate::test_runner::Config;
+    let mut runner = TestRunner::new(Config::default());
+
+    for _ in 0..200 {
+        let x = int_with_bits(100).new_value(&mut runner).unwrap().current();
+        assert!(x.sign() >= 0 && x.bit_length() <= 100);
+
+        let x = any_int().new_value(&mut runner).unwrap().current();
+        assert!(x.abs().bit_length() as usize <= ANY_INT_BITS);
+    }
+
+    let lo = -(Int::from(1) << 200);
+    let hi = Int::from(-7);
+    let s = int_in_range(lo.clone()..hi.clone());
+    for _ in 0..200 {
+        let x = s.new_value(&mut runner).unwrap().current();
+        assert!(lo <= x && x < hi);
+    }
+}
+
+#[test]
+fn shrinking() {
+    use proptest_crate::test_runner::Config;
+    let mut runner = TestRunner::new(Config::default());
+    let cases = [
+        (any_int(), Int::zero()),
+        (int_in_range(Int::from(1000)..Int::from(1) << 300), Int::from(1000)),
+        (int_in_range(-(Int::from(1) << 300)..Int::from(-1000)), Int::from(-1001)),
+        (int_in_range(Int::from(-5)..Int::from(5)), Int::zero()),
+    ];
+    for &(ref s, ref target) in cases.iter() {
+        let mut tree = s.new_value(&mut runner).unwrap();
+        while tree.simplify() {}
+        assert_eq!(tree.current(), *target);
+    }
+
+    // A property that fails for values of at least 2^70 shrinks to exactly
+    // that value
+    let mut tree = int_with_bits(200).new_value(&mut runner).unwrap();
+    while tree.current().bit_length() <= 70 {
+        tree = int_with_bits(200).new_value(&mut runner).unwrap();
+    }
+    let limit = Int::from(1) << 70;
+    loop {
+        let progressed = if tree.current() >= limit {
+            tree.simplify()
+        } else {
+            tree.complicate()
+        };
+        if !progressed {
+            break;
+        }
+    }
+    assert!(tree.current() == limit || tree.current() == &limit - 1);
+}
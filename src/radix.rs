@@ -0,0 +1,294 @@
+// Copyright 2015 The Ramp Developers
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Cached radix conversion.
+//!
+//! Large numbers are converted to a non power-of-two base by recursively
+//! splitting them with powers of the base, which only depend on the base and
+//! on the size of the number. When many numbers of similar size are
+//! converted, those powers can be computed once and reused.
+//!
+//! It also provides `DigitAccumulator`, for parsing decimal numbers that
+//! arrive in pieces.
+
+use std::cmp::Ordering;
+use std::io;
+
+use ll;
+use ll::limb::Limb;
+use int::{Int, ParseIntError, ErrorKind};
+
+// Numbers up to this many limbs are converted directly, using the quadratic
+// algorithm in `ll::base::to_base`.
+const DC_THRESHOLD: i32 = 30;
+
+/// A conversion context for a given base.
+///
+/// `BaseConverter` keeps the powers `B^(2^k)` around, where `B` is the
+/// largest power of the base that fits in a single limb. The table grows as
+/// bigger numbers are converted and is reused by all subsequent conversions.
+///
+/// # Examples
+///
+/// ```rust
+/// use framp::Int;
+/// use framp::int::radix::BaseConverter;
+///
+/// let mut conv = BaseConverter::new(10);
+/// let i = Int::from(-1234567);
+/// assert_eq!(conv.to_str(&i, false), "-1234567");
+/// ```
+pub struct BaseConverter {
+    base: u8,
+    chunk_digits: usize,
+    powers: Vec<Int>,
+}
+
+impl BaseConverter {
+    /// Creates a new converter for the base `base`.
+    ///
+    /// Panics if `base` is less than two or greater than 36.
+    pub fn new(base: u8) -> BaseConverter {
+        if base < 2 || base > 36 {
+            panic!("Invalid base: {}", base);
+        }
+
+        let (powers, chunk_digits) = if (base as u32).is_power_of_two() {
+            // Power-of-two bases are converted in linear time anyway
+            (Vec::new(), 0)
+        } else {
+            let (big_base, digits) = ll::base::big_base(base as u32);
+            (vec![Int::from_single_limb(big_base)], digits as usize)
+        };
+
+        BaseConverter {
+            base: base,
+            chunk_digits: chunk_digits,
+            powers: powers,
+        }
+    }
+
+    /// Returns the base this converter was created for.
+    pub fn base(&self) -> u8 {
+        self.base
+    }
+
+    /// Returns a string containing the value of `n`, like `Int::to_str_radix`.
+    pub fn to_str(&mut self, n: &Int, upper: bool) -> String {
+        let mut buf = Vec::new();
+        self.write(n, &mut buf, upper).unwrap();
+
+        unsafe { String::from_utf8_unchecked(buf) }
+    }
+
+    /// Writes the value of `n` to `w`, like `Int::write_radix`.
+    pub fn write<W: io::Write>(&mut self, n: &Int, w: &mut W, upper: bool) -> io::Result<()> {
+        if self.powers.is_empty() || n.abs_size() <= DC_THRESHOLD {
+            return n.write_radix(w, self.base, upper);
+        }
+
+        self.ensure_powers(n.abs_size());
+
+        let letter = if upper { b'A' } else { b'a' };
+        let mut buf = Vec::new();
+        if n.sign() == -1 {
+            buf.push(b'-');
+        }
+
+        self.convert(&n.clone().abs(), 0, &mut buf);
+
+        for b in buf.iter_mut() {
+            if *b == b'-' { continue; }
+            *b = if *b < 10 { *b + b'0' } else { (*b - 10) + letter };
+        }
+
+        w.write_all(&buf)
+    }
+
+    // Makes sure that the largest cached power has at least half as many limbs as a number of
+    // `size` limbs, so the splits stay balanced.
+    fn ensure_powers(&mut self, size: i32) {
+        loop {
+            let next = {
+                let last = self.powers.last().unwrap();
+                if last.abs_size() * 2 > size {
+                    return;
+                }
+                last.square()
+            };
+            self.powers.push(next);
+        }
+    }
+
+    // Appends the raw digits of the non-negative `n` to `out`. If `width` is non-zero, the
+    // output is padded with leading zeros to exactly `width` digits.
+    fn convert(&self, n: &Int, width: usize, out: &mut Vec<u8>) {
+        if n.abs_size() <= DC_THRESHOLD {
+            let mut digits = Vec::new();
+            if n.sign() != 0 || width == 0 {
+                unsafe {
+                    ll::base::to_base(self.base as u32, n.limbs(), n.abs_len(), |b| digits.push(b));
+                }
+            }
+
+            if width > digits.len() {
+                let pad = width - digits.len();
+                out.extend((0..pad).map(|_| 0));
+            }
+            out.extend_from_slice(&digits);
+            return;
+        }
+
+        let mut k = self.powers.len() - 1;
+        while k > 0 && self.powers[k].abs_cmp(n) == Ordering::Greater {
+            k -= 1;
+        }
+
+        let (q, r) = n.divmod(&self.powers[k]);
+        let low_width = self.chunk_digits << k;
+        let high_width = if width == 0 { 0 } else { width - low_width };
+
+        self.convert(&q, high_width, out);
+        self.convert(&r, low_width, out);
+    }
+}
+
+/// An incremental parser for decimal numbers.
+///
+/// Digits are pushed in arbitrarily sized pieces and folded into the result a
+/// limb-sized chunk at a time (19 digits with 64-bit limbs), so the input
+/// never needs to be stored in full.
+///
+/// # Examples
+///
+/// ```rust
+/// use framp::Int;
+/// use framp::int::radix::DigitAccumulator;
+///
+/// let mut acc = DigitAccumulator::new();
+/// acc.push_ascii_digits(b"12345678901234567890").unwrap();
+/// acc.push_ascii_digits(b"1234567890").unwrap();
+/// let i = acc.finish();
+/// assert_eq!(i, "123456789012345678901234567890".parse::<Int>().unwrap());
+/// ```
+pub struct DigitAccumulator {
+    value: Int,
+    chunk: Limb,
+    chunk_digits: u32,
+    big_base: Limb,
+    digits_per_limb: u32,
+    pushed: usize,
+}
+
+impl DigitAccumulator {
+    /// Creates a new, empty accumulator.
+    pub fn new() -> DigitAccumulator {
+        let (big_base, digits_per_limb) = ll::base::big_base(10);
+
+        DigitAccumulator {
+            value: Int::zero(),
+            chunk: Limb(0),
+            chunk_digits: 0,
+            big_base: big_base,
+            digits_per_limb: digits_per_limb,
+            pushed: 0,
+        }
+    }
+
+    /// Appends the ASCII decimal digits in `digits` to the number.
+    ///
+    /// Returns an error if `digits` contains anything but `'0'` to `'9'`, in
+    /// which case none of the digits from this call are used. The offset of
+    /// the error counts all the digits pushed so far.
+    pub fn push_ascii_digits(&mut self, digits: &[u8]) -> Result<(), ParseIntError> {
+        if let Some(off) = digits.iter().position(|&c| c < b'0' || c > b'9') {
+            return Err(ParseIntError::new(ErrorKind::InvalidDigit, self.pushed + off));
+        }
+        self.pushed += digits.len();
+
+        for &c in digits {
+            self.chunk = self.chunk * 10 + ((c - b'0') as ll::limb::BaseInt);
+            self.chunk_digits += 1;
+
+            if self.chunk_digits == self.digits_per_limb {
+                self.value *= self.big_base;
+                self.value += self.chunk;
+                self.chunk = Limb(0);
+                self.chunk_digits = 0;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Consumes the accumulator, returning the parsed number. An accumulator
+    /// that was never given any digits yields zero.
+    pub fn finish(self) -> Int {
+        let mut value = self.value;
+        if self.chunk_digits > 0 {
+            let mut scale = Limb(1);
+            for _ in 0..self.chunk_digits {
+                scale = scale * 10;
+            }
+            value *= scale;
+            value += self.chunk;
+        }
+
+        value
+    }
+}
+
+impl Default for DigitAccumulator {
+    fn default() -> DigitAccumulator {
+        DigitAccumulator::new()
+    }
+}
+
+#[test]
+fn to_str_matches() {
+    let mut conv = BaseConverter::new(10);
+    let mut x = Int::from(7);
+    for _ in 0..12 {
+        x = x.square() + 1;
+        assert_eq!(conv.to_str(&x, false), x.to_str_radix(10, false));
+        let neg = -x.clone();
+        assert_eq!(conv.to_str(&neg, false), neg.to_str_radix(10, false));
+    }
+
+    let mut conv = BaseConverter::new(36);
+    let x = Int::from(10).pow(2000) - 1;
+    assert_eq!(conv.to_str(&x, true), x.to_str_radix(36, true));
+
+    // Zero chunks in the middle of the number must be padded
+    let mut conv = BaseConverter::new(10);
+    let x = Int::from(10).pow(1500) + 3;
+    assert_eq!(conv.to_str(&x, false), x.to_str_radix(10, false));
+}
+
+#[test]
+fn digit_accumulator() {
+    let s = "98765432109876543210987654321098765432109876543210987654321000001";
+    for step in 1..s.len() + 1 {
+        let mut acc = DigitAccumulator::new();
+        for piece in s.as_bytes().chunks(step) {
+            acc.push_ascii_digits(piece).unwrap();
+        }
+        assert_eq!(acc.finish(), s.parse::<Int>().unwrap());
+    }
+
+    let mut acc = DigitAccumulator::new();
+    assert!(acc.push_ascii_digits(b"12a").is_err());
+    acc.push_ascii_digits(b"12").unwrap();
+    assert_eq!(acc.finish(), 12);
+}
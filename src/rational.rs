@@ -884,9 +884,18 @@ macro_rules! impl_from_float {
 impl_from_float!(f32, 23);
 impl_from_float!(f64, 52);
 
+/// An error which can be returned when parsing a `Rational`.
 #[derive(Debug, Clone, PartialEq)]
 pub struct ParseRationalError(ParseIntError);
 
+impl ParseRationalError {
+    /// Returns the underlying integer parse error. Its offset is relative to the start of the
+    /// whole input, including the numerator for errors in the denominator.
+    pub fn int_error(&self) -> &ParseIntError {
+        &self.0
+    }
+}
+
 impl std::error::Error for ParseRationalError {
     fn description<'a>(&'a self) -> &'a str {
         self.0.description()
@@ -910,7 +919,11 @@ impl std::str::FromStr for Rational {
 
     fn from_str(s: &str) -> Result<Rational, ParseRationalError> {
         match s.find('/') {
-            Some(i) => Ok(Rational::new(Int::from_str(&s[..i])?, Int::from_str(&s[i + 1..])?)),
+            Some(i) => {
+                let n = Int::from_str(&s[..i])?;
+                let d = Int::from_str(&s[i + 1..]).map_err(|e| e.shifted(i + 1))?;
+                Ok(Rational::new(n, d))
+            }
             None => Ok(Rational::new(Int::from_str(s)?, Int::one())),
         }
     }
@@ -0,0 +1,292 @@
+// Copyright 2015 The Ramp Developers
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Integers with a shared, copy-on-write buffer.
+//!
+//! Cloning an `Int` copies all of its limbs. `RcInt` keeps its value behind a
+//! reference count instead, so cloning it only bumps the count, and the limbs
+//! are copied the first time a shared value is modified. This suits code that
+//! passes big values around far more often than it changes them, such as
+//! interpreters and symbolic algebra.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{
+    Add, AddAssign, Deref, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign,
+};
+use std::rc::Rc;
+use std::str::FromStr;
+
+use int::{Int, ParseIntError};
+
+/// An arbitrary-precision signed integer with a reference-counted buffer.
+///
+/// An `RcInt` dereferences to `Int`, so all the read-only methods of `Int`
+/// are available on it directly. Modifying it, through `make_mut` or one of
+/// the compound assignment operators, first copies the value if it is shared.
+///
+/// # Examples
+///
+/// ```rust
+/// use framp::Int;
+/// use framp::rc::RcInt;
+///
+/// let a = RcInt::from(Int::from(10).pow(100));
+/// let mut b = a.clone();
+/// assert!(RcInt::ptr_eq(&a, &b));
+///
+/// b += &Int::from(1);
+/// assert!(!RcInt::ptr_eq(&a, &b));
+/// assert_eq!(&*b - &*a, 1);
+/// ```
+#[derive(Clone, Default)]
+pub struct RcInt {
+    inner: Rc<Int>,
+}
+
+impl RcInt {
+    /// Creates a new `RcInt` holding `i`.
+    pub fn new(i: Int) -> RcInt {
+        RcInt { inner: Rc::new(i) }
+    }
+
+    /// Returns a mutable reference to the value, copying it first if it is
+    /// shared with other `RcInt`s.
+    pub fn make_mut(&mut self) -> &mut Int {
+        Rc::make_mut(&mut self.inner)
+    }
+
+    /// Returns a mutable reference to the value if it is not shared, or
+    /// `None` otherwise.
+    pub fn get_mut(&mut self) -> Option<&mut Int> {
+        Rc::get_mut(&mut self.inner)
+    }
+
+    /// Returns true if `a` and `b` share the same buffer.
+    pub fn ptr_eq(a: &RcInt, b: &RcInt) -> bool {
+        Rc::ptr_eq(&a.inner, &b.inner)
+    }
+
+    /// Returns true if no other `RcInt` shares this value.
+    pub fn is_unique(&self) -> bool {
+        Rc::strong_count(&self.inner) == 1
+    }
+
+    /// Consumes the `RcInt`, returning the value. The value is only copied
+    /// if it is shared.
+    pub fn into_int(self) -> Int {
+        match Rc::try_unwrap(self.inner) {
+            Ok(i) => i,
+            Err(rc) => (*rc).clone(),
+        }
+    }
+}
+
+impl Deref for RcInt {
+    type Target = Int;
+
+    fn deref(&self) -> &Int {
+        &self.inner
+    }
+}
+
+impl AsRef<Int> for RcInt {
+    fn as_ref(&self) -> &Int {
+        &self.inner
+    }
+}
+
+impl From<Int> for RcInt {
+    fn from(i: Int) -> RcInt {
+        RcInt::new(i)
+    }
+}
+
+impl<'a> From<&'a Int> for RcInt {
+    fn from(i: &'a Int) -> RcInt {
+        RcInt::new(i.clone())
+    }
+}
+
+impl From<RcInt> for Int {
+    fn from(r: RcInt) -> Int {
+        r.into_int()
+    }
+}
+
+impl FromStr for RcInt {
+    type Err = ParseIntError;
+
+    fn from_str(src: &str) -> Result<RcInt, ParseIntError> {
+        src.parse().map(RcInt::new)
+    }
+}
+
+impl fmt::Display for RcInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&*self.inner, f)
+    }
+}
+
+impl fmt::Debug for RcInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&*self.inner, f)
+    }
+}
+
+impl PartialEq for RcInt {
+    fn eq(&self, other: &RcInt) -> bool {
+        RcInt::ptr_eq(self, other) || *self.inner == *other.inner
+    }
+}
+
+impl Eq for RcInt {}
+
+impl PartialEq<Int> for RcInt {
+    fn eq(&self, other: &Int) -> bool {
+        *self.inner == *other
+    }
+}
+
+impl PartialOrd for RcInt {
+    fn partial_cmp(&self, other: &RcInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RcInt {
+    fn cmp(&self, other: &RcInt) -> Ordering {
+        if RcInt::ptr_eq(self, other) {
+            Ordering::Equal
+        } else {
+            self.inner.cmp(&other.inner)
+        }
+    }
+}
+
+impl Hash for RcInt {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.hash(state)
+    }
+}
+
+impl Neg for RcInt {
+    type Output = RcInt;
+
+    fn neg(mut self) -> RcInt {
+        self.make_mut().negate();
+        self
+    }
+}
+
+impl<'a> Neg for &'a RcInt {
+    type Output = RcInt;
+
+    fn neg(self) -> RcInt {
+        RcInt::new(-&*self.inner)
+    }
+}
+
+macro_rules! impl_rc_op {
+    ($tr:ident, $meth:ident, $tr_assign:ident, $meth_assign:ident) => {
+        impl<'a, 'b> $tr<&'a RcInt> for &'b RcInt {
+            type Output = RcInt;
+
+            fn $meth(self, other: &'a RcInt) -> RcInt {
+                RcInt::new((&*self.inner).$meth(&*other.inner))
+            }
+        }
+
+        impl<'a> $tr<&'a RcInt> for RcInt {
+            type Output = RcInt;
+
+            fn $meth(mut self, other: &'a RcInt) -> RcInt {
+                self.$meth_assign(other);
+                self
+            }
+        }
+
+        impl $tr<RcInt> for RcInt {
+            type Output = RcInt;
+
+            fn $meth(mut self, other: RcInt) -> RcInt {
+                self.$meth_assign(&other);
+                self
+            }
+        }
+
+        impl<'a> $tr_assign<&'a RcInt> for RcInt {
+            fn $meth_assign(&mut self, other: &'a RcInt) {
+                // Clone the handle first in case `other` shares our buffer
+                let other = other.clone();
+                self.make_mut().$meth_assign(&*other.inner);
+            }
+        }
+
+        impl<'a> $tr_assign<&'a Int> for RcInt {
+            fn $meth_assign(&mut self, other: &'a Int) {
+                self.make_mut().$meth_assign(other);
+            }
+        }
+    }
+}
+
+impl_rc_op!(Add, add, AddAssign, add_assign);
+impl_rc_op!(Sub, sub, SubAssign, sub_assign);
+impl_rc_op!(Mul, mul, MulAssign, mul_assign);
+impl_rc_op!(Div, div, DivAssign, div_assign);
+impl_rc_op!(Rem, rem, RemAssign, rem_assign);
+
+#[test]
+fn copy_on_write() {
+    let a = RcInt::from(Int::from(3).pow(200));
+    let mut b = a.clone();
+    assert!(RcInt::ptr_eq(&a, &b));
+    assert!(!a.is_unique());
+    assert!(b.get_mut().is_none());
+
+    b *= &Int::from(3);
+    assert!(!RcInt::ptr_eq(&a, &b));
+    assert!(a.is_unique() && b.is_unique());
+    assert_eq!(*a, Int::from(3).pow(200));
+    assert_eq!(*b, Int::from(3).pow(201));
+
+    b.get_mut().unwrap().negate();
+    assert_eq!(-&b, RcInt::from(Int::from(3).pow(201)));
+
+    let c = a.clone();
+    assert_eq!(c.into_int(), *a);
+    assert_eq!(a.into_int(), Int::from(3).pow(200));
+}
+
+#[test]
+fn ops() {
+    let x: RcInt = "-123456789012345678901234567890".parse().unwrap();
+    let y = RcInt::from(Int::from(987654321));
+    let (xi, yi) = (Int::from(x.clone()), Int::from(y.clone()));
+
+    assert_eq!(&x + &y, xi.clone() + &yi);
+    assert_eq!(&x - &y, xi.clone() - &yi);
+    assert_eq!(&x * &y, xi.clone() * &yi);
+    assert_eq!(&x / &y, xi.clone() / &yi);
+    assert_eq!(&x % &y, xi.clone() % &yi);
+
+    // Operands sharing a buffer
+    let mut z = x.clone();
+    z += &x;
+    assert_eq!(z, xi.clone() * 2);
+    assert_eq!(x, xi);
+    assert_eq!(x.clone() - x.clone(), Int::zero());
+}
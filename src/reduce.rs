@@ -0,0 +1,290 @@
+// Copyright 2015 The Ramp Developers
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Pluggable modular reduction.
+//!
+//! A `Reducer` is a modulus together with a way of reducing products by it.
+//! Modular exponentiation, through `Int::modpow_with`, only relies on the
+//! trait, so a modulus of a special shape can get a reduction of its own
+//! without the exponentiation code having to change.
+//!
+//! Three reducers are provided:
+//!
+//! * `MtgyModulus`, Montgomery reduction, for odd moduli
+//! * `Barrett`, for any positive modulus
+//! * `SpecialForm`, for moduli of the form `2^k - c` with a small `c`, like
+//!   the pseudo-Mersenne primes of elliptic curve cryptography
+
+use num_integer::Integer;
+
+use int::Int;
+
+/// A modulus with a way of reducing values by it.
+///
+/// A reducer may keep values in a representation of its own, like the
+/// Montgomery form, between `to_repr` and `from_repr`. `reduce`, `mul_mod`
+/// and `sqr_mod` take and return values in that representation.
+///
+/// # Examples
+///
+/// A reducer for `2^255 - 19`, the modulus of Curve25519:
+///
+/// ```rust
+/// use framp::Int;
+/// use framp::int::reduce::{Reducer, SpecialForm};
+///
+/// let p = SpecialForm::new(255, &Int::from(19));
+/// let x = Int::from(9);
+/// // Fermat's little theorem
+/// assert_eq!(p.pow(&x, &(p.modulus() - 1)), 1);
+/// assert_eq!(x.modpow_with(&(p.modulus() - 2), &p) * &x % p.modulus(), 1);
+/// ```
+pub trait Reducer {
+    /// Returns the modulus.
+    fn modulus(&self) -> &Int;
+
+    /// Reduces `a`, which must be non-negative and less than the square of
+    /// the modulus.
+    fn reduce(&self, a: Int) -> Int;
+
+    /// Converts a value between zero and the modulus to the representation
+    /// of the reducer. This is the identity unless overridden.
+    fn to_repr(&self, a: &Int) -> Int {
+        a.clone()
+    }
+
+    /// Converts a value from the representation of the reducer.
+    fn from_repr(&self, a: &Int) -> Int {
+        a.clone()
+    }
+
+    /// Returns the reduced product of `a` and `b`.
+    fn mul_mod(&self, a: &Int, b: &Int) -> Int {
+        self.reduce(a * b)
+    }
+
+    /// Returns the reduced square of `a`.
+    fn sqr_mod(&self, a: &Int) -> Int {
+        self.reduce(a.square())
+    }
+
+    /// Returns `base^exp` modulo the modulus. Unlike the other methods, the
+    /// base and the result are in natural form, and the base may be any
+    /// value.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `exp` is negative.
+    fn pow(&self, base: &Int, exp: &Int) -> Int {
+        window_pow(self, base, exp)
+    }
+}
+
+// The width of the exponent windows of `window_pow`
+const WINDOW: u32 = 4;
+
+/// Computes `base^exp` modulo the modulus of `r` with fixed windows of the
+/// exponent, using only `mul_mod` and `sqr_mod`. This is the default for
+/// `Reducer::pow`.
+///
+/// # Panic
+///
+/// Panics if `exp` is negative.
+pub fn window_pow<R: Reducer + ?Sized>(r: &R, base: &Int, exp: &Int) -> Int {
+    assert!(exp.sign() >= 0, "negative exponent");
+    let m = r.modulus();
+
+    // base^0 to base^(2^WINDOW - 1)
+    let b = r.to_repr(&base.mod_floor(m));
+    let mut table = Vec::with_capacity(1 << WINDOW);
+    table.push(r.to_repr(&(Int::one() % m)));
+    for i in 1..(1 << WINDOW) {
+        let next = r.mul_mod(&table[i - 1], &b);
+        table.push(next);
+    }
+
+    let mut acc = table[0].clone();
+    for (i, w) in exp.windows(WINDOW).enumerate() {
+        if i > 0 {
+            for _ in 0..WINDOW {
+                acc = r.sqr_mod(&acc);
+            }
+        }
+        if w != 0 {
+            acc = r.mul_mod(&acc, &table[w as usize]);
+        }
+    }
+    r.from_repr(&acc)
+}
+
+/// Barrett reduction, for any positive modulus.
+///
+/// A reduction costs two multiplications, by a reciprocal of the modulus
+/// computed once, instead of a division.
+#[derive(Clone, Debug)]
+pub struct Barrett {
+    modulus: Int,
+    // floor(4^k / modulus)
+    mu: Int,
+    // The bit length of the modulus
+    k: usize,
+}
+
+impl Barrett {
+    /// Creates a reducer for `modulus`.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the modulus isn't positive.
+    pub fn new(modulus: &Int) -> Barrett {
+        assert!(modulus.sign() > 0, "Barrett modulus must be positive");
+        let k = modulus.bit_length() as usize;
+        Barrett {
+            modulus: modulus.clone(),
+            mu: (Int::one() << (2 * k)) / modulus,
+            k: k,
+        }
+    }
+}
+
+impl Reducer for Barrett {
+    fn modulus(&self) -> &Int {
+        &self.modulus
+    }
+
+    fn reduce(&self, a: Int) -> Int {
+        debug_assert!(a.sign() >= 0);
+        // The estimate of the quotient is at most two less than the quotient
+        let q = ((&a >> (self.k - 1)) * &self.mu) >> (self.k + 1);
+        let mut r = a - q * &self.modulus;
+        while r >= self.modulus {
+            r -= &self.modulus;
+        }
+        r
+    }
+}
+
+/// Reduction by a modulus of the form `2^k - c`, with `c` small next to
+/// `2^k`.
+///
+/// As `2^k` is congruent to `c`, the bits of a value above the `k`th can be
+/// folded down by a multiplication by `c`, which takes a couple of rounds
+/// when `c` is much shorter than `2^k`. This covers the pseudo-Mersenne
+/// primes, like `2^255 - 19` or `2^521 - 1`.
+#[derive(Clone, Debug)]
+pub struct SpecialForm {
+    modulus: Int,
+    k: usize,
+    c: Int,
+    // 2^k - 1
+    mask: Int,
+}
+
+impl SpecialForm {
+    /// Creates a reducer for `2^k - c`.
+    ///
+    /// # Panic
+    ///
+    /// Panics unless `0 < c < 2^(k-1)`.
+    pub fn new(k: usize, c: &Int) -> SpecialForm {
+        assert!(k > 1 && c.sign() > 0 && (c.bit_length() as usize) < k,
+                "special form modulus needs 0 < c < 2^(k-1)");
+        let pow = Int::one() << k;
+        SpecialForm {
+            modulus: &pow - c,
+            k: k,
+            c: c.clone(),
+            mask: pow - 1,
+        }
+    }
+}
+
+impl Reducer for SpecialForm {
+    fn modulus(&self) -> &Int {
+        &self.modulus
+    }
+
+    fn reduce(&self, mut a: Int) -> Int {
+        debug_assert!(a.sign() >= 0);
+        while a.bit_length() as usize > self.k {
+            let high = &a >> self.k;
+            a = high * &self.c + (a & &self.mask);
+        }
+        while a >= self.modulus {
+            a -= &self.modulus;
+        }
+        a
+    }
+}
+
+#[test]
+fn reducers_agree() {
+    use rand::{self, Rng};
+    use int::RandomInt;
+    use int::mtgy::MtgyModulus;
+    let mut rng = rand::thread_rng();
+
+    let p25519 = SpecialForm::new(255, &Int::from(19));
+    for _ in 0..20 {
+        let bits = rng.gen_range(2, 600);
+        let odd = rng.gen_uint(bits) * 2 + 3;
+        let any = rng.gen_uint(bits) + 1;
+        let mtgy = MtgyModulus::new(&odd);
+        let barrett_odd = Barrett::new(&odd);
+        let barrett = Barrett::new(&any);
+
+        let a = rng.gen_int(2 * bits);
+        let e = rng.gen_uint(bits);
+        let expected = a.mod_floor(&odd).modpow(&e, &odd);
+        assert_eq!(Reducer::pow(&mtgy, &a, &e), expected);
+        assert_eq!(barrett_odd.pow(&a, &e), expected);
+        assert_eq!(window_pow(&mtgy, &a, &e), expected);
+        assert_eq!(barrett.pow(&a, &e), a.mod_floor(&any).modpow(&e, &any));
+
+        let x = rng.gen_uint(255) % p25519.modulus();
+        let y = rng.gen_uint(255) % p25519.modulus();
+        assert_eq!(p25519.mul_mod(&x, &y), &x * &y % p25519.modulus());
+        assert_eq!(p25519.pow(&x, &e), x.modpow(&e, p25519.modulus()));
+
+        let b = rng.gen_uint(bits) % &any;
+        let c = rng.gen_uint(bits) % &any;
+        assert_eq!(barrett.mul_mod(&b, &c), &b * &c % &any);
+        let (b, c) = (b % &odd, c % &odd);
+        let bc = mtgy.mul_mod(&mtgy.to_repr(&b), &mtgy.to_repr(&c));
+        assert_eq!(mtgy.from_repr(&bc), &b * &c % &odd);
+    }
+}
+
+#[test]
+fn edge_cases() {
+    let one = Barrett::new(&Int::one());
+    assert_eq!(one.pow(&Int::from(5), &Int::from(3)), 0);
+    assert_eq!(one.pow(&Int::from(5), &Int::zero()), 0);
+
+    let m = Barrett::new(&Int::from(1000));
+    assert_eq!(m.pow(&Int::from(-3), &Int::from(3)), 973);
+    assert_eq!(m.pow(&Int::from(7), &Int::zero()), 1);
+    assert_eq!(m.reduce(Int::from(999) * 999), 999 * 999 % 1000);
+
+    let m = SpecialForm::new(521, &Int::one());
+    let top = m.modulus() - 1;
+    assert_eq!(m.reduce(&top * &top), 1);
+    assert_eq!(m.reduce(m.modulus().clone()), 0);
+}
+
+#[test]
+#[should_panic(expected = "negative exponent")]
+fn negative_exponent() {
+    Barrett::new(&Int::from(7)).pow(&Int::from(2), &Int::from(-1));
+}
@@ -0,0 +1,308 @@
+// Copyright 2015 The Ramp Developers
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Residue number system arithmetic (experimental).
+//!
+//! A value in residue form is stored as its remainders modulo a set of
+//! single-limb primes. Additions and multiplications then work on each
+//! remainder independently and in linear time, whatever the size of the
+//! value, at the price of a quadratic conversion back to an `Int`. This pays
+//! off for long chains of multiplications whose result fits in a known
+//! number of bits, and is only needed in positional form at the end.
+//!
+//! This module is experimental, and its interface may change.
+
+use int::Int;
+use ll::limb::{self, BaseInt, Limb};
+
+/// A set of primes for residue arithmetic on values of a bounded size.
+///
+/// All the primes have their top bit set, so they can be used with the
+/// single-limb division routines directly.
+///
+/// # Examples
+///
+/// ```rust
+/// use framp::Int;
+/// use framp::rns::RnsBasis;
+///
+/// // a * b + a has about 1040 bits
+/// let basis = RnsBasis::new(1100);
+/// let a = Int::from(3).pow(300);
+/// let b = -Int::from(7).pow(200);
+/// let (ra, rb) = (basis.to_rns(&a), basis.to_rns(&b));
+/// let r = basis.add(&basis.mul(&ra, &rb), &ra);
+/// assert_eq!(basis.to_int(&r), &a * &b + &a);
+/// ```
+#[derive(Debug)]
+pub struct RnsBasis {
+    bits: usize,
+    primes: Vec<Limb>,
+    // The inverse of each prime, for `div_preinv`
+    invs: Vec<Limb>,
+    // The inverse of the product of the previous primes modulo each prime,
+    // for the conversion back to an `Int`
+    garner: Vec<Limb>,
+    product: Int,
+    half: Int,
+}
+
+/// A value in residue form.
+///
+/// Like `MtgyInt`, a value is only meaningful with the `RnsBasis` that
+/// created it, and it's up to the user not to mix up values from different
+/// bases.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RnsInt(Vec<Limb>);
+
+impl RnsBasis {
+    /// Creates a basis able to represent all the values whose magnitude is
+    /// less than `2^bits`.
+    pub fn new(bits: usize) -> RnsBasis {
+        // Each prime is above 2^(Limb::BITS - 1), and the product has to be
+        // above 2^(bits + 1) to leave room for the sign
+        let count = (bits + 1) / (Limb::BITS - 1) + 1;
+
+        let mut primes = Vec::with_capacity(count);
+        let mut invs = Vec::with_capacity(count);
+        let mut garner = Vec::with_capacity(count);
+        let mut product = Int::one();
+        let mut candidate = !Limb(0);
+        while primes.len() < count {
+            assert!(candidate.high_bit_set(), "RnsBasis::new: ran out of primes");
+            if is_prime(candidate) {
+                let inv = candidate.invert();
+                let r = residue(&product, candidate, inv);
+                garner.push(inv_mod(r, candidate, inv));
+                primes.push(candidate);
+                invs.push(inv);
+                product *= candidate;
+            }
+            candidate = candidate - 2;
+        }
+
+        let half = &product >> 1;
+        RnsBasis {
+            bits: bits,
+            primes: primes,
+            invs: invs,
+            garner: garner,
+            product: product,
+            half: half,
+        }
+    }
+
+    /// Returns the number of bits the basis was created for.
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+
+    /// Returns the primes of the basis.
+    pub fn primes(&self) -> &[Limb] {
+        &self.primes
+    }
+
+    /// Converts an `Int` to residue form.
+    ///
+    /// Values out of range are reduced modulo the product of the primes, and
+    /// don't convert back to the same `Int`.
+    pub fn to_rns(&self, x: &Int) -> RnsInt {
+        let neg = x.sign() < 0;
+        RnsInt(self.primes.iter().zip(&self.invs).map(|(&p, &inv)| {
+            let r = residue(x, p, inv);
+            if neg && r != 0 { p - r } else { r }
+        }).collect())
+    }
+
+    /// Converts a value in residue form back to an `Int`.
+    ///
+    /// The result is the value of least magnitude with the given residues,
+    /// which is exact as long as no intermediate result went out of the
+    /// range of the basis. This takes time quadratic in the number of
+    /// primes.
+    pub fn to_int(&self, x: &RnsInt) -> Int {
+        self.check(x);
+        // Garner's algorithm, adding one prime at a time
+        let mut acc = Int::zero();
+        let mut prod = Int::one();
+        for (i, &p) in self.primes.iter().enumerate() {
+            let inv = self.invs[i];
+            let t = sub_mod(x.0[i], residue(&acc, p, inv), p);
+            let v = mul_mod(t, self.garner[i], p, inv);
+            acc += &prod * v.0;
+            prod *= p;
+        }
+        if acc > self.half {
+            acc -= &self.product;
+        }
+        acc
+    }
+
+    /// Returns `a + b`.
+    pub fn add(&self, a: &RnsInt, b: &RnsInt) -> RnsInt {
+        self.check(a);
+        self.check(b);
+        RnsInt(self.primes.iter().zip(a.0.iter().zip(&b.0)).map(|(&p, (&x, &y))| {
+            add_mod(x, y, p)
+        }).collect())
+    }
+
+    /// Returns `a - b`.
+    pub fn sub(&self, a: &RnsInt, b: &RnsInt) -> RnsInt {
+        self.check(a);
+        self.check(b);
+        RnsInt(self.primes.iter().zip(a.0.iter().zip(&b.0)).map(|(&p, (&x, &y))| {
+            sub_mod(x, y, p)
+        }).collect())
+    }
+
+    /// Returns `-a`.
+    pub fn neg(&self, a: &RnsInt) -> RnsInt {
+        self.check(a);
+        RnsInt(self.primes.iter().zip(&a.0).map(|(&p, &x)| {
+            sub_mod(Limb(0), x, p)
+        }).collect())
+    }
+
+    /// Returns `a * b`.
+    pub fn mul(&self, a: &RnsInt, b: &RnsInt) -> RnsInt {
+        self.check(a);
+        self.check(b);
+        let pi = self.primes.iter().zip(&self.invs);
+        RnsInt(pi.zip(a.0.iter().zip(&b.0)).map(|((&p, &inv), (&x, &y))| {
+            mul_mod(x, y, p, inv)
+        }).collect())
+    }
+
+    fn check(&self, a: &RnsInt) {
+        assert_eq!(a.0.len(), self.primes.len(), "value from a different RnsBasis");
+    }
+}
+
+fn add_mod(a: Limb, b: Limb, p: Limb) -> Limb {
+    let (s, carry) = a.add_overflow(b);
+    if carry || s >= p { s - p } else { s }
+}
+
+fn sub_mod(a: Limb, b: Limb, p: Limb) -> Limb {
+    let (d, borrow) = a.sub_overflow(b);
+    if borrow { d + p } else { d }
+}
+
+fn mul_mod(a: Limb, b: Limb, p: Limb, inv: Limb) -> Limb {
+    let (h, l) = a.mul_hilo(b);
+    limb::div_preinv(h, l, p, inv).1
+}
+
+fn pow_mod(mut a: Limb, mut e: Limb, p: Limb, inv: Limb) -> Limb {
+    let mut r = Limb(1);
+    while e != 0 {
+        if e & Limb(1) != 0 {
+            r = mul_mod(r, a, p, inv);
+        }
+        a = mul_mod(a, a, p, inv);
+        e = e >> 1;
+    }
+    r
+}
+
+// The inverse of a non-zero `a` modulo the prime `p`
+fn inv_mod(a: Limb, p: Limb, inv: Limb) -> Limb {
+    pow_mod(a, p - 2, p, inv)
+}
+
+// The remainder of the magnitude of `x` modulo `p`, which has its top bit set
+fn residue(x: &Int, p: Limb, inv: Limb) -> Limb {
+    x.as_limb_slice().iter().rev().fold(Limb(0), |r, &l| limb::div_preinv(r, l, p, inv).1)
+}
+
+// Miller-Rabin with bases that give the right answer for all 64-bit numbers
+fn is_prime(n: Limb) -> bool {
+    const BASES: [u32; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    for &b in BASES.iter() {
+        if n.0 % (b as BaseInt) == 0 {
+            return false;
+        }
+    }
+
+    let inv = n.invert();
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> (s as usize);
+    'bases: for &b in BASES.iter() {
+        let mut x = pow_mod(Limb(b as BaseInt), d, n, inv);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n, inv);
+            if x == n - 1 {
+                continue 'bases;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+#[test]
+fn primes() {
+    let basis = RnsBasis::new(10 * Limb::BITS);
+    assert!(basis.primes().len() >= 11);
+    for (i, &p) in basis.primes().iter().enumerate() {
+        assert!(p.high_bit_set());
+        assert_eq!(Int::from(p.0).gcd(&Int::from(2 * 3 * 5 * 7 * 11 * 13)), 1);
+        assert!(basis.primes()[..i].iter().all(|&q| q > p));
+    }
+    assert!(basis.product.bit_length() as usize > basis.bits() + 1);
+}
+
+#[test]
+fn round_trip_and_ops() {
+    use rand;
+    use int::RandomInt;
+    let mut rng = rand::thread_rng();
+
+    let basis = RnsBasis::new(2000);
+    let zero = basis.to_rns(&Int::zero());
+    assert_eq!(basis.to_int(&zero), 0);
+
+    for _ in 0..20 {
+        let a = rng.gen_int(999);
+        let b = rng.gen_int(999);
+        let (ra, rb) = (basis.to_rns(&a), basis.to_rns(&b));
+        assert_eq!(basis.to_int(&ra), a);
+        assert_eq!(basis.to_int(&basis.add(&ra, &rb)), &a + &b);
+        assert_eq!(basis.to_int(&basis.sub(&ra, &rb)), &a - &b);
+        assert_eq!(basis.to_int(&basis.neg(&ra)), -&a);
+        assert_eq!(basis.to_int(&basis.mul(&ra, &rb)), &a * &b);
+    }
+
+    // A chain of products that is only converted back at the end
+    let mut expected = Int::one();
+    let mut acc = basis.to_rns(&Int::one());
+    for _ in 0..19 {
+        let x = rng.gen_int(100);
+        expected *= &x;
+        acc = basis.mul(&acc, &basis.to_rns(&x));
+    }
+    assert_eq!(basis.to_int(&acc), expected);
+}
+
+#[test]
+#[should_panic(expected = "value from a different RnsBasis")]
+fn mixed_bases() {
+    let a = RnsBasis::new(100);
+    let b = RnsBasis::new(1000);
+    a.add(&a.to_rns(&Int::one()), &b.to_rns(&Int::one()));
+}
@@ -18,3 +18,11 @@ pub trait DivRem<RHS = Self> {
 
     fn divrem(self, rhs: RHS) -> Self::Output;
 }
+
+/// Assignment that reuses the storage of the target.
+///
+/// `a.assign(&b)` is equivalent to `a = b.clone()`, except that `a` keeps its
+/// allocation when it is already big enough to hold the new value.
+pub trait Assign<Src = Self> {
+    fn assign(&mut self, src: Src);
+}
@@ -0,0 +1,126 @@
+//! Replays the vectors of `tests/vectors`, whose results were computed with
+//! GMP by `src/bin/gen_vectors.rs`. This checks against GMP without needing
+//! it, unlike the quickcheck tests with the `rust-gmp` feature.
+
+extern crate framp as ramp;
+
+use ramp::Int;
+
+// The vectors of a file, as their space-separated fields, skipping the
+// comments
+fn vectors(text: &'static str, fields: usize) -> Vec<Vec<&'static str>> {
+    let v: Vec<Vec<&str>> = text.lines()
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| l.split(' ').collect())
+        .collect();
+    assert!(!v.is_empty());
+    for f in &v {
+        assert_eq!(f.len(), fields, "malformed vector {:?}", f);
+    }
+    v
+}
+
+fn int(s: &str) -> Int {
+    Int::from_str_radix(s, 16).unwrap()
+}
+
+#[test]
+fn add() {
+    for v in vectors(include_str!("vectors/add.txt"), 3) {
+        let (a, b, c) = (int(v[0]), int(v[1]), int(v[2]));
+        assert_eq!(&a + &b, c, "{} + {}", v[0], v[1]);
+        let mut x = a.clone();
+        x += &b;
+        assert_eq!(x, c, "{} += {}", v[0], v[1]);
+    }
+}
+
+#[test]
+fn sub() {
+    for v in vectors(include_str!("vectors/sub.txt"), 3) {
+        let (a, b, c) = (int(v[0]), int(v[1]), int(v[2]));
+        assert_eq!(&a - &b, c, "{} - {}", v[0], v[1]);
+        assert_eq!(&b - &a, -&c, "{} - {}", v[1], v[0]);
+    }
+}
+
+#[test]
+fn mul() {
+    for v in vectors(include_str!("vectors/mul.txt"), 3) {
+        let (a, b, c) = (int(v[0]), int(v[1]), int(v[2]));
+        assert_eq!(&a * &b, c, "{} * {}", v[0], v[1]);
+        assert_eq!(&b * &a, c, "{} * {}", v[1], v[0]);
+    }
+}
+
+#[test]
+fn divrem() {
+    for v in vectors(include_str!("vectors/divrem.txt"), 4) {
+        let (a, b, q, r) = (int(v[0]), int(v[1]), int(v[2]), int(v[3]));
+        assert_eq!(a.divmod(&b), (q.clone(), r.clone()), "divmod({}, {})", v[0], v[1]);
+        assert_eq!(&a / &b, q, "{} / {}", v[0], v[1]);
+        assert_eq!(&a % &b, r, "{} % {}", v[0], v[1]);
+    }
+}
+
+#[test]
+fn gcd_lcm() {
+    for v in vectors(include_str!("vectors/gcd.txt"), 4) {
+        let (a, b, g, l) = (int(v[0]), int(v[1]), int(v[2]), int(v[3]));
+        assert_eq!(a.gcd(&b), g, "gcd({}, {})", v[0], v[1]);
+        assert_eq!(a.lcm(&b), l, "lcm({}, {})", v[0], v[1]);
+    }
+}
+
+#[test]
+fn modpow() {
+    for v in vectors(include_str!("vectors/powm.txt"), 4) {
+        let (b, e, m, r) = (int(v[0]), int(v[1]), int(v[2]), int(v[3]));
+        assert_eq!(b.modpow(&e, &m), r, "modpow({}, {}, {})", v[0], v[1], v[2]);
+    }
+}
+
+#[test]
+fn pow() {
+    for v in vectors(include_str!("vectors/pow.txt"), 3) {
+        let (a, k, r) = (int(v[0]), v[1].parse().unwrap(), int(v[2]));
+        assert_eq!(a.pow(k), r, "{}.pow({})", v[0], k);
+    }
+}
+
+#[test]
+fn shl() {
+    for v in vectors(include_str!("vectors/shl.txt"), 3) {
+        let (a, n, r): (Int, usize, Int) = (int(v[0]), v[1].parse().unwrap(), int(v[2]));
+        assert_eq!(&a << n, r, "{} << {}", v[0], n);
+    }
+}
+
+// Both round towards negative infinity
+#[test]
+fn shr() {
+    for v in vectors(include_str!("vectors/shr.txt"), 3) {
+        let (a, n, r): (Int, usize, Int) = (int(v[0]), v[1].parse().unwrap(), int(v[2]));
+        assert_eq!(&a >> n, r, "{} >> {}", v[0], n);
+    }
+}
+
+#[test]
+fn bitops() {
+    for v in vectors(include_str!("vectors/bitops.txt"), 5) {
+        let (a, b) = (int(v[0]), int(v[1]));
+        assert_eq!(&a & &b, int(v[2]), "{} & {}", v[0], v[1]);
+        assert_eq!(&a | &b, int(v[3]), "{} | {}", v[0], v[1]);
+        assert_eq!(&a ^ &b, int(v[4]), "{} ^ {}", v[0], v[1]);
+    }
+}
+
+#[test]
+fn decimal() {
+    for v in vectors(include_str!("vectors/decimal.txt"), 2) {
+        let a = int(v[0]);
+        assert_eq!(a.to_string(), v[1], "{}.to_string()", v[0]);
+        assert_eq!(v[1].parse::<Int>().unwrap(), a, "parse({})", v[1]);
+        assert_eq!(a.to_str_radix(16, false), v[0]);
+    }
+}
@@ -0,0 +1,326 @@
+//! Cross-checks the public `Int` operations against num-bigint on random
+//! inputs.
+//!
+//! Unlike the quickcheck tests, the inputs are drawn from a seeded generator
+//! biased towards the values that exercise carries and borrows: limbs of all
+//! zeros or all ones, lone top bits, and sizes on either side of the
+//! multiplication thresholds. Every test runs `RAMP_ORACLE_ROUNDS` rounds,
+//! 2000 by default, so a long run is a matter of
+//!
+//! ```text
+//! RAMP_ORACLE_ROUNDS=1000000 cargo test --release --test oracle
+//! ```
+//!
+//! A failure prints the seed, the round and the inputs, and setting
+//! `RAMP_ORACLE_SEED` to the seed replays it.
+
+extern crate framp as ramp;
+extern crate num_bigint;
+extern crate num_integer;
+extern crate num_traits;
+extern crate rand;
+
+use std::env;
+use std::fmt;
+
+use num_bigint::{BigInt, BigUint};
+use num_integer::Integer;
+use num_traits::{Signed, Zero};
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+use ramp::Int;
+
+const DEFAULT_ROUNDS: usize = 2000;
+const DEFAULT_SEED: u32 = 0x5eed;
+
+fn rounds() -> usize {
+    env::var("RAMP_ORACLE_ROUNDS").ok().and_then(|s| s.parse().ok()).unwrap_or(DEFAULT_ROUNDS)
+}
+
+fn seed() -> u32 {
+    env::var("RAMP_ORACLE_SEED").ok().and_then(|s| s.parse().ok()).unwrap_or(DEFAULT_SEED)
+}
+
+// Each test gets a generator of its own, so they don't depend on the order
+// they run in.
+fn rng(test: u32) -> XorShiftRng {
+    XorShiftRng::from_seed([seed(), test, 0x9e3779b9, 0x7f4a7c15])
+}
+
+// The inputs of a round, printed when a check fails
+struct Case<'a> {
+    round: usize,
+    inputs: &'a [&'a str],
+}
+
+impl<'a> fmt::Display for Case<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "seed {}, round {}, inputs", seed(), self.round));
+        for s in self.inputs {
+            try!(write!(f, " {}", s));
+        }
+        Ok(())
+    }
+}
+
+// Compares an `Int` with a num-bigint value through their hexadecimal form.
+macro_rules! check {
+    ($case:expr, $op:expr, $r:expr, $g:expr) => {{
+        let r = $r.to_str_radix(16, false);
+        let g = $g.to_str_radix(16);
+        if r != g {
+            panic!("{}: {} gave {} instead of {}", $case, $op, r, g);
+        }
+    }}
+}
+
+// A random integer in hexadecimal. Most are a few 64-bit words long, some
+// are long enough for the subquadratic algorithms, and a third are negative.
+fn random_hex<R: Rng>(rng: &mut R) -> String {
+    let words = match rng.gen_range(0, 10) {
+        0 => 0,
+        1 | 2 | 3 | 4 | 5 | 6 => rng.gen_range(1, 5),
+        7 | 8 => rng.gen_range(5, 40),
+        _ => rng.gen_range(40, 120),
+    };
+    let mut s = String::new();
+    if rng.gen_range(0, 3) == 0 {
+        s.push('-');
+    }
+    for i in 0..words {
+        let w: u64 = match rng.gen_range(0, 8) {
+            0 => 0,
+            1 => !0,
+            2 => 1 << 63,
+            3 => 1,
+            _ => rng.gen(),
+        };
+        if i == 0 {
+            s.push_str(&format!("{:x}", w));
+        } else {
+            s.push_str(&format!("{:016x}", w));
+        }
+    }
+    if words == 0 {
+        s.push('0');
+    }
+    s
+}
+
+fn parse(s: &str) -> (Int, BigInt) {
+    (Int::from_str_radix(s, 16).unwrap(), BigInt::parse_bytes(s.as_bytes(), 16).unwrap())
+}
+
+fn magnitude(g: &BigInt) -> BigUint {
+    g.abs().to_biguint().unwrap()
+}
+
+#[test]
+fn arithmetic() {
+    let mut rng = rng(1);
+    for round in 0..rounds() {
+        let (sa, sb) = (random_hex(&mut rng), random_hex(&mut rng));
+        let case = Case { round: round, inputs: &[&sa, &sb] };
+        let ((a, ga), (b, gb)) = (parse(&sa), parse(&sb));
+
+        check!(case, "a + b", &a + &b, &ga + &gb);
+        check!(case, "a - b", &a - &b, &ga - &gb);
+        check!(case, "a * b", &a * &b, &ga * &gb);
+        check!(case, "a * a", &a * &a, &ga * &ga);
+        check!(case, "-a", -&a, -&ga);
+        check!(case, "abs(a)", a.abs(), ga.abs());
+
+        let mut c = a.clone();
+        c += &b;
+        c -= &a;
+        c *= &a;
+        check!(case, "(a += b, -= a, *= a)", c, &gb * &ga);
+
+        if a.cmp(&b) != ga.cmp(&gb) {
+            panic!("{}: a.cmp(b) gave {:?}", case, a.cmp(&b));
+        }
+    }
+}
+
+#[test]
+fn division() {
+    let mut rng = rng(2);
+    for round in 0..rounds() {
+        let (sa, sb) = (random_hex(&mut rng), random_hex(&mut rng));
+        let case = Case { round: round, inputs: &[&sa, &sb] };
+        let ((a, ga), (b, gb)) = (parse(&sa), parse(&sb));
+        if gb.is_zero() {
+            continue;
+        }
+
+        // Both round towards zero
+        check!(case, "a / b", &a / &b, &ga / &gb);
+        check!(case, "a % b", &a % &b, &ga % &gb);
+        let (q, r) = a.divmod(&b);
+        check!(case, "divmod(a, b).0", q, &ga / &gb);
+        check!(case, "divmod(a, b).1", r, &ga % &gb);
+
+        // A product divides exactly, whatever the sizes
+        let p = &a * &b;
+        check!(case, "a * b / b", &p / &b, ga);
+        check!(case, "a * b % b", &p % &b, BigInt::zero());
+    }
+}
+
+#[test]
+fn shifts() {
+    let mut rng = rng(3);
+    for round in 0..rounds() {
+        let sa = random_hex(&mut rng);
+        let shift = rng.gen_range(0, 300);
+        let case = Case { round: round, inputs: &[&sa] };
+        let (a, ga) = parse(&sa);
+
+        check!(case, format!("a << {}", shift), &a << shift, &ga << shift);
+
+        // `>>` rounds towards negative infinity, while num-bigint rounds
+        // towards zero
+        let expected = if ga.is_negative() {
+            -((-&ga - BigInt::from(1)) >> shift) - BigInt::from(1)
+        } else {
+            &ga >> shift
+        };
+        check!(case, format!("a >> {}", shift), &a >> shift, expected);
+    }
+}
+
+// The bitwise operations are checked on magnitudes, as num-bigint only has
+// them for unsigned values.
+#[test]
+fn bit_ops() {
+    let mut rng = rng(4);
+    for round in 0..rounds() {
+        let (sa, sb) = (random_hex(&mut rng), random_hex(&mut rng));
+        let case = Case { round: round, inputs: &[&sa, &sb] };
+        let ((a, ga), (b, gb)) = (parse(&sa), parse(&sb));
+        let (a, b) = (a.abs(), b.abs());
+        let (ga, gb) = (magnitude(&ga), magnitude(&gb));
+
+        check!(case, "|a| & |b|", &a & &b, &ga & &gb);
+        check!(case, "|a| | |b|", &a | &b, &ga | &gb);
+        check!(case, "|a| ^ |b|", &a ^ &b, &ga ^ &gb);
+
+        let bits = ga.bits();
+        if a.bit_length() as usize != if bits == 0 { 1 } else { bits } {
+            panic!("{}: |a|.bit_length() gave {}", case, a.bit_length());
+        }
+        let i = rng.gen_range(0, bits + 70);
+        let expected = (&ga >> i) & BigUint::from(1u32) == BigUint::from(1u32);
+        if a.bit(i as u32) != expected {
+            panic!("{}: |a|.bit({}) gave {}", case, i, a.bit(i as u32));
+        }
+    }
+}
+
+#[test]
+fn conversions() {
+    let mut rng = rng(5);
+    for round in 0..rounds() {
+        let sa = random_hex(&mut rng);
+        let case = Case { round: round, inputs: &[&sa] };
+        let (a, ga) = parse(&sa);
+
+        let dec = ga.to_str_radix(10);
+        if a.to_string() != dec {
+            panic!("{}: a.to_string() gave {}", case, a.to_string());
+        }
+        check!(case, "parse(decimal)", dec.parse::<Int>().unwrap(), ga);
+
+        let radix = rng.gen_range(2, 37);
+        let s = a.to_str_radix(radix as u8, false);
+        if s != ga.to_str_radix(radix) {
+            panic!("{}: a.to_str_radix({}) gave {}", case, radix, s);
+        }
+        check!(case, format!("from_str_radix(_, {})", radix),
+               Int::from_str_radix(&s, radix as u8).unwrap(), ga);
+        check!(case, format!("from_str_radix(uppercase, {})", radix),
+               Int::from_str_radix(&s.to_uppercase(), radix as u8).unwrap(), ga);
+
+        let (_, bytes) = ga.to_bytes_be();
+        let bytes = if ga.is_zero() { Vec::new() } else { bytes };
+        if a.to_bytes_be() != bytes {
+            panic!("{}: a.to_bytes_be() gave {:?}", case, a.to_bytes_be());
+        }
+        check!(case, "from_bytes_be", Int::from_bytes_be(&bytes), ga.abs());
+        let mut le = bytes.clone();
+        le.reverse();
+        check!(case, "from_bytes_le", Int::from_bytes_le(&le), ga.abs());
+
+        if ga.bits() < 64 {
+            let x: i64 = dec.parse().unwrap();
+            check!(case, "Int::from(i64)", Int::from(x), ga);
+            if i64::from(&a) != x {
+                panic!("{}: i64::from(a) gave {}", case, i64::from(&a));
+            }
+        }
+    }
+}
+
+// Square-and-multiply, for a reference modular exponentiation
+fn ref_modpow(base: &BigUint, exp: &BigUint, m: &BigUint) -> BigUint {
+    let one = BigUint::from(1u32);
+    let mut result = &one % m;
+    for i in (0..exp.bits()).rev() {
+        result = &result * &result % m;
+        if (exp >> i) & &one == one {
+            result = &result * base % m;
+        }
+    }
+    result
+}
+
+#[test]
+fn pow_and_modpow() {
+    let mut rng = rng(6);
+    // Modular exponentiation is slow enough on the long inputs to need fewer
+    // rounds
+    for round in 0..(rounds() + 9) / 10 {
+        let (sa, sb, sm) = (random_hex(&mut rng), random_hex(&mut rng), random_hex(&mut rng));
+        let case = Case { round: round, inputs: &[&sa, &sb, &sm] };
+        let ((a, ga), (b, gb), (m, gm)) = (parse(&sa), parse(&sb), parse(&sm));
+
+        let k = rng.gen_range(0, 12);
+        check!(case, format!("a.pow({})", k), a.pow(k), num_traits::pow(ga.clone(), k));
+
+        let m = m.abs();
+        if m.is_zero() {
+            continue;
+        }
+        let gm = magnitude(&gm);
+        // The exponent is kept to a few words, as the reference is slow
+        let (e, ge) = if b.bit_length() > 256 {
+            (b.abs() >> (b.bit_length() as usize - 256), magnitude(&gb) >> (gb.bits() - 256))
+        } else {
+            (b.abs(), magnitude(&gb))
+        };
+        let base = a.abs() % &m;
+        let gbase = magnitude(&ga) % &gm;
+        check!(case, "(|a| % |m|).modpow(|b|, |m|)", base.modpow(&e, &m),
+               ref_modpow(&gbase, &ge, &gm));
+    }
+}
+
+#[test]
+fn gcd() {
+    let mut rng = rng(7);
+    for round in 0..rounds() {
+        let (sa, sb) = (random_hex(&mut rng), random_hex(&mut rng));
+        let case = Case { round: round, inputs: &[&sa, &sb] };
+        let ((a, ga), (b, gb)) = (parse(&sa), parse(&sb));
+
+        check!(case, "gcd(a, b)", a.gcd(&b), ga.gcd(&gb));
+        if !ga.is_zero() && !gb.is_zero() {
+            check!(case, "lcm(a, b)", a.lcm(&b), ga.lcm(&gb));
+        }
+
+        // A common factor has to show up in the result
+        let c = &a * &b;
+        let gc = &ga * &gb;
+        check!(case, "gcd(a * b, a)", c.gcd(&a), gc.gcd(&ga));
+    }
+}
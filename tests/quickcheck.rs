@@ -351,6 +351,50 @@ fn set_bit(a: BigIntStr, bit: u16, b: bool) {
     assert_eq!(ar.bit(bit as u32), b);
 }
 
+#[quickcheck]
+fn hamming_distance(a: BigIntStr, b: BigIntStr) {
+    let (ar, _) = a.parse();
+    let (br, _) = b.parse();
+
+    let expected = if (ar < 0) != (br < 0) {
+        std::usize::MAX
+    } else {
+        let end = std::cmp::max(ar.bit_length(), br.bit_length()) + 1;
+        (0..end).filter(|&i| ar.bit(i) != br.bit(i)).count()
+    };
+
+    assert_eq!(ar.hamming_distance(&br), expected);
+}
+
+#[quickcheck]
+fn bit_scan(a: BigIntStr, from: u16) {
+    let (ar, _) = a.parse();
+    let from = from as u32;
+
+    // every bit beyond the bit length is a copy of the sign
+    let end = std::cmp::max(from, ar.bit_length() + 1) + 1;
+    let naive = |val: bool| (from..end).find(|&i| ar.bit(i) == val);
+
+    assert_eq!(ar.bit_scan1(from), naive(true));
+    assert_eq!(ar.bit_scan0(from), naive(false));
+}
+
+#[quickcheck]
+fn clear_toggle_bit(a: BigIntStr, bit: u16) {
+    let (ar, _) = a.parse();
+    let before = ar.bit(bit as u32);
+
+    let mut t = ar.clone();
+    t.toggle_bit(bit as u32);
+    assert_eq!(t.bit(bit as u32), !before);
+    t.toggle_bit(bit as u32);
+    assert_eq!(t, ar);
+
+    let mut c = ar.clone();
+    c.clear_bit(bit as u32);
+    assert!(!c.bit(bit as u32));
+}
+
 fn order_asc<T: Ord>(a: T, b: T) -> (T, T) {
     if a < b {
         (a, b)
@@ -677,8 +721,13 @@ macro_rules! test_shiftop {
 
 test_shiftop! {
     shl: <<, <<=;
-    // FIXME(#27): currently >> doesn't match primitives/GMP for negative values
-    // shr: >>, >>=;
+}
+
+// >> rounds towards negative infinity like GMP does, while num-bigint rounds
+// towards zero for negative values
+#[cfg(feature="rust-gmp")]
+test_shiftop! {
+    shr: >>, >>=;
 }
 
 macro_rules! test_cmpop {